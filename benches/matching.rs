@@ -0,0 +1,107 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use semantic_code_search::data::{Database, QueryOps, UnitFlow};
+use std::env;
+
+/// Steps in each synthetic flow
+const FLOW_LEN: usize = 8;
+
+const QUERIES: &[&str] = &[
+    "List",
+    "int, @Some.1",
+    "#>=2",
+    "^, Tree, .., Result",
+    "List:generated && @Cons",
+];
+
+/// Flows in the benchmarked databases, overridden with a comma-separated
+/// `SCS_BENCH_FLOWS`
+fn sizes() -> Vec<usize> {
+    env::var("SCS_BENCH_FLOWS")
+        .ok()
+        .map(|sizes| {
+            sizes
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_else(|| vec![1_000, 10_000])
+}
+
+fn match_flow(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match_flow");
+    for n in sizes() {
+        let db = Database::generate_synthetic(n, FLOW_LEN);
+        for query in QUERIES {
+            let ops = QueryOps::parse_query(query).unwrap();
+            group.bench_with_input(BenchmarkId::new(*query, n), &ops, |b, ops| {
+                b.iter(|| {
+                    db.data_flows
+                        .iter()
+                        .filter(|flow| db.match_flow(flow, black_box(ops)))
+                        .count()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+/// Visiting every step of every flow, which is bound by how the flows are
+/// laid out in memory rather than by matching
+fn scan_steps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scan_steps");
+    for n in sizes() {
+        let db = Database::generate_synthetic(n, FLOW_LEN);
+        group.bench_function(BenchmarkId::from_parameter(n), |b| {
+            b.iter(|| {
+                db.data_flows
+                    .iter()
+                    .flat_map(|flow| flow.iter())
+                    .filter(|uf| matches!(black_box(uf), UnitFlow::Type(_)))
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn count_typevar_flows(c: &mut Criterion) {
+    let mut group = c.benchmark_group("count_typevar_flows");
+    for n in sizes() {
+        let db = Database::generate_synthetic(n, FLOW_LEN);
+        let names: Vec<String> = db
+            .typevar_degrees()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        group.bench_with_input(BenchmarkId::from_parameter(n), &names, |b, names| {
+            b.iter(|| {
+                names
+                    .iter()
+                    .map(|name| db.count_typevar_flows(black_box(name)))
+                    .sum::<usize>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Parsing the query and searching the whole database, as a search from
+/// the command line does once the database is loaded
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    for n in sizes() {
+        let db = Database::generate_synthetic(n, FLOW_LEN);
+        for query in QUERIES {
+            group.bench_with_input(BenchmarkId::new(*query, n), query, |b, query| {
+                b.iter(|| {
+                    let ops = QueryOps::parse_query(black_box(query)).unwrap();
+                    db.search(&ops).len()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, match_flow, scan_steps, count_typevar_flows, search);
+criterion_main!(benches);
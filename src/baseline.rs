@@ -0,0 +1,80 @@
+use crate::data::{flow_id, UnitFlow};
+use std::collections::BTreeSet;
+use std::fs;
+
+/// Matches accepted as known, kept by [flow_id] in a JSON file so that later
+/// searches report only the flows introduced since it was recorded
+#[derive(Debug, Default)]
+pub struct Baseline {
+    path: String,
+    pub flow_ids: BTreeSet<String>,
+}
+
+impl Baseline {
+    /// Load the baseline at `path`. A missing file suppresses nothing, so a
+    /// first `--update-baseline` can create it.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let flow_ids = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| format!("Invalid baseline file {}: {}", path, e))?,
+            Err(_) => BTreeSet::new(),
+        };
+        Ok(Baseline {
+            path: path.to_string(),
+            flow_ids,
+        })
+    }
+
+    pub fn contains(&self, flow: &[UnitFlow]) -> bool {
+        self.flow_ids.contains(&flow_id(flow))
+    }
+
+    /// The `flows` not in the baseline, in order, with the number dropped
+    pub fn suppress<'a>(&self, flows: &[&'a [UnitFlow]]) -> (Vec<&'a [UnitFlow]>, usize) {
+        let kept: Vec<_> = flows
+            .iter()
+            .filter(|flow| !self.contains(flow))
+            .copied()
+            .collect();
+        let suppressed = flows.len() - kept.len();
+        (kept, suppressed)
+    }
+
+    /// Replace the recorded ids with those of `flows`
+    pub fn record(&mut self, flows: &[&[UnitFlow]]) {
+        self.flow_ids = flows.iter().map(|flow| flow_id(flow)).collect();
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(&self.flow_ids).map_err(|e| e.to_string())?;
+        fs::write(&self.path, data).map_err(|e| format!("Could not write {}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_baseline() {
+        let db = crate::tutorial::load_database();
+        let path = std::env::temp_dir().join(format!("scs-baseline-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let mut baseline = Baseline::load(path).unwrap();
+        let flows: Vec<_> = db.data_flows.iter().collect();
+        assert_eq!(baseline.suppress(&flows).1, 0);
+
+        baseline.record(&flows[..2]);
+        baseline.save().unwrap();
+        let baseline = Baseline::load(path).unwrap();
+        let (kept, suppressed) = baseline.suppress(&flows);
+        assert_eq!(suppressed, 2);
+        assert_eq!(kept.len(), flows.len() - 2);
+        assert!(baseline.contains(flows[1]) && !baseline.contains(flows[2]));
+
+        fs::write(path, "{").unwrap();
+        assert!(Baseline::load(path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+use std::fs;
+
+/// What to do with the bookmarks of a database
+#[derive(Debug, PartialEq, Eq)]
+pub enum BookmarkAction {
+    /// Bookmark the flow whose id starts with the prefix
+    Add(String),
+    List,
+    /// Drop the bookmark whose id starts with the prefix
+    Remove(String),
+}
+
+/// Flows collected during an investigation, kept by
+/// [flow_id](crate::data::flow_id) in a sidecar file next to the database.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    path: String,
+    /// Bookmarked flow ids, oldest first
+    pub flow_ids: Vec<String>,
+}
+
+impl Bookmarks {
+    /// `<data_json>.bookmarks.json`
+    pub fn sidecar_path(data_json: &str) -> String {
+        format!("{}.bookmarks.json", data_json)
+    }
+
+    /// Load the bookmarks of the database at `data_json`. A missing sidecar
+    /// file holds no bookmarks.
+    pub fn load(data_json: &str) -> Result<Self, String> {
+        let path = Self::sidecar_path(data_json);
+        let flow_ids = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| format!("Invalid bookmarks file {}: {}", path, e))?,
+            Err(_) => vec![],
+        };
+        Ok(Bookmarks { path, flow_ids })
+    }
+
+    /// Bookmark a flow, returning false if it already was
+    pub fn add(&mut self, flow_id: &str) -> bool {
+        if self.flow_ids.iter().any(|id| id == flow_id) {
+            return false;
+        }
+        self.flow_ids.push(flow_id.to_string());
+        true
+    }
+
+    /// Remove the bookmark whose id starts with `prefix`, if exactly one does
+    pub fn remove(&mut self, prefix: &str) -> Option<String> {
+        let mut found = self
+            .flow_ids
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| id.starts_with(prefix))
+            .map(|(idx, _)| idx);
+        match (found.next(), found.next()) {
+            (Some(idx), None) => Some(self.flow_ids.remove(idx)),
+            _ => None,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(&self.flow_ids).map_err(|e| e.to_string())?;
+        fs::write(&self.path, data).map_err(|e| format!("Could not write {}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks() {
+        let mut bookmarks = Bookmarks::default();
+        assert!(bookmarks.add("a3f9000000000000"));
+        assert!(bookmarks.add("a3f1000000000000"));
+        assert!(!bookmarks.add("a3f9000000000000"));
+
+        assert_eq!(bookmarks.remove("a3f"), None);
+        assert_eq!(
+            bookmarks.remove("a3f9"),
+            Some("a3f9000000000000".to_string())
+        );
+        assert_eq!(bookmarks.flow_ids, vec!["a3f1000000000000"]);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::data::QueryOps;
+use crate::engine::Match;
+use crate::rank::Score;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Results kept by a [crate::SearchEngine] by default
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+/// What a [ResultCache] is looked up by: the [content
+/// hash](crate::data::Database::content_hash) of the database searched and
+/// the query written out canonically, so that spellings of one query differing
+/// only in spacing or quoting share their results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub database: u64,
+    pub query: String,
+}
+
+impl CacheKey {
+    pub fn new(database: u64, query: &[QueryOps]) -> Self {
+        let steps: Vec<String> = query.iter().map(ToString::to_string).collect();
+        CacheKey {
+            database,
+            query: steps.join(", "),
+        }
+    }
+}
+
+/// A [Match] without its borrow of the database, to be cached
+#[derive(Debug, Clone)]
+pub struct CachedMatch {
+    pub index: usize,
+    pub flow_id: String,
+    pub positions: Vec<usize>,
+    pub captures: BTreeMap<String, usize>,
+    pub score: Score,
+}
+
+impl CachedMatch {
+    pub fn new(m: &Match) -> Self {
+        CachedMatch {
+            index: m.index,
+            flow_id: m.flow_id.clone(),
+            positions: m.positions.clone(),
+            captures: m.captures.clone(),
+            score: m.score,
+        }
+    }
+}
+
+/// Lookups of a [ResultCache] so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Results held now
+    pub entries: usize,
+}
+
+/// The matches of the last searched queries, dropping the least recently
+/// used past its capacity
+#[derive(Debug)]
+pub struct ResultCache {
+    capacity: usize,
+    /// Most recently used first
+    entries: VecDeque<(CacheKey, Vec<CachedMatch>)>,
+    hits: usize,
+    misses: usize,
+}
+
+impl ResultCache {
+    /// A cache of up to `capacity` results; none are kept at 0
+    pub fn new(capacity: usize) -> Self {
+        ResultCache {
+            capacity,
+            entries: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// The matches cached for `key`, marking them most recently used
+    pub fn get(&mut self, key: &CacheKey) -> Option<&[CachedMatch]> {
+        let Some(idx) = self.entries.iter().position(|(k, _)| k == key) else {
+            self.misses += 1;
+            return None;
+        };
+        self.hits += 1;
+        let entry = self.entries.remove(idx)?;
+        self.entries.push_front(entry);
+        self.entries.front().map(|(_, matches)| matches.as_slice())
+    }
+
+    pub fn insert(&mut self, key: CacheKey, matches: Vec<CachedMatch>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.push_front((key, matches));
+        self.entries.truncate(self.capacity);
+    }
+
+    /// Drop every result, as when the database changes
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+        }
+    }
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_cache() {
+        let key = |query: &str| CacheKey::new(1, &QueryOps::parse_query(query).unwrap());
+        assert_eq!(key("List,  @Cons"), key("List , @Cons"));
+        assert_ne!(
+            key("List"),
+            CacheKey::new(2, &QueryOps::parse_query("List").unwrap())
+        );
+
+        let mut cache = ResultCache::new(2);
+        assert!(cache.get(&key("List")).is_none());
+        cache.insert(key("List"), vec![]);
+        cache.insert(key("bool"), vec![]);
+        assert!(cache.get(&key("List")).is_some());
+        // `bool` is now the least recently used
+        cache.insert(key("int"), vec![]);
+        assert!(cache.get(&key("bool")).is_none());
+        assert!(cache.get(&key("List")).is_some());
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                entries: 2
+            }
+        );
+        cache.clear();
+        assert!(cache.get(&key("int")).is_none());
+    }
+}
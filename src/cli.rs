@@ -0,0 +1,904 @@
+use crate::data::Position;
+use crate::federated::Origin;
+use clap::builder::RangedU64ValueParser;
+use clap::{value_parser, Arg, ArgAction, Command};
+
+const AFTER_HELP: &str =
+    "A <DATA> path may also be a directory of data JSON files, or several joined with `:`,
+searched as one database. Running `<DATA> <QUERY>` without a subcommand is the same as `search`.";
+
+/// The command line interface: subcommands and their flags. [crate::Config::build]
+/// turns the matches into a [crate::Config].
+pub fn command() -> Command {
+    Command::new("semantic-code-search")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Search the data flows of a program by the types and constructors they pass through")
+        .after_help(AFTER_HELP)
+        .subcommand_required(true)
+        .args(global_args())
+        .subcommand(
+            Command::new("search")
+                .about("Search the database with a query or rule pack")
+                .args(data_and_query())
+                .arg(
+                    Arg::new("rules")
+                        .long("rules")
+                        .visible_alias("query-file")
+                        .value_name("RULES_FILE")
+                        .help("Evaluate the named queries of a TOML or JSON rule pack instead of a single query"),
+                )
+                .arg(flag("partial", "On no matches, report the longest query prefix that still matched"))
+                .arg(flag("suggest", "On no matches, report the single-step relaxations of the query that match"))
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .value_name("N")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .help("Show only the N best ranked flows"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .value_name("ORDER")
+                        .value_parser(["flows", "id", "file", "line", "score", "length"])
+                        .help("Order matched flows by database order (the default), flow id, file, line, rank or length, breaking ties by flow id"),
+                )
+                .arg(flag("count", "Print only the number of matched flows"))
+                .arg(flag("invert", "Show the flows the query does not match instead, like grep -v").conflicts_with("top"))
+                .arg(
+                    flag("all", "Assert that the query matches every flow: show those it does not and exit 1 if there are any")
+                        .conflicts_with_all(["top", "invert"]),
+                )
+                .arg(
+                    Arg::new("scope")
+                        .long("scope")
+                        .value_name("QUERY")
+                        .help("With --invert or --all, consider only the flows this query matches"),
+                )
+                .arg(
+                    Arg::new("limit")
+                        .long("limit")
+                        .value_name("N")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .help("Show at most N flows, in database order unless ranked with --top, and stop searching after them"),
+                )
+                .arg(
+                    Arg::new("offset")
+                        .long("offset")
+                        .value_name("M")
+                        .value_parser(value_parser!(usize))
+                        .help("Skip the first M matched flows"),
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .value_name("SECS")
+                        .value_parser(value_parser!(u64).range(1..))
+                        .help("Stop searching after SECS seconds and show the flows matched so far, exiting 2 if there are none"),
+                )
+                .arg(
+                    Arg::new("save-results")
+                        .long("save-results")
+                        .value_name("RESULTS_PATH")
+                        .help("Save the matched flows as a result set"),
+                )
+                .arg(
+                    Arg::new("export")
+                        .long("export")
+                        .value_name("EXPORT_PATH")
+                        .help("Write the matched flows as a database in the input's format, line-delimited for .ndjson or .jsonl paths"),
+                )
+                .arg(
+                    Arg::new("baseline")
+                        .long("baseline")
+                        .value_name("BASELINE_PATH")
+                        .help("Suppress the matched flows whose ids this file records, reporting only new ones"),
+                )
+                .arg(flag(
+                    "update-baseline",
+                    "Record the matched flows in the --baseline file instead of reporting them",
+                ))
+                .arg(flag("watch", "Re-run the search whenever the database file changes"))
+                .arg(
+                    Arg::new("on-change")
+                        .long("on-change")
+                        .value_name("SHELL_COMMAND")
+                        .help("Run a command when a watched search's matches change"),
+                )
+                .arg(
+                    Arg::new("webhook")
+                        .long("webhook")
+                        .value_name("URL")
+                        .help("POST to a URL when a watched search's matches change"),
+                )
+                .arg(flag("show-id", "Print the id of each matched flow"))
+                .arg(flag(
+                    "cluster",
+                    "Group near-identical matched flows, showing one flow of each group with how many it stands for",
+                ))
+                .arg(
+                    Arg::new("expand-cluster")
+                        .long("expand-cluster")
+                        .value_name("CLUSTER_ID")
+                        .help("Show every flow of the --cluster group with this id"),
+                )
+                .arg(flag(
+                    "show-steps",
+                    "Print every step of each matched flow as an arrow chain, not only its locations",
+                ))
+                .arg(
+                    Arg::new("id")
+                        .long("id")
+                        .value_name("FLOW_ID")
+                        .help("Show the flow with this id prefix, or #N for the flow at index N, in full instead of matching a query"),
+                )
+                .arg(
+                    Arg::new("trace-match")
+                        .long("trace-match")
+                        .value_name("FLOW_ID")
+                        .help("Explain step by step why the flow with this id prefix, or #N for the flow at index N, does or doesn't match the query"),
+                )
+                .arg(flag(
+                    "verbose",
+                    "Print the time spent loading the database, indexing it and matching, and the flows left after prefiltering",
+                ))
+                .arg(
+                    Arg::new("like")
+                        .long("like")
+                        .value_name("FLOW_ID")
+                        .help("Rank flows by similarity to the flow with this id prefix, or #N for the flow at index N, instead of matching a query"),
+                )
+                .arg(flag("repl", "Search interactively"))
+                .arg(flag("tui", "Browse matched flows in a full-screen terminal UI"))
+                .arg(flag("remote", "Send the search to a running daemon"))
+                .arg(
+                    Arg::new("db")
+                        .long("db")
+                        .value_name("[LABEL=]DATA")
+                        .action(ArgAction::Append)
+                        .value_parser(|s: &str| Ok::<_, String>(Origin::parse(s)))
+                        .help("Search a further database alongside <DATA>"),
+                )
+                .arg(
+                    Arg::new("only")
+                        .long("only")
+                        .value_name("LABEL")
+                        .action(ArgAction::Append)
+                        .help("Keep results from the database with this label"),
+                ),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Show how each step of a query parses and narrows the matched flows, flagging steps that cannot match")
+                .args(data_and_query()),
+        )
+        .subcommand(
+            Command::new("index")
+                .about("Write a binary index of the database, loaded in place of the JSON while newer")
+                .arg(data()),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Flow lengths, frequent types and constructors, type variable in-degrees and location coverage")
+                .arg(data()),
+        )
+        .subcommand(
+            Command::new("typevars")
+                .about("Type variables by name with the number of flows through each, to query with #'a")
+                .arg(data()),
+        )
+        .subcommand(
+            Command::new("graph-stats")
+                .about("Fan-in and fan-out of type variables, connected components and central types")
+                .arg(data()),
+        )
+        .subcommand(Command::new("tutorial").about("Guided tutorial over the bundled example database"))
+        .subcommand(
+            Command::new("daemon").about("Serve searches over a Unix socket, keeping databases loaded"),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Answer JSON-RPC search requests from editor plugins, one per line, keeping databases loaded")
+                .arg(flag("unix", "Listen on the --socket path instead of stdin and stdout")),
+        )
+        .subcommand(
+            Command::new("lsp")
+                .about("Language server showing the matches of a rule pack as diagnostics in editors")
+                .arg(Arg::new("data").value_name("DATA").required(true))
+                .arg(
+                    Arg::new("rules")
+                        .long("rules")
+                        .visible_alias("query-file")
+                        .value_name("RULES_FILE")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("regress")
+                .about("Flows each rule matches in the new database but not the old, and the other way round")
+                .arg(Arg::new("old").value_name("OLD_DATA").required(true))
+                .arg(Arg::new("new").value_name("NEW_DATA").required(true))
+                .arg(
+                    Arg::new("rules")
+                        .long("rules")
+                        .visible_alias("query-file")
+                        .value_name("RULES_FILE")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("diff")
+                .about("Flows the query matches in the new database but not the old, and the other way round; exits with 1 when any appeared")
+                .arg(Arg::new("old").value_name("OLD_DATA").required(true))
+                .arg(Arg::new("new").value_name("NEW_DATA").required(true))
+                .arg(Arg::new("query").long("query").value_name("QUERY").required(true)),
+        )
+        .subcommand(
+            Command::new("connect")
+                .about("Flows passing through one source position and then another")
+                .arg(data())
+                .arg(position("from"))
+                .arg(position("to")),
+        )
+        .subcommand(
+            Command::new("join")
+                .about("Pairs of flows, one matched by each query, passing through a common type variable")
+                .arg(data())
+                .arg(query().required(true))
+                .arg(Arg::new("other-query").value_name("OTHER_QUERY").required(true)),
+        )
+        .subcommand(
+            Command::new("at")
+                .about("Flows passing through a source position")
+                .arg(data())
+                .arg(position("pos")),
+        )
+        .subcommand(
+            Command::new("path")
+                .about("Shortest chain of flows leading from one type to another")
+                .arg(data())
+                .arg(Arg::new("from").value_name("FROM_TYPE").required(true))
+                .arg(Arg::new("to").value_name("TO_TYPE").required(true)),
+        )
+        .subcommand(
+            Command::new("stitch")
+                .about("Search flows materialized by joining flows on shared type variables")
+                .arg(data())
+                .arg(query())
+                .arg(
+                    Arg::new("max-chain")
+                        .long("max-chain")
+                        .value_name("N")
+                        .value_parser(value_parser!(usize))
+                        .default_value("3")
+                        .help("Most flows joined into one"),
+                )
+                .arg(
+                    Arg::new("max-stitched")
+                        .long("max-stitched")
+                        .value_name("N")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .default_value("10000")
+                        .help("Most joined flows to search, as their number grows exponentially with --max-chain"),
+                ),
+        )
+        .subcommand(
+            Command::new("text")
+                .about("Flows ranked by how relevant their descriptions are to the words, by TF-IDF")
+                .arg(data())
+                .arg(Arg::new("words").value_name("WORDS").required(true))
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .value_name("COUNT")
+                        .value_parser(value_parser!(usize))
+                        .default_value("10")
+                        .help("Number of flows to show"),
+                )
+                .arg(
+                    Arg::new("query")
+                        .long("query")
+                        .value_name("QUERY")
+                        .help("Rank only the flows the query matches"),
+                ),
+        )
+        .subcommand(
+            Command::new("cluster")
+                .about("Group matched (or all) flows by structure")
+                .arg(data())
+                .arg(query()),
+        )
+        .subcommand(
+            Command::new("dupes")
+                .about("Groups of flows identical up to positions and descriptions")
+                .arg(data())
+                .arg(query()),
+        )
+        .subcommand(
+            Command::new("similar-flows")
+                .about("The flows most similar to a flow")
+                .arg(data())
+                .arg(flow_id())
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .value_name("COUNT")
+                        .value_parser(value_parser!(usize))
+                        .default_value("10")
+                        .help("Number of flows to show"),
+                ),
+        )
+        .subcommand(
+            Command::new("fix-locs")
+                .about("Propose repairs for program locations with out-of-range or inverted ranges")
+                .arg(data())
+                .arg(Arg::new("out").value_name("OUT").help("Where to write the repaired database, <DATA> by default")),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Rewrite a database in the current schema, keeping its flows as they are")
+                .arg(data())
+                .arg(Arg::new("out").value_name("OUT").help("Where to write the migrated database, <DATA> by default")),
+        )
+        .subcommand(
+            Command::new("export-flow")
+                .about("Write a flow to a database of its own")
+                .arg(data())
+                .arg(flow_id())
+                .arg(Arg::new("out").value_name("OUT").required(true))
+                .arg(
+                    Arg::new("redact")
+                        .long("redact")
+                        .value_name("FIELDS")
+                        .action(ArgAction::Append)
+                        .help("Strip a comma-separated list of descs, lines and files"),
+                )
+                .arg(
+                    Arg::new("hash")
+                        .long("hash")
+                        .value_name("FIELDS")
+                        .action(ArgAction::Append)
+                        .help("Hash a comma-separated list of descs, lines and files"),
+                ),
+        )
+        .subcommand(
+            Command::new("repl")
+                .about("Interactive search, refining the previous result set on request")
+                .arg(data()),
+        )
+        .subcommand(
+            Command::new("qdiff")
+                .about("Compare the flows matched by two queries")
+                .arg(data())
+                .arg(query().required(true))
+                .arg(Arg::new("other-query").value_name("OTHER_QUERY").required(true)),
+        )
+        .subcommand(
+            Command::new("graph-export")
+                .about("Write the type graph as DOT, or GraphML when <OUT> ends in .graphml")
+                .arg(data())
+                .arg(Arg::new("out").value_name("OUT").required(true)),
+        )
+        .subcommand(
+            Command::new("note")
+                .about("Attach a note to a flow")
+                .arg(data())
+                .arg(flow_id())
+                .arg(Arg::new("text").value_name("TEXT").required(true)),
+        )
+        .subcommand(
+            Command::new("bookmark")
+                .about("Manage the flows bookmarked in the database")
+                .subcommand_required(true)
+                .subcommand(Command::new("add").arg(data()).arg(flow_id()))
+                .subcommand(Command::new("remove").arg(data()).arg(flow_id()))
+                .subcommand(Command::new("list").arg(data())),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("List past queries, or re-run one")
+                .arg(
+                    Arg::new("n")
+                        .value_name("N")
+                        .value_parser(value_parser!(usize))
+                        .help("Number of the entry to re-run"),
+                ),
+        )
+        .subcommand(
+            Command::new("load-results")
+                .about("Re-render a saved result set, combined with others and narrowed by a query")
+                .arg(Arg::new("path").value_name("RESULTS_PATH").required(true))
+                .arg(query())
+                .args(["union", "intersect", "minus"].map(|op| {
+                    Arg::new(op)
+                        .long(op)
+                        .value_name("RESULTS_PATH")
+                        .action(ArgAction::Append)
+                }))
+        )
+}
+
+/// Flags accepted by every subcommand
+fn global_args() -> Vec<Arg> {
+    vec![
+        Arg::new("include")
+            .long("include")
+            .visible_alias("path")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .global(true)
+            .help("Only search flows through files matching the glob"),
+        Arg::new("exclude")
+            .long("exclude")
+            .value_name("GLOB")
+            .action(ArgAction::Append)
+            .global(true)
+            .help("Skip flows through files matching the glob"),
+        flag(
+            "no-ignore",
+            "Keep flows through files matched by .gitignore or .scsignore",
+        )
+        .global(true),
+        flag(
+            "quiet",
+            "Print nothing; exit 0 when any flows match, 1 when none do and 2 on errors",
+        )
+        .short('q')
+        .global(true),
+        Arg::new("log-verbosity")
+            .short('v')
+            .action(ArgAction::Count)
+            .global(true)
+            .help("Log loading, indexing and searching to stderr: -v for progress, -vv to debug, -vvv to trace; RUST_LOG overrides"),
+        flag(
+            "ignore-case",
+            "Match type names, constructor names and descriptions ignoring case",
+        )
+        .global(true),
+        flag(
+            "no-hierarchy",
+            "Match types by name only, ignoring the subtypes declared in the database",
+        )
+        .global(true),
+        flag(
+            "keep-duplicates",
+            "Keep every copy of identical flows instead of counting them",
+        )
+        .global(true),
+        flag(
+            "lenient",
+            "Skip malformed flows instead of failing, counting how many were dropped",
+        )
+        .global(true),
+        flag(
+            "repair-spans",
+            "Clamp out-of-range or inverted location ranges to their lines instead of skipping the locations",
+        )
+        .global(true),
+        Arg::new("max-file-size")
+            .long("max-file-size")
+            .value_name("BYTES")
+            .value_parser(value_parser!(u64))
+            .global(true)
+            .help("Refuse to load database files, or what they decompress to, of more than BYTES"),
+        Arg::new("max-flows")
+            .long("max-flows")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Refuse to load databases of more than N flows"),
+        Arg::new("max-flow-len")
+            .long("max-flow-len")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Refuse to load databases with a flow of more than N steps"),
+        Arg::new("max-string-len")
+            .long("max-string-len")
+            .value_name("BYTES")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Refuse to load databases with a name, source line or description of more than BYTES"),
+        Arg::new("min-len")
+            .long("min-len")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Keep only flows of at least N steps, or N locations with --len-of locs"),
+        Arg::new("max-len")
+            .long("max-len")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Keep only flows of at most N steps, or N locations with --len-of locs"),
+        Arg::new("len-of")
+            .long("len-of")
+            .value_name("UNIT")
+            .value_parser(["steps", "locs"])
+            .global(true)
+            .help("Count flow lengths for --min-len and --max-len in steps or program locations (steps)"),
+        Arg::new("since")
+            .long("since")
+            .value_name("REV")
+            .global(true)
+            .help("Keep only flows stamped with a commit after this revision"),
+        Arg::new("until")
+            .long("until")
+            .value_name("REV")
+            .global(true)
+            .help("Keep only flows stamped with a commit up to this revision"),
+        flag(
+            "narrate",
+            "Narrate matched flows step by step instead of listing locations",
+        )
+        .global(true),
+        flag(
+            "elaborate",
+            "Show the concrete type carried at each program location",
+        )
+        .global(true),
+        Arg::new("context")
+            .short('C')
+            .long("context")
+            .value_name("N")
+            .value_parser(value_parser!(usize))
+            .global(true)
+            .help("Show N lines of the source file around each program location"),
+        Arg::new("highlight-style")
+            .long("highlight-style")
+            .value_name("STYLE")
+            .value_parser(["inline", "underline", "both"])
+            .global(true)
+            .help("Color matched spans inside the source line, underline them, or both"),
+        Arg::new("tab-width")
+            .long("tab-width")
+            .value_name("N")
+            .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+            .global(true)
+            .help("Expand tabs in source lines to stops N columns apart, lining up the carets under them (8)"),
+        Arg::new("format")
+            .long("format")
+            .value_name("FORMAT")
+            .value_parser(["text", "json", "sarif", "md", "html", "dot", "quickfix"])
+            .global(true)
+            .help("Print results as text, JSON, SARIF 2.1.0 for code scanning, a Markdown or HTML report, a Graphviz graph, or file:line:col lines for an editor's quickfix list"),
+        flag(
+            "cluster-files",
+            "With --format dot, draw the types and constructors of each file in a cluster",
+        )
+        .global(true),
+        flag("json", "Same as --format json").global(true),
+        Arg::new("color")
+            .long("color")
+            .value_name("WHEN")
+            .value_parser(["auto", "always", "never"])
+            .global(true)
+            .help("Print with colors always, never, or only to a terminal without NO_COLOR set (auto)"),
+        flag("no-color", "Same as --color never").global(true),
+        flag(
+            "no-pager",
+            "Print results to the terminal directly instead of through $PAGER (less by default)",
+        )
+        .global(true),
+        Arg::new("config")
+            .long("config")
+            .value_name("CONFIG_PATH")
+            .global(true)
+            .help("Read defaults from this file instead of ~/.config/semantic-code-search.toml"),
+        Arg::new("socket")
+            .long("socket")
+            .value_name("SOCKET_PATH")
+            .global(true)
+            .help("Unix socket the daemon listens on"),
+    ]
+}
+
+fn flag(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
+fn data() -> Arg {
+    Arg::new("data")
+        .value_name("DATA")
+        .required(true)
+        .help("Data JSON file of the flows")
+}
+
+fn query() -> Arg {
+    Arg::new("query")
+        .value_name("QUERY")
+        .help("Query the flows must match, such as `List, @Cons`")
+}
+
+fn flow_id() -> Arg {
+    Arg::new("flow")
+        .value_name("FLOW_ID")
+        .required(true)
+        .help("Id prefix of the flow, or #N for the flow at index N")
+}
+
+fn position(name: &'static str) -> Arg {
+    Arg::new(name)
+        .value_name("FILE:LINE[:COL]")
+        .value_parser(Position::parse)
+        .required(true)
+}
+
+/// `<DATA> <QUERY>` given positionally or as `--data` and `--query`
+fn data_and_query() -> Vec<Arg> {
+    vec![
+        Arg::new("data")
+            .value_name("DATA")
+            .help("Data JSON file of the flows to search"),
+        query(),
+        Arg::new("data-flag")
+            .long("data")
+            .value_name("DATA")
+            .conflicts_with("data")
+            .help("Same as <DATA>"),
+        Arg::new("query-flag")
+            .long("query")
+            .value_name("QUERY")
+            .conflicts_with("query")
+            .help("Same as <QUERY>"),
+    ]
+}
+
+/// Whether `arg` names a subcommand, or asks for help, rather than starting
+/// the `<DATA> <QUERY>` shorthand for `search`
+pub fn is_subcommand(arg: &str) -> bool {
+    matches!(arg, "help" | "-h" | "--help" | "-V" | "--version")
+        || command()
+            .get_subcommands()
+            .any(|sub| sub.get_name() == arg || sub.get_all_aliases().any(|alias| alias == arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ColorChoice;
+    use crate::data::HighlightStyle;
+    use crate::report::ReportFormat;
+    use crate::results::SetOp;
+    use crate::{Command, Config};
+    use std::time::Duration;
+
+    fn build(args: &str) -> Config {
+        let args: Vec<String> = ["scs"]
+            .iter()
+            .chain(&args.split(' ').collect::<Vec<_>>())
+            .map(|s| s.to_string())
+            .collect();
+        Config::build(&args).unwrap()
+    }
+
+    #[test]
+    fn test_build_config() {
+        let config = build("db.json List --elaborate --top 3");
+        assert!(matches!(config.command, Command::Search));
+        assert_eq!(
+            (config.data_json.as_str(), config.query_json.as_str()),
+            ("db.json", "List")
+        );
+        assert!(config.elaborate);
+        assert_eq!(config.context, 0);
+        assert_eq!(build("db.json List -C 2").context, 2);
+        assert_eq!(config.top, Some(3));
+        let args: Vec<String> = ["scs", "db.json", "List", "--top", "0"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+        let config = build("db.json List --count --limit 5 --offset 10");
+        assert!(config.count);
+        assert_eq!((config.limit, config.offset), (Some(5), 10));
+        let args: Vec<String> = ["scs", "db.json", "List", "--limit", "0"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+        assert_eq!(config.highlight_style, HighlightStyle::Underline);
+        assert_eq!(
+            build("db.json List --highlight-style both").highlight_style,
+            HighlightStyle::Both
+        );
+
+        let files =
+            build("db.json List --path src/parser/** --exclude tests/** --include lib/**").files;
+        assert_eq!(files.include, ["src/parser/**", "lib/**"]);
+        assert_eq!(files.exclude, ["tests/**"]);
+
+        assert_eq!(
+            build("db.json --query-file rules.json").rules.as_deref(),
+            Some("rules.json")
+        );
+
+        let config = build("search --data db.json --query bool --format json --no-color");
+        assert_eq!(
+            (config.data_json.as_str(), config.query_json.as_str()),
+            ("db.json", "bool")
+        );
+        assert!(config.json);
+        assert_eq!(config.color, ColorChoice::Never);
+        assert!(!config.quiet && build("db.json List -q").quiet);
+        assert!(!config.no_pager && build("db.json List --no-pager").no_pager);
+        assert!(!config.repair_spans && build("stats db.json --repair-spans").repair_spans);
+        assert!(!config.dot && !config.cluster_files);
+        assert!(!config.quickfix && build("db.json List --format quickfix").quickfix);
+        let config = build("db.json List --format dot --cluster-files");
+        assert!(config.dot && config.cluster_files);
+        assert!(config.lengths.is_empty());
+        assert!(config.limits.is_empty());
+        let limits = build(
+            "db.json List --max-file-size 1000 --max-flows 5 --max-flow-len 9 --max-string-len 80",
+        )
+        .limits;
+        assert_eq!(limits.max_file_size, Some(1000));
+        assert_eq!(
+            (limits.max_flows, limits.max_flow_len, limits.max_string_len),
+            (Some(5), Some(9), Some(80))
+        );
+        let lengths = build("db.json List --min-len 2 --max-len 8 --len-of locs").lengths;
+        assert_eq!((lengths.locs, lengths.min, lengths.max), (true, 2, Some(8)));
+        let args: Vec<String> = ["scs", "db.json", "List", "--min-len", "3", "--max-len", "2"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+
+        assert!(matches!(build("stats db.json").command, Command::Stats));
+        assert!(matches!(
+            build("migrate db.json").command,
+            Command::Migrate { out } if out == "db.json"
+        ));
+        assert!(matches!(
+            build("graph-stats db.json").command,
+            Command::GraphStats
+        ));
+        assert!(matches!(
+            build("typevars db.json").command,
+            Command::TypeVars
+        ));
+        assert!(matches!(build("db.json --repl").command, Command::Repl));
+        assert!(matches!(build("db.json --tui").command, Command::Tui));
+        assert!(build("db.json bool --show-id").show_id);
+        assert!(build("db.json bool --cluster").cluster && !config.cluster);
+        let config = build("db.json bool --expand-cluster 2e39");
+        assert!(config.cluster);
+        assert_eq!(config.expand_cluster.as_deref(), Some("2e39"));
+        assert!(build("db.json bool --show-steps").show_steps);
+        assert_eq!(
+            build("db.json bool --format html").report,
+            Some(ReportFormat::Html)
+        );
+        assert_eq!(
+            build("db.json bool --format md").report,
+            Some(ReportFormat::Markdown)
+        );
+        assert!(matches!(
+            build("db.json --id 2e39").command,
+            Command::ShowFlow { flow } if flow == "2e39"
+        ));
+        assert!(matches!(
+            build("db.json List --trace-match #2").command,
+            Command::TraceMatch { flow } if flow == "#2"
+        ));
+        assert!(build("db.json List --verbose").verbose && !config.verbose);
+        assert_eq!(build("db.json List -vv").log_verbosity, 2);
+        assert_eq!(config.log_verbosity, 0);
+        let config = build("db.json UserInput --all --scope @Print");
+        assert!(config.all && !config.invert);
+        assert_eq!(config.scope.map(|scope| scope.len()), Some(1));
+        assert!(build("db.json List --invert").invert && build("db.json List").scope.is_none());
+        let args: Vec<String> = ["scs", "db.json", "List", "--all", "--invert"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+        let config = build("db.json List --timeout 5");
+        assert_eq!(config.timeout, Some(Duration::from_secs(5)));
+        let args: Vec<String> = ["scs", "db.json", "List", "--timeout", "0"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+        let config = build("db.json List --export matched.json");
+        assert_eq!(config.export.as_deref(), Some("matched.json"));
+        let config = build("db.json List --baseline known.json --update-baseline");
+        assert_eq!(config.baseline.as_deref(), Some("known.json"));
+        assert!(config.update_baseline && build("db.json List").baseline.is_none());
+        let args: Vec<String> = ["scs", "db.json", "List", "--update-baseline"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+
+        let config = build("join db.json string @Print");
+        assert!(matches!(
+            &config.command,
+            Command::Join { other_query_json, .. } if other_query_json == "@Print"
+        ));
+        assert_eq!(config.query_json, "string");
+
+        assert!(matches!(
+            build("stitch db.json List").command,
+            Command::Stitch {
+                max_chain: 3,
+                max_stitched: 10000
+            }
+        ));
+        assert!(matches!(
+            build("stitch db.json List --max-chain 5 --max-stitched 20").command,
+            Command::Stitch {
+                max_chain: 5,
+                max_stitched: 20
+            }
+        ));
+        let args: Vec<String> = ["scs", "stitch", "db.json", "List", "--max-stitched", "0"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+
+        assert!(matches!(
+            build("text db.json input --query List -n 3").command,
+            Command::Text { words, count: 3 } if words == "input"
+        ));
+
+        let config = build("diff old.json new.json --query List");
+        assert!(matches!(
+            &config.command,
+            Command::Diff { new_data_json } if new_data_json == "new.json"
+        ));
+        assert_eq!(config.data_json, "old.json");
+        assert_eq!(config.query_json, "List");
+        assert!(matches!(
+            build("db.json --like #2 --top 3").command,
+            Command::SimilarFlows { flow, count: 3 } if flow == "#2"
+        ));
+
+        let Command::LoadResults { set_ops, .. } =
+            build("load-results a.json --minus b.json --union c.json").command
+        else {
+            panic!("expected load-results");
+        };
+        assert_eq!(
+            set_ops,
+            vec![
+                (SetOp::Minus, "b.json".to_string()),
+                (SetOp::Union, "c.json".to_string())
+            ]
+        );
+
+        let args: Vec<String> = ["scs", "db.json"].map(String::from).to_vec();
+        assert!(Config::build(&args).is_err());
+    }
+
+    #[test]
+    fn test_config_file_precedence() {
+        let path = std::env::temp_dir().join(format!("scs-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "color = \"never\"\ncontext = 2\nformat = \"md\"\ndata = \"db.json\"\n\n[aliases]\nbools = \"bool, ..\"\n",
+        )
+        .unwrap();
+        let with = |args: &str| build(&format!("--config {} {}", path.display(), args));
+
+        let config = with("bools");
+        assert_eq!(config.data_json, "db.json");
+        assert_eq!(config.query_json, "bool, ..");
+        assert_eq!(config.color, ColorChoice::Never);
+        assert_eq!(config.context, 2);
+        assert_eq!(config.report, Some(ReportFormat::Markdown));
+        assert_eq!(with("--rules rules.toml").data_json, "db.json");
+
+        // Flags given on the command line win over the file
+        let config = with("other.json List -C 0 --format json");
+        assert_eq!(config.data_json, "other.json");
+        assert_eq!(config.query_json, "List");
+        assert_eq!(config.context, 0);
+        assert!(config.json && config.report.is_none());
+        assert_eq!(with("--color always List").color, ColorChoice::Always);
+
+        std::fs::remove_file(&path).unwrap();
+        let args: Vec<String> = ["scs", "--config", path.to_str().unwrap(), "db.json", "List"]
+            .map(String::from)
+            .to_vec();
+        assert!(Config::build(&args).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::data::{edit_distance, UnitFlow};
+use std::collections::BTreeMap;
+
+/// Flows sharing the same structure: the same sequence of step kinds and names
+pub struct Cluster<'a> {
+    /// [UnitFlow::shape] of every step of the clustered flows
+    pub shape: Vec<String>,
+    pub flows: Vec<&'a [UnitFlow]>,
+}
+
+impl Cluster<'_> {
+    /// The flow shown on behalf of the whole cluster
+    pub fn representative(&self) -> &[UnitFlow] {
+        self.flows[0]
+    }
+}
+
+/// Group flows by structure, largest clusters first. Clusters of equal size
+/// keep the order in which their first flow appeared.
+pub fn cluster_flows<'a>(flows: &[&'a [UnitFlow]]) -> Vec<Cluster<'a>> {
+    let mut order = vec![];
+    let mut groups: BTreeMap<Vec<String>, Vec<&'a [UnitFlow]>> = BTreeMap::new();
+    for flow in flows {
+        let shape: Vec<String> = flow.iter().map(UnitFlow::shape).collect();
+        let group = groups.entry(shape.clone()).or_default();
+        if group.is_empty() {
+            order.push(shape);
+        }
+        group.push(flow);
+    }
+
+    let mut clusters: Vec<Cluster> = order
+        .into_iter()
+        .map(|shape| Cluster {
+            flows: groups.remove(&shape).unwrap_or_default(),
+            shape,
+        })
+        .collect();
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.flows.len()));
+    clusters
+}
+
+/// Edits, relative to the length of the longer flow, within which
+/// [near_clusters] puts a flow in the cluster of another
+pub const NEAR_CLUSTER_DISTANCE: f64 = 0.25;
+
+/// Group flows whose structures differ by at most [NEAR_CLUSTER_DISTANCE]
+/// step edits from the first flow of a cluster, largest clusters first. Each
+/// flow joins the first cluster close enough to it, so the [Cluster::shape]
+/// is that of the [Cluster::representative].
+pub fn near_clusters<'a>(flows: &[&'a [UnitFlow]]) -> Vec<Cluster<'a>> {
+    let mut clusters: Vec<Cluster> = vec![];
+    for flow in flows {
+        let shape: Vec<String> = flow.iter().map(UnitFlow::shape).collect();
+        let near = |cluster: &&mut Cluster| {
+            let longest = shape.len().max(cluster.shape.len());
+            edit_distance(&shape, &cluster.shape) as f64 <= NEAR_CLUSTER_DISTANCE * longest as f64
+        };
+        match clusters.iter_mut().find(near) {
+            Some(cluster) => cluster.flows.push(flow),
+            None => clusters.push(Cluster {
+                shape,
+                flows: vec![flow],
+            }),
+        }
+    }
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.flows.len()));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{flow_id, ProgLoc, Type, TypeVar};
+
+    #[test]
+    fn test_cluster_flows() {
+        let step = |name: &str| UnitFlow::Type(Type::new(name, vec![]));
+        let loc = |line: &str| UnitFlow::ProgLoc(ProgLoc::new(line, (1, 2)));
+        let flows = [
+            vec![step("int"), loc("x")],
+            vec![step("bool"), loc("b")],
+            vec![step("int"), loc("y")],
+            vec![step("bool"), UnitFlow::TypeVar(TypeVar::new("'a"))],
+            vec![step("int"), loc("z")],
+        ];
+        let flows: Vec<&[UnitFlow]> = flows.iter().map(Vec::as_slice).collect();
+        let clusters = cluster_flows(&flows);
+
+        let shapes: Vec<_> = clusters.iter().map(|c| c.shape.join(" ")).collect();
+        // Largest first, then in the order their first flows appeared
+        assert_eq!(shapes, ["int ProgLoc", "bool ProgLoc", "bool TypeVar"]);
+        let ids = |flows: &[&[UnitFlow]]| flows.iter().map(|f| flow_id(f)).collect::<Vec<_>>();
+        assert_eq!(
+            ids(&clusters[0].flows),
+            ids(&[flows[0], flows[2], flows[4]])
+        );
+        assert_eq!(ids(&[clusters[0].representative()]), ids(&flows[..1]));
+        assert_eq!(ids(&clusters[1].flows), ids(&[flows[1]]));
+        assert_eq!(ids(&clusters[2].flows), ids(&[flows[3]]));
+        assert!(cluster_flows(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_near_clusters() {
+        let shapes = |s: &str| -> Vec<String> { s.split(' ').map(String::from).collect() };
+        assert_eq!(edit_distance(&shapes("a b c"), &shapes("a b c")), 0);
+        assert_eq!(edit_distance(&shapes("a b c"), &shapes("a c")), 1);
+        assert_eq!(edit_distance(&shapes("a b c"), &shapes("x b y z")), 3);
+        assert_eq!(edit_distance(&[], &shapes("a b")), 2);
+
+        let db = crate::tutorial::load_database();
+        let flows: Vec<&[UnitFlow]> = db.data_flows.iter().collect();
+        let near = near_clusters(&flows);
+        let exact = cluster_flows(&flows);
+        assert!(near.len() <= exact.len());
+        assert_eq!(
+            near.iter().map(|c| c.flows.len()).sum::<usize>(),
+            flows.len()
+        );
+        assert!(near
+            .windows(2)
+            .all(|w| w[0].flows.len() >= w[1].flows.len()));
+        for cluster in &near {
+            let shape: Vec<String> = cluster
+                .representative()
+                .iter()
+                .map(UnitFlow::shape)
+                .collect();
+            assert_eq!(cluster.shape, shape);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::data::{Database, UnitFlow};
+use std::collections::BTreeSet;
+use std::process::Command;
+
+/// A git revision range flows must have been extracted in, as with
+/// `git rev-list <since>..<until>`
+#[derive(Debug, Default)]
+pub struct CommitRange {
+    /// Exclusive lower bound
+    pub since: Option<String>,
+    /// Inclusive upper bound, `HEAD` when unset
+    pub until: Option<String>,
+}
+
+impl CommitRange {
+    pub fn is_empty(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+
+    /// Full hashes of the commits in the range, resolved by git in the
+    /// current directory
+    pub fn commits(&self) -> Result<BTreeSet<String>, String> {
+        let until = self.until.as_deref().unwrap_or("HEAD");
+        let range = match &self.since {
+            Some(since) => format!("{}..{}", since, until),
+            None => until.to_string(),
+        };
+        let output = Command::new("git")
+            .args(["rev-list", &range])
+            .output()
+            .map_err(|e| format!("Could not run git: {}", e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Could not resolve {}: {}",
+                range,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// The database restricted to the flows stamped with a commit in the
+    /// range. Unstamped flows are dropped as their origin is unknown.
+    pub fn apply(&self, db: Database) -> Result<Database, String> {
+        if self.is_empty() {
+            return Ok(db);
+        }
+        Ok(retain_commits(db, &self.commits()?))
+    }
+}
+
+/// Keep the flows whose commit, possibly abbreviated, is one of `commits`
+fn retain_commits(db: Database, commits: &BTreeSet<String>) -> Database {
+    let in_range = |stamp: &str| {
+        commits
+            .range(stamp.to_string()..)
+            .next()
+            .is_some_and(|commit| commit.starts_with(stamp))
+    };
+    let flows = db
+        .data_flows
+        .iter()
+        .filter(|flow| db.flow_commit(flow).is_some_and(in_range))
+        .map(<[UnitFlow]>::to_vec)
+        .collect();
+    db.with_flows(flows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retain_commits() {
+        let loc = |commit: &str| {
+            format!(
+                r#"{{"ProgLoc": {{"line": "x", "char_range": [1, 2], "desc": null, "depth": 0, "commit": {}}}}}"#,
+                commit
+            )
+        };
+        let data = format!(
+            r#"{{"file_path": "a.ml", "dataflow": [[{}], [{}], [{}]]}}"#,
+            loc("\"3f2a\""),
+            loc("\"9e01c4d\""),
+            loc("null")
+        );
+        let db = Database::load_from_str(&data).unwrap();
+        let commits = BTreeSet::from(["3f2a7bd0c1".to_string(), "77aa000000".to_string()]);
+
+        let db = retain_commits(db, &commits);
+        assert_eq!(db.data_flows.len(), 1);
+        assert_eq!(db.flow_commit(&db.data_flows[0]), Some("3f2a"));
+    }
+}
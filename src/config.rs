@@ -0,0 +1,165 @@
+use crate::data::Aliases;
+use crate::error::{self, SemanticSearchError};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind, IsTerminal};
+use std::path::{Path, PathBuf};
+
+/// Values `--format` accepts
+const FORMATS: &[&str] = &["text", "json", "sarif", "md", "html", "dot", "quickfix"];
+
+/// When to print with colors, from `--color` or the config file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color output to a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(name: &str) -> Option<ColorChoice> {
+        match name {
+            "auto" => Some(ColorChoice::Auto),
+            "always" => Some(ColorChoice::Always),
+            "never" => Some(ColorChoice::Never),
+            _ => None,
+        }
+    }
+
+    /// Whether to print colors to stdout, as [ColorChoice::enabled] decides
+    /// for it and the environment
+    pub fn use_color(self) -> bool {
+        self.enabled(
+            io::stdout().is_terminal(),
+            env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()),
+        )
+    }
+
+    /// Whether to print colors to a `terminal` or not, with `no_color` when
+    /// the non-empty `NO_COLOR` environment variable asks for none
+    pub fn enabled(self, terminal: bool, no_color: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// Defaults for the command line read from a TOML file, such as
+///
+/// ```toml
+/// color = "never"
+/// context = 2
+/// format = "md"
+/// data = "_build/dataflow.json"
+///
+/// [aliases]
+/// unchecked_input = "string:~input, .., @Some.1"
+/// ```
+///
+/// Flags given on the command line take precedence over the file, which
+/// takes precedence over the built-in defaults.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// When to print with colors, unless `--color` or `--no-color` is given
+    pub color: Option<ColorChoice>,
+    /// Lines of source shown around each location, unless `--context` is
+    /// given
+    pub context: Option<usize>,
+    /// Columns between tab stops in source lines, unless `--tab-width` is
+    /// given
+    pub tab_width: Option<usize>,
+    /// Output format, unless `--format` or `--json` is given
+    pub format: Option<String>,
+    /// Database searched when only a query is given
+    pub data: Option<String>,
+    /// Queries by name, usable as steps of the queries given on the command
+    /// line
+    pub aliases: Aliases,
+}
+
+impl ConfigFile {
+    /// `semantic-code-search.toml` in `$XDG_CONFIG_HOME`, or else in
+    /// `.config` in the home directory
+    pub fn default_path() -> PathBuf {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_default()
+            .join("semantic-code-search.toml")
+    }
+
+    /// Load the file at `path`, or at [ConfigFile::default_path] when none
+    /// is given. Only the default file may be missing, which leaves every
+    /// default unset.
+    pub fn load(path: Option<&Path>) -> error::Result<ConfigFile> {
+        let (path, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (Self::default_path(), false),
+        };
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound && !explicit => {
+                return Ok(ConfigFile::default())
+            }
+            Err(e) => return Err(SemanticSearchError::io(path.display(), e)),
+        };
+        Self::parse(&data).map_err(|message| SemanticSearchError::Index {
+            path: path.display().to_string(),
+            message,
+        })
+    }
+
+    pub fn parse(data: &str) -> Result<ConfigFile, String> {
+        let file: ConfigFile = toml::from_str(data).map_err(|e| e.to_string())?;
+        if let Some(format) = &file.format {
+            if !FORMATS.contains(&format.as_str()) {
+                return Err(format!(
+                    "Invalid format `{}`, expected one of {}",
+                    format,
+                    FORMATS.join(", ")
+                ));
+            }
+        }
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let file = ConfigFile::parse(
+            "color = \"never\"\ncontext = 2\nformat = \"md\"\n\n[aliases]\nlists = \"List, @Cons.1\"\n",
+        )
+        .unwrap();
+        assert_eq!(file.color, Some(ColorChoice::Never));
+        assert_eq!(file.context, Some(2));
+        assert_eq!(file.format.as_deref(), Some("md"));
+        assert_eq!(file.data, None);
+        assert_eq!(file.aliases["lists"], "List, @Cons.1");
+
+        assert!(ConfigFile::parse("format = \"xml\"").is_err());
+        assert!(ConfigFile::parse("colour = true").is_err());
+        assert!(ConfigFile::load(Some(Path::new("/nonexistent/scs.toml"))).is_err());
+    }
+
+    #[test]
+    fn test_color_choice() {
+        assert!(ColorChoice::Auto.enabled(true, false));
+        assert!(!ColorChoice::Auto.enabled(false, false));
+        assert!(!ColorChoice::Auto.enabled(true, true));
+        assert!(ColorChoice::Always.enabled(false, true));
+        assert!(!ColorChoice::Never.enabled(true, false));
+        assert_eq!(ColorChoice::parse("never"), Some(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::data::{Database, Language, QueryOps, Subtypes, UnitFlow};
+use crate::notes::Notes;
+use crate::SearchEngine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Largest message accepted, to bound the buffer a corrupt length prefix
+/// would allocate
+const MAX_MESSAGE_LEN: usize = 256 * 1024 * 1024;
+
+/// A search sent to the daemon
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub data_json: String,
+    pub query: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    /// The matched flows, with the `file_path`, `language` and subtypes of
+    /// their database
+    Matches {
+        file_path: String,
+        #[serde(default)]
+        language: Language,
+        #[serde(default)]
+        subtypes: Subtypes,
+        flows: Vec<Vec<UnitFlow>>,
+    },
+    Error(String),
+}
+
+/// `$XDG_RUNTIME_DIR/scs.sock`, or `scs.sock` in an `scs-$USER` directory of
+/// the temporary directory, which [bind] keeps private to the user
+pub fn default_socket_path() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(|dir| PathBuf::from(dir).join("scs.sock"))
+        .unwrap_or_else(temp_socket_path)
+}
+
+/// `scs.sock` in the current user's own directory of the temporary
+/// directory, as other users can create files in the temporary directory
+/// itself
+fn temp_socket_path() -> PathBuf {
+    let user = env::var("USER")
+        .or_else(|_| env::var("LOGNAME"))
+        .unwrap_or_default();
+    env::temp_dir()
+        .join(format!("scs-{}", user))
+        .join("scs.sock")
+}
+
+/// Write `message` as a big-endian `u32` byte length followed by its JSON
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    let data = serde_json::to_vec(message)?;
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message too large"))?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+/// Read a message written by [write_message]
+pub fn read_message<T: DeserializeOwned>(reader: &mut impl Read) -> io::Result<T> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Message of {} bytes exceeds the limit", len),
+        ));
+    }
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Modification times of a database file and its notes sidecar
+type Stamp = (SystemTime, Option<SystemTime>);
+
+/// Answers searches from databases kept loaded between requests, with the
+/// results of repeated queries cached. A database is reloaded when its file
+/// or notes change.
+#[derive(Default)]
+pub struct Daemon {
+    databases: HashMap<String, (Stamp, SearchEngine)>,
+}
+
+impl Daemon {
+    pub fn answer(&mut self, request: &Request) -> Response {
+        let query = match QueryOps::parse_query(&request.query) {
+            Ok(query) => query,
+            Err(e) => return Response::Error(e.to_string()),
+        };
+        let engine = match self.engine(&request.data_json) {
+            Ok(engine) => engine,
+            Err(e) => return Response::Error(e),
+        };
+        let db = engine.database();
+        Response::Matches {
+            file_path: db.file_path.clone(),
+            language: db.language,
+            subtypes: db.subtypes().clone(),
+            flows: engine
+                .matching_indices(&query)
+                .into_iter()
+                .map(|idx| db.data_flows[idx].to_vec())
+                .collect(),
+        }
+    }
+
+    /// The database at `data_json`, loaded on first use and reloaded when
+    /// it or its notes changed since
+    pub fn database(&mut self, data_json: &str) -> Result<&Database, String> {
+        self.engine(data_json).map(SearchEngine::database)
+    }
+
+    /// A search engine over the database at `data_json`, opened on first
+    /// use and again, with an empty cache, when the database or its notes
+    /// changed since
+    pub fn engine(&mut self, data_json: &str) -> Result<&SearchEngine, String> {
+        let stamp = (
+            fs::metadata(data_json)
+                .and_then(|m| m.modified())
+                .map_err(|e| format!("Could not read {}: {}", data_json, e))?,
+            fs::metadata(Notes::sidecar_path(data_json))
+                .and_then(|m| m.modified())
+                .ok(),
+        );
+        let stale = self
+            .databases
+            .get(data_json)
+            .is_none_or(|(loaded, _)| *loaded != stamp);
+        if stale {
+            tracing::info!(data_json, "loading database");
+            let mut db =
+                Database::read_data(data_json).and_then(|data| Database::load_from_str(&data))?;
+            db.set_notes(Notes::load(data_json)?.notes);
+            self.databases
+                .insert(data_json.to_string(), (stamp, SearchEngine::new(db)));
+        }
+        Ok(&self.databases[data_json].1)
+    }
+
+    /// Answer requests on the socket at `path` until the process is killed,
+    /// one connection at a time. A connection may send several requests.
+    #[cfg(unix)]
+    pub fn serve(&mut self, path: &Path) -> io::Result<()> {
+        let listener = bind(path)?;
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                // Such as a client hanging up before it was accepted
+                Err(e) => {
+                    tracing::warn!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            while let Ok(request) = read_message::<Request>(&mut stream) {
+                if write_message(&mut stream, &self.answer(&request)).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Listen on the socket at `path`, replacing one left behind by a server
+/// that is no longer running. Only the user may connect to the socket, and
+/// the directory of the [default](default_socket_path) one in the temporary
+/// directory is created, or must already be, accessible to them alone.
+#[cfg(unix)]
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path == temp_socket_path() {
+        if let Some(dir) = path.parent() {
+            private_dir(dir)?;
+        }
+    }
+    // A stale socket would make bind fail
+    if path.exists() && UnixStream::connect(path).is_err() {
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Create `dir` with mode 0700, or check that the one already there is a
+/// directory only its owner may use. A directory of another user's with
+/// such a mode cannot be bound in either.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> io::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+        created => return created,
+    }
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory private to the user; remove it or pass --socket",
+                dir.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Send a single request to the daemon listening at `path`
+#[cfg(unix)]
+pub fn query(path: &Path, request: &Request) -> Result<Response, String> {
+    let mut stream = UnixStream::connect(path)
+        .map_err(|e| format!("Could not connect to daemon at {}: {}", path.display(), e))?;
+    write_message(&mut stream, request).map_err(|e| e.to_string())?;
+    read_message(&mut stream).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol() {
+        let request = Request {
+            data_json: concat!(env!("CARGO_MANIFEST_DIR"), "/data/tutorial.json").to_string(),
+            query: "List".to_string(),
+        };
+        let mut buffer = vec![];
+        write_message(&mut buffer, &request).unwrap();
+        assert_eq!(
+            u32::from_be_bytes(buffer[..4].try_into().unwrap()) as usize,
+            buffer.len() - 4
+        );
+        assert_eq!(
+            read_message::<Request>(&mut buffer.as_slice()).unwrap(),
+            request
+        );
+
+        let mut daemon = Daemon::default();
+        match daemon.answer(&request) {
+            Response::Matches { flows, .. } => assert_eq!(flows.len(), 2),
+            Response::Error(e) => panic!("{}", e),
+        }
+        assert!(matches!(
+            daemon.answer(&Request {
+                data_json: request.data_json.clone(),
+                query: "@x.1.2".to_string(),
+            }),
+            Response::Error(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_socket() {
+        let dir = env::temp_dir().join(format!("scs-daemon-{}", std::process::id()));
+        private_dir(&dir).unwrap();
+        private_dir(&dir).unwrap();
+        assert_eq!(
+            fs::metadata(&dir).unwrap().permissions().mode() & 0o777,
+            0o700
+        );
+
+        let path = dir.join("scs.sock");
+        let _listener = bind(&path).unwrap();
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_dir(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
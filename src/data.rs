@@ -1,50 +1,319 @@
+use crate::error::{self, SemanticSearchError};
 use colored::*;
+use rayon::prelude::*;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
 use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use unicode_width::UnicodeWidthChar;
+
+mod access;
+mod alias;
+mod arena;
+mod capture;
+mod compress;
+mod diff;
+mod dupes;
+mod edges;
+mod explain;
+mod fuzzy;
+mod generics;
+mod graph;
+mod hierarchy;
+mod index;
+mod intern;
+mod join;
+mod lang;
+mod length;
+mod limits;
+mod locfix;
+mod memory;
+mod merge;
+mod other;
+mod parser;
+mod prefilter;
+mod redact;
+mod relax;
+mod repeat;
+mod schema;
+mod similar;
+mod steps;
+mod stitch;
+mod stream;
+mod suggest;
+mod summary;
+mod synthetic;
+mod text;
+mod trace;
+mod unsat;
+mod update;
+mod version;
+pub use alias::Aliases;
+pub use arena::Flows;
+pub use diff::{ChangedIds, MatchDiff, MatchDiffIds, QueryDiff};
+pub use dupes::DupeGroup;
+pub use edges::FlowGraph;
+use fuzzy::desc_matches;
+pub use join::FlowPair;
+pub use length::LengthBounds;
+pub use limits::{load_limits, set_load_limits, with_limits, LoadLimits};
+pub use fuzzy::{fuzzy_score, FUZZY_DESC_THRESHOLD};
+pub use graph::{Connection, GraphStats, NodeKind, NodeStats, TypeGraph};
+pub use hierarchy::Subtypes;
+pub use intern::{interning, Name, Str, Symbol};
+pub use lang::{Language, TypePrinter};
+pub use locfix::LocFix;
+pub use memory::MemoryUsage;
+pub use merge::data_json_paths;
+pub(crate) use parser::is_identifier;
+pub use redact::{RedactField, RedactMode, Redaction};
+pub use relax::Relaxation;
+pub use similar::Similarity;
+pub use steps::STEP_ARROW;
+pub use stitch::{Link, StitchedFlow, Stitches};
+pub use stream::FlowStream;
+pub use suggest::{edit_distance, Suggestion};
+pub use summary::{DatabaseStats, LocCoverage};
+pub use text::TextMatch;
+pub use trace::{MatchTrace, StepOutcome, TraceStep};
+pub use unsat::Contradiction;
+pub use version::{schema_version, Migration, SCHEMA_VERSION};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Database {
-    pub data_flows: Vec<DataFlow>,
+    pub data_flows: Flows,
     pub file_path: String,
+    /// Language of the program the flows were extracted from
+    #[serde(default)]
+    pub language: Language,
     types: BTreeMap<String, Type>,
     type_vars: BTreeSet<String>,
+    /// Indices of the flows mentioning each type
+    type_flows: BTreeMap<String, Vec<usize>>,
+    /// Indices of the flows through each type variable
+    type_var_flows: BTreeMap<String, Vec<usize>>,
+    /// Indices of the flows mentioning each constructor
+    constructor_flows: BTreeMap<String, Vec<usize>>,
+    /// Indices of the flows whose descriptions use each lowercased word,
+    /// repeated for each use
+    desc_words: BTreeMap<String, Vec<usize>>,
+    /// Links between the steps of flows, by [flow_id], for the flows whose
+    /// steps do not simply each feed into the next
+    edges: BTreeMap<String, Vec<(usize, usize)>>,
+    /// Times each flow was seen, by [flow_id], for the flows
+    /// [Database::deduplicated] merged copies of
+    counts: BTreeMap<String, usize>,
+    /// Types declared to stand for others, by the name of the supertype
+    subtypes: Subtypes,
+    /// Notes attached to flows, by [flow_id]
+    #[serde(skip)]
+    notes: BTreeMap<String, Vec<String>>,
+    /// Match names and descriptions ignoring case
+    #[serde(skip)]
+    ignore_case: bool,
+    /// Match type steps by their own names only, not those of supertypes
+    #[serde(skip)]
+    no_hierarchy: bool,
+    /// Problems found loading the database that did not stop it loading
+    #[serde(skip)]
+    warnings: Vec<String>,
+    /// Time spent building the inverted maps when the flows were indexed
+    #[serde(skip)]
+    index_time: Duration,
 }
 
 impl Database {
-    pub fn load_from_json(path: &str) -> Self {
-        #[derive(Deserialize)]
-        struct Wrapper {
-            file_path: String,
-            dataflow: Vec<Vec<UnitFlow>>,
-        }
+    /// Load the data JSON file at `path` like
+    /// [Database::load_with_duplicates], keeping one copy of each flow
+    /// with [Database::deduplicated]
+    pub fn load_from_json(path: &str) -> error::Result<Self> {
+        Ok(Self::load_with_duplicates(path)?.deduplicated())
+    }
 
-        let data = fs::read_to_string(path).expect("Could not read file");
-        let parsed: Wrapper = serde_json::from_str(&data).expect("JSON parse error");
+    /// Load the data JSON file at `path`, or its binary index when that is
+    /// up to date, decompressing gzip or Zstandard files. A directory of data
+    /// JSON files, or several joined like `PATH`, are loaded with
+    /// [Database::load_from_many], and line-delimited databases, however
+    /// named, flow by flow with [FlowStream].
+    pub fn load_with_duplicates(path: &str) -> error::Result<Self> {
+        Self::load_file(path, false)
+    }
 
-        let mut type_map: BTreeMap<String, Type> = BTreeMap::new();
-        let mut type_vars = BTreeSet::new();
+    /// Like [Database::load_with_duplicates], but drop the malformed flows
+    /// of a data JSON or line-delimited file rather than failing, with a
+    /// [warning](Database::warnings) counting them
+    pub fn load_lenient(path: &str) -> error::Result<Self> {
+        Self::load_file(path, true)
+    }
 
-        for flow in &parsed.dataflow {
-            for uf in flow {
-                match uf {
-                    UnitFlow::Type(t) => {
-                        type_map.insert(t.name.clone(), t.clone());
-                    }
-                    UnitFlow::TypeVar(tv) => {
-                        type_vars.insert(tv.name.clone());
-                    }
-                    _ => {}
-                }
+    fn load_file(path: &str, lenient: bool) -> error::Result<Self> {
+        let _span = tracing::info_span!("load", path).entered();
+        // Names and source lines repeat across flows, so equal ones are read
+        // into shared strings
+        let db = interning(|| {
+            if !Path::new(path).is_file() {
+                return Self::load_from_many(&data_json_paths(path)?);
             }
+            if let Some(db) = Self::load_fresh_index(path) {
+                return Ok(db);
+            }
+            if Self::is_ndjson(path) {
+                return Self::stream_flows(path)?.collect_database(lenient);
+            }
+            let data = Self::read_data(path)?;
+            if stream::looks_like_ndjson(&data) {
+                return FlowStream::new(data.as_bytes(), path)?.collect_database(lenient);
+            }
+            if lenient {
+                Self::parse_lenient(&data, Some(path))
+            } else {
+                Self::parse(&data, Some(path))
+            }
+        })?;
+        tracing::info!(flows = db.data_flows.len(), "loaded database");
+        Ok(db)
+    }
+
+    pub fn load_from_str(data: &str) -> error::Result<Self> {
+        interning(|| Self::parse(data, None))
+    }
+
+    /// Parse a database read from the file at `path`, if any, pointing out
+    /// the first malformed step of a database that is valid JSON
+    fn parse(data: &str, path: Option<&str>) -> error::Result<Self> {
+        let parsed: DataJson<Flows> = serde_json::from_str(data).map_err(|e| {
+            limits::exceeded(path)
+                .or_else(|| schema::first_problem(data, path))
+                .unwrap_or_else(|| SemanticSearchError::json(path, e))
+        })?;
+        version::check(parsed.version, path)?;
+        Ok(Self::from_data_json(parsed))
+    }
+
+    fn from_data_json(parsed: DataJson<Flows>) -> Self {
+        let mut db = Self::from_flows(parsed.file_path, parsed.dataflow);
+        db.language = parsed.language;
+        db.subtypes = parsed.subtypes;
+        db.edges = db
+            .data_flows
+            .iter()
+            .zip(parsed.edges)
+            .filter(|(_, edges)| !edges.is_empty())
+            .map(|(flow, edges)| (flow_id(flow), edges))
+            .collect();
+        db
+    }
+
+    /// Build a database from flows, indexing the types, type variables and
+    /// constructors they mention.
+    pub fn new(file_path: String, data_flows: Vec<Vec<UnitFlow>>) -> Self {
+        Self::from_flows(file_path, data_flows.into())
+    }
+
+    /// [Database::new] from flows already in an arena
+    pub fn from_flows(file_path: String, data_flows: Flows) -> Self {
+        let mut db = Database {
+            data_flows,
+            file_path,
+            language: Language::default(),
+            types: BTreeMap::new(),
+            type_vars: BTreeSet::new(),
+            type_flows: BTreeMap::new(),
+            type_var_flows: BTreeMap::new(),
+            constructor_flows: BTreeMap::new(),
+            desc_words: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            counts: BTreeMap::new(),
+            subtypes: Subtypes::new(),
+            notes: BTreeMap::new(),
+            ignore_case: false,
+            no_hierarchy: false,
+            warnings: vec![],
+            index_time: Duration::ZERO,
+        };
+        // wasm32 has no clock, and asking it for the time panics
+        #[cfg(not(target_arch = "wasm32"))]
+        let start = std::time::Instant::now();
+        for idx in 0..db.data_flows.len() {
+            db.index_flow(idx);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            db.index_time = start.elapsed();
         }
+        db
+    }
+
+    /// A database of `data_flows` with the file path and language of this one
+    pub fn with_flows(&self, data_flows: Vec<Vec<UnitFlow>>) -> Self {
+        let mut db = Self::new(self.file_path.clone(), data_flows);
+        db.language = self.language;
+        db.ignore_case = self.ignore_case;
+        db.subtypes = self.subtypes.clone();
+        db.no_hierarchy = self.no_hierarchy;
+        db.warnings = self.warnings.clone();
+        db.edges = db
+            .data_flows
+            .iter()
+            .filter_map(|flow| {
+                let id = flow_id(flow);
+                let edges = self.edges.get(&id)?.clone();
+                Some((id, edges))
+            })
+            .collect();
+        db.counts = db
+            .data_flows
+            .iter()
+            .filter_map(|flow| {
+                let id = flow_id(flow);
+                let count = *self.counts.get(&id)?;
+                Some((id, count))
+            })
+            .collect();
+        db
+    }
 
-        Database {
-            data_flows: parsed.dataflow,
-            file_path: parsed.file_path,
-            types: type_map,
-            type_vars,
+    /// Serialize in the format read by [Database::load_from_str]
+    pub fn to_json(&self) -> Result<String, String> {
+        #[derive(Serialize)]
+        struct Wrapper<'a> {
+            version: u32,
+            file_path: &'a str,
+            language: Language,
+            #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+            subtypes: &'a Subtypes,
+            dataflow: &'a Flows,
+            ids: Vec<String>,
+            #[serde(skip_serializing_if = "Vec::is_empty")]
+            edges: Vec<&'a [(usize, usize)]>,
         }
+
+        let edges = if self.edges.is_empty() {
+            vec![]
+        } else {
+            self.data_flows
+                .iter()
+                .map(|flow| {
+                    self.edges
+                        .get(&flow_id(flow))
+                        .map_or(&[][..], Vec::as_slice)
+                })
+                .collect()
+        };
+        serde_json::to_string_pretty(&Wrapper {
+            version: SCHEMA_VERSION,
+            file_path: &self.file_path,
+            language: self.language,
+            subtypes: &self.subtypes,
+            dataflow: &self.data_flows,
+            ids: self.data_flows.iter().map(flow_id).collect(),
+            edges,
+        })
+        .map_err(|e| e.to_string())
     }
 
     pub fn match_unit_flow(&self, uf: &UnitFlow, query: &QueryOps) -> bool {
@@ -52,256 +321,1639 @@ impl Database {
             (UnitFlow::TypeVar(tv), QueryOps::QTypeVar(count)) => {
                 self.count_typevar_flows(&tv.name) == *count
             }
-            (UnitFlow::Type(t), QueryOps::QType(q)) => t.name == q.name,
+            (UnitFlow::TypeVar(tv), QueryOps::QTypeVarRange { min, max }) => {
+                let count = self.count_typevar_flows(&tv.name);
+                count >= *min && max.is_none_or(|max| count <= max)
+            }
+            (UnitFlow::TypeVar(tv), QueryOps::QTypeVarName { name, min, max }) => {
+                let count = self.count_typevar_flows(&tv.name);
+                name.matches(&tv.name, self.ignore_case)
+                    && count >= *min
+                    && max.is_none_or(|max| count <= max)
+            }
+            (UnitFlow::Type(t), QueryOps::QType(q)) => {
+                self.type_name_matches(&q.name, &t.name)
+                    && q.matches_args(t, self.ignore_case)
+                    && uf.matches_desc(q.desc.as_deref(), self.ignore_case)
+            }
             (UnitFlow::ConstructorArg(c), QueryOps::QConstructorArg(q)) => {
-                c.name == q.name && q.arg_index.map_or(true, |idx| c.arg_index == idx)
+                q.name.matches(&c.name, self.ignore_case)
+                    && q.arg_index
+                        .as_ref()
+                        .is_none_or(|idx| idx.contains(c.arg_index))
+                    && uf.matches_desc(q.desc.as_deref(), self.ignore_case)
             }
-            (_, QueryOps::QDesc(d)) => match uf {
-                UnitFlow::Type(t) => t.desc.as_deref() == Some(d),
-                UnitFlow::ConstructorArg(c) => c.desc.as_deref() == Some(d),
-                UnitFlow::TypeVar(tv) => tv.desc.as_deref() == Some(d),
-                UnitFlow::ProgLoc(p) => p.desc.as_deref() == Some(d),
-            },
+            (_, QueryOps::QNot(inner)) => !self.match_unit_flow(uf, inner),
+            (_, QueryOps::QBind(_, inner)) => self.match_unit_flow(uf, inner),
+            (_, QueryOps::QAt(_, inner)) => self.match_unit_flow(uf, inner),
+            (_, QueryOps::QRepeat { op, .. }) => self.match_unit_flow(uf, op),
+            (_, QueryOps::QDesc(d)) => uf.matches_desc(Some(d), self.ignore_case),
+            (_, QueryOps::QKind(k)) => pattern_matches(k, uf.kind(), self.ignore_case),
             _ => false,
         }
     }
 
-    pub fn match_flow(&self, flow: &[UnitFlow], query: &[QueryOps]) -> bool {
-        match (flow, query) {
-            (f, [next_query, rest @ ..]) => {
-                // Try each position until we find a match for the next query item
-                for (idx, unit_flow) in f.iter().enumerate() {
-                    if self.match_unit_flow(unit_flow, next_query) {
-                        // Found a match for the item after wildcard, try to match the rest
-                        if self.match_flow(&f[idx + 1..], rest) {
-                            return true;
+    /// Whether `flow` carries every note queried with `note:` and passes
+    /// through the other query steps in order. Negated steps reject flows
+    /// with any matching step, or with a matching step at that position once
+    /// the query has anchors or gaps.
+    pub fn match_flow(&self, flow: &[UnitFlow], query: &[QueryOps]) -> bool {
+        self.match_flow_positions(flow, query).is_some()
+    }
+
+    /// Like [Database::match_flow], but returns the indices of the steps of
+    /// `flow` that satisfied the query, in query order. Notes and negations
+    /// checked against the whole flow contribute no position. Queries joined
+    /// with `&&` give the positions of each in turn.
+    pub fn match_flow_positions(
+        &self,
+        flow: &[UnitFlow],
+        query: &[QueryOps],
+    ) -> Option<Vec<usize>> {
+        if let [QueryOps::QAll(queries)] = query {
+            let mut positions = vec![];
+            for query in queries {
+                positions.extend(self.match_flow_positions(flow, query)?);
+            }
+            return Some(positions);
+        }
+        let positional = query.iter().any(QueryOps::is_structural);
+        if !query.iter().any(|op| op.is_flow_level(positional)) {
+            return self.match_steps(flow, query);
+        }
+
+        let mut steps = vec![];
+        for op in query {
+            if op.is_flow_level(positional) {
+                if !self.flow_contains(flow, op) {
+                    return None;
+                }
+            } else {
+                steps.push(op.clone());
+            }
+        }
+        self.match_steps(flow, &steps)
+    }
+
+    /// Whether a note or some step of `flow` matches `op`
+    fn flow_contains(&self, flow: &[UnitFlow], op: &QueryOps) -> bool {
+        match op {
+            QueryOps::QNote(text) => {
+                let text = text.to_lowercase();
+                self.notes_for(flow)
+                    .iter()
+                    .any(|note| note.to_lowercase().contains(&text))
+            }
+            QueryOps::QLen(bounds) => bounds.contains(flow),
+            QueryOps::QNot(inner) => !self.flow_contains(flow, inner),
+            QueryOps::QAt(idx, inner) => flow
+                .get(*idx)
+                .is_some_and(|uf| self.match_unit_flow(uf, inner)),
+            QueryOps::QAll(queries) => queries.iter().all(|query| self.match_flow(flow, query)),
+            QueryOps::QEdge { .. } => !self
+                .edge_starts(flow, &self.flow_graph(flow), op)
+                .is_empty(),
+            op => flow.iter().any(|uf| self.match_unit_flow(uf, op)),
+        }
+    }
+
+    /// Positions of the steps of `flow` matching the steps of `query` in
+    /// order. Without anchors or gaps, any number of flow steps may precede
+    /// each query step. Otherwise query steps must be adjacent unless a `..`
+    /// gap separates them, and the match may only start later in the flow or
+    /// end early unless anchored by `^` or `$`.
+    fn match_steps(&self, flow: &[UnitFlow], query: &[QueryOps]) -> Option<Vec<usize>> {
+        let mut positions = vec![];
+        let matched = if query.iter().any(QueryOps::is_structural) {
+            (0..=flow.len()).any(|start| self.match_at(flow, start, query, false, &mut positions))
+        } else {
+            self.match_at(flow, 0, query, true, &mut positions)
+        };
+        matched.then_some(positions)
+    }
+
+    fn match_at(
+        &self,
+        flow: &[UnitFlow],
+        idx: usize,
+        query: &[QueryOps],
+        gaps: bool,
+        positions: &mut Vec<usize>,
+    ) -> bool {
+        match query {
+            [] => true,
+            [QueryOps::QGap, rest @ ..] => {
+                (idx..=flow.len()).any(|next| self.match_at(flow, next, rest, gaps, positions))
+            }
+            [QueryOps::QStart, rest @ ..] => {
+                idx == 0 && self.match_at(flow, idx, rest, gaps, positions)
+            }
+            [QueryOps::QEnd, rest @ ..] => {
+                idx == flow.len() && self.match_at(flow, idx, rest, gaps, positions)
+            }
+            [QueryOps::QRepeat { op, min, max }, rest @ ..] => {
+                self.match_repeat(flow, idx, op, (*min, *max), rest, gaps, positions)
+            }
+            [op, rest @ ..] if gaps => {
+                (idx..flow.len()).any(|next| self.match_step(flow, next, op, rest, gaps, positions))
+            }
+            [op, rest @ ..] => {
+                idx < flow.len() && self.match_step(flow, idx, op, rest, gaps, positions)
+            }
+        }
+    }
+
+    /// Match `op` at `idx` and the rest of the query after it, recording
+    /// `idx` in `positions` unless the match fails
+    fn match_step(
+        &self,
+        flow: &[UnitFlow],
+        idx: usize,
+        op: &QueryOps,
+        rest: &[QueryOps],
+        gaps: bool,
+        positions: &mut Vec<usize>,
+    ) -> bool {
+        if !self.match_unit_flow(&flow[idx], op) {
+            return false;
+        }
+        positions.push(idx);
+        if self.match_at(flow, idx + 1, rest, gaps, positions) {
+            return true;
+        }
+        positions.pop();
+        false
+    }
+
+    pub fn set_notes(&mut self, notes: BTreeMap<String, Vec<String>>) {
+        self.notes = notes;
+    }
+
+    /// Match type names, constructor names and descriptions ignoring case
+    pub fn set_ignore_case(&mut self, ignore_case: bool) {
+        self.ignore_case = ignore_case;
+    }
+
+    /// Problems found loading the database that did not stop it loading,
+    /// like the incomplete last flow of a line-delimited database
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Time [Database::new] spent indexing the types, type variables and
+    /// constructors of the flows; zero for databases read from an index
+    pub fn index_time(&self) -> Duration {
+        self.index_time
+    }
+
+    /// Notes attached to `flow`, oldest first
+    pub fn notes_for(&self, flow: &[UnitFlow]) -> &[String] {
+        if self.notes.is_empty() {
+            return &[];
+        }
+        self.notes.get(&flow_id(flow)).map_or(&[], Vec::as_slice)
+    }
+
+    /// Index of the flow whose [flow_id] starts with `prefix`, if exactly one
+    /// does. `#N` names the flow at index N in database order instead.
+    pub fn find_flow(&self, prefix: &str) -> Option<usize> {
+        if let Some(idx) = prefix.strip_prefix('#') {
+            return idx.parse().ok().filter(|idx| *idx < self.data_flows.len());
+        }
+        let mut found = self
+            .data_flows
+            .iter()
+            .enumerate()
+            .filter(|(_, flow)| flow_id(flow).starts_with(prefix))
+            .map(|(idx, _)| idx);
+        match (found.next(), found.next()) {
+            (Some(idx), None) => Some(idx),
+            _ => None,
+        }
+    }
+
+    /// The flows with the given [flow_id]s, in the order given. Ids not
+    /// present in the database are skipped.
+    pub fn flows_by_id(&self, ids: &[String]) -> Vec<&DataFlow> {
+        let mut by_id: Vec<(String, &DataFlow)> = self
+            .data_flows
+            .iter()
+            .map(|flow| (flow_id(flow), flow))
+            .collect();
+        by_id.sort_by(|a, b| a.0.cmp(&b.0));
+
+        ids.iter()
+            .filter_map(|id| {
+                by_id
+                    .binary_search_by(|(flow_id, _)| flow_id.cmp(id))
+                    .ok()
+                    .map(|idx| by_id[idx].1)
+            })
+            .collect()
+    }
+
+    /// The flows matched by `query`, in database order. Only the
+    /// [Database::candidates] of queries with an indexed step are matched;
+    /// otherwise databases of at least [PARALLEL_SEARCH_FLOWS] flows are
+    /// searched on all cores.
+    pub fn search(&self, query: &[QueryOps]) -> Vec<&DataFlow> {
+        let _span = tracing::debug_span!("search", steps = query.len()).entered();
+        let matched: Vec<&DataFlow> = if let Some(candidates) = self.candidates(query) {
+            tracing::debug!(candidates = candidates.len(), "prefiltered flows");
+            candidates
+                .into_iter()
+                .map(|idx| &self.data_flows[idx])
+                .filter(|flow| self.match_flow(flow, query))
+                .collect()
+        } else if self.data_flows.len() < PARALLEL_SEARCH_FLOWS {
+            self.data_flows
+                .iter()
+                .filter(|flow| self.match_flow(flow, query))
+                .collect()
+        } else {
+            self.data_flows
+                .par_iter()
+                .filter(|flow| self.match_flow(flow, query))
+                .collect()
+        };
+        tracing::debug!(matched = matched.len(), "searched flows");
+        matched
+    }
+
+    /// The flows matched by `query` in database order, skipping the first
+    /// `offset` and stopping the search after `limit` more, if given
+    pub fn search_page(
+        &self,
+        query: &[QueryOps],
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Vec<&DataFlow> {
+        self.candidate_flows(query)
+            .filter(|(_, flow)| self.match_flow(flow, query))
+            .map(|(_, flow)| flow)
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    /// Number of flows matched by `query`, counted without collecting them.
+    /// Queries without an indexed step on databases of at least
+    /// [PARALLEL_SEARCH_FLOWS] flows are searched on all cores.
+    pub fn count_matches(&self, query: &[QueryOps]) -> usize {
+        if let Some(candidates) = self.candidates(query) {
+            return candidates
+                .into_iter()
+                .filter(|&idx| self.match_flow(&self.data_flows[idx], query))
+                .count();
+        }
+        if self.data_flows.len() < PARALLEL_SEARCH_FLOWS {
+            return self
+                .data_flows
+                .iter()
+                .filter(|flow| self.match_flow(flow, query))
+                .count();
+        }
+        self.data_flows
+            .par_iter()
+            .filter(|flow| self.match_flow(flow, query))
+            .count()
+    }
+
+    /// The flows not matched by `query`, in database order, as `grep -v`
+    /// would list them. With a `scope`, only the flows it matches are
+    /// considered, so that checking none are left asserts that every flow
+    /// matched by `scope` is also matched by `query`.
+    pub fn search_inverted(
+        &self,
+        query: &[QueryOps],
+        scope: Option<&[QueryOps]>,
+    ) -> Vec<&DataFlow> {
+        let scoped = |flow: &&DataFlow| scope.is_none_or(|scope| self.match_flow(flow, scope));
+        self.data_flows
+            .iter()
+            .filter(scoped)
+            .filter(|flow| !self.match_flow(flow, query))
+            .collect()
+    }
+
+    /// Indices of the flows matched by `query`
+    pub fn matching_indices(&self, query: &[QueryOps]) -> Vec<usize> {
+        self.candidate_flows(query)
+            .filter(|(_, flow)| self.match_flow(flow, query))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// The indices in `flows` of the flows matched by `query`, in the order
+    /// given, for searching within earlier results rather than the whole
+    /// database
+    pub fn matching_indices_in(&self, flows: &[usize], query: &[QueryOps]) -> Vec<usize> {
+        let candidates: Option<BTreeSet<usize>> =
+            self.candidates(query).map(|c| c.into_iter().collect());
+        flows
+            .iter()
+            .filter(|idx| candidates.as_ref().is_none_or(|c| c.contains(idx)))
+            .filter(|idx| self.match_flow(&self.data_flows[**idx], query))
+            .copied()
+            .collect()
+    }
+
+    /// Length of the longest prefix of `query` matching any flow, with the
+    /// number of flows it matches
+    pub fn longest_matching_prefix(&self, query: &[QueryOps]) -> (usize, usize) {
+        let mut longest = (0, self.data_flows.len());
+        for len in 1..=query.len() {
+            match self.matching_indices(&query[..len]).len() {
+                0 => break,
+                count => longest = (len, count),
+            }
+        }
+        longest
+    }
+
+    /// Number of flows through the type variable `typevar_name`, looked up
+    /// in the map of flows indexed at load time
+    pub fn count_typevar_flows(&self, typevar_name: &str) -> usize {
+        self.type_var_flows.get(typevar_name).map_or(0, Vec::len)
+    }
+
+    /// Each type variable with its in-degree, the number of flows through
+    /// it, by name
+    pub fn typevar_degrees(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.type_var_flows
+            .iter()
+            .map(|(name, flows)| (name.as_str(), flows.len()))
+    }
+
+    /// Indices of the flows mentioning the type `name`
+    pub fn flows_with_type(&self, name: &str) -> &[usize] {
+        self.type_flows.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Indices of the flows through the type variable `name`
+    pub fn flows_with_type_var(&self, name: &str) -> &[usize] {
+        self.type_var_flows.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// Hash of everything searches depend on: the flows with their indexes,
+    /// edges and subtypes, the notes attached to them and how names are
+    /// compared. Equal databases hash the same across runs.
+    pub fn content_hash(&self) -> u64 {
+        // Hashed as it is serialized, rather than serialized into a copy of
+        // the whole database first
+        let mut hasher = Fnv1a::default();
+        bincode::serialize_into(&mut hasher, self).expect("Databases always serialize");
+        bincode::serialize_into(&mut hasher, &self.notes).expect("Notes always serialize");
+        hasher.update(&[self.ignore_case as u8, self.no_hierarchy as u8]);
+        hasher.0
+    }
+
+    /// File the first program location of `flow` lies in, or the database
+    /// file when it has none
+    pub fn flow_file<'a>(&'a self, flow: &'a [UnitFlow]) -> &'a str {
+        flow.iter()
+            .find_map(|uf| match uf {
+                UnitFlow::ProgLoc(p) => p.file.as_deref(),
+                _ => None,
+            })
+            .unwrap_or(&self.file_path)
+    }
+
+    /// Files the program locations of `flow` lie in, or the database file
+    /// when it has none
+    pub fn flow_files<'a>(&'a self, flow: &'a [UnitFlow]) -> BTreeSet<&'a str> {
+        let mut files: BTreeSet<&str> = flow
+            .iter()
+            .filter_map(|uf| match uf {
+                UnitFlow::ProgLoc(p) => Some(p.file.as_deref().unwrap_or(&self.file_path)),
+                _ => None,
+            })
+            .collect();
+        if files.is_empty() {
+            files.insert(&self.file_path);
+        }
+        files
+    }
+
+    /// Commit `flow` was extracted from, taken from its first stamped
+    /// program location
+    pub fn flow_commit<'a>(&self, flow: &'a [UnitFlow]) -> Option<&'a str> {
+        flow.iter().find_map(|uf| match uf {
+            UnitFlow::ProgLoc(p) => p.commit.as_deref(),
+            _ => None,
+        })
+    }
+
+    /// Names of the types mentioned by the flows, sorted
+    pub fn type_names(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+
+    /// Names of the type variables mentioned by the flows, sorted
+    pub fn typevar_names(&self) -> impl Iterator<Item = &str> {
+        self.type_vars.iter().map(String::as_str)
+    }
+
+    /// The types mentioned by the flows, by name
+    pub fn types(&self) -> &BTreeMap<String, Type> {
+        &self.types
+    }
+
+    /// Names of the constructors mentioned by the flows, sorted
+    pub fn constructor_names(&self) -> BTreeSet<&str> {
+        self.data_flows
+            .iter()
+            .flatten()
+            .filter_map(|uf| match uf {
+                UnitFlow::ConstructorArg(c) => Some(c.name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Descriptions attached to any step of the flows, sorted
+    pub fn descriptions(&self) -> BTreeSet<&str> {
+        self.data_flows
+            .iter()
+            .flatten()
+            .filter_map(|uf| match uf {
+                UnitFlow::Type(t) => t.desc.as_deref(),
+                UnitFlow::ConstructorArg(c) => c.desc.as_deref(),
+                UnitFlow::TypeVar(tv) => tv.desc.as_deref(),
+                UnitFlow::ProgLoc(p) => p.desc.as_deref(),
+                UnitFlow::Other { fields, .. } => UnitFlow::other_desc(fields),
+            })
+            .collect()
+    }
+
+    /// Flows passing through `from` and later through `to`, paired with the
+    /// number of steps between the two locations, fewest steps first.
+    pub fn connecting_flows(&self, from: &Position, to: &Position) -> Vec<(&DataFlow, usize)> {
+        let mut connecting: Vec<(&DataFlow, usize)> = self
+            .data_flows
+            .iter()
+            .filter_map(|flow| {
+                let ends = self.steps_at(flow, to);
+                self.steps_at(flow, from)
+                    .into_iter()
+                    .flat_map(|start| {
+                        ends.iter()
+                            .filter(move |end| **end > start)
+                            .map(move |end| end - start - 1)
+                    })
+                    .min()
+                    .map(|steps| (flow, steps))
+            })
+            .collect();
+
+        connecting.sort_by_key(|(_, steps)| *steps);
+        connecting
+    }
+
+    /// Flows passing through `pos`, with the indices of the steps located there
+    pub fn flows_at(&self, pos: &Position) -> Vec<(&DataFlow, Vec<usize>)> {
+        self.data_flows
+            .iter()
+            .filter_map(|flow| {
+                let steps = self.steps_at(flow, pos);
+                (!steps.is_empty()).then_some((flow, steps))
+            })
+            .collect()
+    }
+
+    /// Indices of the program locations of `flow` lying at `pos`
+    fn steps_at(&self, flow: &[UnitFlow], pos: &Position) -> Vec<usize> {
+        flow.iter()
+            .enumerate()
+            .filter_map(|(idx, uf)| match uf {
+                UnitFlow::ProgLoc(p) if p.is_at(pos, &self.file_path) => Some(idx),
+                _ => None,
+            })
+            .collect()
+    }
+}
+type DataFlow = [UnitFlow];
+
+/// Number of flows from which [Database::search] matches flows in parallel
+pub const PARALLEL_SEARCH_FLOWS: usize = 10_000;
+
+/// Regexes [pattern_matches] keeps compiled per thread
+const MAX_COMPILED_PATTERNS: usize = 256;
+
+/// Whether `text` equals `pattern`, starts with it when it is a plain name or
+/// phrase ending in `*` like `Read*`, or matches it in full as a regex, as names and
+/// descriptions are queried. A pattern that is not a valid regex only matches
+/// itself.
+fn pattern_matches(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    if pattern == text || (ignore_case && pattern.to_lowercase() == text.to_lowercase()) {
+        return true;
+    }
+    if let Some(prefix) = pattern.strip_suffix('*') {
+        let plain = |c: char| c.is_alphanumeric() || "_-' ".contains(c);
+        if !prefix.is_empty() && prefix.chars().all(plain) {
+            return if ignore_case {
+                text.to_lowercase().starts_with(&prefix.to_lowercase())
+            } else {
+                text.starts_with(prefix)
+            };
+        }
+    }
+    if regex::escape(pattern) == pattern {
+        return false;
+    }
+
+    thread_local! {
+        static COMPILED: RefCell<HashMap<(String, bool), Option<Regex>>> =
+            RefCell::new(HashMap::new());
+    }
+    COMPILED.with(|compiled| {
+        let mut compiled = compiled.borrow_mut();
+        // Long-running searches see ever new patterns; start over rather
+        // than keep them all
+        let key = (pattern.to_string(), ignore_case);
+        if compiled.len() >= MAX_COMPILED_PATTERNS && !compiled.contains_key(&key) {
+            compiled.clear();
+        }
+        compiled
+            .entry(key)
+            .or_insert_with(|| {
+                let flags = if ignore_case { "(?i)" } else { "" };
+                Regex::new(&format!("{}^(?:{})$", flags, pattern)).ok()
+            })
+            .as_ref()
+            .is_some_and(|regex| regex.is_match(text))
+    })
+}
+
+/// Stable identifier of a flow: the FNV-1a hash of its JSON serialization in
+/// hex, unchanged across runs and reorderings of the database.
+pub fn flow_id(flow: &[UnitFlow]) -> String {
+    let bytes = serde_json::to_vec(flow).expect("Flows always serialize");
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.update(bytes);
+    hasher.0
+}
+
+/// FNV-1a hash of the bytes written to it so far
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv1a {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+    }
+}
+
+impl std::io::Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A source position given as `file:line[:col]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+}
+
+impl Position {
+    pub fn parse(input: &str) -> Result<Position, String> {
+        let parts: Vec<&str> = input.rsplitn(3, ':').collect();
+        let number = |s: &str| s.parse::<usize>().ok();
+        match parts.as_slice() {
+            [col, line, file] if number(line).is_some() => Ok(Position {
+                file: file.to_string(),
+                line: number(line).unwrap(),
+                column: Some(number(col).ok_or("Invalid column number")?),
+            }),
+            [line, rest @ ..] if !rest.is_empty() => Ok(Position {
+                file: rest.iter().rev().cloned().collect::<Vec<_>>().join(":"),
+                line: number(line).ok_or("Invalid line number")?,
+                column: None,
+            }),
+            _ => Err(format!(
+                "Invalid position `{}`, expected <file>:<line>[:<col>]",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Type {
+    name: Name,
+    args: Vec<Str>,
+    /// Additional description about the specific flow
+    desc: Option<Str>,
+}
+
+impl Type {
+    /// Render the type with its arguments, substituting bound type variables
+    pub fn render(&self, bindings: &BTreeMap<String, String>, printer: &dyn TypePrinter) -> String {
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|arg| match bindings.get(arg.as_str()) {
+                Some(ty) => ty.clone(),
+                None if is_type_var(arg) => printer.render_var(arg),
+                None => arg.to_string(),
+            })
+            .collect();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        printer.render_type(&self.name, &args)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstructorArg {
+    name: Name,
+    arg_index: usize,
+    desc: Option<Str>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgLoc {
+    line: Str,
+    /// 1-based start and exclusive end of the highlighted span, counted in
+    /// characters of `line` rather than bytes. An empty span marks the
+    /// insertion point before its start, and a span running onto the newline,
+    /// one past the end of the line, takes in the rest of the line.
+    char_range: (usize, usize),
+    desc: Option<Str>,
+    depth : usize,
+    /// Source file of the location, when it differs from the database `file_path`
+    #[serde(default)]
+    file: Option<Str>,
+    /// 1-based line number of `line` in its source file
+    #[serde(default)]
+    line_number: Option<usize>,
+    /// 1-based column in its source file of the start of the span, when
+    /// `line` is not the whole source line
+    #[serde(default)]
+    column: Option<usize>,
+    /// Commit the flow was extracted from, as stamped by the extractor
+    #[serde(default)]
+    commit: Option<Str>,
+}
+
+/// Source lines around a [ProgLoc] with their 1-based line numbers
+pub type ContextLines = Vec<(usize, String)>;
+
+/// Columns between the tab stops tabs in source lines are expanded to, unless
+/// `--tab-width` says otherwise
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Columns `c` takes up on screen starting at column `col`, counted from 0:
+/// a tab reaches the next tab stop
+fn char_columns(c: char, col: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' => tab_width.max(1) - col % tab_width.max(1),
+        c => c.width().unwrap_or(0),
+    }
+}
+
+/// `text` starting at column `col` with its tabs replaced by the spaces they
+/// take up, and the column it ends at
+fn expand_tabs(text: &str, col: usize, tab_width: usize) -> (String, usize) {
+    let mut expanded = String::with_capacity(text.len());
+    let mut col = col;
+    for c in text.chars() {
+        let width = char_columns(c, col, tab_width);
+        match c {
+            '\t' => expanded += &" ".repeat(width),
+            c => expanded.push(c),
+        }
+        col += width;
+    }
+    (expanded, col)
+}
+
+impl ProgLoc {
+    /// The highlighted part of the line, if `char_range` lies within it
+    pub fn span_text(&self) -> Option<&str> {
+        let (_, span, _) = self.split_span()?;
+        Some(span).filter(|s| !s.is_empty())
+    }
+
+    /// The line without the `\r` a CRLF line ending leaves at its end
+    fn text(&self) -> &str {
+        self.line.strip_suffix('\r').unwrap_or(&self.line)
+    }
+
+    /// Byte offset in the line's [text](ProgLoc::text) of the character at
+    /// `chars`, or of its end
+    fn byte_offset(&self, chars: usize) -> Option<usize> {
+        let text = self.text();
+        text.char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(text.len()))
+            .nth(chars)
+    }
+
+    /// The position of the start of the span, in its own file or else
+    /// `default_file`, if the location has a line number
+    pub fn position(&self, default_file: &str) -> Option<Position> {
+        Some(Position {
+            file: self
+                .file
+                .clone()
+                .map_or_else(|| default_file.to_string(), String::from),
+            line: self.line_number?,
+            column: Some(self.columns().0),
+        })
+    }
+
+    /// `char_range` with an end past the newline, or on the `\r` of a CRLF
+    /// line ending, brought back to the end of the line
+    fn span(&self) -> (usize, usize) {
+        let (start, end) = self.char_range;
+        (start, end.min(self.text().chars().count() + 1))
+    }
+
+    /// Whether the span is empty, marking an insertion point
+    pub fn is_insertion_point(&self) -> bool {
+        self.char_range.0 == self.char_range.1
+    }
+
+    /// The span as 1-based, end-exclusive columns of its source line:
+    /// `char_range` moved to start at `column` when one is given
+    pub fn columns(&self) -> (usize, usize) {
+        let (start, end) = self.span();
+        match self.column {
+            Some(column) => (column, column + end.saturating_sub(start)),
+            None => (start, end),
+        }
+    }
+
+    /// The line split around the highlighted span into the text before it,
+    /// the span and the text after it, if `char_range` lies within the line
+    pub fn split_span(&self) -> Option<(&str, &str, &str)> {
+        let (start, end) = self.span();
+        let start = self.byte_offset(start.saturating_sub(1))?;
+        let end = self.byte_offset(end.checked_sub(1)?)?;
+        if start > end {
+            return None;
+        }
+        let text = self.text();
+        Some((&text[..start], &text[start..end], &text[end..]))
+    }
+
+    /// The line with its tabs expanded to stops `tab_width` apart, as it is
+    /// printed above its [carets](ProgLoc::carets)
+    pub fn expanded_line(&self, tab_width: usize) -> String {
+        expand_tabs(self.text(), 0, tab_width).0
+    }
+
+    /// Carets under the characters of `char_range`, as many as each takes up
+    /// on screen with tabs expanded to stops `tab_width` apart, preceded by
+    /// spaces as wide as the rest of the line. An insertion point gets a
+    /// single caret where it falls.
+    pub fn carets(&self, tab_width: usize) -> String {
+        let mut carets = " ".repeat(self.depth * 2);
+        let (start, end) = self.span();
+        let mut col = 0;
+        for (idx, c) in self.text().chars().enumerate() {
+            let width = char_columns(c, col, tab_width);
+            col += width;
+            if self.is_insertion_point() && idx + 1 == start {
+                carets += "^";
+                carets += &" ".repeat(width.saturating_sub(1));
+                continue;
+            }
+            let mark = if (start..end).contains(&(idx + 1)) {
+                "^"
+            } else {
+                " "
+            };
+            carets += &mark.repeat(width);
+        }
+        if self.is_insertion_point() && start > self.text().chars().count() {
+            carets += "^";
+        }
+        carets
+    }
+
+    /// Whether this location lies at `pos`. Locations without a file of their
+    /// own are taken to be in `default_file`, and a column, when given, must
+    /// fall inside the span's [ProgLoc::columns].
+    pub fn is_at(&self, pos: &Position, default_file: &str) -> bool {
+        let file = self.file.as_deref().unwrap_or(default_file);
+        let (start, end) = self.columns();
+        Path::new(file).ends_with(&pos.file)
+            && self.line_number == Some(pos.line)
+            && pos.column.is_none_or(|col| start <= col && col < end)
+    }
+
+    /// Up to `n` numbered lines of the source file above and below the
+    /// location, read from its own file or else `default_file`. `None` when
+    /// the location has no line number, is not printed for its invalid range,
+    /// or the file cannot be read.
+    pub fn context_lines(
+        &self,
+        default_file: &str,
+        n: usize,
+    ) -> Option<(ContextLines, ContextLines)> {
+        if !self.has_valid_range() {
+            return None;
+        }
+        let file = self.file.as_deref().unwrap_or(default_file);
+        let source = fs::read_to_string(file).ok()?;
+        let lines: Vec<&str> = source.lines().collect();
+        let idx = self
+            .line_number?
+            .checked_sub(1)
+            .filter(|idx| *idx < lines.len())?;
+        let numbered = |range: std::ops::Range<usize>| -> ContextLines {
+            range.map(|i| (i + 1, lines[i].to_string())).collect()
+        };
+        Some((
+            numbered(idx.saturating_sub(n)..idx),
+            numbered(idx + 1..(idx + 1 + n).min(lines.len())),
+        ))
+    }
+
+    /// Print context lines from [ProgLoc::context_lines], indented like the
+    /// location itself
+    pub fn print_context(&self, lines: &[(usize, String)]) {
+        print!("{}", self.render_context(lines));
+    }
+
+    /// The lines [ProgLoc::print_context] prints
+    pub fn render_context(&self, lines: &[(usize, String)]) -> String {
+        lines
+            .iter()
+            .map(|(number, text)| {
+                format!(
+                    "{}{} {}{}\n",
+                    format!("{:<5}", number).bright_black(),
+                    "│".bright_black(),
+                    " ".repeat(self.depth * 2),
+                    text.bright_black()
+                )
+            })
+            .collect()
+    }
+
+    pub fn print_location(loc: &ProgLoc, itr: &usize) -> bool {
+        Self::print_marked_location(
+            loc,
+            itr,
+            Highlight::Normal,
+            HighlightStyle::default(),
+            DEFAULT_TAB_WIDTH,
+        )
+    }
+
+    /// Print a location like [ProgLoc::print_location], drawing attention to
+    /// it or fading it out as `highlight` says, marking its span as `style`
+    /// says and expanding tabs to stops `tab_width` apart
+    pub fn print_marked_location(
+        loc: &ProgLoc,
+        itr: &usize,
+        highlight: Highlight,
+        style: HighlightStyle,
+        tab_width: usize,
+    ) -> bool {
+        match Self::render_marked_location(loc, itr, highlight, style, tab_width) {
+            Some(text) => {
+                print!("{}", text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The lines [ProgLoc::print_marked_location] prints, or `None` for a
+    /// location not printed for its invalid range. Tabs are expanded in the
+    /// source line as in its carets, so that the two line up whatever the
+    /// terminal's tab stops.
+    pub fn render_marked_location(
+        loc: &ProgLoc,
+        itr: &usize,
+        highlight: Highlight,
+        style: HighlightStyle,
+        tab_width: usize,
+    ) -> Option<String> {
+        if !loc.has_valid_range() {
+            return None;
+        }
+
+        let depth_spaces = " ".repeat(loc.depth * 2);
+        let line_text = match (style, loc.split_span()) {
+            (HighlightStyle::Inline | HighlightStyle::Both, Some((before, span, after))) => {
+                let (before, col) = expand_tabs(before, 0, tab_width);
+                let (span, col) = expand_tabs(span, col, tab_width);
+                let (after, _) = expand_tabs(after, col, tab_width);
+                let span = match highlight {
+                    Highlight::Normal => span.as_str().red().bold(),
+                    Highlight::Marked => span.as_str().yellow().bold(),
+                    Highlight::Dimmed => span.as_str().bright_black().bold(),
+                };
+                let rest = |text: &str| match highlight {
+                    Highlight::Dimmed => text.bright_black(),
+                    _ => text.normal(),
+                };
+                format!("{}{}{}{}", depth_spaces, rest(&before), span, rest(&after))
+            }
+            _ => {
+                let line = loc.expanded_line(tab_width);
+                match highlight {
+                    Highlight::Dimmed => format!("{}{}", depth_spaces, line.bright_black()),
+                    _ => format!("{}{}", depth_spaces, line),
+                }
+            }
+        };
+        let max_padding = 7;
+
+        let tag = if format!("{itr}").len() == 1 {
+            format!("[{}]  ", itr)
+        } else {
+            format!("[{}] ", itr)
+        };
+        let itr_space = (tag.len() + 1).min(max_padding);
+
+        let mut text = format!(
+            "{}{} {}\n",
+            match highlight {
+                Highlight::Normal => tag.bright_blue(),
+                Highlight::Marked => tag.yellow().bold(),
+                Highlight::Dimmed => tag.bright_black(),
+            },
+            "│".bright_black(),
+            line_text
+        );
+        // An insertion point has no text to color, so it keeps its caret
+        if style == HighlightStyle::Inline && !loc.is_insertion_point() {
+            return Some(text);
+        }
+        let carets = loc.carets(tab_width);
+
+        text += &format!(
+            "{}{} {}\n",
+            " ".repeat(itr_space - 1),
+            "└".bright_black(),
+            match highlight {
+                Highlight::Normal => carets.green(),
+                Highlight::Marked => carets.yellow().bold(),
+                Highlight::Dimmed => carets.bright_black(),
+            }
+        );
+        Some(text)
+    }
+}
+
+/// How [ProgLoc::print_marked_location] draws a location
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Highlight {
+    Normal,
+    Marked,
+    Dimmed,
+}
+
+/// How [ProgLoc::print_marked_location] marks the span of a location
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HighlightStyle {
+    /// Color the span within the source line
+    Inline,
+    /// Draw carets under the span
+    #[default]
+    Underline,
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeVar {
+    name: Name,
+    desc: Option<Str>,
+}
+
+/// The layout of a data JSON file, with flows read as `F`
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "F: limits::CheckedFlows<'de>"))]
+struct DataJson<F> {
+    /// [SCHEMA_VERSION] the database was written in
+    #[serde(default = "version::first_schema")]
+    version: u32,
+    file_path: String,
+    #[serde(default)]
+    language: Language,
+    /// Types standing for others, matched by their subtypes' steps
+    #[serde(default, alias = "type_aliases")]
+    subtypes: Subtypes,
+    #[serde(deserialize_with = "limits::checked_flows")]
+    dataflow: F,
+    /// Links between step indices, one list per flow
+    #[serde(default)]
+    edges: Vec<Vec<(usize, usize)>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum UnitFlow {
+    Type(Type),
+    ConstructorArg(ConstructorArg),
+    TypeVar(TypeVar),
+    ProgLoc(ProgLoc),
+    /// A step of a kind other than these, such as `FunctionCall`, kept with
+    /// its fields as read so that it is written back on export
+    Other {
+        kind: String,
+        fields: serde_json::Value,
+    },
+}
+
+impl UnitFlow {
+    /// Describe this step as a plain-English sentence. The first step of a
+    /// flow introduces the value, later steps refer back to it. Type
+    /// variables are shown alongside the concrete type they are bound to.
+    pub fn narrate(
+        &self,
+        first: bool,
+        bindings: &BTreeMap<String, String>,
+        printer: &dyn TypePrinter,
+    ) -> String {
+        let subject = if first { "A value" } else { "It" };
+        let (sentence, desc) = match self {
+            UnitFlow::Type(t) if first => (
+                format!(
+                    "A value of type `{}` enters the flow",
+                    t.render(bindings, printer)
+                ),
+                &t.desc,
+            ),
+            UnitFlow::Type(t) => (
+                format!("It takes on the type `{}`", t.render(bindings, printer)),
+                &t.desc,
+            ),
+            UnitFlow::ConstructorArg(c) => (
+                format!(
+                    "{} flows into the {} argument of `{}`",
+                    subject,
+                    ordinal(c.arg_index),
+                    c.name
+                ),
+                &c.desc,
+            ),
+            UnitFlow::TypeVar(tv) => (
+                match bindings.get(tv.name.as_str()) {
+                    Some(ty) => format!(
+                        "{} is unified with the type variable `{}` (here `{}`)",
+                        subject,
+                        printer.render_var(&tv.name),
+                        ty
+                    ),
+                    None => format!(
+                        "{} is unified with the type variable `{}`",
+                        subject,
+                        printer.render_var(&tv.name)
+                    ),
+                },
+                &tv.desc,
+            ),
+            UnitFlow::ProgLoc(p) => (
+                match p.span_text() {
+                    Some(span) => {
+                        format!("{} appears as `{}` in `{}`", subject, span, p.line.trim())
+                    }
+                    None => format!("{} appears in `{}`", subject, p.line.trim()),
+                },
+                &p.desc,
+            ),
+            UnitFlow::Other { kind, fields } => {
+                let sentence = format!("{} passes through a `{}` step", subject, kind);
+                return match Self::other_desc(fields) {
+                    Some(d) => format!("{} ({}).", sentence, d),
+                    None => format!("{}.", sentence),
+                };
+            }
+        };
+
+        match desc {
+            Some(d) => format!("{} ({}).", sentence, d),
+            None => format!("{}.", sentence),
+        }
+    }
+
+    /// Structural description of the step: kind and name, without positions,
+    /// descriptions, or type variable names, which differ between otherwise
+    /// identical flows.
+    pub fn shape(&self) -> String {
+        match self {
+            UnitFlow::Type(t) => t.name.to_string(),
+            UnitFlow::ConstructorArg(c) => format!("@{}.{}", c.name, c.arg_index),
+            UnitFlow::TypeVar(_) => "TypeVar".to_string(),
+            UnitFlow::ProgLoc(_) => "ProgLoc".to_string(),
+            UnitFlow::Other { kind, .. } => format!("%{}", kind),
+        }
+    }
+
+    pub fn desc(&self) -> Option<&str> {
+        match self {
+            UnitFlow::Type(t) => &t.desc,
+            UnitFlow::ConstructorArg(c) => &c.desc,
+            UnitFlow::TypeVar(tv) => &tv.desc,
+            UnitFlow::ProgLoc(p) => &p.desc,
+            UnitFlow::Other { fields, .. } => return Self::other_desc(fields),
+        }
+        .as_deref()
+    }
+
+    /// Whether the step's description matches the queried one, if any
+    fn matches_desc(&self, pattern: Option<&str>, ignore_case: bool) -> bool {
+        pattern.is_none_or(|pattern| {
+            self.desc()
+                .is_some_and(|desc| desc_matches(pattern, desc, ignore_case))
+        })
+    }
+
+    /// Short name of the step as a query would refer to it
+    pub fn label(&self) -> String {
+        match self {
+            UnitFlow::Type(t) => t.name.to_string(),
+            UnitFlow::ConstructorArg(c) => format!("@{}.{}", c.name, c.arg_index),
+            UnitFlow::TypeVar(tv) => tv.name.to_string(),
+            UnitFlow::ProgLoc(p) => p.span_text().unwrap_or_else(|| p.line.trim()).to_string(),
+            UnitFlow::Other { kind, .. } => format!("%{}", kind),
+        }
+    }
+
+    /// Narrate every step of a flow in order.
+    pub fn narrate_flow(flow: &[UnitFlow], printer: &dyn TypePrinter) -> Vec<String> {
+        let bindings = Self::type_bindings(flow, printer);
+        flow.iter()
+            .enumerate()
+            .map(|(idx, uf)| uf.narrate(idx == 0, &bindings, printer))
+            .collect()
+    }
+
+    /// Bind the type variables of a flow to the concrete types they are
+    /// unified with in this particular flow.
+    ///
+    /// Bindings come from two sources: arguments of same-named types that
+    /// disagree only by a variable (`List<'a>` against `List<int>` binds `'a`
+    /// to `int`), and the concrete type nearest to each [UnitFlow::TypeVar]
+    /// step, preferring the one preceding it. Bound types are rendered with
+    /// `printer`.
+    pub fn type_bindings(flow: &[UnitFlow], printer: &dyn TypePrinter) -> BTreeMap<String, String> {
+        let types: Vec<(usize, &Type)> = flow
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, uf)| match uf {
+                UnitFlow::Type(t) => Some((idx, t)),
+                _ => None,
+            })
+            .collect();
+
+        let mut bindings = BTreeMap::new();
+        for (i, (_, a)) in types.iter().enumerate() {
+            for (_, b) in &types[i + 1..] {
+                if a.name != b.name || a.args.len() != b.args.len() {
+                    continue;
+                }
+                for (x, y) in a.args.iter().zip(&b.args) {
+                    match (is_type_var(x), is_type_var(y)) {
+                        (true, false) => {
+                            bindings
+                                .entry(x.to_string())
+                                .or_insert_with(|| y.to_string());
                         }
+                        (false, true) => {
+                            bindings
+                                .entry(y.to_string())
+                                .or_insert_with(|| x.to_string());
+                        }
+                        _ => {}
                     }
                 }
+            }
+        }
 
-                false
+        for (idx, uf) in flow.iter().enumerate() {
+            if let UnitFlow::TypeVar(tv) = uf {
+                let nearest = types
+                    .iter()
+                    .rev()
+                    .find(|(t_idx, _)| *t_idx < idx)
+                    .or_else(|| types.iter().find(|(t_idx, _)| *t_idx > idx));
+                if let Some((_, t)) = nearest.filter(|(_, t)| !is_type_var(&t.name)) {
+                    let ty = t.render(&bindings, printer);
+                    bindings.entry(tv.name.to_string()).or_insert(ty);
+                }
             }
-            (_, []) => true,
-            _ => false,
         }
+
+        bindings
     }
 
-    pub fn count_typevar_flows(&self, typevar_name: &str) -> usize {
-        self.data_flows
-            .iter()
-            .filter(|flow| {
-                flow.iter().any(|unit| {
-                    if let UnitFlow::TypeVar(tv) = unit {
-                        tv.name == typevar_name
-                    } else {
-                        false
+    /// The elaborated type carried by the value at each step of the flow,
+    /// with type variables replaced by their bindings where known.
+    pub fn elaborated_types(flow: &[UnitFlow], printer: &dyn TypePrinter) -> Vec<Option<String>> {
+        let bindings = Self::type_bindings(flow, printer);
+        let mut current = None;
+        flow.iter()
+            .map(|uf| {
+                match uf {
+                    UnitFlow::Type(t) => current = Some(t.render(&bindings, printer)),
+                    UnitFlow::TypeVar(tv) => {
+                        current = bindings
+                            .get(tv.name.as_str())
+                            .cloned()
+                            .or(current.take())
+                            .or_else(|| Some(printer.render_var(&tv.name)))
                     }
-                })
+                    _ => {}
+                }
+                current.clone()
             })
-            .count()
+            .collect()
     }
 }
-type DataFlow = Vec<UnitFlow>;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Type {
-    name: String,
-    args: Vec<String>,
-    /// Additional description about the specific flow
-    desc: Option<String>,
+fn is_type_var(name: &str) -> bool {
+    name.starts_with('\'')
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ConstructorArg {
-    name: String,
-    arg_index: usize,
-    desc: Option<String>,
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProgLoc {
-    line: String,
-    char_range: (usize, usize),
-    desc: Option<String>,
-    depth : usize,
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+/// Match constructor argument in the data flow by name
+pub struct QConstructorArg {
+    pub name: Name,
+    /// Optionally match on specific arguments unified
+    pub arg_index: Option<ArgIndex>,
+    /// Optionally match on description
+    pub desc: Option<String>,
 }
 
-impl ProgLoc {
-    pub fn print_location(loc: &ProgLoc, itr: &usize) -> bool {
-        if {
-            loc.char_range.0 >= loc.line.len()
-                || loc.char_range.1 > loc.line.len() + 1
-                || loc.char_range.0 >= loc.char_range.1
-        } {
-            return false;
-        }
-
-        let depth_spaces = " ".repeat(loc.depth * 2);
-        let line_text = format!("{}{}", depth_spaces, loc.line);
-        let max_padding = 7;
-        let mut itr_space = 0;
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+/// Argument indices a [QConstructorArg] matches
+pub enum ArgIndex {
+    /// `@x.1`
+    One(usize),
+    /// `@x.{0,2}`, any of the indices
+    Set(Vec<usize>),
+    /// `@x.0..3`, from `start` up to `end` inclusive
+    Range { start: usize, end: usize },
+}
 
-        if format!("{itr}").len() == 1 {
-            itr_space = format!("[{}]  |", itr).len().min(max_padding);
-        } else {
-            itr_space = format!("[{}] |", itr).len().min(max_padding);
+impl ArgIndex {
+    pub fn contains(&self, idx: usize) -> bool {
+        match self {
+            ArgIndex::One(one) => *one == idx,
+            ArgIndex::Set(set) => set.contains(&idx),
+            ArgIndex::Range { start, end } => (*start..=*end).contains(&idx),
         }
+    }
 
-        if format!("{itr}").len() == 1 {
-            println!(
-                "{}{} {}",
-                format!("[{}]  ", itr).bright_blue(),
-                format!("│").bright_black(),
-                line_text
-            );
-        } else {
-            println!(
-                "{}{} {}",
-                format!("[{}] ", itr).bright_blue(),
-                format!("│").bright_black(),
-                line_text
-            );
-        }
-        let start = loc.char_range.0 + (loc.depth*2);
-        let end = loc.char_range.1 + (loc.depth*2);
-
-        let mut highlight = String::with_capacity(line_text.len());
-        for i in 1..(line_text.len() + 1) {
-            if i >= start && i < end {
-                highlight.push('^');
-            } else {
-                highlight.push(' ');
-            }
+    /// Whether any index from `min` up to `max` inclusive is matched
+    pub fn any_within(&self, min: usize, max: usize) -> bool {
+        match self {
+            ArgIndex::One(idx) => (min..=max).contains(idx),
+            ArgIndex::Set(set) => set.iter().any(|idx| (min..=max).contains(idx)),
+            ArgIndex::Range { start, end } => *start <= max && min <= *end,
         }
-
-        println!(
-            "{}{} {}",
-            " ".repeat(itr_space - 1),
-            "└".bright_black(),
-            highlight.green()
-        );
-
-        true
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TypeVar {
-    name: String,
-    desc: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub enum UnitFlow {
-    Type(Type),
-    ConstructorArg(ConstructorArg),
-    TypeVar(TypeVar),
-    ProgLoc(ProgLoc),
-}
-
-#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
-/// Match constructor argument in the data flow by name
-pub struct QConstructorArg {
-    pub name: String,
-    /// Optionally match on specific argument unified
-    pub arg_index: Option<usize>,
-    /// Optionally match on description
-    pub desc: Option<String>,
+impl fmt::Display for ArgIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgIndex::One(idx) => write!(f, "{}", idx),
+            ArgIndex::Set(set) => {
+                let set: Vec<String> = set.iter().map(usize::to_string).collect();
+                write!(f, "{{{}}}", set.join(","))
+            }
+            ArgIndex::Range { start, end } => write!(f, "{}..{}", start, end),
+        }
+    }
 }
 
-#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
 /// Match type by name
 pub struct QType {
-    pub name: String,
+    pub name: Name,
+    /// Optionally match on type arguments, `_` matching any
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
     /// Optionally match on description
     pub desc: Option<String>,
 }
 
-#[derive(serde::Deserialize, Debug, PartialEq, Eq)]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum QueryOps {
     /// Match type variable by in-degree
     QTypeVar(usize),
+    /// Match type variable by in-degree from `min` up to `max` inclusive, or
+    /// unbounded above without `max`
+    QTypeVarRange { min: usize, max: Option<usize> },
+    /// Match type variable by name, with an in-degree from `min` up to `max`
+    /// inclusive, or unbounded above without `max`
+    QTypeVarName {
+        name: Name,
+        min: usize,
+        max: Option<usize>,
+    },
     /// Match constructor argument in the data flow by name
     QConstructorArg(QConstructorArg),
     /// Match type by name
     QType(QType),
     /// Match based on string description for a [UnitFlow]
     QDesc(String),
+    /// Match any step of a kind, `Type`, `ConstructorArg`, `TypeVar`,
+    /// `ProgLoc` or the kind of an [UnitFlow::Other], whatever its name
+    QKind(String),
+    /// Match flows with a note containing the text, ignoring case
+    QNote(String),
+    /// Match flows whose number of steps, or of program locations, is within
+    /// the bounds
+    QLen(LengthBounds),
+    /// Match flows without the step, or a step other than it at this position
+    /// once the query has anchors or gaps
+    QNot(Box<QueryOps>),
+    /// Match flows with a step matching `from` linked to one matching `to`,
+    /// directly or, when `transitive`, through any number of steps
+    QEdge {
+        from: Box<QueryOps>,
+        to: Box<QueryOps>,
+        transitive: bool,
+    },
+    /// Match the step and bind the flow step it matched to a name, reported
+    /// by [QueryOps::captures]
+    QBind(String, Box<QueryOps>),
+    /// Match flows whose step at this 0-based index matches the step,
+    /// wherever the other steps match
+    QAt(usize, Box<QueryOps>),
+    /// Match flows matching every one of the queries, each on its own so
+    /// their steps may interleave in any order
+    QAll(Vec<Vec<QueryOps>>),
+    /// Match the step from `min` up to `max` times in order, or unbounded
+    /// above without `max`
+    QRepeat {
+        op: Box<QueryOps>,
+        min: usize,
+        max: Option<usize>,
+    },
+    /// Anchor the next step to the start of the flow
+    QStart,
+    /// Anchor the previous step to the end of the flow
+    QEnd,
+    /// Allow any number of flow steps between the surrounding steps
+    QGap,
+}
+
+/// Renders a query step back in the query language
+impl fmt::Display for QueryOps {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryOps::QTypeVar(count) => write!(f, "#{}", count),
+            QueryOps::QTypeVarRange { min, max: None } => write!(f, "#>={}", min),
+            QueryOps::QTypeVarRange {
+                min: 0,
+                max: Some(max),
+            } => write!(f, "#<={}", max),
+            QueryOps::QTypeVarRange {
+                min,
+                max: Some(max),
+            } => write!(f, "#{}..{}", min, max),
+            QueryOps::QTypeVarName { name, min, max } => {
+                write!(f, "#{}", name)?;
+                match (min, max) {
+                    (0, None) => Ok(()),
+                    (min, None) => write!(f, ">={}", min),
+                    (0, Some(max)) => write!(f, "<={}", max),
+                    (min, Some(max)) if min == max => write!(f, "={}", min),
+                    (min, Some(max)) => write!(f, "={}..{}", min, max),
+                }
+            }
+            QueryOps::QConstructorArg(q) => {
+                write!(f, "@{}", q.name)?;
+                if let Some(idx) = &q.arg_index {
+                    write!(f, ".{}", idx)?;
+                }
+                if let Some(desc) = &q.desc {
+                    write!(f, ":{}", parser::bare_or_quoted(desc))?;
+                }
+                Ok(())
+            }
+            QueryOps::QType(q) => {
+                write!(f, "{}", q.name)?;
+                if let Some(args) = &q.args {
+                    write!(f, "<{}>", args.join(", "))?;
+                }
+                if let Some(desc) = &q.desc {
+                    write!(f, ":{}", parser::bare_or_quoted(desc))?;
+                }
+                Ok(())
+            }
+            QueryOps::QDesc(desc) => match desc.strip_prefix('~') {
+                Some(fuzzy) => write!(f, "~{}", parser::quote(fuzzy)),
+                None => write!(f, "{}", parser::quote(desc)),
+            },
+            QueryOps::QNote(text) => write!(f, "note:{}", parser::bare_or_quoted(text)),
+            QueryOps::QLen(bounds) => write!(f, "{}", bounds),
+            QueryOps::QKind(kind) => write!(f, "%{}", kind),
+            QueryOps::QNot(op) => write!(f, "!{}", op),
+            QueryOps::QBind(name, op) => write!(f, "{} as {}", op, name),
+            QueryOps::QAt(idx, op) => write!(f, "[{}]{}", idx, op),
+            QueryOps::QRepeat { op, min, max } if *max == Some(*min) => {
+                write!(f, "{}{{{}}}", op, min)
+            }
+            QueryOps::QRepeat { op, min, max: None } => write!(f, "{}{{{},}}", op, min),
+            QueryOps::QRepeat {
+                op,
+                min,
+                max: Some(max),
+            } => write!(f, "{}{{{},{}}}", op, min, max),
+            QueryOps::QAll(queries) => {
+                let queries: Vec<String> = queries
+                    .iter()
+                    .map(|query| {
+                        let steps: Vec<String> = query.iter().map(QueryOps::to_string).collect();
+                        steps.join(", ")
+                    })
+                    .collect();
+                write!(f, "{}", queries.join(" && "))
+            }
+            QueryOps::QEdge {
+                from,
+                to,
+                transitive,
+            } => write!(
+                f,
+                "{} {} {}",
+                from,
+                if *transitive { "->>" } else { "->" },
+                to
+            ),
+            QueryOps::QStart => write!(f, "^"),
+            QueryOps::QEnd => write!(f, "$"),
+            QueryOps::QGap => write!(f, ".."),
+        }
+    }
 }
 
-/// A simplified parser for query language
+/// The query language, read by the lexer and parser in [parser]
 /// Examples:
 ///   #2          -> QTypeVar(2) (# for count/number)
+///   #>=2, #<5   -> QTypeVarRange, also with > and <=
+///   #2..5       -> QTypeVarRange from 2 to 5 inclusive
+///   #'a         -> QTypeVarName('a), with any in-degree
+///   #'a>=2      -> QTypeVarName('a) of in-degree at least 2, also with
+///                  >, <, <=, =2 and =2..5
 ///   List        -> QType(List)
 ///   List:desc   -> QType(List) with description
+///   List<int>   -> QType(List) with type argument int
+///   Map<_, int> -> QType(Map) with two arguments, the first any
 ///   @x          -> QConstructorArg(x)
 ///   @x.1        -> QConstructorArg(x) at index 1
+///   @x.{0,2}    -> QConstructorArg(x) at index 0 or 2
+///   @x.1..3     -> QConstructorArg(x) at index 1 to 3 inclusive
 ///   @x:desc     -> QConstructorArg(x) with description
-///   "desc"      -> QDesc(desc)
+///   @x.1:desc   -> QConstructorArg(x) at index 1 with description; both
+///                  must hold
+///   "desc"      -> QDesc(desc) matching descriptions containing desc
+///   ~"desc"     -> QDesc(~desc) matching descriptions resembling desc
+///   List:~desc  -> QType(List) with a description resembling desc
+///   List.*      -> QType matching type names by regex
+///   Read*       -> QType matching type names starting with Read, as do
+///                  @Read* and "Read*" for constructors and descriptions
+///   "alloc.*"   -> QDesc matching descriptions by regex
+///   %ProgLoc    -> QKind(ProgLoc) matching any step of the kind, as do
+///                  %Type, %ConstructorArg, %TypeVar and %Effect for steps
+///                  of a kind other than those
+///   "a, b"      -> QDesc(a, b); quoted text may hold `,`, `:` and `->`,
+///                  escaping `"` and `\` with a `\`
+///   List:"a, b" -> QType(List) with a quoted description, as for
+///                  constructors and notes
+///   note:text   -> QNote(text)
+///   len>=5      -> QLen matching flows of 5 steps or more, also with <=, >,
+///                  <, =5 and =2..5; locs>=2 counts program locations instead
+///   List as src -> QBind(src, QType(List)) capturing the matched step
+///   @x -> List  -> QEdge where a `@x` step links directly into a `List` one
+///   @x ->> List -> QEdge where the link may pass through other steps
+///   !List       -> QNot(QType(List)) rejecting flows through a `List`
+///   [0]List     -> QAt(0, QType(List)) for flows whose first step is a
+///                  `List`, wherever the other steps match
+///   a, b && c   -> QAll([a, b], [c]) for flows matching both queries,
+///                  whatever the order of their steps to each other; `&&`
+///                  only joins whole queries
+///   List{2,}    -> QRepeat of QType(List) at least twice, `{2}` exactly
+///                  twice and `{1,3}` one to three times; repetitions are
+///                  adjacent once the query has an anchor or gap
+///   ^, $        -> QStart, QEnd anchoring the flow's first or last step
+///   .. or *     -> QGap; once a query has an anchor or gap, steps without
+///                  a gap between them must be adjacent
+/// Regexes match the whole name and cannot contain `,`, `"` or `->`, nor `:`
+/// or `<` in types.
 impl QueryOps {
-    fn parse_token(token: &str) -> Result<QueryOps, String> {
-        match token.trim() {
-            // Handle type variable count: #2
-            s if s.starts_with('#') => s[1..]
-                .parse()
-                .map(QueryOps::QTypeVar)
-                .map_err(|_| "Invalid type variable count".to_string()),
-
-            // Handle constructor arg: @x, @x.1, @x:desc
-            s if s.starts_with('@') => {
-                let parts: Vec<&str> = s[1..].split(|c| c == '.' || c == ':').collect();
-                match parts.as_slice() {
-                    [name] => Ok(QueryOps::QConstructorArg(QConstructorArg {
-                        name: name.to_string(),
-                        arg_index: None,
-                        desc: None,
-                    })),
-                    [name, idx] if idx.parse::<usize>().is_ok() => {
-                        Ok(QueryOps::QConstructorArg(QConstructorArg {
-                            name: name.to_string(),
-                            arg_index: Some(idx.parse().unwrap()),
-                            desc: None,
-                        }))
-                    }
-                    [name, desc] => Ok(QueryOps::QConstructorArg(QConstructorArg {
-                        name: name.to_string(),
-                        arg_index: None,
-                        desc: Some(desc.to_string()),
-                    })),
-                    _ => Err("Invalid constructor arg syntax".to_string()),
+    /// Whether the step constrains where the other steps match rather than
+    /// matching a flow step itself
+    pub fn is_structural(&self) -> bool {
+        matches!(self, QueryOps::QStart | QueryOps::QEnd | QueryOps::QGap)
+    }
+
+    /// Whether the step is checked against the flow as a whole rather than
+    /// matching one of its steps: notes, links, steps at a position, and
+    /// negations unless the query is `positional`, having anchors or gaps,
+    /// and queries joined with `&&`
+    fn is_flow_level(&self, positional: bool) -> bool {
+        match self {
+            QueryOps::QNote(_)
+            | QueryOps::QLen(_)
+            | QueryOps::QEdge { .. }
+            | QueryOps::QAt(..)
+            | QueryOps::QAll(_) => true,
+            QueryOps::QNot(inner) => !positional || inner.is_flow_level(positional),
+            _ => false,
+        }
+    }
+
+    /// Parse the in-degree bounds after the name of a type variable: none,
+    /// a comparison as [QueryOps::parse_typevar_count] reads, or `=` with a
+    /// count or range
+    fn parse_typevar_name(name: &str, bounds: &str) -> Result<QueryOps, String> {
+        let (min, max) = match bounds {
+            "" => (0, None),
+            _ if bounds.starts_with(['<', '>']) || bounds.starts_with('=') => {
+                match Self::parse_typevar_count(bounds.trim_start_matches('='))? {
+                    QueryOps::QTypeVar(count) => (count, Some(count)),
+                    QueryOps::QTypeVarRange { min, max } => (min, max),
+                    _ => unreachable!("parse_typevar_count reads counts"),
                 }
             }
+            _ => {
+                return Err(format!(
+                    "Expected a comparison like >=2 or =2 after #{}",
+                    name
+                ))
+            }
+        };
+        Ok(QueryOps::QTypeVarName {
+            name: Name::pattern(name),
+            min,
+            max,
+        })
+    }
 
-            // Handle quoted description: "desc"
-            s if s.starts_with('"') && s.ends_with('"') => {
-                Ok(QueryOps::QDesc(s[1..s.len() - 1].to_string()))
-            }
-
-            // Handle type: List or List:desc
-            s => {
-                let parts: Vec<&str> = s.split(':').collect();
-                match parts.as_slice() {
-                    [name] => Ok(QueryOps::QType(QType {
-                        name: name.to_string(),
-                        desc: None,
-                    })),
-                    [name, desc] => Ok(QueryOps::QType(QType {
-                        name: name.to_string(),
-                        desc: Some(desc.to_string()),
-                    })),
-                    _ => Err("Invalid type syntax".to_string()),
-                }
+    fn parse_typevar_count(s: &str) -> Result<QueryOps, String> {
+        let count = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| "Invalid type variable count".to_string())
+        };
+        let range = |min, max| Ok(QueryOps::QTypeVarRange { min, max });
+        if let Some(n) = s.strip_prefix(">=") {
+            range(count(n)?, None)
+        } else if let Some(n) = s.strip_prefix("<=") {
+            range(0, Some(count(n)?))
+        } else if let Some(n) = s.strip_prefix('>') {
+            range(count(n)? + 1, None)
+        } else if let Some(n) = s.strip_prefix('<') {
+            match count(n)?.checked_sub(1) {
+                Some(max) => range(0, Some(max)),
+                None => Err("Type variable counts cannot be below 0".to_string()),
             }
+        } else if let Some((min, max)) = s.split_once("..") {
+            match (count(min)?, count(max)?) {
+                (min, max) if min > max => Err(format!("Empty count range {}..{}", min, max)),
+                (min, max) => range(min, Some(max)),
+            }
+        } else {
+            count(s).map(QueryOps::QTypeVar)
         }
     }
 
-    pub fn parse_query(input: &str) -> Result<Vec<QueryOps>, String> {
-        input
-            .split(',')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .map(Self::parse_token)
-            .collect()
+    /// Parse a comma-separated query with [parser], reporting the span of
+    /// the first part that fails to parse
+    pub fn parse_query(input: &str) -> error::Result<Vec<QueryOps>> {
+        parser::parse_query(input)
     }
 }
 
@@ -327,7 +1979,7 @@ mod tests {
         let query = QueryOps::parse_query("@x.1").unwrap();
         if let QueryOps::QConstructorArg(qa) = &query[0] {
             assert_eq!(qa.name, "x");
-            assert_eq!(qa.arg_index, Some(1));
+            assert_eq!(qa.arg_index, Some(ArgIndex::One(1)));
         }
 
         // Test description
@@ -342,12 +1994,13 @@ mod tests {
             query,
             vec![
                 QueryOps::QType(QType {
-                    name: "List".to_string(),
+                    name: "List".into(),
+                    args: None,
                     desc: None,
                 }),
                 QueryOps::QConstructorArg(QConstructorArg {
-                    name: "x".to_string(),
-                    arg_index: Some(2),
+                    name: "x".into(),
+                    arg_index: Some(ArgIndex::One(2)),
                     desc: None
                 }),
                 QueryOps::QDesc("foo bar".to_string())
@@ -359,7 +2012,8 @@ mod tests {
             query,
             vec![
                 QueryOps::QType(QType {
-                    name: "bool".to_string(),
+                    name: "bool".into(),
+                    args: None,
                     desc: None,
                 }),
                 QueryOps::QDesc("if-then-else condition".to_string())
@@ -370,8 +2024,8 @@ mod tests {
         assert_eq!(
             query,
             vec![QueryOps::QConstructorArg(QConstructorArg {
-                name: "Tuple".to_string(),
-                arg_index: Some(2),
+                name: "Tuple".into(),
+                arg_index: Some(ArgIndex::One(2)),
                 desc: None,
             })]
         );
@@ -381,16 +2035,590 @@ mod tests {
             query,
             vec![
                 QueryOps::QType(QType {
-                    name: "bool".to_string(),
+                    name: "bool".into(),
+                    args: None,
                     desc: None,
                 }),
                 QueryOps::QConstructorArg(QConstructorArg {
-                    name: "Tuple".to_string(),
-                    arg_index: Some(1),
+                    name: "Tuple".into(),
+                    arg_index: Some(ArgIndex::One(1)),
                     desc: None,
                 }),
                 QueryOps::QDesc("if-then-else condition".to_string())
             ]
         );
     }
+
+    #[test]
+    fn test_longest_matching_prefix() {
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("bool, @Tuple, List").unwrap();
+        assert_eq!(db.longest_matching_prefix(&query), (2, 1));
+        assert_eq!(db.longest_matching_prefix(&query[..1]), (1, 2));
+        assert_eq!(db.longest_matching_prefix(&query[2..]), (1, 2));
+    }
+
+    #[test]
+    fn test_anchors_and_gaps() {
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        assert_eq!(indices("^, bool"), vec![0, 5]);
+        assert_eq!(indices("^, bool, @Tuple"), vec![0]);
+        assert_eq!(indices("List, @Cons"), vec![2]);
+        assert_eq!(indices("^, List, @Cons"), Vec::<usize>::new());
+        assert_eq!(indices("^, List, .., @Cons"), vec![2]);
+        assert_eq!(indices("List, *, @Cons"), vec![2]);
+        assert_eq!(indices("bool, $"), Vec::<usize>::new());
+        assert_eq!(indices("^, bool, .., $"), vec![0, 5]);
+        assert_eq!(
+            QueryOps::parse_query("^, List, .., $")
+                .unwrap()
+                .iter()
+                .map(QueryOps::to_string)
+                .collect::<Vec<_>>(),
+            vec!["^", "List", "..", "$"]
+        );
+    }
+
+    #[test]
+    fn test_negation() {
+        let mut db = crate::tutorial::load_database();
+        let indices = |db: &Database, query: &str| {
+            db.matching_indices(&QueryOps::parse_query(query).unwrap())
+        };
+
+        assert_eq!(indices(&db, "!List"), vec![0, 1, 4, 5]);
+        assert_eq!(indices(&db, "bool, !@Tuple"), vec![5]);
+        assert_eq!(indices(&db, "!@Tuple.2, @Tuple"), vec![0]);
+        assert_eq!(indices(&db, "^, !bool"), vec![1, 2, 3, 4]);
+        assert_eq!(indices(&db, "^, List, !@Cons"), vec![2, 3]);
+
+        db.set_notes([(flow_id(&db.data_flows[0]), vec!["noise".to_string()])].into());
+        assert_eq!(indices(&db, "bool, !note:noise"), vec![5]);
+        assert!(QueryOps::parse_query("!^").is_err());
+    }
+
+    #[test]
+    fn test_desc_constraints() {
+        let tutorial = crate::tutorial::load_database();
+        let mut flows = tutorial.data_flows.to_vec();
+        for uf in &mut flows[2] {
+            if let UnitFlow::ConstructorArg(c) = uf {
+                c.desc = Some("head of list".into());
+            }
+        }
+        let db = tutorial.with_flows(flows);
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        assert_eq!(indices("List:user input"), vec![3]);
+        assert_eq!(indices("List:output"), Vec::<usize>::new());
+        assert_eq!(indices("@Cons:head"), vec![2]);
+        assert_eq!(indices("@Cons.1:head of list"), vec![2]);
+        assert_eq!(indices("@Cons.2:head of list"), Vec::<usize>::new());
+        assert_eq!(indices("@Cons.1:tail"), Vec::<usize>::new());
+        assert_eq!(indices("@Some.1:head"), Vec::<usize>::new());
+
+        let query = QueryOps::parse_query("@Cons.1:a.b").unwrap();
+        assert_eq!(
+            query,
+            vec![QueryOps::QConstructorArg(QConstructorArg {
+                name: "Cons".into(),
+                arg_index: Some(ArgIndex::One(1)),
+                desc: Some("a.b".to_string()),
+            })]
+        );
+        assert_eq!(query[0].to_string(), "@Cons.1:a.b");
+        assert!(QueryOps::parse_query("@Cons.x:desc").is_err());
+
+        assert_eq!(indices("@Cons.{0,1}:head"), vec![2]);
+        assert_eq!(indices("@Cons.{ 2, 3 }"), Vec::<usize>::new());
+        assert_eq!(indices("@Cons.0..1"), vec![2]);
+        assert_eq!(indices("@Cons.2..4"), Vec::<usize>::new());
+        for query in ["@Cons.{0,2}:head", "@Cons.1..3"] {
+            let ops = QueryOps::parse_query(query).unwrap();
+            assert_eq!(ops[0].to_string(), query);
+        }
+        assert!(QueryOps::parse_query("@Cons.3..1").is_err());
+        assert!(QueryOps::parse_query("@Cons.{1,x}").is_err());
+    }
+
+    #[test]
+    fn test_typevar_count_ranges() {
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        assert_eq!(indices("#>=2"), vec![1, 2, 3, 4]);
+        assert_eq!(indices("#>2"), Vec::<usize>::new());
+        assert_eq!(indices("#<3"), vec![1, 2, 3, 4]);
+        assert_eq!(indices("#<=1"), Vec::<usize>::new());
+        assert_eq!(indices("#1..2, @Cons"), vec![2]);
+        assert_eq!(
+            db.typevar_degrees().collect::<Vec<_>>(),
+            vec![("'a", 2), ("'b", 2)]
+        );
+
+        for query in ["#>=2", "#<=4", "#1..5"] {
+            assert_eq!(QueryOps::parse_query(query).unwrap()[0].to_string(), query);
+        }
+        assert!(QueryOps::parse_query("#<0").is_err());
+        assert!(QueryOps::parse_query("#5..2").is_err());
+    }
+
+    #[test]
+    fn test_named_typevars() {
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        let a = indices("#'a");
+        assert_eq!(a.len(), 2);
+        assert_eq!(indices("#'a>=2"), a);
+        assert_eq!(indices("#'a=2"), a);
+        assert!(indices("#'a>2").is_empty());
+        assert!(indices("#'c").is_empty());
+        assert_eq!(
+            QueryOps::parse_query("#'a_1>=2").unwrap()[0],
+            QueryOps::QTypeVarName {
+                name: "'a_1".into(),
+                min: 2,
+                max: None,
+            }
+        );
+
+        for query in ["#'a", "#'a>=2", "#'a<=3", "#'a=2", "#'a=1..3"] {
+            assert_eq!(QueryOps::parse_query(query).unwrap()[0].to_string(), query);
+        }
+        assert!(QueryOps::parse_query("#'a 2").is_err());
+        assert!(QueryOps::parse_query("#'a=3..1").is_err());
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let tutorial = crate::tutorial::load_database();
+        let flows = tutorial.data_flows.iter().cycle();
+        let db = tutorial.with_flows(
+            flows
+                .take(PARALLEL_SEARCH_FLOWS)
+                .map(<[UnitFlow]>::to_vec)
+                .collect(),
+        );
+        let query = QueryOps::parse_query("List, @Cons").unwrap();
+
+        let found = db.search(&query);
+        assert_eq!(found.len(), db.matching_indices(&query).len());
+        assert!(found
+            .iter()
+            .all(|flow| flow_id(flow) == flow_id(&tutorial.data_flows[2])));
+    }
+
+    #[test]
+    fn test_match_positions() {
+        let db = crate::tutorial::load_database();
+        let positions = |idx: usize, query: &str| {
+            db.match_flow_positions(&db.data_flows[idx], &QueryOps::parse_query(query).unwrap())
+        };
+
+        assert_eq!(positions(2, "List, @Cons"), Some(vec![0, 2]));
+        assert_eq!(positions(2, "List, .., @Cons, $"), None);
+        assert_eq!(
+            positions(0, "^, bool, .., \"if-then-else condition\""),
+            Some(vec![0, 3])
+        );
+        assert_eq!(positions(5, "bool, !@Tuple"), Some(vec![0]));
+        assert_eq!(positions(3, "bool"), None);
+    }
+
+    #[test]
+    fn test_pattern_queries() {
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        assert_eq!(indices("Li.*"), indices("List"));
+        assert_eq!(indices("L"), Vec::<usize>::new());
+        assert_eq!(indices("bool|List"), vec![0, 2, 3, 5]);
+        assert_eq!(indices("\"if-.*\""), indices("\"if-then-else condition\""));
+        assert_eq!(indices("\"(unclosed\""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_single_flow_export() {
+        let db = crate::tutorial::load_database();
+        let repro = db.with_flows(vec![db.data_flows[2].to_vec()]);
+        let loaded = Database::load_from_str(&repro.to_json().unwrap()).unwrap();
+
+        assert_eq!(loaded.file_path, db.file_path);
+        assert_eq!(flow_id(&loaded.data_flows[0]), flow_id(&db.data_flows[2]));
+        assert_eq!(loaded.type_names().collect::<Vec<_>>(), vec!["List"]);
+        assert_eq!(loaded.type_vars.iter().collect::<Vec<_>>(), vec!["'a"]);
+    }
+
+    #[test]
+    fn test_matched_flows_export() {
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("List").unwrap();
+        let subset = db.with_flows(
+            db.search(&query)
+                .into_iter()
+                .map(<[UnitFlow]>::to_vec)
+                .collect(),
+        );
+        let loaded = Database::load_from_str(&subset.to_json().unwrap()).unwrap();
+
+        assert_eq!(loaded.file_path, db.file_path);
+        assert_eq!(loaded.language, db.language);
+        let ids = |db: &Database| db.data_flows.iter().map(flow_id).collect::<Vec<_>>();
+        assert_eq!(ids(&loaded), ids(&subset));
+        assert_eq!(loaded.matching_indices(&query), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_flow_narration() {
+        let flow = vec![
+            UnitFlow::Type(Type {
+                name: "bool".into(),
+                args: vec![],
+                desc: None,
+            }),
+            UnitFlow::ConstructorArg(ConstructorArg {
+                name: "Tuple".into(),
+                arg_index: 1,
+                desc: None,
+            }),
+            UnitFlow::ProgLoc(ProgLoc {
+                line: "  if cond then a else b".into(),
+                char_range: (6, 10),
+                desc: Some("if-then-else condition".into()),
+                depth: 0,
+                file: None,
+                line_number: Some(42),
+                column: None,
+                commit: None,
+            }),
+        ];
+
+        assert_eq!(
+            UnitFlow::narrate_flow(&flow, &Language::Generic),
+            vec![
+                "A value of type `bool` enters the flow.",
+                "It flows into the 1st argument of `Tuple`.",
+                "It appears as `cond` in `if cond then a else b` (if-then-else condition).",
+            ]
+        );
+        assert_eq!(ordinal(12), "12th");
+        assert_eq!(ordinal(22), "22nd");
+    }
+
+    #[test]
+    fn test_type_bindings() {
+        let list = |arg: &str| {
+            UnitFlow::Type(Type {
+                name: "List".into(),
+                args: vec![arg.into()],
+                desc: None,
+            })
+        };
+        let var = |name: &str| {
+            UnitFlow::TypeVar(TypeVar {
+                name: name.into(),
+                desc: None,
+            })
+        };
+        let int = UnitFlow::Type(Type {
+            name: "int".into(),
+            args: vec![],
+            desc: None,
+        });
+
+        let flow = vec![list("'a"), var("'b"), int, var("'c"), list("int")];
+        let bindings = UnitFlow::type_bindings(&flow, &Language::Generic);
+        assert_eq!(bindings.get("'a").map(String::as_str), Some("int"));
+        assert_eq!(bindings.get("'b").map(String::as_str), Some("List<int>"));
+        assert_eq!(bindings.get("'c").map(String::as_str), Some("int"));
+
+        assert_eq!(
+            UnitFlow::elaborated_types(&flow, &Language::Generic),
+            vec![
+                Some("List<int>".to_string()),
+                Some("List<int>".to_string()),
+                Some("int".to_string()),
+                Some("int".to_string()),
+                Some("List<int>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unicode_ranges() {
+        let loc = |line: &str, char_range| ProgLoc {
+            line: line.into(),
+            char_range,
+            desc: None,
+            depth: 1,
+            file: None,
+            line_number: None,
+            column: None,
+            commit: None,
+        };
+
+        let accented = loc("let café = crème", (5, 9));
+        assert!(accented.has_valid_range());
+        assert_eq!(accented.span_text(), Some("café"));
+        assert_eq!(accented.carets(DEFAULT_TAB_WIDTH), "      ^^^^        ");
+
+        let wide = loc("s = \"日本\" ^ 🦀", (6, 8));
+        assert_eq!(wide.span_text(), Some("日本"));
+        assert_eq!(wide.carets(DEFAULT_TAB_WIDTH), "       ^^^^      ");
+        assert_eq!(wide.split_span(), Some(("s = \"", "日本", "\" ^ 🦀")));
+        assert_eq!(loc("日本", (3, 3)).split_span(), Some(("日本", "", "")));
+        assert_eq!(loc("日本", (2, 1)).split_span(), None);
+        assert!(loc("日本", (1, 3)).has_valid_range());
+        assert!(!loc("日本", (1, 7)).has_valid_range());
+    }
+
+    #[test]
+    fn test_insertion_points_and_whole_lines() {
+        let inserted = ProgLoc::new("f x y", (3, 3));
+        assert!(inserted.has_valid_range() && inserted.is_insertion_point());
+        assert_eq!(inserted.carets(DEFAULT_TAB_WIDTH), "  ^  ");
+        assert_eq!(inserted.span_text(), None);
+        let rendered = ProgLoc::render_marked_location(
+            &inserted,
+            &1,
+            Highlight::Normal,
+            HighlightStyle::Inline,
+            DEFAULT_TAB_WIDTH,
+        )
+        .unwrap();
+        assert!(rendered.contains('^'));
+        assert_eq!(
+            ProgLoc::new("f x y", (6, 6)).carets(DEFAULT_TAB_WIDTH),
+            "     ^"
+        );
+        assert_eq!(ProgLoc::new("", (1, 1)).carets(DEFAULT_TAB_WIDTH), "^");
+        assert!(!ProgLoc::new("f x y", (7, 7)).has_valid_range());
+
+        // Running onto the newline takes in the whole line
+        let whole = ProgLoc::new("f x y", (1, 7));
+        assert!(whole.has_valid_range());
+        assert_eq!(whole.span_text(), Some("f x y"));
+        assert_eq!(whole.carets(DEFAULT_TAB_WIDTH), "^^^^^");
+        assert_eq!(whole.columns(), (1, 6));
+        assert!(!ProgLoc::new("f x y", (1, 8)).has_valid_range());
+    }
+
+    #[test]
+    fn test_tabs_and_crlf_line_endings() {
+        // The tab reaches the stop at column 4, then 8 after `x`
+        let tabbed = ProgLoc::new("\tx\ty = 1", (4, 5));
+        assert_eq!(tabbed.span_text(), Some("y"));
+        assert_eq!(tabbed.expanded_line(4), "    x   y = 1");
+        assert_eq!(tabbed.carets(4), "        ^    ");
+        assert_eq!(tabbed.carets(2), "    ^    ");
+        let rendered = ProgLoc::render_marked_location(
+            &tabbed,
+            &1,
+            Highlight::Normal,
+            HighlightStyle::Underline,
+            4,
+        )
+        .unwrap();
+        let rows: Vec<&str> = rendered.lines().collect();
+        assert_eq!(rows[0].find('y'), rows[1].find('^'));
+        assert!(!rendered.contains('\t'));
+
+        let crlf = ProgLoc::new("let x = 1\r", (5, 6));
+        assert!(crlf.has_valid_range());
+        assert_eq!(crlf.split_span(), Some(("let ", "x", " = 1")));
+        assert_eq!(crlf.carets(DEFAULT_TAB_WIDTH), "    ^    ");
+        assert_eq!(crlf.expanded_line(DEFAULT_TAB_WIDTH), "let x = 1");
+        // Running onto the `\r\n` takes in the whole line, without the `\r`
+        let whole = ProgLoc::new("let x = 1\r", (1, 12));
+        assert!(whole.has_valid_range());
+        assert_eq!(whole.span_text(), Some("let x = 1"));
+        assert_eq!(whole.columns(), (1, 10));
+        let rendered = ProgLoc::render_marked_location(
+            &whole,
+            &1,
+            Highlight::Normal,
+            HighlightStyle::Both,
+            DEFAULT_TAB_WIDTH,
+        )
+        .unwrap();
+        assert!(!rendered.contains('\r'));
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let path = std::env::temp_dir().join(format!("scs-context-{}.ml", std::process::id()));
+        fs::write(&path, "a\nb\nc\nd\ne\n").unwrap();
+        let loc = ProgLoc {
+            line: "let b".into(),
+            char_range: (1, 4),
+            desc: None,
+            depth: 0,
+            file: None,
+            line_number: Some(2),
+            column: None,
+            commit: None,
+        };
+        let file = path.to_string_lossy();
+        let (above, below) = loc.context_lines(&file, 2).unwrap();
+        assert_eq!(above, vec![(1, "a".to_string())]);
+        assert_eq!(below, vec![(3, "c".to_string()), (4, "d".to_string())]);
+        assert!(loc.context_lines("/nonexistent.ml", 2).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_location_columns() {
+        let loc = ProgLoc::new("x + y", (5, 6))
+            .with_file("lib/add.ml")
+            .with_line_number(3);
+        assert_eq!(loc.columns(), (5, 6));
+        let loc = loc.with_column(13);
+        assert_eq!(loc.columns(), (13, 14));
+        let pos = loc.position("lib/main.ml").unwrap();
+        assert_eq!(pos.file, "lib/add.ml");
+        assert_eq!(pos.column, Some(13));
+        assert!(loc.is_at(&pos, "lib/main.ml"));
+        assert!(!loc.is_at(
+            &Position {
+                column: Some(5),
+                ..pos
+            },
+            "lib/main.ml"
+        ));
+    }
+
+    #[test]
+    fn test_position_parsing() {
+        assert_eq!(
+            Position::parse("src/main.ml:42").unwrap(),
+            Position {
+                file: "src/main.ml".to_string(),
+                line: 42,
+                column: None,
+            }
+        );
+        assert_eq!(
+            Position::parse("src/main.ml:42:7").unwrap(),
+            Position {
+                file: "src/main.ml".to_string(),
+                line: 42,
+                column: Some(7),
+            }
+        );
+        assert_eq!(
+            Position::parse("C:\\src\\main.ml:3").unwrap().file,
+            "C:\\src\\main.ml"
+        );
+        assert!(Position::parse("src/main.ml").is_err());
+        assert!(Position::parse("src/main.ml:x").is_err());
+    }
+
+    #[test]
+    fn test_search_page() {
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("..").unwrap();
+        let ids = |flows: Vec<&DataFlow>| -> Vec<String> {
+            flows.into_iter().map(flow_id).collect()
+        };
+        let all = ids(db.search(&query));
+        assert_eq!(all.len(), 6);
+        assert_eq!(ids(db.search_page(&query, 0, Some(2))), all[..2]);
+        assert_eq!(ids(db.search_page(&query, 4, None)), all[4..]);
+        assert_eq!(ids(db.search_page(&query, 5, Some(3))), all[5..]);
+        assert!(db.search_page(&query, 9, Some(1)).is_empty());
+        assert_eq!(db.count_matches(&QueryOps::parse_query("List").unwrap()), 2);
+    }
+
+    #[test]
+    fn test_search_inverted() {
+        let db = crate::tutorial::load_database();
+        let query = |query: &str| QueryOps::parse_query(query).unwrap();
+        let indices = |flows: Vec<&DataFlow>| -> Vec<usize> {
+            flows
+                .into_iter()
+                .map(|flow| db.data_flows.iter().position(|f| std::ptr::eq(f, flow)).unwrap())
+                .collect()
+        };
+        assert_eq!(
+            indices(db.search_inverted(&query("List"), None)),
+            vec![0, 1, 4, 5]
+        );
+        let scope = query("\"if-then*\"");
+        assert_eq!(
+            indices(db.search_inverted(&query("List"), Some(&scope))),
+            vec![0, 5]
+        );
+        assert!(db
+            .search_inverted(&query("Li*"), Some(&query("List")))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_case_and_prefix_matching() {
+        let mut db = crate::tutorial::load_database();
+        let indices = |db: &Database, query: &str| {
+            db.matching_indices(&QueryOps::parse_query(query).unwrap())
+        };
+        assert_eq!(indices(&db, "list"), Vec::<usize>::new());
+        assert_eq!(indices(&db, "Li*"), vec![2, 3]);
+        assert_eq!(indices(&db, "@Tu*.2"), vec![1]);
+        assert_eq!(indices(&db, "\"if-then*\""), vec![0, 5]);
+
+        db.set_ignore_case(true);
+        assert_eq!(indices(&db, "list"), vec![2, 3]);
+        assert_eq!(indices(&db, "li*"), vec![2, 3]);
+        assert_eq!(indices(&db, "@cons"), vec![2]);
+        assert_eq!(indices(&db, "STR.NG"), vec![4]);
+        assert!(db
+            .suggestions(&QueryOps::parse_query("LIST").unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_step_positions() {
+        let db = crate::tutorial::load_database();
+        let parse = |query: &str| QueryOps::parse_query(query).unwrap();
+        let indices = |query: &str| db.matching_indices(&parse(query));
+        assert_eq!(indices("[0]bool"), vec![0, 5]);
+        assert_eq!(indices("[1]@Tuple"), vec![0, 1]);
+        assert_eq!(indices("[1]#2"), vec![2, 4]);
+        assert_eq!(indices("[0]List, @Cons"), vec![2]);
+        assert_eq!(indices("[0]!bool, @Tuple"), vec![1]);
+        assert_eq!(indices("^, @Tuple, [0]int"), Vec::<usize>::new());
+        assert_eq!(parse("[2] 'a")[0].to_string(), "[2]'a");
+        assert_eq!(db.contradictions(&parse("[5]bool")).len(), 1);
+        assert!(QueryOps::parse_query("[0]List as l").is_err());
+        assert!(QueryOps::parse_query("[0]List -> @Cons").is_err());
+    }
+
+    #[test]
+    fn test_query_composition() {
+        let db = crate::tutorial::load_database();
+        let parse = |query: &str| QueryOps::parse_query(query).unwrap();
+        let indices = |query: &str| db.matching_indices(&parse(query));
+        assert_eq!(indices("@Cons, List"), Vec::<usize>::new());
+        assert_eq!(indices("@Cons && List"), vec![2]);
+        assert_eq!(indices("List, @Some && #2"), vec![3]);
+        assert_eq!(indices("List && bool"), Vec::<usize>::new());
+        assert_eq!(
+            db.match_flow_positions(&db.data_flows[2], &parse("@Cons && List")),
+            Some(vec![2, 0])
+        );
+        assert_eq!(
+            parse("List,@Cons&&bool")[0].to_string(),
+            "List, @Cons && bool"
+        );
+
+        let captures = db
+            .captures(&db.data_flows[2], &parse("@Cons as c && List as l"))
+            .unwrap();
+        assert_eq!(captures["c"].label(), "@Cons.1");
+        assert_eq!(captures["l"].label(), "List");
+
+        assert!(QueryOps::parse_query("&& List").is_err());
+        assert!(QueryOps::parse_query("List &&").is_err());
+    }
 }
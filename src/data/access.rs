@@ -0,0 +1,220 @@
+use super::{ConstructorArg, Name, ProgLoc, Str, Type, TypeVar};
+
+impl Type {
+    /// A type without a description, `args` being type names or variables
+    pub fn new(name: impl Into<Name>, args: Vec<String>) -> Self {
+        Type {
+            name: name.into(),
+            args: args.into_iter().map(Str::from).collect(),
+            desc: None,
+        }
+    }
+
+    pub fn with_desc(self, desc: impl Into<Str>) -> Self {
+        Type {
+            desc: Some(desc.into()),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn args(&self) -> &[Str] {
+        &self.args
+    }
+
+    pub fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
+    }
+}
+
+impl ConstructorArg {
+    /// The `arg_index`th (1-based) argument of the constructor `name`
+    pub fn new(name: impl Into<Name>, arg_index: usize) -> Self {
+        ConstructorArg {
+            name: name.into(),
+            arg_index,
+            desc: None,
+        }
+    }
+
+    pub fn with_desc(self, desc: impl Into<Str>) -> Self {
+        ConstructorArg {
+            desc: Some(desc.into()),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arg_index(&self) -> usize {
+        self.arg_index
+    }
+
+    pub fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
+    }
+}
+
+impl TypeVar {
+    /// A type variable such as `'a`
+    pub fn new(name: impl Into<Name>) -> Self {
+        TypeVar {
+            name: name.into(),
+            desc: None,
+        }
+    }
+
+    pub fn with_desc(self, desc: impl Into<Str>) -> Self {
+        TypeVar {
+            desc: Some(desc.into()),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
+    }
+}
+
+impl ProgLoc {
+    /// A location in the database's `file_path` at depth 0, highlighting the
+    /// 1-based, end-exclusive character range `char_range` of `line`
+    pub fn new(line: impl Into<Str>, char_range: (usize, usize)) -> Self {
+        ProgLoc {
+            line: line.into(),
+            char_range,
+            desc: None,
+            depth: 0,
+            file: None,
+            line_number: None,
+            column: None,
+            commit: None,
+        }
+    }
+
+    pub fn with_desc(self, desc: impl Into<Str>) -> Self {
+        ProgLoc {
+            desc: Some(desc.into()),
+            ..self
+        }
+    }
+
+    pub fn with_depth(self, depth: usize) -> Self {
+        ProgLoc { depth, ..self }
+    }
+
+    pub fn with_file(self, file: impl Into<Str>) -> Self {
+        ProgLoc {
+            file: Some(file.into()),
+            ..self
+        }
+    }
+
+    pub fn with_line_number(self, line_number: usize) -> Self {
+        ProgLoc {
+            line_number: Some(line_number),
+            ..self
+        }
+    }
+
+    pub fn with_column(self, column: usize) -> Self {
+        ProgLoc {
+            column: Some(column),
+            ..self
+        }
+    }
+
+    pub fn with_commit(self, commit: impl Into<Str>) -> Self {
+        ProgLoc {
+            commit: Some(commit.into()),
+            ..self
+        }
+    }
+
+    /// The source line the location is on
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn desc(&self) -> Option<&str> {
+        self.desc.as_deref()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Source file of the location, when it differs from the database
+    /// `file_path`
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// 1-based line number of [ProgLoc::line] in its source file
+    pub fn line_number(&self) -> Option<usize> {
+        self.line_number
+    }
+
+    /// 1-based column of the start of the span in its source file, when
+    /// [ProgLoc::line] is not the whole source line
+    pub fn column(&self) -> Option<usize> {
+        self.column
+    }
+
+    pub fn commit(&self) -> Option<&str> {
+        self.commit.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Database, QueryOps, UnitFlow};
+
+    #[test]
+    fn test_build_and_inspect_flows() {
+        let flow = vec![
+            UnitFlow::Type(Type::new("List", vec!["'a".to_string()])),
+            UnitFlow::ProgLoc(
+                ProgLoc::new("let xs = [x]", (5, 7))
+                    .with_file("lib/a.ml")
+                    .with_line_number(4),
+            ),
+            UnitFlow::ConstructorArg(ConstructorArg::new("Cons", 1).with_desc("head")),
+            UnitFlow::TypeVar(TypeVar::new("'a")),
+        ];
+        let db = Database::new("lib/main.ml".to_string(), vec![flow]);
+        let query = QueryOps::parse_query("List, @Cons.1:head").unwrap();
+        let matched = db.search(&query);
+        assert_eq!(matched.len(), 1);
+
+        let steps = &matched[0];
+        match (&steps[0], &steps[1], &steps[2], &steps[3]) {
+            (
+                UnitFlow::Type(t),
+                UnitFlow::ProgLoc(loc),
+                UnitFlow::ConstructorArg(c),
+                UnitFlow::TypeVar(tv),
+            ) => {
+                assert_eq!((t.name(), t.args()), ("List", &["'a".into()][..]));
+                assert_eq!((loc.file(), loc.line_number()), (Some("lib/a.ml"), Some(4)));
+                assert_eq!(loc.span_text(), Some("xs"));
+                assert_eq!(
+                    (c.name(), c.arg_index(), c.desc()),
+                    ("Cons", 1, Some("head"))
+                );
+                assert_eq!((tv.name(), tv.desc()), ("'a", None));
+            }
+            _ => panic!("steps out of order"),
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use super::parser::is_identifier;
+use super::QueryOps;
+use crate::error::{self, SemanticSearchError};
+use std::collections::BTreeMap;
+
+/// Queries by name, usable as steps of other queries
+pub type Aliases = BTreeMap<String, String>;
+
+/// A step or `&&`-joined query of a query, with the byte offset it starts
+/// at and the separator following it, if any
+struct Piece<'a> {
+    start: usize,
+    text: &'a str,
+    separator: Option<&'static str>,
+}
+
+/// Split `query` at the commas and `&&`s between its steps, outside quotes,
+/// type arguments and braces
+fn pieces(query: &str) -> Vec<Piece<'_>> {
+    let mut pieces = vec![];
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut chars = query.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let separator = match c {
+            _ if escaped => {
+                escaped = false;
+                None
+            }
+            '\\' if quoted => {
+                escaped = true;
+                None
+            }
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            '<' | '{' if !quoted => {
+                depth += 1;
+                None
+            }
+            '>' | '}' if !quoted => {
+                depth = depth.saturating_sub(1);
+                None
+            }
+            ',' if !quoted && depth == 0 => Some(","),
+            '&' if !quoted && depth == 0 && query[idx..].starts_with("&&") => {
+                chars.next();
+                Some("&&")
+            }
+            _ => None,
+        };
+        if let Some(separator) = separator {
+            pieces.push(Piece {
+                start,
+                text: &query[start..idx],
+                separator: Some(separator),
+            });
+            start = idx + separator.len();
+        }
+    }
+    pieces.push(Piece {
+        start,
+        text: &query[start..],
+        separator: None,
+    });
+    pieces
+}
+
+impl QueryOps {
+    /// `query` with every step that is exactly the name of an alias replaced
+    /// by the steps of that alias, themselves expanded. An alias joining
+    /// queries with `&&` can only stand between other `&&`s, where splicing
+    /// it in keeps its meaning.
+    pub fn expand_aliases(query: &str, aliases: &Aliases) -> error::Result<String> {
+        if aliases.is_empty() {
+            Ok(query.to_string())
+        } else {
+            expand(query, aliases, &mut vec![])
+        }
+    }
+
+    /// Parse `query` after [QueryOps::expand_aliases]
+    pub fn parse_query_with(query: &str, aliases: &Aliases) -> error::Result<Vec<QueryOps>> {
+        QueryOps::parse_query(&QueryOps::expand_aliases(query, aliases)?)
+    }
+}
+
+/// Expand the aliases in `query`, which is the body of the aliases on
+/// `stack` in turn
+fn expand(query: &str, aliases: &Aliases, stack: &mut Vec<String>) -> error::Result<String> {
+    let mut expanded = String::new();
+    let mut before = None;
+    for piece in pieces(query) {
+        let name = piece.text.trim();
+        let body = aliases.get(name).filter(|_| is_identifier(name));
+        let Some(body) = body else {
+            expanded += piece.text;
+            expanded += piece.separator.unwrap_or_default();
+            before = piece.separator;
+            continue;
+        };
+        let start = piece.start + piece.text.len() - piece.text.trim_start().len();
+        let error = |message: String| SemanticSearchError::QueryParse {
+            query: query.to_string(),
+            position: start,
+            end: start + name.len(),
+            message,
+        };
+        if let Some(at) = stack.iter().position(|alias| alias == name) {
+            let mut cycle = stack[at..].to_vec();
+            cycle.push(name.to_string());
+            return Err(error(format!(
+                "Alias `{}` refers back to itself: {}",
+                name,
+                cycle.join(" -> ")
+            )));
+        }
+        stack.push(name.to_string());
+        // Errors in the aliases used are reported where this one is used
+        let body = expand(body, aliases, stack).map_err(|e| match e {
+            SemanticSearchError::QueryParse { message, .. } => error(message),
+            e => e,
+        })?;
+        stack.pop();
+
+        let joins = pieces(&body).iter().any(|p| p.separator == Some("&&"));
+        if joins && (before == Some(",") || piece.separator == Some(",")) {
+            return Err(error(format!(
+                "Alias `{}` joins queries with `&&`, so it cannot be a step between commas",
+                name
+            )));
+        }
+        expanded += &piece.text[..piece.text.len() - piece.text.trim_start().len()];
+        expanded += body.trim();
+        expanded += &piece.text[piece.text.trim_end().len()..];
+        expanded += piece.separator.unwrap_or_default();
+        before = piece.separator;
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aliases(defs: &[(&str, &str)]) -> Aliases {
+        defs.iter()
+            .map(|(name, body)| (name.to_string(), body.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let defs = aliases(&[
+            ("unsafe_flow", "bool,@Tuple.1,\"if-then-else condition\""),
+            ("guarded", "^, unsafe_flow, .."),
+            ("both", "List && @Cons"),
+            ("loop", "List, back"),
+            ("back", "Option, loop"),
+        ]);
+        let expand = |query: &str| QueryOps::expand_aliases(query, &defs);
+
+        assert_eq!(
+            expand("guarded, $").unwrap(),
+            "^, bool,@Tuple.1,\"if-then-else condition\", .., $"
+        );
+        // Only whole steps are aliases, not names inside other steps
+        assert_eq!(
+            expand("unsafe_flow:x, \"guarded\"").unwrap(),
+            "unsafe_flow:x, \"guarded\""
+        );
+        assert_eq!(expand("both && int").unwrap(), "List && @Cons && int");
+        assert_eq!(
+            QueryOps::parse_query_with("guarded", &defs).unwrap(),
+            QueryOps::parse_query("^, bool, @Tuple.1, \"if-then-else condition\", ..").unwrap()
+        );
+
+        match expand("int, loop") {
+            Err(SemanticSearchError::QueryParse {
+                position, message, ..
+            }) => {
+                assert_eq!(position, 5);
+                assert!(message.ends_with("loop -> back -> loop"), "{}", message);
+            }
+            _ => panic!("cycle not detected"),
+        }
+        assert!(expand("both, int").is_err());
+    }
+}
@@ -0,0 +1,384 @@
+use super::UnitFlow;
+use rayon::prelude::*;
+use serde::de::{self, DeserializeSeed, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::mem::{size_of, take};
+use std::ops::{Index, IndexMut};
+use std::slice;
+
+/// The flows of a [Database](super::Database), their steps stored one after
+/// another in a single arena with the `(start, len)` of each flow, so that
+/// matching reads neighbouring flows from contiguous memory rather than
+/// following a pointer to an allocation of each. Flows are indexed and
+/// iterated as slices of steps, in the order they were added, with the
+/// read-only methods of `Vec<Vec<UnitFlow>>`, and serialized as a list of
+/// lists of steps, the same as it.
+#[derive(Debug, Clone, Default)]
+pub struct Flows {
+    steps: Vec<UnitFlow>,
+    /// Where each flow lies in `steps`; flows follow each other without
+    /// gaps, in order
+    spans: Vec<(usize, usize)>,
+}
+
+impl Flows {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&[UnitFlow]> {
+        let &(start, len) = self.spans.get(idx)?;
+        Some(&self.steps[start..start + len])
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut [UnitFlow]> {
+        let &(start, len) = self.spans.get(idx)?;
+        Some(&mut self.steps[start..start + len])
+    }
+
+    pub fn first(&self) -> Option<&[UnitFlow]> {
+        self.get(0)
+    }
+
+    pub fn last(&self) -> Option<&[UnitFlow]> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    /// Index of `flow` when it is borrowed from these flows, rather than
+    /// a copy of one or a flow from elsewhere
+    pub fn position(&self, flow: &[UnitFlow]) -> Option<usize> {
+        let bytes = (flow.as_ptr() as usize).checked_sub(self.steps.as_ptr() as usize)?;
+        let start = bytes / size_of::<UnitFlow>();
+        if bytes % size_of::<UnitFlow>() != 0 || start + flow.len() > self.steps.len() {
+            return None;
+        }
+        let first = self.spans.partition_point(|&(span, _)| span < start);
+        self.spans[first..]
+            .iter()
+            .take_while(|&&(span, _)| span == start)
+            .position(|&(_, len)| len == flow.len())
+            .map(|idx| first + idx)
+    }
+
+    /// Add a flow after the others
+    pub fn push(&mut self, flow: impl IntoIterator<Item = UnitFlow>) {
+        let start = self.steps.len();
+        self.steps.extend(flow);
+        self.spans.push((start, self.steps.len() - start));
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            steps: &self.steps,
+            spans: self.spans.iter(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut {
+            rest: &mut self.steps,
+            spans: self.spans.iter(),
+        }
+    }
+
+    /// The flows in order, to be searched on all cores
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &[UnitFlow]> {
+        self.spans
+            .par_iter()
+            .map(|&(start, len)| &self.steps[start..start + len])
+    }
+
+    /// Keep only the flows `keep` accepts, in order, packing their steps
+    /// together again
+    pub fn retain(&mut self, mut keep: impl FnMut(&[UnitFlow]) -> bool) {
+        let Flows { steps, spans } = take(self);
+        let mut steps = steps.into_iter();
+        for (_, len) in spans {
+            let kept = keep(&steps.as_slice()[..len]);
+            let flow = steps.by_ref().take(len);
+            if kept {
+                self.push(flow);
+            } else {
+                flow.for_each(drop);
+            }
+        }
+    }
+
+    /// The flows, each in its own list of steps
+    pub fn to_vec(&self) -> Vec<Vec<UnitFlow>> {
+        self.iter().map(<[UnitFlow]>::to_vec).collect()
+    }
+
+    /// Read a list of flows, moving the steps of each straight into the
+    /// arena and calling `check` with the index and number of steps of each
+    /// flow as soon as it is read, to stop at the first one it rejects
+    pub(super) fn deserialize_checked<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        check: impl FnMut(usize, usize) -> Result<(), String>,
+    ) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(FlowsVisitor(check))
+    }
+
+    /// Bytes held by the arena and the spans of the flows in it, without
+    /// what the steps themselves point to
+    pub fn allocated(&self) -> usize {
+        self.steps.capacity() * size_of::<UnitFlow>()
+            + self.spans.capacity() * size_of::<(usize, usize)>()
+    }
+}
+
+impl Index<usize> for Flows {
+    type Output = [UnitFlow];
+
+    fn index(&self, idx: usize) -> &[UnitFlow] {
+        let (start, len) = self.spans[idx];
+        &self.steps[start..start + len]
+    }
+}
+
+impl IndexMut<usize> for Flows {
+    fn index_mut(&mut self, idx: usize) -> &mut [UnitFlow] {
+        let (start, len) = self.spans[idx];
+        &mut self.steps[start..start + len]
+    }
+}
+
+/// The flows of [Flows::iter]
+#[derive(Debug, Clone)]
+pub struct Iter<'a> {
+    steps: &'a [UnitFlow],
+    spans: slice::Iter<'a, (usize, usize)>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a [UnitFlow];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(start, len) = self.spans.next()?;
+        Some(&self.steps[start..start + len])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.spans.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for Iter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let &(start, len) = self.spans.next_back()?;
+        Some(&self.steps[start..start + len])
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// The flows of [Flows::iter_mut]
+#[derive(Debug)]
+pub struct IterMut<'a> {
+    /// The steps of the flows not yet returned
+    rest: &'a mut [UnitFlow],
+    spans: slice::Iter<'a, (usize, usize)>,
+}
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = &'a mut [UnitFlow];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &(_, len) = self.spans.next()?;
+        let (flow, rest) = take(&mut self.rest).split_at_mut(len);
+        self.rest = rest;
+        Some(flow)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.spans.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+impl<'a> IntoIterator for &'a Flows {
+    type Item = &'a [UnitFlow];
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Flows {
+    type Item = &'a mut [UnitFlow];
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+/// The flows, each moved into its own list of steps
+impl IntoIterator for Flows {
+    type Item = Vec<UnitFlow>;
+    type IntoIter = std::vec::IntoIter<Vec<UnitFlow>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut steps = self.steps.into_iter();
+        let flows: Vec<Vec<UnitFlow>> = self
+            .spans
+            .iter()
+            .map(|&(_, len)| steps.by_ref().take(len).collect())
+            .collect();
+        flows.into_iter()
+    }
+}
+
+impl<F: IntoIterator<Item = UnitFlow>> Extend<F> for Flows {
+    fn extend<I: IntoIterator<Item = F>>(&mut self, flows: I) {
+        for flow in flows {
+            self.push(flow);
+        }
+    }
+}
+
+impl<F: IntoIterator<Item = UnitFlow>> FromIterator<F> for Flows {
+    fn from_iter<I: IntoIterator<Item = F>>(flows: I) -> Self {
+        let mut arena = Flows::new();
+        arena.extend(flows);
+        arena
+    }
+}
+
+impl From<Vec<Vec<UnitFlow>>> for Flows {
+    fn from(flows: Vec<Vec<UnitFlow>>) -> Self {
+        flows.into_iter().collect()
+    }
+}
+
+impl From<Flows> for Vec<Vec<UnitFlow>> {
+    fn from(flows: Flows) -> Self {
+        flows.into_iter().collect()
+    }
+}
+
+impl Serialize for Flows {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Flows {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Flows::deserialize_checked(deserializer, |_, _| Ok(()))
+    }
+}
+
+/// Reads the flows of [Flows::deserialize_checked]
+struct FlowsVisitor<C>(C);
+
+impl<'de, C: FnMut(usize, usize) -> Result<(), String>> Visitor<'de> for FlowsVisitor<C> {
+    type Value = Flows;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of flows")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<Flows, A::Error> {
+        let mut flows = Flows::new();
+        flows.spans.reserve(seq.size_hint().unwrap_or(0));
+        loop {
+            let start = flows.steps.len();
+            if seq.next_element_seed(Steps(&mut flows.steps))?.is_none() {
+                return Ok(flows);
+            }
+            let len = flows.steps.len() - start;
+            (self.0)(flows.spans.len(), len).map_err(de::Error::custom)?;
+            flows.spans.push((start, len));
+        }
+    }
+}
+
+/// Appends the steps of one flow to the arena as they are read
+struct Steps<'a>(&'a mut Vec<UnitFlow>);
+
+impl<'de> DeserializeSeed<'de> for Steps<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Steps<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of steps")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(step) = seq.next_element::<UnitFlow>()? {
+            self.0.push(step);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::flow_id;
+
+    #[test]
+    fn test_flow_arena() {
+        let db = crate::tutorial::load_database();
+        let flows = db.data_flows.to_vec();
+        let mut arena = Flows::from(flows.clone());
+        assert_eq!(arena.len(), flows.len());
+        for (idx, flow) in arena.iter().enumerate() {
+            assert_eq!(flow.len(), arena[idx].len());
+            assert_eq!(flow_id(flow), flow_id(&flows[idx]));
+        }
+        assert_eq!(arena.iter().rev().count(), flows.len());
+        assert_eq!(flow_id(arena.first().unwrap()), flow_id(&flows[0]));
+        assert_eq!(
+            flow_id(arena.last().unwrap()),
+            flow_id(flows.last().unwrap())
+        );
+        assert!(Flows::new().last().is_none());
+        for (idx, flow) in arena.iter().enumerate() {
+            assert_eq!(arena.position(flow), Some(idx));
+        }
+        assert_eq!(arena.position(&flows[1]), None);
+        assert_eq!(arena.position(&arena[1][1..]), None);
+        assert_eq!(arena.par_iter().count(), flows.len());
+
+        let json = serde_json::to_string(&arena).unwrap();
+        assert_eq!(json, serde_json::to_string(&flows).unwrap());
+        let bytes = bincode::serialize(&arena).unwrap();
+        assert_eq!(bytes, bincode::serialize(&flows).unwrap());
+        let read: Flows = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(Vec::from(read).len(), flows.len());
+        let read: Flows = serde_json::from_str(&json).unwrap();
+        assert!(read
+            .iter()
+            .zip(&arena)
+            .all(|(a, b)| flow_id(a) == flow_id(b)));
+
+        let short = |flow: &[UnitFlow]| flow.len() < 4;
+        arena.retain(short);
+        let kept: Vec<_> = flows.iter().filter(|flow| short(flow)).collect();
+        assert_eq!(arena.len(), kept.len());
+        for (flow, kept) in arena.iter_mut().zip(&kept) {
+            assert_eq!(flow_id(flow), flow_id(kept));
+        }
+        assert_eq!(arena.into_iter().count(), kept.len());
+    }
+}
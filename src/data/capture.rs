@@ -0,0 +1,103 @@
+use super::{Database, QueryOps, UnitFlow};
+use std::collections::BTreeMap;
+
+impl QueryOps {
+    /// The step without its binding, if any
+    pub fn unbound(&self) -> &QueryOps {
+        match self {
+            QueryOps::QBind(_, op) => op,
+            op => op,
+        }
+    }
+
+    /// The names bound by `query`, each with the index of the flow step it
+    /// captured, given the `positions` a flow matched the query at as
+    /// returned by [Database::match_flow_positions]
+    pub fn captures(query: &[QueryOps], positions: &[usize]) -> BTreeMap<String, usize> {
+        if let [QueryOps::QAll(queries)] = query {
+            let mut captures = BTreeMap::new();
+            let mut positions = positions;
+            for query in queries {
+                let positional = query.iter().any(QueryOps::is_structural);
+                let steps = query
+                    .iter()
+                    .filter(|op| !op.is_structural() && !op.is_flow_level(positional))
+                    .map(QueryOps::step_count)
+                    .sum::<usize>();
+                let (own, rest) = positions.split_at(steps.min(positions.len()));
+                captures.extend(Self::captures(query, own));
+                positions = rest;
+            }
+            return captures;
+        }
+        let positional = query.iter().any(QueryOps::is_structural);
+        let mut at = 0;
+        let mut captures = BTreeMap::new();
+        for op in query
+            .iter()
+            .filter(|op| !op.is_structural() && !op.is_flow_level(positional))
+        {
+            if let (QueryOps::QBind(name, _), Some(idx)) = (op, positions.get(at)) {
+                captures.insert(name.clone(), *idx);
+            }
+            at += op.step_count();
+        }
+        captures
+    }
+}
+
+impl Database {
+    /// The flow steps captured by the names `query` binds, if it matches
+    /// `flow`
+    pub fn captures<'a>(
+        &self,
+        flow: &'a [UnitFlow],
+        query: &[QueryOps],
+    ) -> Option<BTreeMap<String, &'a UnitFlow>> {
+        let positions = self.match_flow_positions(flow, query)?;
+        Some(
+            QueryOps::captures(query, &positions)
+                .into_iter()
+                .map(|(name, idx)| (name, &flow[idx]))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_captures() {
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("List as src, @Cons.1 as sink").unwrap();
+        assert_eq!(query[0].to_string(), "List as src");
+        assert_eq!(db.matching_indices(&query), vec![2]);
+
+        let captures = db.captures(&db.data_flows[2], &query).unwrap();
+        let labels: Vec<_> = captures
+            .iter()
+            .map(|(name, uf)| (name.as_str(), uf.label()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![("sink", "@Cons.1".to_string()), ("src", "List".to_string())]
+        );
+
+        let query =
+            QueryOps::parse_query("^, bool as cond, .., \"if-then-else condition\"").unwrap();
+        assert_eq!(
+            QueryOps::captures(&query, &[0, 3]),
+            [("cond".to_string(), 0)].into()
+        );
+        assert!(db.captures(&db.data_flows[1], &query).is_none());
+
+        assert_eq!(
+            QueryOps::parse_query("\"used as index\"").unwrap(),
+            vec![QueryOps::QDesc("used as index".to_string())]
+        );
+        assert!(QueryOps::parse_query("!List as x").is_err());
+        assert!(QueryOps::parse_query(".. as x").is_err());
+    }
+}
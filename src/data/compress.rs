@@ -0,0 +1,134 @@
+use super::limits::{self, Limited};
+use super::Database;
+use crate::error::{self, SemanticSearchError};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Extensions of compressed databases, stripped to tell their format
+pub const COMPRESSED_SUFFIXES: &[&str] = &[".gz", ".zst"];
+
+/// `path` without a compression extension, so `flows.ndjson.gz` is read as
+/// line-delimited
+pub fn uncompressed_name(path: &str) -> &str {
+    COMPRESSED_SUFFIXES
+        .iter()
+        .find_map(|suffix| path.strip_suffix(suffix))
+        .unwrap_or(path)
+}
+
+impl Database {
+    /// Open the database file at `path`, decompressing it when it starts
+    /// with the gzip or Zstandard magic bytes. Files, and what they
+    /// decompress to, are held to the `max_file_size` of the
+    /// [load limits](super::load_limits).
+    pub fn open_data(path: &str) -> error::Result<Box<dyn Read>> {
+        let io_error = |e| SemanticSearchError::io(path, e);
+        let file = File::open(path).map_err(io_error)?;
+        let size = file.metadata().map_err(io_error)?.len();
+        limits::check_file_size(size).map_err(|message| SemanticSearchError::Limit {
+            path: Some(path.to_string()),
+            message,
+        })?;
+        let mut file = BufReader::new(file);
+        let magic = file.fill_buf().map_err(io_error)?;
+        let (gzipped, zstandard) = (magic.starts_with(GZIP_MAGIC), magic.starts_with(ZSTD_MAGIC));
+        let data = match (gzipped, zstandard) {
+            (true, _) => gzip(file).map_err(io_error)?,
+            (_, true) => zstd(file).map_err(io_error)?,
+            _ => Box::new(file),
+        };
+        Ok(Box::new(Limited::new(data)))
+    }
+
+    /// The contents of the database file at `path`, decompressed like
+    /// [Database::open_data]
+    pub fn read_data(path: &str) -> error::Result<String> {
+        let mut data = String::new();
+        Self::open_data(path)?
+            .read_to_string(&mut data)
+            .map_err(|e| SemanticSearchError::io(path, e))?;
+        Ok(data)
+    }
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn unsupported(feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("compressed, but built without the `{}` feature", feature),
+    )
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(file: BufReader<File>) -> io::Result<Box<dyn Read>> {
+    Ok(Box::new(flate2::read::MultiGzDecoder::new(file)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip(_: BufReader<File>) -> io::Result<Box<dyn Read>> {
+    Err(unsupported("gzip"))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd(file: BufReader<File>) -> io::Result<Box<dyn Read>> {
+    Ok(Box::new(zstd::stream::read::Decoder::new(file)?))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd(_: BufReader<File>) -> io::Result<Box<dyn Read>> {
+    Err(unsupported("zstd"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{with_limits, LoadLimits};
+    use std::{env, fs};
+
+    #[test]
+    fn test_compressed_databases() {
+        let dir = env::temp_dir().join(format!("scs-compress-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let plain = dir.join("tutorial.json").to_string_lossy().to_string();
+        fs::write(&plain, crate::tutorial::TUTORIAL_JSON).unwrap();
+        assert_eq!(
+            Database::read_data(&plain).unwrap(),
+            crate::tutorial::TUTORIAL_JSON
+        );
+        let limits = LoadLimits {
+            max_file_size: Some(10),
+            ..LoadLimits::NONE
+        };
+        assert!(matches!(
+            with_limits(limits, || Database::read_data(&plain)),
+            Err(SemanticSearchError::Limit { .. })
+        ));
+
+        #[cfg(feature = "gzip")]
+        {
+            use std::io::Write;
+            let gz = format!("{}.gz", plain);
+            let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+            encoder
+                .write_all(crate::tutorial::TUTORIAL_JSON.as_bytes())
+                .unwrap();
+            fs::write(&gz, encoder.finish().unwrap()).unwrap();
+            let db = Database::load_from_json(&gz).unwrap();
+            assert_eq!(db.data_flows.len(), 6);
+
+            let limits = LoadLimits {
+                max_file_size: Some(fs::metadata(&gz).unwrap().len()),
+                ..LoadLimits::NONE
+            };
+            let error = with_limits(limits, || Database::read_data(&gz)).unwrap_err();
+            assert!(error.to_string().contains("decompresses to more than"));
+        }
+
+        assert_eq!(uncompressed_name("flows.ndjson.zst"), "flows.ndjson");
+        assert_eq!(uncompressed_name("flows.json"), "flows.json");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,187 @@
+use super::{flow_id, DataFlow, Database, QueryOps, UnitFlow};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// How the flows a query matches differ between an old and a new database
+pub struct MatchDiff<'a> {
+    /// Flows matched in the new database with no counterpart in the old one
+    pub appeared: Vec<&'a DataFlow>,
+    /// Flows matched in the old database with no counterpart in the new one
+    pub disappeared: Vec<&'a DataFlow>,
+    /// Flows matched in both with the same steps but other positions or
+    /// descriptions, old then new
+    pub changed: Vec<(&'a DataFlow, &'a DataFlow)>,
+    /// Number of flows matched unchanged in both
+    pub unchanged: usize,
+}
+
+/// A [MatchDiff] as flow ids, for `--json`
+#[derive(Debug, Serialize)]
+pub struct MatchDiffIds {
+    pub appeared: Vec<String>,
+    pub disappeared: Vec<String>,
+    pub changed: Vec<ChangedIds>,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedIds {
+    pub old: String,
+    pub new: String,
+}
+
+/// How the flows two queries match in one database overlap, each list
+/// holding indices in [Database::data_flows] in order
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QueryDiff {
+    /// Flows only the first query matches
+    pub only_first: Vec<usize>,
+    /// Flows only the second query matches
+    pub only_second: Vec<usize>,
+    /// Flows both queries match
+    pub both: Vec<usize>,
+}
+
+impl Database {
+    /// Compare the flows `query` matches here with those it matches in
+    /// `new`. Flows with the same [flow_id] are unchanged, and the rest are
+    /// paired up by [UnitFlow::similarity_key] as changed.
+    pub fn diff_matches<'a>(&'a self, new: &'a Database, query: &[QueryOps]) -> MatchDiff<'a> {
+        let old_flows = self.search(query);
+        let new_flows = new.search(query);
+        let old_ids: BTreeSet<String> = old_flows.iter().map(|flow| flow_id(flow)).collect();
+        let new_ids: BTreeSet<String> = new_flows.iter().map(|flow| flow_id(flow)).collect();
+        let disappeared: Vec<_> = old_flows
+            .into_iter()
+            .filter(|flow| !new_ids.contains(&flow_id(flow)))
+            .collect();
+        let (kept, appeared): (Vec<_>, Vec<_>) = new_flows
+            .into_iter()
+            .partition(|flow| old_ids.contains(&flow_id(flow)));
+
+        let mut unpaired: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        for (idx, flow) in disappeared.iter().enumerate().rev() {
+            unpaired
+                .entry(UnitFlow::similarity_key(flow))
+                .or_default()
+                .push(idx);
+        }
+        let mut paired = vec![false; disappeared.len()];
+        let mut diff = MatchDiff {
+            appeared: vec![],
+            disappeared: vec![],
+            changed: vec![],
+            unchanged: kept.len(),
+        };
+        for flow in appeared {
+            let old = unpaired
+                .get_mut(&UnitFlow::similarity_key(flow))
+                .and_then(Vec::pop);
+            match old {
+                Some(idx) => {
+                    paired[idx] = true;
+                    diff.changed.push((disappeared[idx], flow));
+                }
+                None => diff.appeared.push(flow),
+            }
+        }
+        diff.disappeared = disappeared
+            .into_iter()
+            .zip(paired)
+            .filter(|(_, paired)| !paired)
+            .map(|(flow, _)| flow)
+            .collect();
+        diff
+    }
+
+    /// Compare the flows `first` and `second` match here
+    pub fn query_diff(&self, first: &[QueryOps], second: &[QueryOps]) -> QueryDiff {
+        let first: BTreeSet<usize> = self.matching_indices(first).into_iter().collect();
+        let second: BTreeSet<usize> = self.matching_indices(second).into_iter().collect();
+        QueryDiff {
+            only_first: first.difference(&second).copied().collect(),
+            only_second: second.difference(&first).copied().collect(),
+            both: first.intersection(&second).copied().collect(),
+        }
+    }
+}
+
+impl MatchDiff<'_> {
+    pub fn ids(&self) -> MatchDiffIds {
+        let ids = |flows: &[&DataFlow]| flows.iter().map(|flow| flow_id(flow)).collect();
+        MatchDiffIds {
+            appeared: ids(&self.appeared),
+            disappeared: ids(&self.disappeared),
+            changed: self
+                .changed
+                .iter()
+                .map(|(old, new)| ChangedIds {
+                    old: flow_id(old),
+                    new: flow_id(new),
+                })
+                .collect(),
+            unchanged: self.unchanged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db(flows: &[(&str, usize)]) -> Database {
+        let flows: Vec<String> = flows
+            .iter()
+            .map(|(ty, line)| {
+                format!(
+                    r#"[{{"Type": {{"name": "{}", "args": [], "desc": null}}}},
+                        {{"ProgLoc": {{"line": "f x", "char_range": [3, 4], "desc": null, "depth": 0, "line_number": {}}}}}]"#,
+                    ty, line
+                )
+            })
+            .collect();
+        let data = format!(
+            r#"{{"file_path": "lib/main.ml", "dataflow": [{}]}}"#,
+            flows.join(",")
+        );
+        Database::load_from_str(&data).unwrap()
+    }
+
+    #[test]
+    fn test_diff_matches() {
+        let old = db(&[("string", 1), ("string", 2), ("int", 3)]);
+        let new = db(&[("string", 1), ("string", 7), ("string", 9), ("int", 4)]);
+        let query = QueryOps::parse_query("string").unwrap();
+        let diff = old.diff_matches(&new, &query);
+
+        let ids = diff.ids();
+        assert_eq!(ids.unchanged, 1);
+        assert_eq!(ids.changed.len(), 1);
+        assert_eq!(ids.changed[0].old, flow_id(&old.data_flows[1]));
+        assert_eq!(ids.changed[0].new, flow_id(&new.data_flows[1]));
+        assert_eq!(ids.appeared, vec![flow_id(&new.data_flows[2])]);
+        assert!(ids.disappeared.is_empty());
+
+        let diff = new.diff_matches(&old, &query);
+        assert!(diff.appeared.is_empty());
+        assert_eq!(diff.ids().disappeared, vec![flow_id(&new.data_flows[2])]);
+    }
+
+    #[test]
+    fn test_query_diff() {
+        let db = db(&[("string", 1), ("int", 2), ("string", 3), ("bool", 4)]);
+        let query = |q: &str| QueryOps::parse_query(q).unwrap();
+        assert_eq!(
+            db.query_diff(&query("string"), &query(".*in.*")),
+            QueryDiff {
+                only_first: vec![],
+                only_second: vec![1],
+                both: vec![0, 2],
+            }
+        );
+        let diff = db.query_diff(&query("string"), &query("int"));
+        assert_eq!(diff.only_first, vec![0, 2]);
+        assert_eq!(diff.only_second, vec![1]);
+        assert!(diff.both.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use super::{flow_id, Database, UnitFlow};
+use std::collections::BTreeMap;
+
+/// Flows identical up to program location positions and descriptions
+#[derive(Debug, PartialEq, Eq)]
+pub struct DupeGroup {
+    /// Indices of the flows in [Database::data_flows]
+    pub flows: Vec<usize>,
+    /// Steps whose fields differ between the flows, with the field names
+    pub differences: Vec<(usize, Vec<&'static str>)>,
+}
+
+impl UnitFlow {
+    /// The step with positions and descriptions stripped
+    fn normalized(&self) -> String {
+        match self {
+            UnitFlow::Type(t) => format!("Type {}<{}>", t.name, t.args.join(", ")),
+            UnitFlow::ConstructorArg(c) => format!("ConstructorArg @{}.{}", c.name, c.arg_index),
+            UnitFlow::TypeVar(tv) => format!("TypeVar {}", tv.name),
+            UnitFlow::ProgLoc(p) => format!("ProgLoc {}", p.line.trim()),
+            UnitFlow::Other { kind, fields } => format!("{} {}", kind, fields),
+        }
+    }
+
+    /// Fields that differ between two steps with the same normalized form
+    fn differing_fields(&self, other: &UnitFlow) -> Vec<&'static str> {
+        let mut fields = vec![];
+        match (self, other) {
+            (UnitFlow::Type(a), UnitFlow::Type(b)) if a.desc != b.desc => fields.push("desc"),
+            (UnitFlow::ConstructorArg(a), UnitFlow::ConstructorArg(b)) if a.desc != b.desc => {
+                fields.push("desc")
+            }
+            (UnitFlow::TypeVar(a), UnitFlow::TypeVar(b)) if a.desc != b.desc => fields.push("desc"),
+            (UnitFlow::ProgLoc(a), UnitFlow::ProgLoc(b)) => {
+                if a.line != b.line {
+                    fields.push("line");
+                }
+                if a.desc != b.desc {
+                    fields.push("desc");
+                }
+                if a.char_range != b.char_range {
+                    fields.push("char_range");
+                }
+                if a.depth != b.depth {
+                    fields.push("depth");
+                }
+                if a.file != b.file {
+                    fields.push("file");
+                }
+                if a.line_number != b.line_number {
+                    fields.push("line_number");
+                }
+                if a.column != b.column {
+                    fields.push("column");
+                }
+            }
+            _ => {}
+        }
+        fields
+    }
+
+    /// The steps of the flow with positions and descriptions stripped,
+    /// equal for near-duplicate flows and for a flow that merely moved in
+    /// the source
+    pub fn similarity_key(flow: &[UnitFlow]) -> Vec<String> {
+        flow.iter().map(UnitFlow::normalized).collect()
+    }
+}
+
+impl Database {
+    /// The database with each structurally identical flow kept once, at its
+    /// first occurrence, counting how many times it was seen
+    pub fn deduplicated(self) -> Self {
+        self.deduplicated_by(flow_id)
+    }
+
+    /// [Database::deduplicated] with flows keyed by `id`. Flows only merge
+    /// when they are equal, not merely when their ids are, so that a flow
+    /// crafted to collide with another cannot hide it.
+    fn deduplicated_by(self, id: impl Fn(&[UnitFlow]) -> String) -> Self {
+        // Indices in `flows` of the kept flows with each id
+        let mut kept: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut flows: Vec<Vec<UnitFlow>> = vec![];
+        let mut counts: Vec<usize> = vec![];
+        for flow in &self.data_flows {
+            let same_id = kept.entry(id(flow)).or_default();
+            match same_id.iter().find(|idx| same_flow(&flows[**idx], flow)) {
+                Some(idx) => counts[*idx] += self.multiplicity(flow),
+                None => {
+                    same_id.push(flows.len());
+                    flows.push(flow.to_vec());
+                    counts.push(self.multiplicity(flow));
+                }
+            }
+        }
+        if flows.len() == self.data_flows.len() {
+            return self;
+        }
+        let mut db = self.with_flows(flows);
+        db.counts = BTreeMap::new();
+        for (id, same_id) in kept {
+            match same_id[..] {
+                [idx] if counts[idx] > 1 => {
+                    db.counts.insert(id, counts[idx]);
+                }
+                [_] => {}
+                _ => db.warnings.push(format!(
+                    "{} different flows share the id {}; they are kept apart and their copies are not counted",
+                    same_id.len(),
+                    id
+                )),
+            }
+        }
+        db
+    }
+
+    /// Number of times `flow` was seen before [Database::deduplicated]
+    /// merged its copies
+    pub fn multiplicity(&self, flow: &[UnitFlow]) -> usize {
+        if self.counts.is_empty() {
+            return 1;
+        }
+        self.counts.get(&flow_id(flow)).copied().unwrap_or(1)
+    }
+
+    /// Group the `candidates` flows that are identical up to program location
+    /// positions and descriptions, suggesting copy-pasted code paths. Groups
+    /// of exact duplicates are left out.
+    pub fn near_duplicates(&self, candidates: &[usize]) -> Vec<DupeGroup> {
+        let mut buckets: BTreeMap<Vec<String>, Vec<usize>> = BTreeMap::new();
+        for idx in candidates {
+            let key = UnitFlow::similarity_key(&self.data_flows[*idx]);
+            buckets.entry(key).or_default().push(*idx);
+        }
+
+        let mut groups: Vec<DupeGroup> = buckets
+            .into_values()
+            .filter(|flows| flows.len() > 1)
+            .filter_map(|flows| {
+                let first = &self.data_flows[flows[0]];
+                let differences: Vec<(usize, Vec<&'static str>)> = (0..first.len())
+                    .filter_map(|step| {
+                        let mut fields: Vec<&'static str> = vec![];
+                        let others = flows[1..]
+                            .iter()
+                            .filter_map(|other| self.data_flows[*other].get(step));
+                        for other in others {
+                            for field in first[step].differing_fields(other) {
+                                if !fields.contains(&field) {
+                                    fields.push(field);
+                                }
+                            }
+                        }
+                        (!fields.is_empty()).then_some((step, fields))
+                    })
+                    .collect();
+                (!differences.is_empty()).then_some(DupeGroup { flows, differences })
+            })
+            .collect();
+
+        groups.sort_by_key(|g| g.flows[0]);
+        groups
+    }
+}
+
+/// Whether two flows are equal, step for step
+fn same_flow(a: &[UnitFlow], b: &[UnitFlow]) -> bool {
+    a.len() == b.len() && serde_json::to_vec(a).ok() == serde_json::to_vec(b).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ProgLoc, Type};
+
+    fn loc(line: &str, line_number: usize, desc: Option<&str>) -> UnitFlow {
+        UnitFlow::ProgLoc(ProgLoc {
+            line: line.into(),
+            char_range: (1, 2),
+            desc: desc.map(Into::into),
+            depth: 0,
+            file: None,
+            line_number: Some(line_number),
+            column: None,
+            commit: None,
+        })
+    }
+
+    fn int() -> UnitFlow {
+        UnitFlow::Type(Type {
+            name: "int".into(),
+            args: vec![],
+            desc: None,
+        })
+    }
+
+    #[test]
+    fn test_near_duplicates() {
+        let db = Database::new(
+            "test.ml".to_string(),
+            vec![
+                vec![int(), loc("x + 1", 3, None)],
+                vec![int(), loc("y + 1", 9, None)],
+                vec![int(), loc("  x + 1", 12, Some("copied"))],
+                vec![int(), loc("y + 1", 9, None)],
+            ],
+        );
+
+        assert_eq!(
+            db.near_duplicates(&[0, 1, 2, 3]),
+            vec![DupeGroup {
+                flows: vec![0, 2],
+                differences: vec![(1, vec!["line", "desc", "line_number"])],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_deduplicated() {
+        let flows = vec![
+            vec![int(), loc("x + 1", 3, None)],
+            vec![int(), loc("y + 1", 9, None)],
+            vec![int(), loc("x + 1", 3, None)],
+            vec![int(), loc("x + 1", 3, None)],
+        ];
+        let db = Database::new("test.ml".to_string(), flows.clone()).deduplicated();
+        assert_eq!(db.data_flows.len(), 2);
+        assert_eq!(db.multiplicity(&flows[0]), 3);
+        assert_eq!(db.multiplicity(&flows[1]), 1);
+        assert_eq!(db.flows_with_type("int"), &[0, 1]);
+
+        let twice = [db.data_flows.to_vec(), db.data_flows.to_vec()].concat();
+        let again = db.with_flows(twice).deduplicated();
+        assert_eq!(again.multiplicity(&again.data_flows[0]), 6);
+
+        // Flows whose ids collide are only merged when they are equal
+        let colliding = Database::new("test.ml".to_string(), flows.clone())
+            .deduplicated_by(|_| "0000000000000000".to_string());
+        assert_eq!(colliding.data_flows.len(), 2);
+        assert_eq!(colliding.multiplicity(&flows[0]), 1);
+        assert_eq!(colliding.warnings().len(), 1);
+    }
+}
@@ -0,0 +1,141 @@
+use super::{flow_id, Database, QueryOps, UnitFlow};
+use std::collections::BTreeSet;
+
+/// Directed links between the steps of one flow, from the database's
+/// `edges` or else each step feeding into the next
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FlowGraph {
+    /// Steps each step links directly into, by step index
+    successors: Vec<Vec<usize>>,
+}
+
+impl FlowGraph {
+    /// Each of `len` steps linked into the one after it
+    pub fn chain(len: usize) -> Self {
+        FlowGraph {
+            successors: (0..len)
+                .map(|idx| if idx + 1 < len { vec![idx + 1] } else { vec![] })
+                .collect(),
+        }
+    }
+
+    /// `len` steps linked by `edges`; edges naming a step past the end are
+    /// ignored
+    pub fn from_edges(len: usize, edges: &[(usize, usize)]) -> Self {
+        let mut successors = vec![vec![]; len];
+        for &(from, to) in edges {
+            if from < len && to < len && !successors[from].contains(&to) {
+                successors[from].push(to);
+            }
+        }
+        FlowGraph { successors }
+    }
+
+    pub fn successors(&self, step: usize) -> &[usize] {
+        self.successors.get(step).map_or(&[], Vec::as_slice)
+    }
+
+    /// Steps reachable from `step` through one or more edges
+    pub fn reachable(&self, step: usize) -> BTreeSet<usize> {
+        let mut seen = BTreeSet::new();
+        let mut stack = self.successors(step).to_vec();
+        while let Some(next) = stack.pop() {
+            if seen.insert(next) {
+                stack.extend(self.successors(next));
+            }
+        }
+        seen
+    }
+}
+
+impl Database {
+    /// The links between the steps of `flow`
+    pub fn flow_graph(&self, flow: &[UnitFlow]) -> FlowGraph {
+        match self.edges.get(&flow_id(flow)) {
+            Some(edges) => FlowGraph::from_edges(flow.len(), edges),
+            None => FlowGraph::chain(flow.len()),
+        }
+    }
+
+    /// The steps of `flow` starting a chain of linked steps that matches
+    /// `op`
+    pub(super) fn edge_starts(
+        &self,
+        flow: &[UnitFlow],
+        graph: &FlowGraph,
+        op: &QueryOps,
+    ) -> Vec<usize> {
+        match op {
+            QueryOps::QEdge {
+                from,
+                to,
+                transitive,
+            } => {
+                let targets: BTreeSet<usize> =
+                    self.edge_starts(flow, graph, to).into_iter().collect();
+                self.edge_starts(flow, graph, from)
+                    .into_iter()
+                    .filter(|step| {
+                        if *transitive {
+                            !graph.reachable(*step).is_disjoint(&targets)
+                        } else {
+                            graph
+                                .successors(*step)
+                                .iter()
+                                .any(|next| targets.contains(next))
+                        }
+                    })
+                    .collect()
+            }
+            op => (0..flow.len())
+                .filter(|step| self.match_unit_flow(&flow[*step], op))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges() {
+        let graph = FlowGraph::from_edges(4, &[(0, 2), (2, 3), (1, 9)]);
+        assert_eq!(graph.successors(0), &[2]);
+        assert_eq!(graph.successors(1), &[] as &[usize]);
+        assert_eq!(graph.reachable(0), [2, 3].into());
+
+        let data = r#"{
+            "file_path": "edges.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ConstructorArg": {"name": "Some", "arg_index": 1, "desc": null}},
+                {"Type": {"name": "option", "args": ["int"], "desc": null}},
+                {"Type": {"name": "string", "args": [], "desc": null}}
+            ]],
+            "edges": [[[0, 2], [1, 2], [2, 3]]]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(indices("int -> option"), vec![0]);
+        assert_eq!(indices("int -> @Some"), Vec::<usize>::new());
+        assert_eq!(indices("int ->> string"), vec![0]);
+        assert_eq!(indices("int -> option -> string"), vec![0]);
+        assert_eq!(indices("@Some -> string"), Vec::<usize>::new());
+        assert_eq!(indices("!int -> @Some"), vec![0]);
+
+        // Without edges, each step feeds into the next
+        let tutorial = crate::tutorial::load_database();
+        let indices =
+            |query: &str| tutorial.matching_indices(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(indices("List -> #2"), vec![2]);
+        assert_eq!(indices("List ->> @Cons"), vec![2]);
+        assert_eq!(indices("List -> @Cons"), Vec::<usize>::new());
+
+        let query = QueryOps::parse_query("int ->> \"a->b\"").unwrap();
+        assert_eq!(query[0].to_string(), "int ->> \"a->b\"");
+        assert!(QueryOps::parse_query("int -> ..").is_err());
+        let reloaded = Database::load_from_str(&db.to_json().unwrap()).unwrap();
+        assert_eq!(reloaded.edges, db.edges);
+    }
+}
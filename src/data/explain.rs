@@ -0,0 +1,161 @@
+use super::{ArgIndex, QueryOps};
+
+impl QueryOps {
+    /// The step as a tree of what it matches, one line per node, for
+    /// `explain` to show how a query was parsed
+    pub fn tree(&self) -> Vec<String> {
+        let (label, children) = self.node();
+        layout(label, children)
+    }
+
+    /// Label of the step and the lines of each of its children
+    fn node(&self) -> (String, Vec<Vec<String>>) {
+        let leaf = |text: String| vec![text];
+        let prefixed = |prefix: &str, op: &QueryOps| {
+            let mut lines = op.tree();
+            lines[0] = format!("{}{}", prefix, lines[0]);
+            lines
+        };
+        match self {
+            QueryOps::QTypeVar(count) => (format!("type variable through {} flows", count), vec![]),
+            QueryOps::QTypeVarRange { min, max: None } => (
+                format!("type variable through at least {} flows", min),
+                vec![],
+            ),
+            QueryOps::QTypeVarRange {
+                min,
+                max: Some(max),
+            } => (
+                format!("type variable through {} to {} flows", min, max),
+                vec![],
+            ),
+            QueryOps::QTypeVarName { name, min, max } => {
+                let through = match (min, max) {
+                    (0, None) => None,
+                    (min, None) => Some(format!("through at least {} flows", min)),
+                    (min, Some(max)) if min == max => Some(format!("through {} flows", min)),
+                    (min, Some(max)) => Some(format!("through {} to {} flows", min, max)),
+                };
+                (
+                    format!("type variable `{}`", name),
+                    through.into_iter().map(leaf).collect(),
+                )
+            }
+            QueryOps::QConstructorArg(q) => {
+                let mut children = vec![];
+                match &q.arg_index {
+                    Some(ArgIndex::One(idx)) => children.push(leaf(format!("argument {}", idx))),
+                    Some(idx) => children.push(leaf(format!("arguments {}", idx))),
+                    None => {}
+                }
+                if let Some(desc) = &q.desc {
+                    children.push(leaf(format!("description `{}`", desc)));
+                }
+                (format!("constructor `{}`", q.name), children)
+            }
+            QueryOps::QType(q) => {
+                let mut children = vec![];
+                if let Some(args) = &q.args {
+                    children.push(leaf(format!("arguments `{}`", args.join("`, `"))));
+                }
+                if let Some(desc) = &q.desc {
+                    children.push(leaf(format!("description `{}`", desc)));
+                }
+                (format!("type `{}`", q.name), children)
+            }
+            QueryOps::QDesc(desc) => match desc.strip_prefix('~') {
+                Some(fuzzy) => (format!("description resembling `{}`", fuzzy), vec![]),
+                None => (format!("description `{}`", desc), vec![]),
+            },
+            QueryOps::QNote(text) => (format!("note containing `{}`", text), vec![]),
+            QueryOps::QLen(bounds) => (format!("length bound `{}`", bounds), vec![]),
+            QueryOps::QKind(kind) => (format!("step of kind `{}`", kind), vec![]),
+            QueryOps::QNot(op) => ("not".to_string(), vec![op.tree()]),
+            QueryOps::QEdge {
+                from,
+                to,
+                transitive,
+            } => (
+                if *transitive {
+                    "linked through any steps".to_string()
+                } else {
+                    "linked directly".to_string()
+                },
+                vec![prefixed("from ", from), prefixed("to ", to)],
+            ),
+            QueryOps::QBind(name, op) => (format!("bound to `{}`", name), vec![op.tree()]),
+            QueryOps::QAt(idx, op) => (format!("at step {}", idx), vec![op.tree()]),
+            QueryOps::QRepeat { op, min, max } => (
+                match max {
+                    Some(max) if max == min => format!("{} times", min),
+                    Some(max) => format!("{} to {} times", min, max),
+                    None => format!("at least {} times", min),
+                },
+                vec![op.tree()],
+            ),
+            QueryOps::QAll(queries) => (
+                "all of".to_string(),
+                queries
+                    .iter()
+                    .map(|query| {
+                        layout(
+                            "query".to_string(),
+                            query.iter().map(QueryOps::tree).collect(),
+                        )
+                    })
+                    .collect(),
+            ),
+            QueryOps::QStart => ("start of the flow".to_string(), vec![]),
+            QueryOps::QEnd => ("end of the flow".to_string(), vec![]),
+            QueryOps::QGap => ("any number of steps".to_string(), vec![]),
+        }
+    }
+}
+
+/// `label` above the lines of each child, joined to it by branches
+fn layout(label: String, children: Vec<Vec<String>>) -> Vec<String> {
+    let mut lines = vec![label];
+    let last = children.len().saturating_sub(1);
+    for (idx, child) in children.into_iter().enumerate() {
+        let (branch, indent) = if idx == last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        for (line_idx, line) in child.into_iter().enumerate() {
+            let prefix = if line_idx == 0 { branch } else { indent };
+            lines.push(format!("{}{}", prefix, line));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_tree() {
+        let tree = |query: &str| QueryOps::parse_query(query).unwrap()[0].tree();
+        assert_eq!(tree("#2"), vec!["type variable through 2 flows"]);
+        assert_eq!(
+            tree("Map<_, int>:keys as m"),
+            vec![
+                "bound to `m`",
+                "└─ type `Map`",
+                "   ├─ arguments `_`, `int`",
+                "   └─ description `keys`",
+            ]
+        );
+        assert_eq!(
+            tree("@Cons.1 ->> !List"),
+            vec![
+                "linked through any steps",
+                "├─ from constructor `Cons`",
+                "│  └─ argument 1",
+                "└─ to not",
+                "   └─ type `List`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use super::{pattern_matches, Database, QueryOps, UnitFlow};
+use std::collections::HashSet;
+
+/// Share of a fuzzy description's trigrams a description must contain to
+/// match it
+pub const FUZZY_DESC_THRESHOLD: f64 = 0.6;
+
+/// Whether `text` matches a description queried as `pattern`: in full as
+/// with [pattern_matches], or as a substring ignoring case. Patterns
+/// starting with `~` match fuzzily, by [fuzzy_score].
+pub(super) fn desc_matches(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    match pattern.strip_prefix('~') {
+        Some(fuzzy) => fuzzy_score(fuzzy, text) >= FUZZY_DESC_THRESHOLD,
+        None => {
+            pattern_matches(pattern, text, ignore_case)
+                || text.to_lowercase().contains(&pattern.to_lowercase())
+        }
+    }
+}
+
+/// Share of the character trigrams of `query` that also occur in `text`,
+/// ignoring case, from 0 to 1. Words are padded so that short words and word
+/// boundaries count.
+pub fn fuzzy_score(query: &str, text: &str) -> f64 {
+    let wanted = trigrams(query);
+    if wanted.is_empty() {
+        return 0.0;
+    }
+    let found = trigrams(text);
+    wanted.intersection(&found).count() as f64 / wanted.len() as f64
+}
+
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let mut grams = HashSet::new();
+    for word in text.to_lowercase().split_whitespace() {
+        let padded: Vec<char> = format!("  {} ", word).chars().collect();
+        grams.extend(padded.windows(3).map(|w| [w[0], w[1], w[2]]));
+    }
+    grams
+}
+
+impl QueryOps {
+    /// The text of the step's description, when it is queried fuzzily
+    fn fuzzy_desc(&self) -> Option<&str> {
+        match self.unbound() {
+            QueryOps::QDesc(desc) => Some(desc.as_str()),
+            QueryOps::QType(q) => q.desc.as_deref(),
+            QueryOps::QConstructorArg(q) => q.desc.as_deref(),
+            _ => None,
+        }
+        .and_then(|desc| desc.strip_prefix('~'))
+    }
+}
+
+impl Database {
+    /// How closely the descriptions of `flow` match the fuzzy steps of
+    /// `query`: the best [fuzzy_score] against any step of the flow, summed
+    /// over the fuzzy steps
+    pub fn fuzzy_desc_score(&self, flow: &[UnitFlow], query: &[QueryOps]) -> f64 {
+        query
+            .iter()
+            .filter_map(QueryOps::fuzzy_desc)
+            .map(|fuzzy| {
+                flow.iter()
+                    .filter_map(UnitFlow::desc)
+                    .map(|desc| fuzzy_score(fuzzy, desc))
+                    .fold(0.0, f64::max)
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_descs() {
+        assert_eq!(fuzzy_score("condition", "if-then-else condition"), 1.0);
+        assert!(fuzzy_score("conditon", "if-then-else condition") >= FUZZY_DESC_THRESHOLD);
+        assert!(fuzzy_score("argument", "if-then-else condition") < FUZZY_DESC_THRESHOLD);
+
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(indices("\"then-else\""), vec![0, 5]);
+        assert_eq!(indices("\"THEN-ELSE\""), vec![0, 5]);
+        assert_eq!(indices("~\"if then else conditon\""), vec![0, 5]);
+        assert_eq!(indices("~\"unrelated words\""), Vec::<usize>::new());
+    }
+}
@@ -0,0 +1,69 @@
+use super::{pattern_matches, QType, Type};
+
+/// Split `input` at the commas outside angle brackets, so the arguments of a
+/// step like `Map<_, string>` stay together
+pub(super) fn split_top_level(input: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, c) in input.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(&input[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+impl QType {
+    /// Whether the arguments of `t` match the queried ones, each by name or
+    /// regex, or anything for `_`; types are matched whatever their
+    /// arguments when none are queried
+    pub(super) fn matches_args(&self, t: &Type, ignore_case: bool) -> bool {
+        self.args.as_ref().is_none_or(|args| {
+            args.len() == t.args.len()
+                && args
+                    .iter()
+                    .zip(&t.args)
+                    .all(|(arg, targ)| arg == "_" || pattern_matches(arg, targ, ignore_case))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{Database, QueryOps};
+
+    #[test]
+    fn test_type_arguments() {
+        let data = r#"{
+            "file_path": "generics.ml",
+            "dataflow": [
+                [{"Type": {"name": "List", "args": ["int"], "desc": null}}],
+                [{"Type": {"name": "Map", "args": ["int", "string"], "desc": null}}],
+                [{"Type": {"name": "Map", "args": ["string", "string"], "desc": "names"}}]
+            ]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(indices("List<int>"), vec![0]);
+        assert_eq!(indices("List<string>"), Vec::<usize>::new());
+        assert_eq!(indices("Map<_, string>"), vec![1, 2]);
+        assert_eq!(indices("Map<int, _>, .."), vec![1]);
+        assert_eq!(indices("Map<_>"), Vec::<usize>::new());
+        assert_eq!(indices("Map<str.*, _>:names"), vec![2]);
+        assert_eq!(indices("Map"), vec![1, 2]);
+
+        let query = QueryOps::parse_query("Map<_,string>:names, List").unwrap();
+        assert_eq!(query.len(), 2);
+        assert_eq!(query[0].to_string(), "Map<_, string>:names");
+        assert!(QueryOps::parse_query("List<int").is_err());
+        assert!(QueryOps::parse_query("List<int,>").is_err());
+    }
+}
@@ -0,0 +1,472 @@
+use super::{DataFlow, Database, UnitFlow};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    Type,
+    Constructor,
+    TypeVar,
+}
+
+/// The types, constructor arguments and type variables of the whole database,
+/// with an edge wherever one directly follows another in some flow
+#[derive(Debug, Default)]
+pub struct TypeGraph {
+    /// Node names (`List`, `@Cons.1`, `'a`) with their kinds
+    pub nodes: BTreeMap<String, NodeKind>,
+    /// Directed edges between node names, with the number of flows taking them
+    pub edges: BTreeMap<(String, String), usize>,
+}
+
+/// Connectivity of one node of the [TypeGraph]
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct NodeStats {
+    pub name: String,
+    /// Distinct nodes flowing directly into this one
+    pub fan_in: usize,
+    /// Distinct nodes this one flows directly into
+    pub fan_out: usize,
+    /// Distinct nodes adjacent either way
+    pub degree: usize,
+}
+
+/// Two types or constructor arguments linked through type variables
+#[derive(Debug, PartialEq, Eq)]
+pub struct Connection {
+    pub from: String,
+    pub to: String,
+    /// Type variables on the paths from `from` to `to`
+    pub via: BTreeSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphStats {
+    /// Every type variable, busiest first
+    pub type_vars: Vec<NodeStats>,
+    /// Node names of each connected component, largest first
+    pub components: Vec<Vec<String>>,
+    /// Types by number of distinct neighbours, most central first
+    pub central_types: Vec<NodeStats>,
+}
+
+impl UnitFlow {
+    fn graph_node(&self) -> Option<(String, NodeKind)> {
+        match self {
+            UnitFlow::Type(t) => Some((t.name.to_string(), NodeKind::Type)),
+            UnitFlow::ConstructorArg(c) => Some((
+                format!("@{}.{}", c.name, c.arg_index),
+                NodeKind::Constructor,
+            )),
+            UnitFlow::TypeVar(tv) => Some((tv.name.to_string(), NodeKind::TypeVar)),
+            UnitFlow::ProgLoc(_) | UnitFlow::Other { .. } => None,
+        }
+    }
+}
+
+impl NodeKind {
+    fn shape(&self) -> &'static str {
+        match self {
+            NodeKind::Type => "box",
+            NodeKind::Constructor => "ellipse",
+            NodeKind::TypeVar => "diamond",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            NodeKind::Type => "type",
+            NodeKind::Constructor => "constructor",
+            NodeKind::TypeVar => "type_var",
+        }
+    }
+}
+
+impl TypeGraph {
+    fn predecessors(&self, node: &str) -> BTreeSet<&str> {
+        self.edges
+            .keys()
+            .filter(|(_, to)| to == node)
+            .map(|(from, _)| from.as_str())
+            .collect()
+    }
+
+    fn successors(&self, node: &str) -> BTreeSet<&str> {
+        self.edges
+            .keys()
+            .filter(|(from, _)| from == node)
+            .map(|(_, to)| to.as_str())
+            .collect()
+    }
+
+    pub fn node_stats(&self, node: &str) -> NodeStats {
+        let predecessors = self.predecessors(node);
+        let successors = self.successors(node);
+        NodeStats {
+            name: node.to_string(),
+            fan_in: predecessors.len(),
+            fan_out: successors.len(),
+            degree: predecessors.union(&successors).count(),
+        }
+    }
+
+    /// Node names of each connected component, ignoring edge direction,
+    /// largest first
+    pub fn components(&self) -> Vec<Vec<String>> {
+        let mut adjacent: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (from, to) in self.edges.keys() {
+            adjacent.entry(from).or_default().push(to);
+            adjacent.entry(to).or_default().push(from);
+        }
+
+        let mut seen = BTreeSet::new();
+        let mut components = vec![];
+        for start in self.nodes.keys() {
+            if !seen.insert(start.as_str()) {
+                continue;
+            }
+            let mut component = vec![];
+            let mut queue = VecDeque::from([start.as_str()]);
+            while let Some(node) = queue.pop_front() {
+                component.push(node.to_string());
+                for next in adjacent.get(node).into_iter().flatten() {
+                    if seen.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            component.sort();
+            components.push(component);
+        }
+        components.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        components
+    }
+
+    /// Types and constructor arguments linked by following edges through one
+    /// or more type variables, with the type variables passed
+    pub fn type_connections(&self) -> Vec<Connection> {
+        let is_type_var = |node: &str| self.nodes.get(node) == Some(&NodeKind::TypeVar);
+        let mut connections: BTreeMap<(String, String), BTreeSet<String>> = BTreeMap::new();
+
+        for from in self.nodes.keys().filter(|node| !is_type_var(node)) {
+            let mut seen = BTreeSet::new();
+            // Type variables still to visit, with those passed to reach them
+            let mut queue: VecDeque<(&str, Vec<&str>)> = self
+                .successors(from)
+                .into_iter()
+                .filter(|next| is_type_var(next))
+                .map(|var| (var, vec![]))
+                .collect();
+            while let Some((var, mut path)) = queue.pop_front() {
+                if !seen.insert(var) {
+                    continue;
+                }
+                path.push(var);
+                for next in self.successors(var) {
+                    if is_type_var(next) {
+                        queue.push_back((next, path.clone()));
+                    } else {
+                        connections
+                            .entry((from.clone(), next.to_string()))
+                            .or_default()
+                            .extend(path.iter().map(|v| v.to_string()));
+                    }
+                }
+            }
+        }
+
+        connections
+            .into_iter()
+            .map(|((from, to), via)| Connection { from, to, via })
+            .collect()
+    }
+
+    /// The [TypeGraph::type_connections] as a Graphviz digraph
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph types {\n");
+        for (name, kind) in &self.nodes {
+            if *kind != NodeKind::TypeVar {
+                dot += &format!("  {} [shape={}];\n", dot_quote(name), kind.shape());
+            }
+        }
+        for c in self.type_connections() {
+            let via: Vec<&str> = c.via.iter().map(String::as_str).collect();
+            dot += &format!(
+                "  {} -> {} [label={}];\n",
+                dot_quote(&c.from),
+                dot_quote(&c.to),
+                dot_quote(&via.join(", "))
+            );
+        }
+        dot + "}\n"
+    }
+
+    /// The [TypeGraph::type_connections] as GraphML
+    pub fn to_graphml(&self) -> String {
+        let escape = |s: &str| {
+            s.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+                .replace('\'', "&apos;")
+        };
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">
+  <key id=\"kind\" for=\"node\" attr.name=\"kind\" attr.type=\"string\"/>
+  <key id=\"via\" for=\"edge\" attr.name=\"via\" attr.type=\"string\"/>
+  <graph id=\"types\" edgedefault=\"directed\">
+",
+        );
+        for (name, kind) in &self.nodes {
+            if *kind != NodeKind::TypeVar {
+                xml += &format!(
+                    "    <node id=\"{}\"><data key=\"kind\">{}</data></node>\n",
+                    escape(name),
+                    kind.label()
+                );
+            }
+        }
+        for c in self.type_connections() {
+            let via: Vec<&str> = c.via.iter().map(String::as_str).collect();
+            xml += &format!(
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"via\">{}</data></edge>\n",
+                escape(&c.from),
+                escape(&c.to),
+                escape(&via.join(", "))
+            );
+        }
+        xml + "  </graph>\n</graphml>\n"
+    }
+
+    /// Fan-in and fan-out of the type variables, connected components and
+    /// the `top` most central types
+    pub fn stats(&self, top: usize) -> GraphStats {
+        let of_kind = |kind: NodeKind| {
+            let mut stats: Vec<NodeStats> = self
+                .nodes
+                .iter()
+                .filter(|(_, k)| **k == kind)
+                .map(|(name, _)| self.node_stats(name))
+                .collect();
+            stats.sort_by_key(|s| std::cmp::Reverse(s.degree));
+            stats
+        };
+
+        let mut central_types = of_kind(NodeKind::Type);
+        central_types.truncate(top);
+        GraphStats {
+            type_vars: of_kind(NodeKind::TypeVar),
+            components: self.components(),
+            central_types,
+        }
+    }
+}
+
+/// A Graphviz string literal
+fn dot_quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Database {
+    /// The types, constructor arguments and type variables of `flows` as a
+    /// Graphviz digraph, with an edge labelled with the number of flows
+    /// wherever one directly follows another. Flows sharing a node meet
+    /// there. With `by_file`, the types and constructor arguments of each
+    /// file's flows are drawn in a cluster of their own, and the clusters are
+    /// linked through the type variables they share.
+    pub fn flows_to_dot(&self, flows: &[&DataFlow], by_file: bool) -> String {
+        let mut files: BTreeMap<&str, BTreeMap<String, (String, NodeKind)>> = BTreeMap::new();
+        let mut shared: BTreeMap<String, NodeKind> = BTreeMap::new();
+        let mut edges: BTreeMap<(String, String), usize> = BTreeMap::new();
+        for flow in flows {
+            let file = self.flow_file(flow);
+            let mut ids = vec![];
+            for (name, kind) in flow.iter().filter_map(UnitFlow::graph_node) {
+                let id = if by_file && kind != NodeKind::TypeVar {
+                    let id = format!("{}:{}", file, name);
+                    files
+                        .entry(file)
+                        .or_default()
+                        .insert(id.clone(), (name, kind));
+                    id
+                } else {
+                    shared.insert(name.clone(), kind);
+                    name
+                };
+                ids.push(id);
+            }
+            let flow_edges: BTreeSet<(&String, &String)> = ids
+                .windows(2)
+                .filter(|pair| pair[0] != pair[1])
+                .map(|pair| (&pair[0], &pair[1]))
+                .collect();
+            for (from, to) in flow_edges {
+                *edges.entry((from.clone(), to.clone())).or_default() += 1;
+            }
+        }
+
+        let mut dot = String::from("digraph flows {\n");
+        for (idx, (file, nodes)) in files.iter().enumerate() {
+            dot += &format!("  subgraph cluster_{} {{\n", idx);
+            dot += &format!("    label={};\n", dot_quote(file));
+            for (id, (name, kind)) in nodes {
+                dot += &format!(
+                    "    {} [label={}, shape={}];\n",
+                    dot_quote(id),
+                    dot_quote(name),
+                    kind.shape()
+                );
+            }
+            dot += "  }\n";
+        }
+        for (id, kind) in &shared {
+            dot += &format!("  {} [shape={}];\n", dot_quote(id), kind.shape());
+        }
+        for ((from, to), count) in &edges {
+            dot += &format!(
+                "  {} -> {} [label=\"{}\"];\n",
+                dot_quote(from),
+                dot_quote(to),
+                count
+            );
+        }
+        dot + "}\n"
+    }
+
+    /// Graph of the types, constructor arguments and type variables of all
+    /// flows, linked in the order the flows pass through them
+    pub fn type_graph(&self) -> TypeGraph {
+        let mut graph = TypeGraph::default();
+        for flow in &self.data_flows {
+            let nodes: Vec<(String, NodeKind)> =
+                flow.iter().filter_map(UnitFlow::graph_node).collect();
+            let mut flow_edges = BTreeSet::new();
+            for pair in nodes.windows(2) {
+                if pair[0].0 != pair[1].0 {
+                    flow_edges.insert((pair[0].0.clone(), pair[1].0.clone()));
+                }
+            }
+            for edge in flow_edges {
+                *graph.edges.entry(edge).or_default() += 1;
+            }
+            graph.nodes.extend(nodes);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Type, TypeVar};
+
+    fn ty(name: &str) -> UnitFlow {
+        UnitFlow::Type(Type {
+            name: name.into(),
+            args: vec![],
+            desc: None,
+        })
+    }
+
+    fn var(name: &str) -> UnitFlow {
+        UnitFlow::TypeVar(TypeVar {
+            name: name.into(),
+            desc: None,
+        })
+    }
+
+    #[test]
+    fn test_graph_stats() {
+        let db = Database::new(
+            "test.ml".to_string(),
+            vec![
+                vec![ty("List"), var("'a"), ty("int")],
+                vec![ty("bool"), var("'a")],
+                vec![ty("string")],
+            ],
+        );
+        let stats = db.type_graph().stats(2);
+
+        assert_eq!(
+            stats.type_vars,
+            vec![NodeStats {
+                name: "'a".to_string(),
+                fan_in: 2,
+                fan_out: 1,
+                degree: 3,
+            }]
+        );
+        assert_eq!(
+            stats.components,
+            vec![vec!["'a", "List", "bool", "int"], vec!["string"]]
+        );
+        let central: Vec<&str> = stats
+            .central_types
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(central, vec!["List", "bool"]);
+    }
+
+    #[test]
+    fn test_graph_export() {
+        let db = Database::new(
+            "test.ml".to_string(),
+            vec![
+                vec![ty("List"), var("'a"), var("'b"), ty("int")],
+                vec![ty("bool"), var("'b")],
+            ],
+        );
+        let graph = db.type_graph();
+
+        let connections = graph.type_connections();
+        let edges: Vec<(&str, &str, usize)> = connections
+            .iter()
+            .map(|c| (c.from.as_str(), c.to.as_str(), c.via.len()))
+            .collect();
+        assert_eq!(edges, vec![("List", "int", 2), ("bool", "int", 1)]);
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("  \"List\" [shape=box];\n"));
+        assert!(dot.contains("  \"List\" -> \"int\" [label=\"'a, 'b\"];\n"));
+        assert!(graph.to_graphml().contains(
+            "<edge source=\"bool\" target=\"int\"><data key=\"via\">&apos;b</data></edge>"
+        ));
+    }
+
+    #[test]
+    fn test_flows_to_dot() {
+        let loc = |file: &str| {
+            format!(
+                r#"{{"ProgLoc": {{"line": "f x", "char_range": [0, 1], "desc": null, "depth": 0, "file": "{}"}}}}"#,
+                file
+            )
+        };
+        let data = format!(
+            r#"{{"file_path": "db.json", "dataflow": [
+                [{}, {{"Type": {{"name": "int", "args": [], "desc": null}}}},
+                 {{"TypeVar": {{"name": "'a", "desc": null}}}}],
+                [{}, {{"TypeVar": {{"name": "'a", "desc": null}}}},
+                 {{"Type": {{"name": "int", "args": [], "desc": null}}}}]
+            ]}}"#,
+            loc("a.ml"),
+            loc("b.ml")
+        );
+        let db = Database::load_from_str(&data).unwrap();
+        let flows: Vec<&DataFlow> = db.data_flows.iter().collect();
+
+        let dot = db.flows_to_dot(&flows, false);
+        assert!(dot.contains("  \"int\" [shape=box];\n"));
+        assert!(dot.contains("  \"'a\" [shape=diamond];\n"));
+        assert!(dot.contains("  \"int\" -> \"'a\" [label=\"1\"];\n"));
+        assert!(!dot.contains("subgraph"));
+
+        let clustered = db.flows_to_dot(&flows, true);
+        assert!(clustered.contains("    label=\"a.ml\";\n"));
+        assert!(clustered.contains("    \"b.ml:int\" [label=\"int\", shape=box];\n"));
+        assert!(clustered.contains("  \"a.ml:int\" -> \"'a\" [label=\"1\"];\n"));
+        assert!(clustered.contains("  \"'a\" -> \"b.ml:int\" [label=\"1\"];\n"));
+    }
+}
@@ -0,0 +1,125 @@
+use super::{pattern_matches, Database, Name};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Names each type stands for, from the `subtypes` (or `type_aliases`)
+/// section of a database: `{"Collection": ["List", "Vec"]}` lets `Collection`
+/// match flows through `List` or `Vec`
+pub type Subtypes = BTreeMap<String, Vec<String>>;
+
+impl Database {
+    /// Types declared to stand for others in the database
+    pub fn subtypes(&self) -> &Subtypes {
+        &self.subtypes
+    }
+
+    pub fn set_subtypes(&mut self, subtypes: Subtypes) {
+        self.subtypes = subtypes;
+    }
+
+    /// Match type steps by the declared subtypes of their names too, unless
+    /// `--no-hierarchy` turns it off
+    pub fn set_hierarchy(&mut self, hierarchy: bool) {
+        self.no_hierarchy = !hierarchy;
+    }
+
+    /// Every type `name` is declared a subtype or alias of, directly or
+    /// through others
+    pub fn supertypes(&self, name: &str) -> BTreeSet<&str> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            for (sup, subs) in &self.subtypes {
+                if subs.iter().any(|sub| sub == name) && found.insert(sup.as_str()) {
+                    pending.push(sup);
+                }
+            }
+        }
+        found
+    }
+
+    /// Every type declared a subtype or alias of `name`, directly or through
+    /// others
+    pub fn subtypes_of(&self, name: &str) -> BTreeSet<&str> {
+        let mut found = BTreeSet::new();
+        let mut pending = vec![name];
+        while let Some(name) = pending.pop() {
+            for sub in self.subtypes.get(name).into_iter().flatten() {
+                if sub != name && found.insert(sub.as_str()) {
+                    pending.push(sub);
+                }
+            }
+        }
+        found.remove(name);
+        found
+    }
+
+    /// Whether a type step named `pattern` matches the type `name`, itself or
+    /// through one of its supertypes
+    pub(super) fn type_name_matches(&self, pattern: &Name, name: &Name) -> bool {
+        pattern.matches(name, self.ignore_case)
+            || (!self.no_hierarchy
+                && !self.subtypes.is_empty()
+                && self
+                    .supertypes(name)
+                    .into_iter()
+                    .any(|sup| pattern_matches(pattern, sup, self.ignore_case)))
+    }
+
+    /// Indices of the flows mentioning the type `name` or, with the
+    /// hierarchy on, any of its subtypes
+    pub(super) fn flows_with_subtypes(&self, name: &str) -> Vec<usize> {
+        let mut indices = self.flows_with_type(name).to_vec();
+        if !self.no_hierarchy {
+            for sub in self.subtypes_of(name) {
+                indices.extend(self.flows_with_type(sub));
+            }
+            indices.sort_unstable();
+            indices.dedup();
+        }
+        indices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+
+    #[test]
+    fn test_type_hierarchy() {
+        let tutorial = crate::tutorial::load_database();
+        let mut db = tutorial.with_flows(tutorial.data_flows.to_vec());
+        db.set_subtypes(BTreeMap::from([
+            ("Collection".to_string(), vec!["Seq".to_string()]),
+            (
+                "Seq".to_string(),
+                vec!["List".to_string(), "Vec".to_string()],
+            ),
+        ]));
+        assert_eq!(db.supertypes("List"), BTreeSet::from(["Collection", "Seq"]));
+        assert_eq!(
+            db.subtypes_of("Collection"),
+            BTreeSet::from(["List", "Seq", "Vec"])
+        );
+
+        let indices = |db: &Database, query: &str| {
+            db.matching_indices(&QueryOps::parse_query(query).unwrap())
+        };
+        assert_eq!(indices(&db, "Collection"), indices(&db, "List"));
+        assert_eq!(indices(&db, "Coll.*"), vec![2, 3]);
+        assert_eq!(
+            db.candidates(&QueryOps::parse_query("Seq").unwrap()),
+            Some(vec![2, 3])
+        );
+
+        db.set_hierarchy(false);
+        assert_eq!(indices(&db, "Collection"), Vec::<usize>::new());
+
+        let data = r#"{"file_path": "a.ml", "type_aliases": {"number": ["int"]},
+            "dataflow": [[{"Type": {"name": "int", "args": [], "desc": null}}]]}"#;
+        let db = Database::load_from_str(data).unwrap();
+        assert_eq!(indices(&db, "number"), vec![0]);
+        let reloaded = Database::load_from_str(&db.to_json().unwrap()).unwrap();
+        assert_eq!(reloaded.subtypes(), db.subtypes());
+    }
+}
@@ -0,0 +1,110 @@
+use super::limits::{check_file_size, check_flow, load_limits};
+use super::Database;
+use crate::error::{self, SemanticSearchError};
+use bincode::Options;
+use std::fs;
+use std::time::SystemTime;
+
+/// Leading bytes of an index file; bump the version when [Database]'s layout
+/// changes so stale indexes are rebuilt rather than misread
+const MAGIC: &[u8] = b"SCSIDX\x00\x07";
+
+impl Database {
+    /// `<data_json>.idx`
+    pub fn index_path(data_json: &str) -> String {
+        format!("{}.idx", data_json)
+    }
+
+    /// Write the database, with its type, type variable and constructor inverted maps, as
+    /// a binary index to `path`
+    pub fn write_index(&self, path: &str) -> Result<(), String> {
+        let _span = tracing::info_span!("index", path).entered();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(bincode::serialize(self).map_err(|e| e.to_string())?);
+        tracing::info!(bytes = bytes.len(), "writing index");
+        fs::write(path, bytes).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    /// Read a binary index written by [Database::write_index]
+    pub fn load_index(path: &str) -> error::Result<Self> {
+        let invalid = |message: String| SemanticSearchError::Index {
+            path: path.to_string(),
+            message,
+        };
+        let size = fs::metadata(path)
+            .map_err(|e| SemanticSearchError::io(path, e))?
+            .len();
+        check_file_size(size).map_err(invalid)?;
+        let bytes = fs::read(path).map_err(|e| SemanticSearchError::io(path, e))?;
+        let data = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| invalid("not a database index".to_string()))?;
+        // Lengths read from a corrupt index could otherwise ask for any
+        // amount of memory
+        let max_size = load_limits().max_file_size.unwrap_or(u64::MAX);
+        bincode::options()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(max_size)
+            .deserialize(data)
+            .map_err(|e| invalid(e.to_string()))
+    }
+
+    /// The index of the database at `data_json`, if there is one no older
+    /// than the database and it can be read
+    pub(super) fn load_fresh_index(data_json: &str) -> Option<Self> {
+        let modified =
+            |path: &str| -> Option<SystemTime> { fs::metadata(path).ok()?.modified().ok() };
+        let index = Self::index_path(data_json);
+        if modified(&index)? < modified(data_json)? {
+            tracing::debug!(index, "index is older than the database");
+            return None;
+        }
+        let db = Self::load_index(&index)
+            .inspect_err(|e| tracing::warn!(index, "skipping index: {}", e))
+            .ok()?;
+        // An index written under other limits holds flows the database is
+        // read past; reading that instead fails on the first of them
+        let within_limits = db
+            .data_flows
+            .iter()
+            .enumerate()
+            .all(|(idx, flow)| check_flow(idx, flow.len()).is_ok());
+        if !within_limits {
+            tracing::debug!(index, "index is past the load limits");
+            return None;
+        }
+        tracing::debug!(index, "loaded index");
+        Some(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+    use std::env;
+
+    #[test]
+    fn test_index_round_trip() {
+        let dir = env::temp_dir().join(format!("scs-index-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let data_json = dir.join("tutorial.json").to_string_lossy().to_string();
+        fs::write(&data_json, crate::tutorial::TUTORIAL_JSON).unwrap();
+
+        let db = crate::tutorial::load_database();
+        db.write_index(&Database::index_path(&data_json)).unwrap();
+        let indexed = Database::load_fresh_index(&data_json).unwrap();
+        assert_eq!(indexed.data_flows.len(), db.data_flows.len());
+        assert_eq!(indexed.flows_with_type("List"), &[2, 3]);
+        assert_eq!(
+            indexed.matching_indices(&QueryOps::parse_query("#2").unwrap()),
+            vec![1, 2, 3, 4]
+        );
+
+        fs::write(&data_json, b"garbage").unwrap();
+        fs::write(Database::index_path(&data_json), b"garbage").unwrap();
+        assert!(Database::load_fresh_index(&data_json).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Once, OnceLock, RwLock};
+
+thread_local! {
+    /// What the [interning] call running on this thread has read so far
+    static INTERNED: RefCell<Option<Interned>> = const { RefCell::new(None) };
+}
+
+#[derive(Default)]
+struct Interned {
+    strings: HashSet<Arc<str>>,
+    /// Symbols of the names read, so that each distinct name takes the
+    /// symbol table's lock once per call rather than once per step
+    symbols: HashMap<Str, Option<Symbol>>,
+}
+
+/// Immutable text of a flow step: a name, source line or description.
+/// Copies read while [interning] share one allocation, as a database names
+/// the same types and repeats the same source lines across many flows.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Str(Arc<str>);
+
+impl Str {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether `self` and `other` share their text's allocation
+    pub fn ptr_eq(&self, other: &Str) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Address of the text, to count each shared allocation once
+    pub(super) fn addr(&self) -> *const u8 {
+        self.0.as_ptr()
+    }
+
+    /// `text` shared with the equal strings read before it, when
+    /// [interning]
+    fn intern(text: &str) -> Self {
+        INTERNED.with(|interned| match interned.borrow_mut().as_mut() {
+            Some(interned) => match interned.strings.get(text) {
+                Some(shared) => Str(shared.clone()),
+                None => {
+                    let shared: Arc<str> = Arc::from(text);
+                    interned.strings.insert(shared.clone());
+                    Str(shared)
+                }
+            },
+            None => Str(Arc::from(text)),
+        })
+    }
+}
+
+/// Run `read`, sharing the text of equal [Str]s it deserializes on this
+/// thread and looking the symbol of each distinct [Name] up once. Nested
+/// calls share the outermost call's strings.
+pub fn interning<T>(read: impl FnOnce() -> T) -> T {
+    let outermost = INTERNED.with(|interned| {
+        let mut interned = interned.borrow_mut();
+        let outermost = interned.is_none();
+        if outermost {
+            *interned = Some(Interned::default());
+        }
+        outermost
+    });
+    let result = read();
+    if outermost {
+        INTERNED.with(|interned| interned.borrow_mut().take());
+    }
+    result
+}
+
+impl Deref for Str {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Str {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Str {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Str {
+    fn from(text: &str) -> Self {
+        Str::intern(text)
+    }
+}
+
+impl From<String> for Str {
+    fn from(text: String) -> Self {
+        Str::intern(&text)
+    }
+}
+
+impl From<&String> for Str {
+    fn from(text: &String) -> Self {
+        Str::intern(text)
+    }
+}
+
+impl From<Str> for String {
+    fn from(text: Str) -> Self {
+        text.0.to_string()
+    }
+}
+
+impl PartialEq<str> for Str {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Str {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Str {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl fmt::Display for Str {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for Str {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl Serialize for Str {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Str {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = std::borrow::Cow::<'de, str>::deserialize(deserializer)?;
+        super::limits::check_string_len(text.len()).map_err(serde::de::Error::custom)?;
+        Ok(Str::intern(&text))
+    }
+}
+
+/// A name's number in the process-wide symbol table: equal names always get
+/// the same symbol, so they compare as integers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/// Most names the symbol table numbers. Entries are never removed, as names
+/// of databases already dropped may still be on queries, so a long-running
+/// process reading many databases stops numbering new names here and
+/// compares them by text instead.
+const MAX_SYMBOLS: usize = 1 << 20;
+
+/// The names read from databases so far, with their symbols. Reading a
+/// database again adds no entries.
+fn symbols() -> &'static RwLock<HashMap<Box<str>, u32>> {
+    static SYMBOLS: OnceLock<RwLock<HashMap<Box<str>, u32>>> = OnceLock::new();
+    SYMBOLS.get_or_init(RwLock::default)
+}
+
+impl Symbol {
+    /// The symbol of `name`, numbering it if it is new, or `None` for a new
+    /// name once the table holds [MAX_SYMBOLS]
+    pub fn intern(name: &str) -> Option<Symbol> {
+        if let Some(symbol) = Symbol::get(name) {
+            return Some(symbol);
+        }
+        let mut symbols = symbols().write().expect("Symbol table poisoned");
+        if let Some(&symbol) = symbols.get(name) {
+            return Some(Symbol(symbol));
+        }
+        if symbols.len() >= MAX_SYMBOLS {
+            static FULL: Once = Once::new();
+            FULL.call_once(|| {
+                tracing::warn!("Symbol table is full, names read from now on compare by text")
+            });
+            return None;
+        }
+        let next = symbols.len() as u32;
+        symbols.insert(name.into(), next);
+        Some(Symbol(next))
+    }
+
+    /// [Symbol::intern], once per distinct name when [interning]
+    fn intern_read(name: &Str) -> Option<Symbol> {
+        INTERNED.with(|interned| match interned.borrow_mut().as_mut() {
+            Some(interned) => *interned
+                .symbols
+                .entry(name.clone())
+                .or_insert_with(|| Symbol::intern(name)),
+            None => Symbol::intern(name),
+        })
+    }
+
+    /// The symbol of `name` if it has one already, without adding it to the
+    /// table. Names of queries are looked up this way, so that the table
+    /// only grows with the databases read: a name without a symbol is on no
+    /// step.
+    pub fn get(name: &str) -> Option<Symbol> {
+        let symbols = symbols().read().expect("Symbol table poisoned");
+        symbols.get(name).map(|&symbol| Symbol(symbol))
+    }
+}
+
+/// A type, type variable or constructor name, of a step or of a query step
+/// matching it, with its [Symbol] to compare names by
+#[derive(Clone)]
+pub struct Name {
+    /// `None` for a [pattern](Name::pattern) no step is named, or for a name
+    /// read once the symbol table was full
+    symbol: Option<Symbol>,
+    /// Whether the name matches only itself, having no regex or `*` syntax
+    literal: bool,
+    text: Str,
+}
+
+impl Name {
+    pub fn new(text: impl Into<Str>) -> Self {
+        let text = text.into();
+        Name {
+            symbol: Symbol::intern_read(&text),
+            literal: regex::escape(&text) == *text,
+            text,
+        }
+    }
+
+    /// The name of a query step, looking its symbol up with [Symbol::get]
+    /// rather than adding it to the table
+    pub fn pattern(text: &str) -> Self {
+        Name {
+            symbol: Symbol::get(text),
+            literal: regex::escape(text) == text,
+            text: text.into(),
+        }
+    }
+
+    pub fn symbol(&self) -> Option<Symbol> {
+        self.symbol
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn text(&self) -> &Str {
+        &self.text
+    }
+
+    /// Whether this name, as a pattern, matches `name`: by symbol when it is
+    /// literal and case matters, otherwise as [super::pattern_matches] does.
+    /// A pattern without a symbol may have been parsed before the database
+    /// naming it was read, so it is compared by text.
+    pub fn matches(&self, name: &Name, ignore_case: bool) -> bool {
+        if self.symbol.is_some() && self.symbol == name.symbol {
+            return true;
+        }
+        if self.literal && !ignore_case {
+            return self.symbol.is_none() && self.text == name.text;
+        }
+        super::pattern_matches(&self.text, &name.text, ignore_case)
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        match (self.symbol, other.symbol) {
+            (Some(symbol), Some(other)) => symbol == other,
+            // A pattern looked up before the name was read
+            _ => self.text == other.text,
+        }
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Name) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Name) -> std::cmp::Ordering {
+        self.text.cmp(&other.text)
+    }
+}
+
+impl std::hash::Hash for Name {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        // By text, as names equal to a pattern without a symbol must hash
+        // like it
+        self.text.hash(state)
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<&str> for Name {
+    fn from(text: &str) -> Self {
+        Name::new(text)
+    }
+}
+
+impl From<String> for Name {
+    fn from(text: String) -> Self {
+        Name::new(text)
+    }
+}
+
+impl From<&String> for Name {
+    fn from(text: &String) -> Self {
+        Name::new(text)
+    }
+}
+
+impl From<Str> for Name {
+    fn from(text: Str) -> Self {
+        Name::new(text)
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        *self.text == *other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        *self.text == **other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        *self.text == **other
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.text, f)
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.text, f)
+    }
+}
+
+impl Serialize for Name {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.text.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Name {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Name::new(Str::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{flow_id, Database};
+
+    #[test]
+    fn test_interning() {
+        let read = || serde_json::from_str::<Vec<Str>>(r#"["List", "int", "List"]"#).unwrap();
+        let strings = interning(read);
+        assert_eq!(strings[0], "List");
+        assert!(strings[0].ptr_eq(&strings[2]));
+        assert!(!strings[0].ptr_eq(&strings[1]));
+
+        let strings = read();
+        assert!(!strings[0].ptr_eq(&strings[2]));
+        assert_eq!(
+            serde_json::to_string(&strings).unwrap(),
+            r#"["List","int","List"]"#
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        let list = Name::new("List");
+        assert_eq!(list.symbol(), Name::new(String::from("List")).symbol());
+        assert_ne!(list.symbol(), Name::new("list").symbol());
+        assert!(list.matches(&Name::new("List"), false));
+        assert!(!list.matches(&Name::new("list"), false));
+        assert!(list.matches(&Name::new("list"), true));
+        assert!(Name::new("Li.*").matches(&list, false));
+        assert!(Name::new("Li*").matches(&list, false));
+        assert_eq!(serde_json::from_str::<Name>("\"List\"").unwrap(), list);
+
+        assert_eq!(Name::pattern("List").symbol(), list.symbol());
+        let unread = Name::pattern("NeverReadFromAnyDatabase");
+        assert_eq!(unread.symbol(), None);
+        assert_eq!(Symbol::get("NeverReadFromAnyDatabase"), None);
+        assert!(!unread.matches(&list, false));
+        let read = Name::new("NeverReadFromAnyDatabase");
+        assert_eq!(unread, read);
+        assert!(unread.matches(&read, false));
+        assert_eq!(
+            Name::pattern("NeverReadFromAnyDatabase").symbol(),
+            read.symbol()
+        );
+    }
+
+    #[test]
+    fn test_reload_keeps_symbols() {
+        let data = r#"{"file_path": "reload.ml", "dataflow": [[
+            {"Type": {"name": "ReloadOnlyA", "args": [], "desc": null}},
+            {"Type": {"name": "ReloadOnlyB", "args": [], "desc": null}},
+            {"Type": {"name": "ReloadOnlyA", "args": [], "desc": null}}
+        ]]}"#;
+        let numbered = || {
+            let symbols = symbols().read().unwrap();
+            symbols
+                .keys()
+                .filter(|name| name.starts_with("ReloadOnly"))
+                .count()
+        };
+        let db = Database::load_from_str(data).unwrap();
+        assert_eq!(numbered(), 2);
+        for _ in 0..3 {
+            let reloaded = Database::load_from_str(data).unwrap();
+            assert_eq!(flow_id(&reloaded.data_flows[0]), flow_id(&db.data_flows[0]));
+        }
+        assert_eq!(numbered(), 2);
+    }
+}
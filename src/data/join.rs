@@ -0,0 +1,89 @@
+use super::{Database, QueryOps, UnitFlow};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Two flows sharing a type variable, the first matching one query and the
+/// second another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowPair {
+    /// Index of the flow matching the first query in [Database::data_flows]
+    pub from: usize,
+    /// Index of the flow matching the second query
+    pub to: usize,
+    /// Type variables both flows pass through, sorted
+    pub via: Vec<String>,
+}
+
+impl Database {
+    /// Pairs of distinct flows, the first matching `from` and the second
+    /// matching `to`, that pass through a common type variable, ordered by
+    /// the first flow and then the second
+    pub fn join_flows(&self, from: &[QueryOps], to: &[QueryOps]) -> Vec<FlowPair> {
+        let targets: BTreeSet<usize> = self.matching_indices(to).into_iter().collect();
+        let mut pairs: BTreeMap<(usize, usize), BTreeSet<String>> = BTreeMap::new();
+        for source in self.matching_indices(from) {
+            for uf in &self.data_flows[source] {
+                let UnitFlow::TypeVar(tv) = uf else {
+                    continue;
+                };
+                let Some(flows) = self.type_var_flows.get(tv.name.as_str()) else {
+                    continue;
+                };
+                for &target in flows {
+                    if target != source && targets.contains(&target) {
+                        pairs
+                            .entry((source, target))
+                            .or_default()
+                            .insert(tv.name.to_string());
+                    }
+                }
+            }
+        }
+        pairs
+            .into_iter()
+            .map(|((from, to), via)| FlowPair {
+                from,
+                to,
+                via: via.into_iter().collect(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"{
+        "file_path": "main.ml",
+        "dataflow": [
+            [{"Type": {"name": "string", "args": [], "desc": "user input"}},
+             {"TypeVar": {"name": "'a", "desc": null}}],
+            [{"TypeVar": {"name": "'a", "desc": null}},
+             {"Type": {"name": "unit", "args": [], "desc": "printed"}}],
+            [{"TypeVar": {"name": "'b", "desc": null}},
+             {"Type": {"name": "unit", "args": [], "desc": "logged"}}]
+        ]
+    }"#;
+
+    #[test]
+    fn test_join_flows() {
+        let db = Database::load_from_str(DATA).unwrap();
+        let join = |from: &str, to: &str| {
+            db.join_flows(
+                &QueryOps::parse_query(from).unwrap(),
+                &QueryOps::parse_query(to).unwrap(),
+            )
+        };
+        assert_eq!(
+            join("\"user input\"", "unit"),
+            vec![FlowPair {
+                from: 0,
+                to: 1,
+                via: vec!["'a".to_string()],
+            }]
+        );
+        assert_eq!(join("unit", "\"user input\"")[0].from, 1);
+        assert!(join("\"user input\"", "\"logged\"").is_empty());
+        assert!(join("\"user input\"", "string").is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+/// Renders types for display, given a type constructor and its already
+/// rendered arguments
+pub trait TypePrinter {
+    fn render_type(&self, name: &str, args: &[&str]) -> String;
+
+    /// Render a type variable such as `'a`
+    fn render_var(&self, name: &str) -> String {
+        name.to_string()
+    }
+}
+
+/// Language of the program a database was extracted from, selecting how its
+/// types are rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// `List<int>`
+    #[default]
+    Generic,
+    /// `int list`, `(int, string) result`
+    OCaml,
+    /// `Vec<T>`
+    Rust,
+    /// `List[int]`, `Dict[str, T]`
+    Python,
+}
+
+impl TypePrinter for Language {
+    fn render_type(&self, name: &str, args: &[&str]) -> String {
+        match (self, args) {
+            (_, []) => name.to_string(),
+            (Language::OCaml, [arg]) => format!("{} {}", arg, name),
+            (Language::OCaml, args) => format!("({}) {}", args.join(", "), name),
+            (Language::Python, args) => format!("{}[{}]", name, args.join(", ")),
+            (Language::Generic | Language::Rust, args) => {
+                format!("{}<{}>", name, args.join(", "))
+            }
+        }
+    }
+
+    fn render_var(&self, name: &str) -> String {
+        match self {
+            // Type parameters are conventionally capitalized, `'a` becomes `A`
+            Language::Rust | Language::Python => name
+                .strip_prefix('\'')
+                .map_or(name.to_string(), str::to_uppercase),
+            Language::Generic | Language::OCaml => name.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_rendering() {
+        let render = |lang: Language, args: &[&str]| lang.render_type("list", args);
+
+        assert_eq!(render(Language::OCaml, &[]), "list");
+        assert_eq!(render(Language::OCaml, &["'a"]), "'a list");
+        assert_eq!(
+            render(Language::OCaml, &["int", "string"]),
+            "(int, string) list"
+        );
+        assert_eq!(render(Language::Rust, &["T"]), "list<T>");
+        assert_eq!(render(Language::Python, &["int", "str"]), "list[int, str]");
+        assert_eq!(Language::Rust.render_var("'t"), "T");
+        assert_eq!(Language::OCaml.render_var("'t"), "'t");
+        assert_eq!(
+            serde_json::from_str::<Language>("\"ocaml\"").unwrap(),
+            Language::OCaml
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Database, QueryOps, UnitFlow};
+use std::fmt;
+
+/// Bounds on the length of a flow, counted in steps or, with `locs`, in
+/// program locations
+#[derive(serde::Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LengthBounds {
+    pub locs: bool,
+    pub min: usize,
+    /// Inclusive, or unbounded above without it
+    pub max: Option<usize>,
+}
+
+impl LengthBounds {
+    /// Whether the bounds keep every flow
+    pub fn is_empty(&self) -> bool {
+        self.min == 0 && self.max.is_none()
+    }
+
+    /// The length of `flow` as the bounds count it
+    pub fn length(&self, flow: &[UnitFlow]) -> usize {
+        if self.locs {
+            flow.iter()
+                .filter(|uf| matches!(uf, UnitFlow::ProgLoc(_)))
+                .count()
+        } else {
+            flow.len()
+        }
+    }
+
+    pub fn contains(&self, flow: &[UnitFlow]) -> bool {
+        let len = self.length(flow);
+        len >= self.min && self.max.is_none_or(|max| len <= max)
+    }
+
+    /// Parse the comparison after `len` or `locs` in a query: `>=5`, `<=20`,
+    /// `>1`, `<10`, `=3` or `=2..5`
+    pub(super) fn parse(locs: bool, s: &str) -> Result<LengthBounds, String> {
+        let count = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid length `{}`", n.trim()))
+        };
+        let bounds = |min, max| Ok(LengthBounds { locs, min, max });
+        if let Some(n) = s.strip_prefix(">=") {
+            bounds(count(n)?, None)
+        } else if let Some(n) = s.strip_prefix("<=") {
+            bounds(0, Some(count(n)?))
+        } else if let Some(n) = s.strip_prefix('>') {
+            bounds(count(n)? + 1, None)
+        } else if let Some(n) = s.strip_prefix('<') {
+            match count(n)?.checked_sub(1) {
+                Some(max) => bounds(0, Some(max)),
+                None => Err("Lengths cannot be below 0".to_string()),
+            }
+        } else if let Some(n) = s.strip_prefix('=') {
+            match n.split_once("..") {
+                Some((min, max)) => match (count(min)?, count(max)?) {
+                    (min, max) if min > max => Err(format!("Empty length range {}..{}", min, max)),
+                    (min, max) => bounds(min, Some(max)),
+                },
+                None => count(n).and_then(|n| bounds(n, Some(n))),
+            }
+        } else {
+            Err("Expected `>=`, `<=`, `>`, `<` or `=` after the length".to_string())
+        }
+    }
+}
+
+/// Renders the bounds as the query token parsing them back
+impl fmt::Display for LengthBounds {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = if self.locs { "locs" } else { "len" };
+        match self.max {
+            None => write!(f, "{}>={}", unit, self.min),
+            Some(max) if self.min == 0 => write!(f, "{}<={}", unit, max),
+            Some(max) if self.min == max => write!(f, "{}={}", unit, max),
+            Some(max) => write!(f, "{}={}..{}", unit, self.min, max),
+        }
+    }
+}
+
+impl QueryOps {
+    /// The unit of a `len` or `locs` length token starting `rest`, whether
+    /// it counts locations, and the unit's length; `None` for other steps,
+    /// such as a type named `len`
+    pub(super) fn length_unit(rest: &str) -> Option<(bool, usize)> {
+        let (locs, unit) = if rest.starts_with("locs") {
+            (true, "locs")
+        } else if rest.starts_with("len") {
+            (false, "len")
+        } else {
+            return None;
+        };
+        rest[unit.len()..]
+            .starts_with(['<', '>', '='])
+            .then_some((locs, unit.len()))
+    }
+}
+
+impl Database {
+    /// The database restricted to the flows whose length is within `bounds`
+    pub fn with_lengths(self, bounds: &LengthBounds) -> Database {
+        if bounds.is_empty() {
+            return self;
+        }
+        let flows = self
+            .data_flows
+            .iter()
+            .filter(|flow| bounds.contains(flow))
+            .map(<[UnitFlow]>::to_vec)
+            .collect();
+        self.with_flows(flows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flow_lengths() {
+        let db = crate::tutorial::load_database();
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+        let lengths: Vec<usize> = db.data_flows.iter().map(<[UnitFlow]>::len).collect();
+        let expected = |keep: &dyn Fn(usize) -> bool| -> Vec<usize> {
+            (0..lengths.len())
+                .filter(|&idx| keep(lengths[idx]))
+                .collect()
+        };
+        assert_eq!(indices("len>=3"), expected(&|len| len >= 3));
+        assert_eq!(indices("len<3"), expected(&|len| len < 3));
+        assert_eq!(indices("List, len=2..4"), {
+            let list = indices("List");
+            expected(&|len| (2..=4).contains(&len))
+                .into_iter()
+                .filter(|idx| list.contains(idx))
+                .collect::<Vec<_>>()
+        });
+        let located: Vec<usize> = (0..lengths.len())
+            .filter(|&idx| {
+                db.data_flows[idx]
+                    .iter()
+                    .any(|uf| matches!(uf, UnitFlow::ProgLoc(_)))
+            })
+            .collect();
+        assert_eq!(indices("locs>=1"), located);
+
+        for token in ["len>=3", "len<=4", "locs=2", "len=2..5"] {
+            let query = QueryOps::parse_query(token).unwrap();
+            assert_eq!(query[0].to_string(), token);
+        }
+        assert!(QueryOps::parse_query("len=5..2").is_err());
+        assert!(QueryOps::parse_query("len<0").is_err());
+        assert!(matches!(
+            QueryOps::parse_query("len").unwrap()[0],
+            QueryOps::QType(_)
+        ));
+
+        let bounds = LengthBounds::parse(false, ">=3").unwrap();
+        let kept = crate::tutorial::load_database().with_lengths(&bounds);
+        assert_eq!(kept.data_flows.len(), expected(&|len| len >= 3).len());
+    }
+}
@@ -0,0 +1,285 @@
+use super::Flows;
+use crate::error::SemanticSearchError;
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde_json::Value;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::io::{self, Read};
+
+thread_local! {
+    /// Limits of the [with_limits] call running on this thread
+    static LIMITS: Cell<LoadLimits> = const { Cell::new(LoadLimits::NONE) };
+    /// Why [checked_flows] last stopped reading, to tell a database past the
+    /// limits from a malformed one
+    static EXCEEDED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Bounds on what a database may hold, so that a corrupt or hostile file
+/// fails to load with an error rather than exhausting memory. Each bound is
+/// unlimited without a value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LoadLimits {
+    /// Bytes of a database file, or of what it decompresses to
+    pub max_file_size: Option<u64>,
+    pub max_flows: Option<usize>,
+    /// Steps of a single flow
+    pub max_flow_len: Option<usize>,
+    /// Bytes of a single name, source line or description
+    pub max_string_len: Option<usize>,
+}
+
+impl LoadLimits {
+    pub const NONE: LoadLimits = LoadLimits {
+        max_file_size: None,
+        max_flows: None,
+        max_flow_len: None,
+        max_string_len: None,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == LoadLimits::NONE
+    }
+}
+
+/// Run `load`, holding the databases it reads on this thread to `limits`.
+/// Nested calls apply their own limits until they return.
+pub fn with_limits<T>(limits: LoadLimits, load: impl FnOnce() -> T) -> T {
+    let outer = LIMITS.with(|current| current.replace(limits));
+    let result = load();
+    LIMITS.with(|current| current.set(outer));
+    result
+}
+
+/// Hold the databases read on this thread to `limits` from now on, as the
+/// command line does for the whole run
+pub fn set_load_limits(limits: LoadLimits) {
+    LIMITS.with(|current| current.set(limits));
+}
+
+/// The limits databases are read with on this thread
+pub fn load_limits() -> LoadLimits {
+    LIMITS.with(Cell::get)
+}
+
+/// Error for a file of `size` bytes, if the limits forbid it
+pub(super) fn check_file_size(size: u64) -> Result<(), String> {
+    match load_limits().max_file_size {
+        Some(max) if size > max => Err(format!(
+            "file of {} bytes is larger than the --max-file-size of {}",
+            size, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Error for a string of `len` bytes, if the limits forbid it
+pub(super) fn check_string_len(len: usize) -> Result<(), String> {
+    match load_limits().max_string_len {
+        Some(max) if len > max => Err(format!(
+            "string of {} bytes is longer than the --max-string-len of {}",
+            len, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Error for a database of `count` flows, if there are too many
+pub(super) fn check_flow_count(count: usize) -> Result<(), String> {
+    match load_limits().max_flows {
+        Some(max) if count > max => Err(format!("more flows than the --max-flows of {}", max)),
+        _ => Ok(()),
+    }
+}
+
+/// Error for the flow at `idx` of `len` steps, if there are too many flows
+/// or it is too long
+pub(super) fn check_flow(idx: usize, len: usize) -> Result<(), String> {
+    check_flow_count(idx + 1)?;
+    match load_limits().max_flow_len {
+        Some(max) if len > max => Err(format!(
+            "flow {} of {} steps is longer than the --max-flow-len of {}",
+            idx, len, max
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The limit error a failed parse of the file at `path` stopped at, if it
+/// read a flow past the limits rather than a malformed one
+pub(super) fn exceeded(path: Option<&str>) -> Option<SemanticSearchError> {
+    let message = EXCEEDED.with(RefCell::take)?;
+    Some(SemanticSearchError::Limit {
+        path: path.map(str::to_string),
+        message,
+    })
+}
+
+/// Check the flow at `idx` of `len` steps, as [check_flow] does, keeping
+/// the reason it is rejected for [exceeded]
+fn check_read_flow(idx: usize, len: usize) -> Result<(), String> {
+    check_flow(idx, len).inspect_err(|message| {
+        EXCEEDED.with(|exceeded| exceeded.replace(Some(message.clone())));
+    })
+}
+
+/// The `dataflow` of a database as it is read
+pub(super) trait CheckedFlows<'de>: Sized {
+    /// Read the flows, checking each against the limits of this thread as
+    /// soon as it is read, so that reading stops at the first one past them.
+    /// [exceeded] tells the error apart.
+    fn deserialize_checked<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+impl<'de> CheckedFlows<'de> for Flows {
+    fn deserialize_checked<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Flows::deserialize_checked(deserializer, check_read_flow)
+    }
+}
+
+/// Flows kept as they were written, to be parsed one by one
+impl<'de> CheckedFlows<'de> for Vec<Value> {
+    fn deserialize_checked<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ValuesVisitor;
+
+        impl<'de> Visitor<'de> for ValuesVisitor {
+            type Value = Vec<Value>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of flows")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<Value>, A::Error> {
+                let mut flows = vec![];
+                while let Some(flow) = seq.next_element::<Value>()? {
+                    let len = flow.as_array().map_or(0, Vec::len);
+                    check_read_flow(flows.len(), len).map_err(de::Error::custom)?;
+                    flows.push(flow);
+                }
+                Ok(flows)
+            }
+        }
+
+        deserializer.deserialize_seq(ValuesVisitor)
+    }
+}
+
+/// Deserialize the `dataflow` of a database with [CheckedFlows]
+pub(super) fn checked_flows<'de, D, F>(deserializer: D) -> Result<F, D::Error>
+where
+    D: Deserializer<'de>,
+    F: CheckedFlows<'de>,
+{
+    F::deserialize_checked(deserializer)
+}
+
+/// Reads at most the `max_file_size` of the limits it was made under from a
+/// database file, failing past it, so that a small compressed file cannot
+/// expand without bound
+pub(super) struct Limited<R> {
+    inner: R,
+    max: Option<u64>,
+    read: u64,
+}
+
+impl<R: Read> Limited<R> {
+    pub(super) fn new(inner: R) -> Self {
+        Limited {
+            inner,
+            max: load_limits().max_file_size,
+            read: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Limited<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(max) = self.max else {
+            return self.inner.read(buf);
+        };
+        // Read one byte past the limit to tell a file ending on it from one
+        // going on
+        let left = (max - self.read).saturating_add(1);
+        let want = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..want])?;
+        self.read += read as u64;
+        if self.read > max {
+            return Err(io::Error::other(format!(
+                "decompresses to more than the --max-file-size of {} bytes",
+                max
+            )));
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Database, FlowStream};
+    use crate::tutorial::TUTORIAL_JSON;
+
+    #[test]
+    fn test_load_limits() {
+        let load = |limits| with_limits(limits, || Database::load_from_str(TUTORIAL_JSON));
+        assert!(load(LoadLimits::NONE).is_ok());
+
+        let error = load(LoadLimits {
+            max_flows: Some(2),
+            ..LoadLimits::NONE
+        })
+        .unwrap_err();
+        assert!(matches!(error, SemanticSearchError::Limit { .. }));
+        assert!(error.to_string().contains("--max-flows"));
+
+        let error = load(LoadLimits {
+            max_flow_len: Some(1),
+            ..LoadLimits::NONE
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("--max-flow-len"));
+
+        let error = load(LoadLimits {
+            max_string_len: Some(4),
+            ..LoadLimits::NONE
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("--max-string-len"));
+        assert_eq!(load_limits(), LoadLimits::NONE);
+
+        let ndjson = Database::load_from_str(TUTORIAL_JSON)
+            .unwrap()
+            .to_ndjson()
+            .unwrap();
+        let limits = LoadLimits {
+            max_flows: Some(2),
+            ..LoadLimits::NONE
+        };
+        let flows: Vec<_> = with_limits(limits, || {
+            FlowStream::new(ndjson.as_bytes(), "tutorial.ndjson")
+                .unwrap()
+                .collect()
+        });
+        assert!(flows[..2].iter().all(Result::is_ok));
+        assert!(matches!(flows[2], Err(SemanticSearchError::Limit { .. })));
+        let error = with_limits(limits, || {
+            FlowStream::new(ndjson.as_bytes(), "tutorial.ndjson")
+                .unwrap()
+                .collect_database(true)
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("--max-flows"));
+
+        let limited = |max| {
+            let mut data = String::new();
+            let limits = LoadLimits {
+                max_file_size: Some(max),
+                ..LoadLimits::NONE
+            };
+            with_limits(limits, || {
+                Limited::new(&b"0123456789"[..]).read_to_string(&mut data)
+            })
+        };
+        assert_eq!(limited(10).unwrap(), 10);
+        assert!(limited(9).is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Database, ProgLoc, UnitFlow};
+use std::cmp::Ordering;
+
+/// A program location whose `char_range` the renderer rejects, with the
+/// range proposed to replace it
+#[derive(Debug, Clone)]
+pub struct LocFix {
+    /// Index of the flow in [Database::data_flows]
+    pub flow: usize,
+    /// Index of the location in the flow
+    pub step: usize,
+    pub loc: ProgLoc,
+    /// None when the line is empty and no range fits
+    pub proposed: Option<(usize, usize)>,
+}
+
+impl ProgLoc {
+    /// Whether `char_range` passes the renderer's bounds checks: a span
+    /// within the line, possibly running onto its newline, or an empty span
+    /// marking an insertion point no further than just past the line's end
+    pub fn has_valid_range(&self) -> bool {
+        let len = self.line.chars().count();
+        let (start, end) = self.char_range;
+        match start.cmp(&end) {
+            Ordering::Less => start < len && end <= len + 2,
+            Ordering::Equal => (1..=len + 1).contains(&start),
+            Ordering::Greater => false,
+        }
+    }
+
+    pub fn char_range(&self) -> (usize, usize) {
+        self.char_range
+    }
+
+    pub fn with_char_range(&self, char_range: (usize, usize)) -> ProgLoc {
+        ProgLoc {
+            char_range,
+            ..self.clone()
+        }
+    }
+
+    /// A valid range for the location: the description found in the line,
+    /// or else the current range put in order and clamped to the line
+    fn repaired_range(&self) -> Option<(usize, usize)> {
+        let found = self
+            .desc
+            .as_deref()
+            .filter(|desc| !desc.is_empty())
+            .and_then(|desc| {
+                let start = self.line[..self.line.find(desc)?].chars().count() + 1;
+                Some((start, start + desc.chars().count()))
+            });
+        if let Some(range) = found.filter(|range| self.with_char_range(*range).has_valid_range()) {
+            return Some(range);
+        }
+
+        let len = self.line.chars().count();
+        if len == 0 {
+            return None;
+        }
+        let (start, end) = match self.char_range {
+            (a, b) if a > b => (b, a),
+            range => range,
+        };
+        let start = start.min(len - 1);
+        let end = end.min(len + 1).max(start + 1);
+        Some((start, end))
+    }
+}
+
+impl Database {
+    /// Program locations the renderer would skip, with proposed repairs
+    pub fn bad_locations(&self) -> Vec<LocFix> {
+        self.data_flows
+            .iter()
+            .enumerate()
+            .flat_map(|(flow_idx, flow)| {
+                flow.iter()
+                    .enumerate()
+                    .filter_map(move |(step, uf)| match uf {
+                        UnitFlow::ProgLoc(p) if !p.has_valid_range() => Some(LocFix {
+                            flow: flow_idx,
+                            step,
+                            loc: p.clone(),
+                            proposed: p.repaired_range(),
+                        }),
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Look for program locations the renderer would skip, recording a
+    /// [warning](Database::warnings) counting them for the database read
+    /// from `path`. With `repair` their ranges are replaced by the proposed
+    /// repairs first, clamping them to their lines.
+    pub fn check_spans(&mut self, path: &str, repair: bool) {
+        let fixes = self.bad_locations();
+        if fixes.is_empty() {
+            return;
+        }
+        if !repair {
+            self.warnings.push(format!(
+                "{}: {} locations with out-of-range or inverted ranges are not shown; --repair-spans clamps them",
+                path,
+                fixes.len()
+            ));
+            return;
+        }
+        self.apply_loc_fixes(&fixes);
+        let unrepaired = fixes.iter().filter(|fix| fix.proposed.is_none()).count();
+        let mut warning = format!(
+            "{}: clamped the ranges of {} locations",
+            path,
+            fixes.len() - unrepaired
+        );
+        if unrepaired > 0 {
+            warning.push_str(&format!(", leaving {} on empty lines", unrepaired));
+        }
+        self.warnings.push(warning);
+    }
+
+    /// Replace the ranges of the locations with their proposed repairs
+    pub fn apply_loc_fixes(&mut self, fixes: &[LocFix]) {
+        for fix in fixes {
+            if let (Some(range), Some(UnitFlow::ProgLoc(p))) = (
+                fix.proposed,
+                self.data_flows
+                    .get_mut(fix.flow)
+                    .and_then(|flow| flow.get_mut(fix.step)),
+            ) {
+                p.char_range = range;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loc(line: &str, char_range: (usize, usize), desc: Option<&str>) -> UnitFlow {
+        UnitFlow::ProgLoc(ProgLoc {
+            line: line.into(),
+            char_range,
+            desc: desc.map(Into::into),
+            depth: 0,
+            file: None,
+            line_number: None,
+            column: None,
+            commit: None,
+        })
+    }
+
+    #[test]
+    fn test_loc_fixes() {
+        let mut db = Database::new(
+            "test.ml".to_string(),
+            vec![vec![
+                loc("let x = y", (5, 6), None),
+                loc("let x = y", (6, 2), None),
+                loc("let y = x", (40, 50), Some("y")),
+                loc("f x", (8, 9), None),
+                loc("", (1, 2), None),
+            ]],
+        );
+
+        let fixes = db.bad_locations();
+        let proposed: Vec<_> = fixes.iter().map(|f| (f.step, f.proposed)).collect();
+        assert_eq!(
+            proposed,
+            vec![
+                (1, Some((2, 6))),
+                (2, Some((5, 6))),
+                (3, Some((2, 4))),
+                (4, None)
+            ]
+        );
+
+        db.apply_loc_fixes(&fixes);
+        assert_eq!(db.bad_locations().len(), 1);
+
+        let mut db = Database::new(
+            "test.ml".to_string(),
+            vec![vec![loc("let x = y", (6, 2), None), loc("", (1, 2), None)]],
+        );
+        db.check_spans("db.json", false);
+        assert_eq!(
+            db.warnings(),
+            ["db.json: 2 locations with out-of-range or inverted ranges are not shown; --repair-spans clamps them"]
+        );
+        assert_eq!(db.loc_coverage().invalid_ranges, 2);
+        db.check_spans("db.json", true);
+        assert_eq!(
+            db.warnings()[1],
+            "db.json: clamped the ranges of 1 locations, leaving 1 on empty lines"
+        );
+        assert_eq!(db.loc_coverage().invalid_ranges, 1);
+    }
+}
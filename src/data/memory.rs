@@ -0,0 +1,109 @@
+use super::{Database, Str, UnitFlow};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::mem::size_of;
+
+/// Approximate bytes a loaded database takes in memory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    /// The flows and their steps, without the text the steps hold
+    pub steps: usize,
+    /// The text of names, lines and descriptions, counting each shared
+    /// string once
+    pub text: usize,
+    /// Text repeated across steps that sharing strings saved storing again
+    pub shared: usize,
+    /// The inverted maps of types, type variables, constructors and
+    /// description words
+    pub indices: usize,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> usize {
+        self.steps + self.text + self.indices
+    }
+}
+
+impl UnitFlow {
+    /// The strings held by the step
+    fn strings(&self) -> Vec<&Str> {
+        match self {
+            UnitFlow::Type(t) => [t.name.text()]
+                .into_iter()
+                .chain(&t.args)
+                .chain(&t.desc)
+                .collect(),
+            UnitFlow::ConstructorArg(c) => [c.name.text()].into_iter().chain(&c.desc).collect(),
+            UnitFlow::TypeVar(tv) => [tv.name.text()].into_iter().chain(&tv.desc).collect(),
+            UnitFlow::ProgLoc(p) => [&p.line]
+                .into_iter()
+                .chain(&p.desc)
+                .chain(&p.file)
+                .chain(&p.commit)
+                .collect(),
+            UnitFlow::Other { .. } => vec![],
+        }
+    }
+}
+
+impl Database {
+    /// How much memory the flows and their indices take, estimated from the
+    /// sizes of their allocations
+    pub fn memory_usage(&self) -> MemoryUsage {
+        // The reference counts stored ahead of each string's text
+        const STR_HEADER: usize = 2 * size_of::<usize>();
+        let mut usage = MemoryUsage::default();
+        let mut seen = HashSet::new();
+        usage.steps = self.data_flows.allocated();
+        for flow in &self.data_flows {
+            for uf in flow {
+                match uf {
+                    UnitFlow::Type(t) => usage.steps += t.args.capacity() * size_of::<Str>(),
+                    // Steps of other kinds hold their own text, never shared
+                    UnitFlow::Other { kind, fields } => {
+                        usage.text += kind.len() + fields.to_string().len()
+                    }
+                    _ => {}
+                }
+                for text in uf.strings() {
+                    if seen.insert(text.addr()) {
+                        usage.text += STR_HEADER + text.len();
+                    } else {
+                        usage.shared += text.len();
+                    }
+                }
+            }
+        }
+        let index_size = |map: &BTreeMap<String, Vec<usize>>| -> usize {
+            map.iter()
+                .map(|(name, flows)| name.len() + flows.capacity() * size_of::<usize>())
+                .sum()
+        };
+        usage.indices = index_size(&self.type_flows)
+            + index_size(&self.type_var_flows)
+            + index_size(&self.constructor_flows)
+            + index_size(&self.desc_words);
+        usage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::Database;
+
+    #[test]
+    fn test_memory_usage() {
+        let flow = r#"[{"Type": {"name": "int", "args": [], "desc": null}},
+            {"ProgLoc": {"line": "let x = f y", "char_range": [5, 6], "desc": null, "depth": 0}}]"#;
+        let data = format!(
+            r#"{{"file_path": "a.ml", "dataflow": [{}, {}]}}"#,
+            flow,
+            flow.replace("[5, 6]", "[9, 10]")
+        );
+        let db = Database::load_from_str(&data).unwrap();
+        let usage = db.memory_usage();
+        assert_eq!(usage.shared, "int".len() + "let x = f y".len());
+        assert!(usage.text > usage.shared && usage.steps > 0 && usage.indices > 0);
+        assert_eq!(usage.total(), usage.steps + usage.text + usage.indices);
+    }
+}
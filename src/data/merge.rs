@@ -0,0 +1,180 @@
+use super::compress::uncompressed_name;
+use super::limits;
+use super::{is_type_var, Database, Subtypes, UnitFlow};
+use crate::error::{self, SemanticSearchError};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Suffixes of the sidecar files kept next to a database
+const SIDECAR_SUFFIXES: &[&str] = &[".notes.json", ".bookmarks.json"];
+
+/// The data JSON files `path` names: the files of a directory, the file
+/// itself, or each entry of a list joined like `PATH`
+pub fn data_json_paths(path: &str) -> error::Result<Vec<PathBuf>> {
+    let named = Path::new(path);
+    if !named.is_dir() {
+        // A file whose name holds the separator is not a list
+        if named.exists() {
+            return Ok(vec![named.to_path_buf()]);
+        }
+        return Ok(env::split_paths(path).collect());
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|e| SemanticSearchError::io(path, e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|p| {
+            let name = p.to_string_lossy();
+            uncompressed_name(&name).ends_with(".json")
+                && !SIDECAR_SUFFIXES.iter().any(|s| name.ends_with(s))
+        })
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+impl Database {
+    /// Load and [merge](Database::merge) the databases at `paths`
+    pub fn load_from_many<P: AsRef<Path>>(paths: &[P]) -> error::Result<Self> {
+        let mut dbs = vec![];
+        let mut flows = 0;
+        for path in paths {
+            let path = path.as_ref().to_string_lossy();
+            let data = Database::read_data(&path)?;
+            let db = Database::parse(&data, Some(&path))?;
+            // Each file is held to the limits as it is read; the flows of
+            // all of them together count against --max-flows too
+            flows += db.data_flows.len();
+            limits::check_flow_count(flows).map_err(|message| SemanticSearchError::Limit {
+                path: Some(path.to_string()),
+                message,
+            })?;
+            dbs.push(db);
+        }
+        Ok(Database::merge(dbs))
+    }
+
+    /// Merge the flows of several databases. Program locations without a file
+    /// of their own are stamped with their database's `file_path`, and type
+    /// variables, which are only meaningful within one database, are suffixed
+    /// with it as `'a@file`. Declared subtypes are combined.
+    pub fn merge(dbs: Vec<Database>) -> Database {
+        let file_path = match dbs.as_slice() {
+            [first, rest @ ..] if rest.iter().all(|db| db.file_path == first.file_path) => {
+                first.file_path.clone()
+            }
+            _ => String::new(),
+        };
+        let language = match dbs.as_slice() {
+            [first, rest @ ..] if rest.iter().all(|db| db.language == first.language) => {
+                first.language
+            }
+            _ => Default::default(),
+        };
+        let mut subtypes = Subtypes::new();
+        for db in &dbs {
+            for (sup, subs) in &db.subtypes {
+                let merged = subtypes.entry(sup.clone()).or_default();
+                for sub in subs {
+                    if !merged.contains(sub) {
+                        merged.push(sub.clone());
+                    }
+                }
+            }
+        }
+
+        let flows = dbs
+            .into_iter()
+            .flat_map(|db| {
+                let source = db.file_path;
+                db.data_flows.into_iter().map(move |mut flow| {
+                    for uf in &mut flow {
+                        stamp_source(uf, &source);
+                    }
+                    flow
+                })
+            })
+            .collect();
+        let mut merged = Database::from_flows(file_path, flows);
+        merged.language = language;
+        merged.subtypes = subtypes;
+        merged
+    }
+}
+
+fn stamp_source(uf: &mut UnitFlow, source: &str) {
+    let qualified = |name: &str| is_type_var(name).then(|| format!("{}@{}", name, source));
+    match uf {
+        UnitFlow::Type(t) => {
+            if let Some(name) = qualified(&t.name) {
+                t.name = name.into();
+            }
+            for arg in &mut t.args {
+                if let Some(name) = qualified(arg) {
+                    *arg = name.into();
+                }
+            }
+        }
+        UnitFlow::TypeVar(tv) => {
+            if let Some(name) = qualified(&tv.name) {
+                tv.name = name.into();
+            }
+        }
+        UnitFlow::ProgLoc(p) => {
+            p.file.get_or_insert_with(|| source.into());
+        }
+        UnitFlow::ConstructorArg(_) | UnitFlow::Other { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+
+    #[test]
+    fn test_merge() {
+        let tutorial = crate::tutorial::load_database();
+        let copy = |file_path: &str| {
+            let mut db = crate::tutorial::load_database();
+            db.file_path = file_path.to_string();
+            db
+        };
+        let merged = Database::merge(vec![copy("a.ml"), copy("b.ml")]);
+
+        assert_eq!(merged.data_flows.len(), 2 * tutorial.data_flows.len());
+        assert_eq!(merged.file_path, "");
+        let files: Vec<_> = merged
+            .data_flows
+            .iter()
+            .map(|flow| merged.flow_files(flow).into_iter().collect::<Vec<_>>())
+            .collect();
+        assert_eq!(files[0], vec!["a.ml"]);
+        assert_eq!(files[6], vec!["b.ml"]);
+
+        // Type variables stay shared by the same flows as in each source
+        let query = QueryOps::parse_query("#2").unwrap();
+        assert_eq!(
+            merged.matching_indices(&query),
+            vec![1, 2, 3, 4, 7, 8, 9, 10]
+        );
+    }
+
+    #[test]
+    fn test_data_json_paths() {
+        let dir = std::env::temp_dir().join(format!("scs-paths-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let joined = env::join_paths([dir.join("a.json"), dir.join("b.json")]).unwrap();
+        let joined = joined.to_str().unwrap();
+        assert_eq!(data_json_paths(joined).unwrap().len(), 2);
+
+        // Once a file has the joined name, it is read on its own
+        let separator = if cfg!(windows) { ';' } else { ':' };
+        let file = dir.join(format!("a{}b.json", separator));
+        fs::write(&file, "{}").unwrap();
+        let path = file.to_str().unwrap();
+        assert_eq!(data_json_paths(path).unwrap(), vec![file.clone()]);
+        assert_eq!(data_json_paths(dir.to_str().unwrap()).unwrap(), vec![file]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use super::{schema, ConstructorArg, ProgLoc, Type, TypeVar, UnitFlow};
+use serde::de::{self, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+
+/// A [UnitFlow] as written to binary indexes, which cannot hold arbitrary
+/// JSON, so the fields of steps of other kinds are kept as JSON text
+#[derive(Deserialize)]
+enum IndexedStep {
+    Type(Type),
+    ConstructorArg(ConstructorArg),
+    TypeVar(TypeVar),
+    ProgLoc(ProgLoc),
+    Other((String, String)),
+}
+
+/// Steps are written in data JSON as objects with one key naming their kind.
+/// Steps of kinds other than the four known ones are written back as they
+/// were read.
+impl Serialize for UnitFlow {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            UnitFlow::Type(t) => serializer.serialize_newtype_variant("UnitFlow", 0, "Type", t),
+            UnitFlow::ConstructorArg(c) => {
+                serializer.serialize_newtype_variant("UnitFlow", 1, "ConstructorArg", c)
+            }
+            UnitFlow::TypeVar(tv) => {
+                serializer.serialize_newtype_variant("UnitFlow", 2, "TypeVar", tv)
+            }
+            UnitFlow::ProgLoc(p) => {
+                serializer.serialize_newtype_variant("UnitFlow", 3, "ProgLoc", p)
+            }
+            UnitFlow::Other { kind, fields } if serializer.is_human_readable() => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(kind, fields)?;
+                map.end()
+            }
+            UnitFlow::Other { kind, fields } => serializer.serialize_newtype_variant(
+                "UnitFlow",
+                4,
+                "Other",
+                &(kind, fields.to_string()),
+            ),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for UnitFlow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            return deserializer.deserialize_map(StepVisitor);
+        }
+        Ok(match IndexedStep::deserialize(deserializer)? {
+            IndexedStep::Type(t) => UnitFlow::Type(t),
+            IndexedStep::ConstructorArg(c) => UnitFlow::ConstructorArg(c),
+            IndexedStep::TypeVar(tv) => UnitFlow::TypeVar(tv),
+            IndexedStep::ProgLoc(p) => UnitFlow::ProgLoc(p),
+            IndexedStep::Other((kind, fields)) => UnitFlow::Other {
+                kind,
+                fields: serde_json::from_str(&fields).map_err(de::Error::custom)?,
+            },
+        })
+    }
+}
+
+struct StepVisitor;
+
+impl<'de> Visitor<'de> for StepVisitor {
+    type Value = UnitFlow;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an object with one key naming the kind of step")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<UnitFlow, A::Error> {
+        let kind: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let step = match kind.as_str() {
+            "Type" => UnitFlow::Type(map.next_value()?),
+            "ConstructorArg" => UnitFlow::ConstructorArg(map.next_value()?),
+            "TypeVar" => UnitFlow::TypeVar(map.next_value()?),
+            "ProgLoc" => UnitFlow::ProgLoc(map.next_value()?),
+            _ => {
+                // A near miss of a known kind is a typo, not a new kind
+                if let Some(known) = schema::misspelled_kind(&kind) {
+                    return Err(de::Error::custom(format!(
+                        "unknown step kind `{}`; did you mean `{}`?",
+                        kind, known
+                    )));
+                }
+                UnitFlow::Other {
+                    fields: map.next_value()?,
+                    kind,
+                }
+            }
+        };
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(step)
+    }
+}
+
+impl UnitFlow {
+    /// Kind of the step as written in data JSON: `Type`, `ConstructorArg`,
+    /// `TypeVar`, `ProgLoc`, or the kind of an [UnitFlow::Other] step
+    pub fn kind(&self) -> &str {
+        match self {
+            UnitFlow::Type(_) => "Type",
+            UnitFlow::ConstructorArg(_) => "ConstructorArg",
+            UnitFlow::TypeVar(_) => "TypeVar",
+            UnitFlow::ProgLoc(_) => "ProgLoc",
+            UnitFlow::Other { kind, .. } => kind,
+        }
+    }
+
+    /// The `desc` field of a step of another kind, when it is a string
+    pub(super) fn other_desc(fields: &Value) -> Option<&str> {
+        fields.get("desc").and_then(Value::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::{flow_id, Database, QueryOps};
+
+    const DATA: &str = r#"{
+        "file_path": "main.ml",
+        "dataflow": [
+            [{"Type": {"name": "int", "args": [], "desc": null}},
+             {"FunctionCall": {"callee": "print_int", "desc": "printed"}},
+             {"Effect": {"name": "io"}}],
+            [{"Type": {"name": "int", "args": [], "desc": null}}]
+        ]
+    }"#;
+
+    #[test]
+    fn test_other_steps() {
+        let db = Database::load_from_str(DATA).unwrap();
+        assert_eq!(db.data_flows[0][1].kind(), "FunctionCall");
+        assert_eq!(db.data_flows[0][1].desc(), Some("printed"));
+
+        let indices = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(indices("%FunctionCall"), vec![0]);
+        assert_eq!(indices("int, %Effect"), vec![0]);
+        assert_eq!(indices("\"printed\""), vec![0]);
+        assert_eq!(indices("%Allocation"), Vec::<usize>::new());
+        assert_eq!(indices("%Type, %FunctionCall"), vec![0]);
+        assert_eq!(indices("%Type"), vec![0, 1]);
+        assert!(QueryOps::parse_query("%Typ")
+            .unwrap_err()
+            .to_string()
+            .contains("did you mean `Type`?"));
+
+        let exported = Database::load_from_str(&db.to_json().unwrap()).unwrap();
+        assert_eq!(flow_id(&exported.data_flows[0]), flow_id(&db.data_flows[0]));
+        let json = serde_json::to_value(&exported.data_flows[0][1]).unwrap();
+        assert_eq!(json["FunctionCall"]["callee"], "print_int");
+
+        let bytes = bincode::serialize(&db.data_flows[0]).unwrap();
+        let indexed: Vec<crate::data::UnitFlow> = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(flow_id(&indexed), flow_id(&db.data_flows[0]));
+    }
+}
@@ -0,0 +1,695 @@
+use super::generics::split_top_level;
+use super::{schema, ArgIndex, LengthBounds, Name, QConstructorArg, QType, QueryOps};
+use crate::error::{self, SemanticSearchError};
+
+/// Characters ending a bare name, besides the `->` of a link, the `&&`
+/// joining queries and the end of the step
+const NAME_END: &[char] = &[',', ':', '<', '>', '"'];
+
+/// A parse error with the byte range of the query it concerns
+struct ParseError {
+    start: usize,
+    end: usize,
+    message: String,
+}
+
+type Parse<T> = Result<T, ParseError>;
+
+/// A cursor over a query, reading the words, quoted strings and symbols the
+/// parser is built from
+struct Lexer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.pos = self.input.len() - self.rest().trim_start().len();
+    }
+
+    /// Consume `symbol` if the query continues with it
+    fn eat(&mut self, symbol: &str) -> bool {
+        let found = self.rest().starts_with(symbol);
+        if found {
+            self.pos += symbol.len();
+        }
+        found
+    }
+
+    /// Consume the keyword `word` if it comes next as a whole word
+    fn keyword(&mut self, word: &str) -> bool {
+        let found = self
+            .rest()
+            .strip_prefix(word)
+            .is_some_and(|after| after.starts_with(char::is_whitespace));
+        if found {
+            self.pos += word.len();
+        }
+        found
+    }
+
+    /// Bare text up to one of `stops`, a `->`, a `&&` or the end of the step,
+    /// leaving out a trailing ` as name` binding and surrounding whitespace
+    fn bare(&mut self, stops: &[char]) -> (usize, &'a str) {
+        let rest = self.rest();
+        let mut len = rest.len();
+        for (idx, c) in rest.char_indices() {
+            if c == ','
+                || stops.contains(&c)
+                || (c == '{' && quantifier(&rest[idx..]).is_some())
+                || rest[idx..].starts_with("->")
+                || rest[idx..].starts_with("&&")
+            {
+                len = idx;
+                break;
+            }
+        }
+        let mut text = &rest[..len];
+        if let Some(head) = binding_head(text) {
+            text = head;
+        }
+        let start = self.pos + text.len() - text.trim_start().len();
+        self.pos += text.trim_end().len();
+        (start, text.trim())
+    }
+
+    /// A double-quoted string, unescaping `\"` and `\\`; other backslashes
+    /// are kept so regexes like `"\d+"` need no doubling
+    fn quoted(&mut self) -> Parse<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += idx + 1;
+                    return Ok(text);
+                }
+                '\\' => match chars.clone().next() {
+                    Some((_, escaped @ ('"' | '\\'))) => {
+                        chars.next();
+                        text.push(escaped);
+                    }
+                    _ => text.push(c),
+                },
+                c => text.push(c),
+            }
+        }
+        Err(ParseError {
+            start,
+            end: self.input.len(),
+            message: "Unclosed `\"`".to_string(),
+        })
+    }
+
+    /// A description or note: a quoted string, `~` and a quoted string, or
+    /// bare text that may hold `:`, `<` and `>`
+    fn text(&mut self) -> Parse<String> {
+        if self.rest().starts_with('"') {
+            return self.quoted();
+        }
+        if self.rest().starts_with("~\"") {
+            self.pos += 1;
+            return Ok(format!("~{}", self.quoted()?));
+        }
+        Ok(self.bare(&[]).1.to_string())
+    }
+
+    fn error<T>(&self, start: usize, end: usize, message: String) -> Parse<T> {
+        Err(ParseError {
+            start,
+            end: end.max(start),
+            message,
+        })
+    }
+
+    /// An error about the word at the cursor, or the end of the query
+    fn unexpected<T>(&self) -> Parse<T> {
+        let word = self
+            .rest()
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .next()
+            .unwrap_or_default();
+        match word {
+            "" if self.peek().is_none() => {
+                self.error(self.pos, self.pos, "Unexpected end of query".to_string())
+            }
+            "" => self.error(self.pos, self.pos + 1, "Expected a step".to_string()),
+            word => self.error(
+                self.pos,
+                self.pos + word.len(),
+                format!("Unexpected `{}`", word),
+            ),
+        }
+    }
+}
+
+/// Whether `name` can be bound with `as`
+pub(crate) fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// `text` without a trailing ` as name` binding, if it ends with one
+fn binding_head(text: &str) -> Option<&str> {
+    let (head, name) = text.rsplit_once(" as ")?;
+    is_identifier(name.trim()).then_some(head)
+}
+
+/// Parse a comma-separated query, skipping empty steps, or queries joined
+/// with `&&` into a single [QueryOps::QAll]
+pub(super) fn parse_query(input: &str) -> error::Result<Vec<QueryOps>> {
+    let mut lexer = Lexer { input, pos: 0 };
+    let query = queries(&mut lexer).and_then(|query| match QueryOps::check_repeats(&query) {
+        Ok(()) => Ok(query),
+        Err(message) => lexer.error(0, input.len(), message),
+    });
+    query.map_err(|e| SemanticSearchError::QueryParse {
+        query: input.to_string(),
+        position: e.start,
+        end: e.end,
+        message: e.message,
+    })
+}
+
+fn queries(lexer: &mut Lexer) -> Parse<Vec<QueryOps>> {
+    let mut queries = vec![steps(lexer)?];
+    while lexer.eat("&&") {
+        let start = lexer.pos - 2;
+        queries.push(steps(lexer)?);
+        if queries.iter().any(Vec::is_empty) {
+            return lexer.error(
+                start,
+                start + 2,
+                "Expected a query on both sides of `&&`".to_string(),
+            );
+        }
+    }
+    match queries.len() {
+        1 => Ok(queries.remove(0)),
+        _ => Ok(vec![QueryOps::QAll(queries)]),
+    }
+}
+
+/// The steps of one query, up to the end of the input or a `&&`
+fn steps(lexer: &mut Lexer) -> Parse<Vec<QueryOps>> {
+    let mut ops = vec![];
+    loop {
+        lexer.skip_whitespace();
+        if lexer.rest().starts_with("&&") {
+            return Ok(ops);
+        }
+        match lexer.peek() {
+            None => return Ok(ops),
+            Some(',') => lexer.pos += 1,
+            Some(_) => {
+                ops.push(step(lexer)?);
+                lexer.skip_whitespace();
+                if !lexer.eat(",") && lexer.peek().is_some() && !lexer.rest().starts_with("&&") {
+                    return lexer.unexpected();
+                }
+            }
+        }
+    }
+}
+
+/// A step, possibly bound to a name: `List as src`
+fn step(lexer: &mut Lexer) -> Parse<QueryOps> {
+    let start = lexer.pos;
+    let mut op = unary(lexer)?;
+    if let Some((min, max, len)) = quantifier(lexer.rest()) {
+        lexer.pos += len;
+        if !op.repeatable() {
+            return lexer.error(start, lexer.pos, format!("Cannot repeat `{}`", op));
+        }
+        if max.is_some_and(|max| max == 0 || max < min) {
+            let message = format!(
+                "Invalid repetition `{}`",
+                &lexer.input[lexer.pos - len..lexer.pos]
+            );
+            return lexer.error(lexer.pos - len, lexer.pos, message);
+        }
+        op = QueryOps::QRepeat {
+            op: Box::new(op),
+            min,
+            max,
+        };
+    }
+    let end = lexer.pos;
+    lexer.skip_whitespace();
+    if !lexer.keyword("as") {
+        return Ok(op);
+    }
+    lexer.skip_whitespace();
+    let (name_start, name) = lexer.bare(&[]);
+    if !is_identifier(name) {
+        let end = name_start + name.len();
+        return lexer.error(name_start, end, format!("Invalid name `{}`", name));
+    }
+    match op {
+        op @ (QueryOps::QStart
+        | QueryOps::QEnd
+        | QueryOps::QGap
+        | QueryOps::QNote(_)
+        | QueryOps::QLen(_)
+        | QueryOps::QNot(_)
+        | QueryOps::QAt(..)
+        | QueryOps::QRepeat { .. }
+        | QueryOps::QEdge { .. }) => lexer.error(start, end, format!("Cannot bind `{}`", op)),
+        op => Ok(QueryOps::QBind(name.to_string(), Box::new(op))),
+    }
+}
+
+/// A negated step, a step at a position or a link between steps: `!List`,
+/// `[0]List`, `@x -> List`
+fn unary(lexer: &mut Lexer) -> Parse<QueryOps> {
+    lexer.skip_whitespace();
+    let start = lexer.pos;
+    // `[0]` rather than a regex class like `[A-Z].*`
+    if let Some((idx, len)) = step_position(lexer.rest()) {
+        lexer.pos += len;
+        return match unary(lexer)? {
+            op @ (QueryOps::QStart
+            | QueryOps::QEnd
+            | QueryOps::QGap
+            | QueryOps::QNote(_)
+            | QueryOps::QLen(_)
+            | QueryOps::QAt(..)
+            | QueryOps::QEdge { .. }) => {
+                lexer.error(start, lexer.pos, format!("Cannot position `{}`", op))
+            }
+            op => Ok(QueryOps::QAt(idx, Box::new(op))),
+        };
+    }
+    if lexer.eat("!") {
+        return match unary(lexer)? {
+            op if op.is_structural() => {
+                lexer.error(start, lexer.pos, format!("Cannot negate `{}`", op))
+            }
+            op => Ok(QueryOps::QNot(Box::new(op))),
+        };
+    }
+    let from = atom(lexer)?;
+    let from_end = lexer.pos;
+    lexer.skip_whitespace();
+    let transitive = if lexer.eat("->>") {
+        true
+    } else if lexer.eat("->") {
+        false
+    } else {
+        lexer.pos = from_end;
+        return Ok(from);
+    };
+    lexer.skip_whitespace();
+    let to_start = lexer.pos;
+    let to = unary(lexer)?;
+    let side = |op: QueryOps, start: usize, end: usize| match op {
+        op @ (QueryOps::QStart
+        | QueryOps::QEnd
+        | QueryOps::QGap
+        | QueryOps::QNote(_)
+        | QueryOps::QLen(_)
+        | QueryOps::QBind(..)) => lexer.error(start, end, format!("Cannot link `{}`", op)),
+        op => Ok(Box::new(op)),
+    };
+    Ok(QueryOps::QEdge {
+        from: side(from, start, from_end)?,
+        to: side(to, to_start, lexer.pos)?,
+        transitive,
+    })
+}
+
+/// The bounds of a `{n}`, `{n,}` or `{n,m}` quantifier at the start of
+/// `rest`, with its length
+fn quantifier(rest: &str) -> Option<(usize, Option<usize>, usize)> {
+    let (inner, _) = rest.strip_prefix('{')?.split_once('}')?;
+    let count = |n: &str| n.trim().parse::<usize>().ok();
+    let (min, max) = match inner.split_once(',') {
+        None => (count(inner)?, count(inner)),
+        Some((min, max)) if max.trim().is_empty() => (count(min)?, None),
+        Some((min, max)) => (count(min)?, Some(count(max)?)),
+    };
+    Some((min, max, inner.len() + 2))
+}
+
+/// The index of a `[0]` position prefix at the start of `rest`, with its
+/// length
+fn step_position(rest: &str) -> Option<(usize, usize)> {
+    let (idx, _) = rest.strip_prefix('[')?.split_once(']')?;
+    Some((idx.trim().parse().ok()?, idx.len() + 2))
+}
+
+/// A single step: an anchor or gap, a type variable count, a constructor, a
+/// note, a description or a type
+fn atom(lexer: &mut Lexer) -> Parse<QueryOps> {
+    lexer.skip_whitespace();
+    let start = lexer.pos;
+    match lexer.peek() {
+        Some('"') => return Ok(QueryOps::QDesc(lexer.quoted()?)),
+        Some('~') if lexer.rest().starts_with("~\"") => {
+            lexer.pos += 1;
+            return Ok(QueryOps::QDesc(format!("~{}", lexer.quoted()?)));
+        }
+        Some('#') if lexer.rest().starts_with("#'") => {
+            lexer.pos += 1;
+            let name_len = lexer.rest()[1..]
+                .find(|c: char| !c.is_alphanumeric() && c != '_' && c != '\'')
+                .map_or(lexer.rest().len(), |len| len + 1);
+            let name = &lexer.rest()[..name_len];
+            lexer.pos += name_len;
+            let (bounds_start, bounds) = lexer.bare(&[]);
+            return QueryOps::parse_typevar_name(name, bounds).or_else(|message| {
+                lexer.error(bounds_start, bounds_start + bounds.len(), message)
+            });
+        }
+        Some('#') => {
+            lexer.pos += 1;
+            let (count_start, count) = lexer.bare(&[]);
+            return QueryOps::parse_typevar_count(count)
+                .or_else(|message| lexer.error(count_start, count_start + count.len(), message));
+        }
+        Some('@') => {
+            lexer.pos += 1;
+            return constructor(lexer);
+        }
+        Some('%') => {
+            lexer.pos += 1;
+            let (kind_start, kind) = lexer.bare(NAME_END);
+            if kind.is_empty() {
+                return lexer.unexpected();
+            }
+            let typo = kind
+                .chars()
+                .all(char::is_alphanumeric)
+                .then(|| schema::misspelled_kind(kind))
+                .flatten()
+                .filter(|known| !known.eq_ignore_ascii_case(kind));
+            if let Some(known) = typo {
+                let message = format!("unknown step kind `{}`; did you mean `{}`?", kind, known);
+                return lexer.error(kind_start, kind_start + kind.len(), message);
+            }
+            return Ok(QueryOps::QKind(kind.to_string()));
+        }
+        _ => {}
+    }
+    if let Some((locs, unit_len)) = QueryOps::length_unit(lexer.rest()) {
+        lexer.pos += unit_len;
+        let (bounds_start, bounds) = lexer.bare(&[]);
+        return LengthBounds::parse(locs, bounds)
+            .map(QueryOps::QLen)
+            .or_else(|message| lexer.error(bounds_start, bounds_start + bounds.len(), message));
+    }
+    if lexer.eat("note:") {
+        return Ok(QueryOps::QNote(lexer.text()?));
+    }
+    let (name_start, name) = lexer.bare(NAME_END);
+    match name {
+        "" => return lexer.unexpected(),
+        "^" => return Ok(QueryOps::QStart),
+        "$" => return Ok(QueryOps::QEnd),
+        ".." | "*" => return Ok(QueryOps::QGap),
+        _ => {}
+    }
+    let args = match lexer.peek() {
+        Some('<') => Some(type_args(lexer, start)?),
+        _ => None,
+    };
+    Ok(QueryOps::QType(QType {
+        name: Name::pattern(name),
+        args,
+        desc: description(lexer, name_start)?,
+    }))
+}
+
+/// The arguments of a type like `Map<_, string>`, from its `<`
+fn type_args(lexer: &mut Lexer, start: usize) -> Parse<Vec<String>> {
+    let open = lexer.pos;
+    let mut depth = 0;
+    let rest = lexer.rest();
+    let close = rest.char_indices().find_map(|(idx, c)| {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => {}
+        }
+        (depth == 0).then_some(idx)
+    });
+    let Some(close) = close else {
+        return lexer.error(open, open + 1, "Unclosed `<`".to_string());
+    };
+    lexer.pos += close + 1;
+    let args: Vec<String> = split_top_level(&rest[1..close])
+        .into_iter()
+        .map(|arg| arg.trim().to_string())
+        .collect();
+    if args.iter().any(String::is_empty) {
+        let message = format!(
+            "Invalid type arguments in `{}`",
+            &lexer.input[start..lexer.pos]
+        );
+        return lexer.error(open, lexer.pos, message);
+    }
+    Ok(args)
+}
+
+/// A constructor and its argument indices, after the `@`: `Cons.1:desc`,
+/// `Cons.{1,3}` or `Cons.1..3`
+fn constructor(lexer: &mut Lexer) -> Parse<QueryOps> {
+    let (name_start, name) = lexer.bare(&[',', ':', '.', '"']);
+    if name.is_empty() {
+        return lexer.unexpected();
+    }
+    let arg_index = if lexer.eat(".") {
+        Some(arg_index(lexer)?)
+    } else {
+        None
+    };
+    Ok(QueryOps::QConstructorArg(QConstructorArg {
+        name: Name::pattern(name),
+        arg_index,
+        desc: description(lexer, name_start)?,
+    }))
+}
+
+/// The argument indices of a constructor step, after the `.`
+fn arg_index(lexer: &mut Lexer) -> Parse<ArgIndex> {
+    let start = lexer.pos;
+    let number = |n: &str| n.trim().parse::<usize>().ok();
+    let parsed = match lexer.rest().strip_prefix('{') {
+        Some(rest) => match rest.split_once('}') {
+            Some((set, _)) => {
+                lexer.pos += set.len() + 2;
+                set.split(',')
+                    .map(number)
+                    .collect::<Option<Vec<_>>>()
+                    .map(ArgIndex::Set)
+            }
+            None => {
+                lexer.pos = lexer.input.len();
+                None
+            }
+        },
+        None => {
+            let (_, idx) = lexer.bare(&[',', ':', '"']);
+            match idx.split_once("..") {
+                Some((min, max)) => match (number(min), number(max)) {
+                    (Some(start), Some(end)) if start <= end => {
+                        Some(ArgIndex::Range { start, end })
+                    }
+                    _ => None,
+                },
+                None => number(idx).map(ArgIndex::One),
+            }
+        }
+    };
+    match parsed {
+        Some(idx) => Ok(idx),
+        None => {
+            let message = "Invalid constructor arg syntax".to_string();
+            lexer.error(start, lexer.pos, message)
+        }
+    }
+}
+
+/// The description after the `:` of a type or constructor, if any
+fn description(lexer: &mut Lexer, start: usize) -> Parse<Option<String>> {
+    if !lexer.eat(":") {
+        return Ok(None);
+    }
+    let desc = lexer.text()?;
+    if desc.is_empty() {
+        return lexer.error(
+            start,
+            lexer.pos,
+            "Expected a description after `:`".to_string(),
+        );
+    }
+    Ok(Some(desc))
+}
+
+/// `text` as a quoted string, escaping `"`, and `\` where it would
+/// otherwise escape the next character
+pub(super) fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' if matches!(chars.peek(), None | Some('"' | '\\')) => quoted.push_str("\\\\"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `text` as written after a `:` or `note:`, quoted when it would not read
+/// back the same bare
+pub(super) fn bare_or_quoted(text: &str) -> String {
+    let bare = !text.is_empty()
+        && text.trim() == text
+        && !text.contains([',', '"'])
+        && !text.contains("->")
+        && binding_head(text).is_none();
+    if bare {
+        text.to_string()
+    } else {
+        quote(text)
+    }
+}
+
+impl QueryOps {
+    /// The steps of a query in the command line syntax, split at the commas
+    /// between them outside quotes, type arguments and braces
+    pub fn split_steps(query: &str) -> Vec<&str> {
+        let mut steps = vec![];
+        let mut depth = 0usize;
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut start = 0;
+        for (idx, c) in query.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '<' | '{' if !quoted => depth += 1,
+                '>' | '}' if !quoted => depth = depth.saturating_sub(1),
+                ',' if !quoted && depth == 0 => {
+                    steps.push(&query[start..idx]);
+                    start = idx + 1;
+                }
+                _ => {}
+            }
+        }
+        steps.push(&query[start..]);
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoting_and_spans() {
+        let parse = |query: &str| QueryOps::parse_query(query).unwrap();
+        assert_eq!(
+            parse("\"foo, bar\", @Tuple.2:has:colon"),
+            vec![
+                QueryOps::QDesc("foo, bar".to_string()),
+                QueryOps::QConstructorArg(QConstructorArg {
+                    name: "Tuple".into(),
+                    arg_index: Some(ArgIndex::One(2)),
+                    desc: Some("has:colon".to_string()),
+                }),
+            ]
+        );
+        assert_eq!(
+            parse(r#"List:"a, \"b\" -> c" as l"#)[0].to_string(),
+            r#"List:"a, \"b\" -> c" as l"#
+        );
+        assert_eq!(parse(r#""\d+""#), vec![QueryOps::QDesc(r"\d+".to_string())]);
+        assert_eq!(
+            QueryOps::split_steps(r#"List, "a, b", Map<_, int>"#),
+            vec!["List", r#" "a, b""#, " Map<_, int>"]
+        );
+        assert_eq!(
+            QueryOps::split_steps("@Cons.{1,2}, int{1,3}"),
+            vec!["@Cons.{1,2}", " int{1,3}"]
+        );
+
+        for query in ["note:\"x, y\"", "@Cons.1:~\"near, by\"", "List:a:b, $"] {
+            let ops = parse(query);
+            let shown: Vec<String> = ops.iter().map(ToString::to_string).collect();
+            assert_eq!(parse(&shown.join(", ")), ops);
+        }
+
+        let span = |query: &str| match QueryOps::parse_query(query) {
+            Err(SemanticSearchError::QueryParse { position, end, .. }) => (position, end),
+            _ => panic!("expected a parse error for {}", query),
+        };
+        assert_eq!(span("List, \"open"), (6, 11));
+        assert_eq!(span("@Cons.x"), (6, 7));
+        assert_eq!(span("List Map<int"), (8, 9));
+        assert_eq!(span("\"d\" as 1x"), (7, 9));
+        assert_eq!(span("List:"), (0, 5));
+        assert_eq!(span("@Cons.{1,"), (6, 9));
+        assert_eq!(span("@Cons.2..a, List"), (6, 10));
+
+        assert_eq!(
+            parse("[0]bool, [A-Z].*"),
+            vec![
+                QueryOps::QAt(0, Box::new(parse("bool").remove(0))),
+                parse("[A-Z].*").remove(0),
+            ]
+        );
+        assert_eq!(span("[1]..").0, 0);
+    }
+
+    #[test]
+    fn test_combined_constraints() {
+        let parse = |query: &str| QueryOps::parse_query(query).unwrap();
+        assert_eq!(
+            parse("@Tuple.1:\"if-then-else condition\", @Cons.{1,2}:head"),
+            vec![
+                QueryOps::QConstructorArg(QConstructorArg {
+                    name: "Tuple".into(),
+                    arg_index: Some(ArgIndex::One(1)),
+                    desc: Some("if-then-else condition".to_string()),
+                }),
+                QueryOps::QConstructorArg(QConstructorArg {
+                    name: "Cons".into(),
+                    arg_index: Some(ArgIndex::Set(vec![1, 2])),
+                    desc: Some("head".to_string()),
+                }),
+            ]
+        );
+        assert_eq!(
+            parse("Map<_, List<int>>:\"user input\""),
+            vec![QueryOps::QType(QType {
+                name: "Map".into(),
+                args: Some(vec!["_".to_string(), "List<int>".to_string()]),
+                desc: Some("user input".to_string()),
+            })]
+        );
+        for query in ["@Tuple.1..2:~\"cond.*\"", "List<string>:a:b as l"] {
+            let ops = parse(query);
+            let shown: Vec<String> = ops.iter().map(ToString::to_string).collect();
+            assert_eq!(parse(&shown.join(", ")), ops);
+        }
+
+        let db = crate::tutorial::load_database();
+        assert_eq!(db.count_matches(&parse("List<string>:\"user input\"")), 1);
+        assert_eq!(db.count_matches(&parse("List<int>:\"user input\"")), 0);
+    }
+}
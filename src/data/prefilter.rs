@@ -0,0 +1,106 @@
+use super::{DataFlow, Database, QueryOps};
+
+impl Database {
+    /// Indices of the only flows that can match `query`, looked up in the
+    /// inverted maps for its first step that some step of every matching flow
+    /// must match, or `None` when no step narrows the search this way
+    pub fn candidates(&self, query: &[QueryOps]) -> Option<Vec<usize>> {
+        query.iter().find_map(|op| self.step_candidates(op))
+    }
+
+    fn step_candidates(&self, op: &QueryOps) -> Option<Vec<usize>> {
+        // Regexes, prefixes and case-insensitive names could match any name
+        let literal = |name: &str| !self.ignore_case && regex::escape(name) == name;
+        match op {
+            QueryOps::QType(q) if literal(&q.name) => Some(self.flows_with_subtypes(&q.name)),
+            QueryOps::QConstructorArg(q) if literal(&q.name) => Some(
+                self.constructor_flows
+                    .get(q.name.as_str())
+                    .map_or(vec![], Vec::clone),
+            ),
+            QueryOps::QTypeVar(_) | QueryOps::QTypeVarRange { .. } => {
+                let mut indices: Vec<usize> = self
+                    .type_var_flows
+                    .iter()
+                    .filter(|(_, flows)| match op {
+                        QueryOps::QTypeVar(count) => flows.len() == *count,
+                        QueryOps::QTypeVarRange { min, max } => {
+                            flows.len() >= *min && max.is_none_or(|max| flows.len() <= max)
+                        }
+                        _ => unreachable!(),
+                    })
+                    .flat_map(|(_, flows)| flows.iter().copied())
+                    .collect();
+                indices.sort_unstable();
+                indices.dedup();
+                Some(indices)
+            }
+            QueryOps::QTypeVarName { name, min, max } if literal(name) => {
+                let flows = self.type_var_flows.get(name.as_str())?;
+                let within = flows.len() >= *min && max.is_none_or(|max| flows.len() <= max);
+                Some(if within { flows.clone() } else { vec![] })
+            }
+            QueryOps::QKind(kind) if literal(kind) => {
+                let map = match kind.as_str() {
+                    "Type" => &self.type_flows,
+                    "ConstructorArg" => &self.constructor_flows,
+                    "TypeVar" => &self.type_var_flows,
+                    _ => return None,
+                };
+                let mut indices: Vec<usize> = map.values().flatten().copied().collect();
+                indices.sort_unstable();
+                indices.dedup();
+                Some(indices)
+            }
+            QueryOps::QBind(_, inner) | QueryOps::QAt(_, inner) => self.step_candidates(inner),
+            QueryOps::QRepeat { op, min, .. } if *min > 0 => self.step_candidates(op),
+            QueryOps::QEdge { from, .. } => self.step_candidates(from),
+            QueryOps::QAll(queries) => queries.iter().find_map(|query| self.candidates(query)),
+            _ => None,
+        }
+    }
+
+    /// The flows that can match `query` with their indices, in database order
+    pub(super) fn candidate_flows<'a>(
+        &'a self,
+        query: &[QueryOps],
+    ) -> Box<dyn Iterator<Item = (usize, &'a DataFlow)> + 'a> {
+        match self.candidates(query) {
+            Some(indices) => Box::new(indices.into_iter().map(|idx| (idx, &self.data_flows[idx]))),
+            None => Box::new(self.data_flows.iter().enumerate()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::QueryOps;
+
+    #[test]
+    fn test_candidates() {
+        let db = crate::tutorial::load_database();
+        let candidates = |query: &str| db.candidates(&QueryOps::parse_query(query).unwrap());
+        assert_eq!(candidates("List"), Some(vec![2, 3]));
+        assert_eq!(candidates("!int, @Some"), Some(vec![3]));
+        assert_eq!(candidates("@Nothing"), Some(vec![]));
+        assert_eq!(candidates("#2"), Some(vec![1, 2, 3, 4]));
+        assert_eq!(candidates("Li.*"), None);
+        assert_eq!(candidates("\"user input\""), None);
+        assert_eq!(candidates("%ConstructorArg"), Some(vec![0, 1, 2, 3]));
+        assert_eq!(candidates("%ProgLoc"), None);
+
+        for query in [
+            "List, #2",
+            "bool, ..",
+            "@Cons.1 -> #2",
+            "Li.*, 'a",
+            "%Type, %ConstructorArg, %ProgLoc",
+        ] {
+            let query = QueryOps::parse_query(query).unwrap();
+            let all: Vec<usize> = (0..db.data_flows.len())
+                .filter(|&idx| db.match_flow(&db.data_flows[idx], &query))
+                .collect();
+            assert_eq!(db.matching_indices(&query), all);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use super::{fnv1a, Database, Str, UnitFlow};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Field of the flows that can be redacted from an exported database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RedactField {
+    /// Descriptions of every step
+    Descs,
+    /// Source lines of program locations
+    Lines,
+    /// File paths of the database and its program locations
+    Files,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactMode {
+    /// Remove the field's contents
+    Strip,
+    /// Replace the field with its hash, so equal values stay equal
+    Hash,
+}
+
+/// Fields to redact and how
+#[derive(Debug, Clone, Default)]
+pub struct Redaction {
+    fields: BTreeMap<RedactField, RedactMode>,
+}
+
+impl Redaction {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Redact each of the comma-separated `fields` (`descs`, `lines`,
+    /// `files`) with `mode`
+    pub fn add(&mut self, fields: &str, mode: RedactMode) -> Result<(), String> {
+        for field in fields.split(',').map(str::trim) {
+            let field = match field {
+                "descs" => RedactField::Descs,
+                "lines" => RedactField::Lines,
+                "files" => RedactField::Files,
+                _ => return Err(format!("Unknown field `{}` to redact", field)),
+            };
+            self.fields.insert(field, mode);
+        }
+        Ok(())
+    }
+
+    fn apply(&self, field: RedactField, value: &str) -> Option<String> {
+        match self.fields.get(&field) {
+            None => Some(value.to_string()),
+            Some(RedactMode::Strip) => None,
+            Some(RedactMode::Hash) => Some(format!("{:016x}", fnv1a(value.as_bytes()))),
+        }
+    }
+
+    fn apply_opt(&self, field: RedactField, value: &Option<Str>) -> Option<Str> {
+        value
+            .as_deref()
+            .and_then(|v| self.apply(field, v))
+            .map(Str::from)
+    }
+}
+
+impl Database {
+    /// A copy of the database with the fields of `redaction` stripped or
+    /// hashed. Flows keep their steps, positions and names.
+    pub fn redacted(&self, redaction: &Redaction) -> Database {
+        let flows = self
+            .data_flows
+            .iter()
+            .map(|flow| {
+                flow.iter()
+                    .map(|uf| {
+                        let mut uf = uf.clone();
+                        match &mut uf {
+                            UnitFlow::Type(t) => {
+                                t.desc = redaction.apply_opt(RedactField::Descs, &t.desc)
+                            }
+                            UnitFlow::ConstructorArg(c) => {
+                                c.desc = redaction.apply_opt(RedactField::Descs, &c.desc)
+                            }
+                            UnitFlow::TypeVar(tv) => {
+                                tv.desc = redaction.apply_opt(RedactField::Descs, &tv.desc)
+                            }
+                            UnitFlow::ProgLoc(p) => {
+                                p.desc = redaction.apply_opt(RedactField::Descs, &p.desc);
+                                p.line = redaction
+                                    .apply(RedactField::Lines, &p.line)
+                                    .unwrap_or_default()
+                                    .into();
+                                p.file = redaction.apply_opt(RedactField::Files, &p.file);
+                            }
+                            UnitFlow::Other { fields, .. } => {
+                                if let Some(desc) = UnitFlow::other_desc(fields) {
+                                    let desc = redaction.apply(RedactField::Descs, desc);
+                                    fields["desc"] = desc.map_or(Value::Null, Value::from);
+                                }
+                            }
+                        }
+                        uf
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut db = self.with_flows(flows);
+        db.file_path = redaction
+            .apply(RedactField::Files, &self.file_path)
+            .unwrap_or_default();
+        db
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let db = crate::tutorial::load_database();
+        let mut redaction = Redaction::default();
+        redaction.add("descs,lines", RedactMode::Strip).unwrap();
+        redaction.add("files", RedactMode::Hash).unwrap();
+        assert!(redaction.add("names", RedactMode::Strip).is_err());
+
+        let redacted = db.redacted(&redaction);
+        assert_eq!(redacted.data_flows.len(), db.data_flows.len());
+        assert_eq!(redacted.file_path.len(), 16);
+        assert!(redacted.descriptions().is_empty());
+        assert!(redacted.data_flows.iter().flatten().all(|uf| match uf {
+            UnitFlow::ProgLoc(p) => p.line.is_empty(),
+            _ => true,
+        }));
+        for (a, b) in db.data_flows.iter().zip(&redacted.data_flows) {
+            let shapes = |flow: &[UnitFlow]| flow.iter().map(UnitFlow::shape).collect::<Vec<_>>();
+            assert_eq!(shapes(a), shapes(b));
+        }
+    }
+}
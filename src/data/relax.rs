@@ -0,0 +1,155 @@
+use super::{Database, QueryOps};
+use std::fmt;
+
+/// A query loosened in one step, and how many flows it matches
+#[derive(Debug, PartialEq, Eq)]
+pub struct Relaxation {
+    /// The step as written in the original query
+    pub from: String,
+    /// The loosened step, `None` if it was dropped
+    pub to: Option<String>,
+    pub query: Vec<QueryOps>,
+    pub matches: usize,
+}
+
+impl fmt::Display for Relaxation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.to {
+            Some(to) => write!(f, "relaxing `{}` to `{}`", self.from, to)?,
+            None => write!(f, "dropping `{}`", self.from)?,
+        }
+        let steps: Vec<String> = self.query.iter().map(QueryOps::to_string).collect();
+        write!(f, " matches {} flows: `{}`", self.matches, steps.join(", "))
+    }
+}
+
+impl QueryOps {
+    /// Looser variants of the step: description, note, negated and anchor
+    /// steps are dropped, positions, repetitions and argument indices removed
+    /// and type variable counts widened by
+    /// one either way. Bound steps keep their binding.
+    fn relaxed(&self) -> Vec<Option<QueryOps>> {
+        match self {
+            QueryOps::QBind(name, op) => op
+                .relaxed()
+                .into_iter()
+                .map(|relaxed| relaxed.map(|op| QueryOps::QBind(name.clone(), Box::new(op))))
+                .collect(),
+            QueryOps::QAt(_, op) | QueryOps::QRepeat { op, .. } => vec![Some((**op).clone())],
+            QueryOps::QAll(queries) if queries.len() > 1 => (0..queries.len())
+                .map(|dropped| {
+                    let mut queries = queries.clone();
+                    queries.remove(dropped);
+                    Some(QueryOps::QAll(queries))
+                })
+                .collect(),
+            QueryOps::QDesc(_)
+            | QueryOps::QKind(_)
+            | QueryOps::QNote(_)
+            | QueryOps::QLen(_)
+            | QueryOps::QNot(_)
+            | QueryOps::QStart
+            | QueryOps::QEnd => vec![None],
+            QueryOps::QConstructorArg(q) if q.arg_index.is_some() => {
+                let mut q = q.clone();
+                q.arg_index = None;
+                vec![Some(QueryOps::QConstructorArg(q))]
+            }
+            QueryOps::QType(q) if q.args.is_some() => {
+                let mut q = q.clone();
+                q.args = None;
+                vec![Some(QueryOps::QType(q))]
+            }
+            QueryOps::QTypeVar(count) => [count.checked_sub(1), count.checked_add(1)]
+                .into_iter()
+                .flatten()
+                .filter(|count| *count > 0)
+                .map(|count| Some(QueryOps::QTypeVar(count)))
+                .collect(),
+            QueryOps::QTypeVarRange { min, max } => {
+                let mut widened = vec![];
+                if *min > 0 {
+                    widened.push(Some(QueryOps::QTypeVarRange {
+                        min: min - 1,
+                        max: *max,
+                    }));
+                }
+                if let Some(max) = max {
+                    widened.push(Some(QueryOps::QTypeVarRange {
+                        min: *min,
+                        max: max.checked_add(1),
+                    }));
+                }
+                widened
+            }
+            QueryOps::QTypeVarName { name, min, max } if *min > 0 || max.is_some() => {
+                vec![Some(QueryOps::QTypeVarName {
+                    name: name.clone(),
+                    min: 0,
+                    max: None,
+                })]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl Database {
+    /// Every query obtained by relaxing a single step of `query`, with the
+    /// number of flows it matches
+    pub fn relaxations(&self, query: &[QueryOps]) -> Vec<Relaxation> {
+        query
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, op)| {
+                op.relaxed().into_iter().map(move |relaxed| {
+                    let mut relaxed_query = query.to_vec();
+                    match &relaxed {
+                        Some(step) => relaxed_query[idx] = step.clone(),
+                        None => {
+                            relaxed_query.remove(idx);
+                        }
+                    }
+                    Relaxation {
+                        from: op.to_string(),
+                        to: relaxed.as_ref().map(QueryOps::to_string),
+                        matches: self.matching_indices(&relaxed_query).len(),
+                        query: relaxed_query,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relaxations() {
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("bool, @Tuple.3, \"nowhere\", #3").unwrap();
+        let relaxations: Vec<(Option<String>, usize)> = db
+            .relaxations(&query)
+            .into_iter()
+            .map(|r| (r.to, r.matches))
+            .collect();
+        assert_eq!(
+            relaxations,
+            vec![
+                (Some("@Tuple".to_string()), 0),
+                (None, 0),
+                (Some("#2".to_string()), 0),
+                (Some("#4".to_string()), 0),
+            ]
+        );
+
+        let query = QueryOps::parse_query("bool, @Tuple.3").unwrap();
+        let relaxation = &db.relaxations(&query)[0];
+        assert_eq!(
+            relaxation.to_string(),
+            "relaxing `@Tuple.3` to `@Tuple` matches 1 flows: `bool, @Tuple`"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use super::{Database, QueryOps, UnitFlow};
+
+impl QueryOps {
+    /// Whether the step can be repeated with a `{n,m}` quantifier: steps
+    /// matching a single flow step, and their negations
+    pub(crate) fn repeatable(&self) -> bool {
+        match self {
+            QueryOps::QType(_)
+            | QueryOps::QConstructorArg(_)
+            | QueryOps::QTypeVar(_)
+            | QueryOps::QTypeVarRange { .. }
+            | QueryOps::QTypeVarName { .. }
+            | QueryOps::QDesc(_)
+            | QueryOps::QKind(_) => true,
+            QueryOps::QNot(inner) => inner.repeatable(),
+            _ => false,
+        }
+    }
+
+    /// Positions the step contributes to [Database::match_flow_positions]:
+    /// `min` for a repeated step, of which a query without anchors or gaps
+    /// matches no more, and one for other steps
+    pub(super) fn step_count(&self) -> usize {
+        match self {
+            QueryOps::QRepeat { min, .. } => *min,
+            _ => 1,
+        }
+    }
+
+    /// Whether the step is repeated a varying number of times, so a query
+    /// with anchors or gaps may match it at more than `min` steps
+    fn varies(&self) -> bool {
+        matches!(self, QueryOps::QRepeat { min, max, .. } if *max != Some(*min))
+    }
+
+    /// Reject queries whose captures could not be told from the positions
+    /// they matched at: those binding names with anchors or gaps and a step
+    /// repeated a varying number of times
+    pub(crate) fn check_repeats(query: &[QueryOps]) -> Result<(), String> {
+        if let [QueryOps::QAll(queries)] = query {
+            return queries
+                .iter()
+                .try_for_each(|query| Self::check_repeats(query));
+        }
+        let positional = query.iter().any(QueryOps::is_structural);
+        let binds = query.iter().any(|op| matches!(op, QueryOps::QBind(..)));
+        match query.iter().find(|op| op.varies()) {
+            Some(op) if positional && binds => Err(format!(
+                "Cannot bind names in a query with anchors or gaps and `{}`, which may match a varying number of steps",
+                op
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Database {
+    /// Match `op` between `min` and `max` more times from `idx`, then the
+    /// rest of the query. With `gaps`, repetitions may be apart and the
+    /// first `min` are enough, as later ones could be skipped over anyway.
+    /// Otherwise repetitions are adjacent and as many are taken as still
+    /// let the rest of the query match.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn match_repeat(
+        &self,
+        flow: &[UnitFlow],
+        idx: usize,
+        op: &QueryOps,
+        (min, max): (usize, Option<usize>),
+        rest: &[QueryOps],
+        gaps: bool,
+        positions: &mut Vec<usize>,
+    ) -> bool {
+        if min == 0 && (gaps || max == Some(0)) {
+            return self.match_at(flow, idx, rest, gaps, positions);
+        }
+        let fewer = (min.saturating_sub(1), max.map(|max| max - 1));
+        let starts = if gaps {
+            idx..flow.len()
+        } else {
+            idx..flow.len().min(idx + 1)
+        };
+        for next in starts {
+            if !self.match_unit_flow(&flow[next], op) {
+                continue;
+            }
+            positions.push(next);
+            if self.match_repeat(flow, next + 1, op, fewer, rest, gaps, positions) {
+                return true;
+            }
+            positions.pop();
+        }
+        min == 0 && self.match_at(flow, idx, rest, gaps, positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_steps() {
+        let data = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [
+                [{"Type": {"name": "Option", "args": [], "desc": null}},
+                 {"Type": {"name": "Option", "args": [], "desc": null}},
+                 {"Type": {"name": "Option", "args": [], "desc": null}},
+                 {"Type": {"name": "int", "args": [], "desc": null}}],
+                [{"Type": {"name": "Option", "args": [], "desc": null}},
+                 {"Type": {"name": "int", "args": [], "desc": null}},
+                 {"Type": {"name": "Option", "args": [], "desc": null}}]
+            ]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let matching = |query: &str| db.matching_indices(&QueryOps::parse_query(query).unwrap());
+
+        assert_eq!(matching("Option{2}"), vec![0, 1]);
+        assert_eq!(matching("Option{3,}"), vec![0]);
+        assert_eq!(matching("Option{2}, int"), vec![0]);
+        assert_eq!(matching("^, Option{2}"), vec![0]);
+        assert_eq!(matching("^, Option{1,2}, int"), vec![1]);
+        assert_eq!(matching("^, Option{1,}, int, $"), vec![0]);
+        assert_eq!(matching("^, !int{3}"), vec![0]);
+        assert_eq!(matching("Option{0,1}, int, Option{0,1}, $"), vec![0, 1]);
+
+        let query = QueryOps::parse_query("^, Option{2,}, int").unwrap();
+        assert_eq!(
+            db.match_flow_positions(&db.data_flows[0], &query),
+            Some(vec![0, 1, 2, 3])
+        );
+        let query = QueryOps::parse_query("Option{2} as o").unwrap_err();
+        assert!(query.to_string().contains("Cannot bind"));
+        let bound = QueryOps::parse_query("Option{2}, int as i").unwrap();
+        let captured = db.captures(&db.data_flows[0], &bound).unwrap();
+        assert!(matches!(captured["i"], UnitFlow::Type(t) if t.name == "int"));
+        assert!(QueryOps::parse_query("^, Option{1,}, int as i").is_err());
+    }
+}
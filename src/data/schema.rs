@@ -0,0 +1,168 @@
+use super::suggest::char_distance;
+use super::{limits, version, DataJson, Database, Flows, UnitFlow};
+use crate::error::{self, SemanticSearchError};
+use serde_json::Value;
+
+/// The kinds of step a flow is made of, as written in data JSON, besides
+/// the steps of other kinds read as [UnitFlow::Other]
+const STEP_KINDS: &[&str] = &["Type", "ConstructorArg", "TypeVar", "ProgLoc"];
+
+/// The first malformed flow of the data JSON `data`, read from `path` if
+/// any, with the step and field at fault. `None` when `data` is not JSON at
+/// all, or its flows are fine and the problem lies elsewhere.
+pub(super) fn first_problem(data: &str, path: Option<&str>) -> Option<SemanticSearchError> {
+    let json: Value = serde_json::from_str(data).ok()?;
+    json.get("dataflow")?
+        .as_array()?
+        .iter()
+        .enumerate()
+        .find_map(|(flow, value)| {
+            let (step, message) = parse_flow(value.clone()).err()?;
+            Some(SemanticSearchError::Schema {
+                path: path.map(str::to_string),
+                flow,
+                step,
+                message,
+            })
+        })
+}
+
+/// The flow `value`, or the index of its malformed step, if the flow is not
+/// a list of steps at all, and what is wrong
+fn parse_flow(value: Value) -> Result<Vec<UnitFlow>, (Option<usize>, String)> {
+    let Value::Array(steps) = value else {
+        return Err((None, format!("expected a list of steps, found `{}`", value)));
+    };
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(idx, step)| parse_step(step).map_err(|message| (Some(idx), message)))
+        .collect()
+}
+
+/// The known step kind `kind` is a near miss of, if any, taken for a typo
+/// rather than a step of another kind
+pub(super) fn misspelled_kind(kind: &str) -> Option<&'static str> {
+    STEP_KINDS
+        .iter()
+        .copied()
+        .filter(|known| *known != kind)
+        .min_by_key(|known| char_distance(kind, known))
+        .filter(|known| char_distance(kind, known) <= 2)
+}
+
+fn parse_step(value: Value) -> Result<UnitFlow, String> {
+    let kinds = STEP_KINDS
+        .iter()
+        .map(|kind| format!("`{}`", kind))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let kind = match value
+        .as_object()
+        .map(|step| step.keys().collect::<Vec<_>>())
+    {
+        Some(keys) if keys.len() == 1 => keys[0].clone(),
+        _ => {
+            return Err(format!(
+                "expected an object with one key of {}, found `{}`",
+                kinds, value
+            ))
+        }
+    };
+    if let Some(known) = misspelled_kind(&kind) {
+        return Err(format!(
+            "unknown step kind `{}`; did you mean `{}`?",
+            kind, known
+        ));
+    }
+    serde_json::from_value(value).map_err(|e| format!("in `{}`: {}", kind, e))
+}
+
+/// A warning that the flows described by `dropped` were skipped, if any
+pub(super) fn dropped_warning(path: &str, dropped: &[String]) -> Option<String> {
+    let first = dropped.first()?;
+    Some(format!(
+        "{}: dropped {} malformed flow{}, the first at {}",
+        path,
+        dropped.len(),
+        if dropped.len() == 1 { "" } else { "s" },
+        first
+    ))
+}
+
+impl Database {
+    /// Parse a database read from the file at `path`, if any, dropping the
+    /// malformed flows and their edges
+    pub(super) fn parse_lenient(data: &str, path: Option<&str>) -> error::Result<Self> {
+        let parsed: DataJson<Vec<Value>> = serde_json::from_str(data).map_err(|e| {
+            limits::exceeded(path).unwrap_or_else(|| SemanticSearchError::json(path, e))
+        })?;
+        version::check(parsed.version, path)?;
+        let mut edges = parsed.edges.into_iter();
+        let mut flows = Flows::new();
+        let mut kept_edges = vec![];
+        let mut dropped = vec![];
+        for (idx, value) in parsed.dataflow.into_iter().enumerate() {
+            let flow_edges = edges.next().unwrap_or_default();
+            match parse_flow(value) {
+                Ok(flow) => {
+                    flows.push(flow);
+                    kept_edges.push(flow_edges);
+                }
+                Err((step, message)) => dropped.push(match step {
+                    Some(step) => format!("flow {}, step {}: {}", idx, step, message),
+                    None => format!("flow {}: {}", idx, message),
+                }),
+            }
+        }
+        let mut db = Self::from_data_json(DataJson {
+            version: parsed.version,
+            file_path: parsed.file_path,
+            language: parsed.language,
+            subtypes: parsed.subtypes,
+            dataflow: flows,
+            edges: kept_edges,
+        });
+        db.warnings
+            .extend(dropped_warning(path.unwrap_or("input"), &dropped));
+        Ok(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_problems() {
+        let data = r#"{
+            "file_path": "main.ml",
+            "dataflow": [
+                [{"Type": {"name": "int", "args": [], "desc": null}}],
+                [{"Type": {"name": "int", "args": [], "desc": null}},
+                 {"ProgLok": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0}}],
+                [{"ProgLoc": {"line": "x", "desc": null, "depth": 0}}]
+            ]
+        }"#;
+        let Err(error) = Database::load_from_str(data) else {
+            panic!("expected a schema error");
+        };
+        assert!(matches!(
+            error,
+            SemanticSearchError::Schema {
+                flow: 1,
+                step: Some(1),
+                ..
+            }
+        ));
+        assert!(error.to_string().contains("did you mean `ProgLoc`?"));
+
+        let db = Database::parse_lenient(data, Some("main.json")).unwrap();
+        assert_eq!(db.data_flows.len(), 1);
+        assert_eq!(db.warnings().len(), 1);
+        assert!(db.warnings()[0].starts_with("main.json: dropped 2 malformed flows"));
+
+        let missing = parse_step(serde_json::from_str(r#"{"ProgLoc": {"line": "x"}}"#).unwrap());
+        assert!(missing.unwrap_err().contains("char_range"));
+    }
+}
@@ -0,0 +1,165 @@
+use super::{Database, UnitFlow};
+use std::collections::BTreeSet;
+
+/// How closely a flow resembles another, each component between 0 and 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Similarity {
+    /// Index of the flow in [Database::data_flows]
+    pub flow: usize,
+    /// Weighted sum of the components below
+    pub score: f64,
+    /// Longest common subsequence of step shapes, relative to flow length
+    pub sequence: f64,
+    /// Overlap of the type, constructor and type variable names
+    pub names: f64,
+    /// Closeness of the nearest pair of program locations in the same file
+    pub location: f64,
+}
+
+const SEQUENCE_WEIGHT: f64 = 0.5;
+const NAMES_WEIGHT: f64 = 0.3;
+const LOCATION_WEIGHT: f64 = 0.2;
+
+/// Lines apart at which two locations count as half as close as on the same
+/// line
+const LOCATION_HALF_DISTANCE: f64 = 20.0;
+
+impl Database {
+    /// The `n` other flows most similar to the flow at `idx`, most similar
+    /// first. Flows sharing nothing with it are left out.
+    pub fn similar_flows(&self, idx: usize, n: usize) -> Vec<Similarity> {
+        let flow = &self.data_flows[idx];
+        let mut similar: Vec<Similarity> = self
+            .data_flows
+            .iter()
+            .enumerate()
+            .filter(|(other_idx, _)| *other_idx != idx)
+            .map(|(other_idx, other)| {
+                let sequence = sequence_similarity(flow, other);
+                let names = jaccard(&flow_names(flow), &flow_names(other));
+                let location = self.location_similarity(flow, other);
+                Similarity {
+                    flow: other_idx,
+                    score: SEQUENCE_WEIGHT * sequence
+                        + NAMES_WEIGHT * names
+                        + LOCATION_WEIGHT * location,
+                    sequence,
+                    names,
+                    location,
+                }
+            })
+            .filter(|s| s.score > 0.0)
+            .collect();
+        similar.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.flow.cmp(&b.flow)));
+        similar.truncate(n);
+        similar
+    }
+
+    fn location_similarity(&self, a: &[UnitFlow], b: &[UnitFlow]) -> f64 {
+        let locations = |flow: &[UnitFlow]| -> Vec<(String, usize)> {
+            flow.iter()
+                .filter_map(|uf| match uf {
+                    UnitFlow::ProgLoc(p) => Some((
+                        p.file.as_deref().unwrap_or(&self.file_path).to_string(),
+                        p.line_number?,
+                    )),
+                    _ => None,
+                })
+                .collect()
+        };
+        let b_locations = locations(b);
+        locations(a)
+            .iter()
+            .flat_map(|(file, line)| {
+                b_locations
+                    .iter()
+                    .filter(move |(other_file, _)| other_file == file)
+                    .map(move |(_, other_line)| line.abs_diff(*other_line))
+            })
+            .min()
+            .map_or(0.0, |distance| {
+                LOCATION_HALF_DISTANCE / (LOCATION_HALF_DISTANCE + distance as f64)
+            })
+    }
+}
+
+fn sequence_similarity(a: &[UnitFlow], b: &[UnitFlow]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 0.0;
+    }
+    let a: Vec<String> = a.iter().map(UnitFlow::shape).collect();
+    let b: Vec<String> = b.iter().map(UnitFlow::shape).collect();
+    let mut row = vec![0; b.len() + 1];
+    for x in &a {
+        let mut diagonal = 0;
+        for (j, y) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if x == y {
+                diagonal + 1
+            } else {
+                row[j + 1].max(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    2.0 * row[b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+fn flow_names(flow: &[UnitFlow]) -> BTreeSet<&str> {
+    flow.iter()
+        .filter_map(|uf| match uf {
+            UnitFlow::Type(t) => Some(t.name.as_str()),
+            UnitFlow::ConstructorArg(c) => Some(c.name.as_str()),
+            UnitFlow::TypeVar(tv) => Some(tv.name.as_str()),
+            UnitFlow::ProgLoc(_) | UnitFlow::Other { .. } => None,
+        })
+        .collect()
+}
+
+fn jaccard(a: &BTreeSet<&str>, b: &BTreeSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{flow_id, ProgLoc};
+
+    #[test]
+    fn test_similar_flows() {
+        let db = crate::tutorial::load_database();
+        let similar = db.similar_flows(2, 10);
+
+        assert!(similar.iter().all(|s| s.flow != 2));
+        assert!(similar.windows(2).all(|w| w[0].score >= w[1].score));
+        // The other `List` flow shares the most with it
+        assert_eq!(similar[0].flow, 3);
+        assert_eq!(db.similar_flows(2, 1).len(), 1);
+
+        assert_eq!(db.find_flow("#2"), Some(2));
+        assert_eq!(db.find_flow("#6"), None);
+        assert_eq!(db.find_flow(&flow_id(&db.data_flows[2])), Some(2));
+
+        let flow = &db.data_flows[2];
+        assert_eq!(sequence_similarity(flow, flow), 1.0);
+
+        let at = |line_number| {
+            vec![UnitFlow::ProgLoc(ProgLoc {
+                line: "".into(),
+                char_range: (0, 0),
+                desc: None,
+                depth: 0,
+                file: None,
+                line_number: Some(line_number),
+                column: None,
+                commit: None,
+            })]
+        };
+        assert_eq!(db.location_similarity(&at(10), &at(30)), 0.5);
+        assert_eq!(db.location_similarity(flow, flow), 0.0);
+    }
+}
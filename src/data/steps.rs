@@ -0,0 +1,68 @@
+use super::{parser, UnitFlow};
+use std::fmt;
+
+/// Joins the rendered steps of a flow
+pub const STEP_ARROW: &str = " → ";
+
+/// Renders a step as `--show-steps` prints it: types and constructor
+/// arguments as a query would name them, program locations by their line,
+/// each followed by its description
+impl fmt::Display for UnitFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnitFlow::Type(t) if t.args.is_empty() => write!(f, "{}", t.name)?,
+            UnitFlow::Type(t) => write!(f, "{}<{}>", t.name, t.args.join(", "))?,
+            UnitFlow::ConstructorArg(c) => write!(f, "@{}.{}", c.name, c.arg_index)?,
+            UnitFlow::TypeVar(tv) => write!(f, "TypeVar {}", tv.name)?,
+            UnitFlow::ProgLoc(loc) => match (&loc.file, loc.line_number) {
+                (Some(file), Some(line)) => write!(f, "[{}:{}]", file, line)?,
+                (None, Some(line)) => write!(f, "[line {}]", line)?,
+                _ => write!(
+                    f,
+                    "[`{}`]",
+                    loc.span_text().unwrap_or_else(|| loc.line.trim())
+                )?,
+            },
+            UnitFlow::Other { kind, .. } => write!(f, "%{}", kind)?,
+        }
+        match self.desc() {
+            Some(desc) => write!(f, ":{}", parser::bare_or_quoted(desc)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl UnitFlow {
+    /// Render every step of a flow in order as an arrow chain, such as
+    /// `List → TypeVar 'a → @Cons.1 → [line 42]`
+    pub fn render_flow(flow: &[UnitFlow]) -> String {
+        let steps: Vec<String> = flow.iter().map(UnitFlow::to_string).collect();
+        steps.join(STEP_ARROW)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{ConstructorArg, ProgLoc, Type, TypeVar};
+
+    #[test]
+    fn test_render_flow() {
+        let flow = vec![
+            UnitFlow::Type(Type::new("List", vec!["'a".to_string()])),
+            UnitFlow::TypeVar(TypeVar::new("'a")),
+            UnitFlow::ConstructorArg(ConstructorArg::new("Cons", 1).with_desc("head")),
+            UnitFlow::ProgLoc(ProgLoc::new("let xs = [x]", (5, 7)).with_line_number(42)),
+            UnitFlow::ProgLoc(
+                ProgLoc::new("f xs", (3, 5))
+                    .with_file("lib/b.ml")
+                    .with_line_number(7),
+            ),
+            UnitFlow::ProgLoc(ProgLoc::new("g xs", (3, 5))),
+        ];
+        assert_eq!(
+            UnitFlow::render_flow(&flow),
+            "List<'a> → TypeVar 'a → @Cons.1:head → [line 42] → [lib/b.ml:7] → [`xs`]"
+        );
+    }
+}
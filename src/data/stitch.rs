@@ -0,0 +1,285 @@
+use super::{Database, UnitFlow};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A flow reached while stitching flows together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    /// Index of the flow in [Database::data_flows]
+    pub flow: usize,
+    /// Type variable or constructor argument shared with the previous flow
+    pub via: Option<String>,
+}
+
+/// Flows joined end to end, materialized into one with [StitchedFlow::flow]
+#[derive(Debug)]
+pub struct StitchedFlow {
+    /// The joined flows in order, each with the connector it shares with the
+    /// flow before it
+    pub provenance: Vec<Link>,
+}
+
+impl StitchedFlow {
+    /// The steps of the joined flows of `db`, one after the other
+    pub fn flow(&self, db: &Database) -> Vec<UnitFlow> {
+        self.provenance
+            .iter()
+            .flat_map(|link| db.data_flows[link.flow].iter().cloned())
+            .collect()
+    }
+}
+
+/// The chains of [Database::stitch_flows], found one at a time as the
+/// iterator advances, since their number can grow exponentially with the
+/// chain length
+pub struct Stitches<'a> {
+    db: &'a Database,
+    /// Flows by their first connector
+    starts: BTreeMap<String, Vec<usize>>,
+    max_chain: usize,
+    /// Flow the next chain starts from, once the current one is exhausted
+    next_start: usize,
+    chain: Vec<Link>,
+    /// For each flow of `chain` being extended, the last connector of the
+    /// chain up to it and how many of the flows it starts were tried
+    extending: Vec<(Option<String>, usize)>,
+}
+
+impl Iterator for Stitches<'_> {
+    type Item = StitchedFlow;
+
+    fn next(&mut self) -> Option<StitchedFlow> {
+        loop {
+            if self.chain.len() > self.extending.len() {
+                let last = self.chain.last().map(|link| link.flow).unwrap_or_default();
+                let terminal = (self.chain.len() < self.max_chain)
+                    .then(|| UnitFlow::last_connector(&self.db.data_flows[last]))
+                    .flatten();
+                self.extending.push((terminal, 0));
+            }
+            let Some((terminal, tried)) = self.extending.last_mut() else {
+                if self.next_start == self.db.data_flows.len() {
+                    return None;
+                }
+                self.chain.push(Link {
+                    flow: self.next_start,
+                    via: None,
+                });
+                self.next_start += 1;
+                continue;
+            };
+            let candidates = terminal
+                .as_ref()
+                .and_then(|terminal| self.starts.get(terminal))
+                .map_or(&[][..], Vec::as_slice);
+            let chain = &self.chain;
+            let next = candidates[*tried..]
+                .iter()
+                .position(|next| chain.iter().all(|link| link.flow != *next));
+            match next {
+                Some(offset) => {
+                    let next = candidates[*tried + offset];
+                    *tried += offset + 1;
+                    let via = terminal.clone();
+                    self.chain.push(Link { flow: next, via });
+                    return Some(StitchedFlow {
+                        provenance: self.chain.clone(),
+                    });
+                }
+                None => {
+                    self.extending.pop();
+                    self.chain.pop();
+                }
+            }
+        }
+    }
+}
+
+impl UnitFlow {
+    /// Name through which this step can be shared with other flows
+    fn connector(&self) -> Option<String> {
+        match self {
+            UnitFlow::TypeVar(tv) => Some(tv.name.to_string()),
+            UnitFlow::ConstructorArg(c) => Some(format!("@{}.{}", c.name, c.arg_index)),
+            _ => None,
+        }
+    }
+
+    fn first_connector(flow: &[UnitFlow]) -> Option<String> {
+        flow.iter().find_map(UnitFlow::connector)
+    }
+
+    fn last_connector(flow: &[UnitFlow]) -> Option<String> {
+        flow.iter().rev().find_map(UnitFlow::connector)
+    }
+
+    fn has_type(flow: &[UnitFlow], name: &str) -> bool {
+        flow.iter()
+            .any(|uf| matches!(uf, UnitFlow::Type(t) if t.name == name))
+    }
+}
+
+impl Database {
+    /// Map each type variable and constructor argument to the flows sharing it
+    fn connector_index(&self) -> BTreeMap<String, BTreeSet<usize>> {
+        let mut index: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+        for (idx, flow) in self.data_flows.iter().enumerate() {
+            for connector in flow.iter().filter_map(UnitFlow::connector) {
+                index.entry(connector).or_default().insert(idx);
+            }
+        }
+        index
+    }
+
+    /// Shortest chain of flows leading from a flow containing type `from` to
+    /// a flow containing type `to`, where consecutive flows share a type
+    /// variable or constructor argument.
+    pub fn shortest_type_path(&self, from: &str, to: &str) -> Option<Vec<Link>> {
+        let index = self.connector_index();
+        let mut parents: BTreeMap<usize, Option<(usize, String)>> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        for (idx, flow) in self.data_flows.iter().enumerate() {
+            if UnitFlow::has_type(flow, from) {
+                parents.insert(idx, None);
+                queue.push_back(idx);
+            }
+        }
+
+        while let Some(idx) = queue.pop_front() {
+            if UnitFlow::has_type(&self.data_flows[idx], to) {
+                let mut path = vec![];
+                let mut current = Some(idx);
+                while let Some(flow) = current {
+                    let parent = parents[&flow].clone();
+                    current = parent.as_ref().map(|(prev, _)| *prev);
+                    path.push(Link {
+                        flow,
+                        via: parent.map(|(_, via)| via),
+                    });
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for connector in self.data_flows[idx].iter().filter_map(UnitFlow::connector) {
+                for next in &index[&connector] {
+                    if !parents.contains_key(next) {
+                        parents.insert(*next, Some((idx, connector.clone())));
+                        queue.push_back(*next);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Join flows end to end wherever one flow's last type variable or
+    /// constructor argument is another flow's first, producing every chain of
+    /// two to `max_chain` distinct flows, each followed by its extensions.
+    pub fn stitch_flows(&self, max_chain: usize) -> Stitches<'_> {
+        let mut starts: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (idx, flow) in self.data_flows.iter().enumerate() {
+            if let Some(connector) = UnitFlow::first_connector(flow) {
+                starts.entry(connector).or_default().push(idx);
+            }
+        }
+        Stitches {
+            db: self,
+            starts,
+            max_chain,
+            next_start: 0,
+            chain: vec![],
+            extending: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Type, TypeVar};
+
+    fn ty(name: &str) -> UnitFlow {
+        UnitFlow::Type(Type {
+            name: name.into(),
+            args: vec![],
+            desc: None,
+        })
+    }
+
+    fn var(name: &str) -> UnitFlow {
+        UnitFlow::TypeVar(TypeVar {
+            name: name.into(),
+            desc: None,
+        })
+    }
+
+    #[test]
+    fn test_shortest_type_path() {
+        let db = Database::new(
+            "test.ml".to_string(),
+            vec![
+                vec![ty("string"), var("'a")],
+                vec![var("'a"), ty("int"), var("'b")],
+                vec![var("'b"), ty("bool")],
+                vec![ty("string"), var("'c")],
+                vec![var("'c"), ty("bool")],
+            ],
+        );
+
+        assert_eq!(
+            db.shortest_type_path("string", "bool").unwrap(),
+            vec![
+                Link { flow: 3, via: None },
+                Link {
+                    flow: 4,
+                    via: Some("'c".to_string())
+                },
+            ]
+        );
+        assert_eq!(db.shortest_type_path("int", "int").unwrap().len(), 1);
+        assert!(db.shortest_type_path("bool", "unit").is_none());
+    }
+
+    #[test]
+    fn test_stitch_flows() {
+        let db = Database::new(
+            "test.ml".to_string(),
+            vec![
+                vec![ty("string"), var("'a")],
+                vec![var("'a"), ty("int"), var("'b")],
+                vec![var("'b"), ty("bool")],
+            ],
+        );
+
+        let stitched: Vec<_> = db.stitch_flows(3).collect();
+        let chains: Vec<Vec<usize>> = stitched
+            .iter()
+            .map(|s| s.provenance.iter().map(|link| link.flow).collect())
+            .collect();
+        assert_eq!(chains, vec![vec![0, 1], vec![0, 1, 2], vec![1, 2]]);
+        assert_eq!(stitched[1].flow(&db).len(), 7);
+        assert_eq!(stitched[1].provenance[2].via.as_deref(), Some("'b"));
+
+        assert_eq!(db.stitch_flows(2).count(), 2);
+    }
+
+    #[test]
+    fn test_stitch_flows_lazily() {
+        // Every flow continues every other, so chains of up to 12 of them
+        // number over a billion
+        let flows = (0..12)
+            .map(|_| vec![var("'a"), ty("int"), var("'a")])
+            .collect();
+        let db = Database::new("test.ml".to_string(), flows);
+
+        let first: Vec<Vec<usize>> = db
+            .stitch_flows(12)
+            .take(3)
+            .map(|s| s.provenance.iter().map(|link| link.flow).collect())
+            .collect();
+        assert_eq!(first, vec![vec![0, 1], vec![0, 1, 2], vec![0, 1, 2, 3]]);
+        assert_eq!(db.stitch_flows(2).count(), 12 * 11);
+    }
+}
@@ -0,0 +1,287 @@
+use super::compress::uncompressed_name;
+use super::limits;
+use super::schema;
+use super::version::{self, SCHEMA_VERSION};
+use super::{Database, Flows, Language, Subtypes, UnitFlow};
+use crate::error::{self, SemanticSearchError};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+
+/// First line of a line-delimited database, followed by one flow per line
+#[derive(Serialize, Deserialize)]
+struct Header {
+    #[serde(default = "version::first_schema")]
+    version: u32,
+    file_path: String,
+    #[serde(default)]
+    language: Language,
+    #[serde(
+        default,
+        alias = "type_aliases",
+        skip_serializing_if = "Subtypes::is_empty"
+    )]
+    subtypes: Subtypes,
+}
+
+/// The flows of a line-delimited (NDJSON) database, read one line at a time
+/// so each can be matched and dropped without holding the whole database in
+/// memory
+pub struct FlowStream<R> {
+    reader: R,
+    path: String,
+    /// 1-based number of the last line read
+    line: usize,
+    /// Number of flows read so far
+    flows: usize,
+    header: Header,
+    /// The first flow of a database without a header line, read while
+    /// looking for one
+    first_flow: Option<String>,
+    /// Whether the last line read had no line break, as a line still being
+    /// appended may not
+    partial: bool,
+    /// Number of the partial last line, when it could not be parsed and was
+    /// skipped
+    truncated: Option<usize>,
+}
+
+/// Whether `data` holds a line-delimited database: its first line is a flow,
+/// or a header object without the `dataflow` of a JSON database
+pub(super) fn looks_like_ndjson(data: &str) -> bool {
+    let Some(first) = data.lines().map(str::trim).find(|line| !line.is_empty()) else {
+        return false;
+    };
+    first.starts_with('[')
+        || serde_json::from_str::<serde_json::Value>(first)
+            .is_ok_and(|header| header.is_object() && header.get("dataflow").is_none())
+}
+
+impl Database {
+    /// Whether `path` names a line-delimited database, read with
+    /// [Database::stream_flows]. Databases named otherwise are told apart
+    /// from JSON ones by their contents with [looks_like_ndjson].
+    pub fn is_ndjson(path: &str) -> bool {
+        let path = uncompressed_name(path);
+        path.ends_with(".ndjson") || path.ends_with(".jsonl")
+    }
+
+    /// Open the line-delimited database at `path`: a header line holding
+    /// `file_path` and `language`, then a JSON array of steps per flow
+    pub fn stream_flows(path: &str) -> error::Result<FlowStream<BufReader<Box<dyn Read>>>> {
+        FlowStream::new(BufReader::new(Self::open_data(path)?), path)
+    }
+
+    /// Serialize in the line-delimited format read by [Database::stream_flows]
+    pub fn to_ndjson(&self) -> Result<String, String> {
+        let header = Header {
+            version: SCHEMA_VERSION,
+            file_path: self.file_path.clone(),
+            language: self.language,
+            subtypes: self.subtypes.clone(),
+        };
+        let mut out = serde_json::to_string(&header).map_err(|e| e.to_string())? + "\n";
+        for flow in &self.data_flows {
+            out += &serde_json::to_string(flow).map_err(|e| e.to_string())?;
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+impl<R: BufRead> FlowStream<R> {
+    /// Read the header of a line-delimited database from `reader`; `path`
+    /// names it in errors, and is the file path of databases starting
+    /// straight with a flow
+    pub fn new(reader: R, path: &str) -> error::Result<Self> {
+        let mut stream = FlowStream {
+            reader,
+            path: path.to_string(),
+            line: 0,
+            flows: 0,
+            header: Header {
+                version: version::first_schema(),
+                file_path: path.to_string(),
+                language: Language::default(),
+                subtypes: Subtypes::new(),
+            },
+            first_flow: None,
+            partial: false,
+            truncated: None,
+        };
+        stream.header = match stream.next_line() {
+            Some(Ok(line)) if line.trim_start().starts_with('[') => {
+                stream.first_flow = Some(line);
+                return Ok(stream);
+            }
+            Some(line) => stream.parse(&line?)?,
+            None => {
+                return Err(SemanticSearchError::JsonParse {
+                    path: Some(stream.path),
+                    line: 0,
+                    column: 0,
+                    message: "missing header line".to_string(),
+                })
+            }
+        };
+        version::check(stream.header.version, Some(path))?;
+        Ok(stream)
+    }
+
+    /// A database without flows, with the file path and language of the
+    /// stream, to match streamed flows against with
+    /// [Database::match_flow]. Type variable counts need every flow, so
+    /// `#N` steps match nothing.
+    pub fn database(&self) -> Database {
+        self.database_of(Flows::new())
+    }
+
+    fn database_of(&self, flows: Flows) -> Database {
+        let mut db = Database::from_flows(self.header.file_path.clone(), flows);
+        db.language = self.header.language;
+        db.subtypes = self.header.subtypes.clone();
+        db
+    }
+
+    /// Read every remaining flow into a database, warning about a partial
+    /// last line that was skipped. `lenient` skips malformed lines too,
+    /// warning how many were dropped, rather than failing on the first.
+    pub fn collect_database(mut self, lenient: bool) -> error::Result<Database> {
+        let mut flows = Flows::new();
+        let mut dropped = vec![];
+        for flow in self.by_ref() {
+            match flow {
+                Ok(flow) => flows.push(flow),
+                Err(SemanticSearchError::JsonParse { line, message, .. }) if lenient => {
+                    dropped.push(format!("line {}: {}", line, message))
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let mut db = self.database_of(flows);
+        db.warnings
+            .extend(schema::dropped_warning(&self.path, &dropped));
+        if let Some(line) = self.truncated {
+            db.warnings.push(format!(
+                "{}: skipped the incomplete flow on line {}",
+                self.path, line
+            ));
+        }
+        Ok(db)
+    }
+
+    /// The next non-blank line
+    fn next_line(&mut self) -> Option<error::Result<String>> {
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line += 1;
+                    self.partial = !line.ends_with('\n');
+                    if !line.trim().is_empty() {
+                        return Some(Ok(line.trim_end_matches(['\n', '\r']).to_string()));
+                    }
+                }
+                Err(e) => return Some(Err(SemanticSearchError::io(&self.path, e))),
+            }
+        }
+    }
+
+    fn parse<T: for<'de> Deserialize<'de>>(&self, line: &str) -> error::Result<T> {
+        serde_json::from_str(line).map_err(|e| SemanticSearchError::JsonParse {
+            path: Some(self.path.clone()),
+            line: self.line,
+            column: e.column(),
+            message: e.to_string(),
+        })
+    }
+}
+
+impl<R: BufRead> Iterator for FlowStream<R> {
+    type Item = error::Result<Vec<UnitFlow>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = match self.first_flow.take() {
+            Some(line) => Ok(line),
+            None => self.next_line()?,
+        };
+        match line.and_then(|line| self.parse::<Vec<UnitFlow>>(&line)) {
+            // A flow still being appended
+            Err(SemanticSearchError::JsonParse { line, .. }) if self.partial => {
+                self.truncated = Some(line);
+                None
+            }
+            Ok(flow) => {
+                let checked = limits::check_flow(self.flows, flow.len());
+                self.flows += 1;
+                Some(
+                    checked
+                        .map(|()| flow)
+                        .map_err(|message| SemanticSearchError::Limit {
+                            path: Some(self.path.clone()),
+                            message,
+                        }),
+                )
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{flow_id, QueryOps};
+
+    #[test]
+    fn test_flow_stream() {
+        let tutorial = crate::tutorial::load_database();
+        let ndjson = tutorial.to_ndjson().unwrap();
+        let stream = FlowStream::new(ndjson.as_bytes(), "tutorial.ndjson").unwrap();
+        let db = stream.database();
+        let query = QueryOps::parse_query("List, @Cons").unwrap();
+        let matched: Vec<_> = stream
+            .map(Result::unwrap)
+            .filter(|flow| db.match_flow(flow, &query))
+            .collect();
+        assert_eq!(matched.len(), 1);
+        assert_eq!(flow_id(&matched[0]), flow_id(&tutorial.data_flows[2]));
+
+        let collected = FlowStream::new(ndjson.as_bytes(), "tutorial.ndjson")
+            .unwrap()
+            .collect_database(false)
+            .unwrap();
+        assert_eq!(collected.data_flows.len(), tutorial.data_flows.len());
+        assert_eq!(collected.flows_with_type("List"), &[2, 3]);
+
+        let broken = ndjson.replacen("\n[", "\n[}", 1);
+        let errors: Vec<_> = FlowStream::new(broken.as_bytes(), "broken.ndjson")
+            .unwrap()
+            .filter_map(Result::err)
+            .collect();
+        assert!(matches!(
+            errors.as_slice(),
+            [SemanticSearchError::JsonParse { line: 2, .. }]
+        ));
+
+        // Flows alone, the last one still being written
+        let flows: String = ndjson
+            .lines()
+            .skip(1)
+            .map(|line| format!("{}\n", line))
+            .collect();
+        let appending = format!("{}[{{\"Type\": {{\"na", flows);
+        assert!(looks_like_ndjson(&appending));
+        assert!(!looks_like_ndjson(crate::tutorial::TUTORIAL_JSON));
+        let db = FlowStream::new(appending.as_bytes(), "flows.log")
+            .unwrap()
+            .collect_database(false)
+            .unwrap();
+        assert_eq!(db.data_flows.len(), tutorial.data_flows.len());
+        assert_eq!(db.file_path, "flows.log");
+        assert_eq!(
+            db.warnings(),
+            ["flows.log: skipped the incomplete flow on line 7"]
+        );
+    }
+}
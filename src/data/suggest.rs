@@ -0,0 +1,142 @@
+use super::{desc_matches, pattern_matches, Database, QueryOps};
+use std::fmt;
+
+/// A queried name that appears nowhere in the database, with the nearest
+/// names that do
+#[derive(Debug, PartialEq, Eq)]
+pub struct Suggestion {
+    /// What the name refers to: `type`, `constructor` or `description`
+    pub kind: &'static str,
+    pub name: String,
+    /// Existing names closest by edit distance, nearest first
+    pub candidates: Vec<String>,
+}
+
+impl fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "no {} `{}`", self.kind, self.name)?;
+        if !self.candidates.is_empty() {
+            let candidates: Vec<String> =
+                self.candidates.iter().map(|c| format!("`{}`", c)).collect();
+            write!(f, "; did you mean {}?", candidates.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Levenshtein distance between two sequences: the fewest items inserted,
+/// removed or replaced to turn `a` into `b`
+pub fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(x != y);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// [edit_distance] between two strings, counted in characters
+pub(super) fn char_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    edit_distance(&a, &b)
+}
+
+/// Up to three of the `known` names within a third of the length of `name`
+/// from it, nearest first
+fn nearest<'a>(name: &str, known: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let max_distance = name.chars().count() / 3 + 1;
+    let mut close: Vec<(usize, &str)> = known
+        .map(|k| (char_distance(name, k), k))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(3)
+        .map(|(_, k)| k.to_string())
+        .collect()
+}
+
+impl Database {
+    /// Suggestions for the names in `query` that appear nowhere in the
+    /// database, or patterns that match none, which make the query match
+    /// nothing
+    pub fn suggestions(&self, query: &[QueryOps]) -> Vec<Suggestion> {
+        if let [QueryOps::QAll(queries)] = query {
+            return queries
+                .iter()
+                .flat_map(|query| self.suggestions(query))
+                .collect();
+        }
+        query
+            .iter()
+            .filter_map(|op| match op.unbound() {
+                QueryOps::QType(q) => (!self
+                    .type_names()
+                    .any(|t| pattern_matches(&q.name, t, self.ignore_case)))
+                .then(|| Suggestion {
+                    kind: "type",
+                    name: q.name.to_string(),
+                    candidates: nearest(&q.name, self.type_names()),
+                }),
+                QueryOps::QConstructorArg(q) => {
+                    let constructors = self.constructor_names();
+                    let matched = constructors
+                        .iter()
+                        .any(|c| pattern_matches(&q.name, c, self.ignore_case));
+                    (!matched).then(|| Suggestion {
+                        kind: "constructor",
+                        name: q.name.to_string(),
+                        candidates: nearest(&q.name, constructors.into_iter()),
+                    })
+                }
+                QueryOps::QDesc(d) => {
+                    let descriptions = self.descriptions();
+                    let matched = descriptions
+                        .iter()
+                        .any(|desc| desc_matches(d, desc, self.ignore_case));
+                    (!matched).then(|| Suggestion {
+                        kind: "description",
+                        name: d.clone(),
+                        candidates: nearest(d, descriptions.into_iter()),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(char_distance("Lst", "List"), 1);
+        assert_eq!(char_distance("kitten", "sitting"), 3);
+        assert_eq!(char_distance("日本", "日本語"), 1);
+
+        let db = crate::tutorial::load_database();
+        let query = QueryOps::parse_query("Lst, bool, @Tupel, \"if-then condition\"").unwrap();
+        let suggestions: Vec<String> = db
+            .suggestions(&query)
+            .iter()
+            .map(Suggestion::to_string)
+            .collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                "no type `Lst`; did you mean `List`, `int`?",
+                "no constructor `Tupel`; did you mean `Tuple`?",
+                "no description `if-then condition`; did you mean `if-then-else condition`?",
+            ]
+        );
+    }
+}
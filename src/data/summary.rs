@@ -0,0 +1,151 @@
+use super::{Database, MemoryUsage, UnitFlow};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// An overview of a database, for getting to know it before querying
+#[derive(Debug, Serialize)]
+pub struct DatabaseStats {
+    pub flows: usize,
+    /// Number of flows of each length in steps
+    pub flow_lengths: BTreeMap<usize, usize>,
+    /// Types by number of steps through them, most frequent first
+    pub top_types: Vec<(String, usize)>,
+    /// Constructors by number of steps through their arguments, most
+    /// frequent first
+    pub top_constructors: Vec<(String, usize)>,
+    /// Number of type variables flowing through each number of flows
+    pub typevar_in_degrees: BTreeMap<usize, usize>,
+    pub coverage: LocCoverage,
+    pub memory: MemoryUsage,
+}
+
+/// How much of the program the locations of a database cover
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct LocCoverage {
+    /// Program locations across all flows
+    pub locations: usize,
+    /// Distinct source lines those locations are on, when numbered
+    pub lines: usize,
+    /// Distinct source files those locations are in
+    pub files: usize,
+    /// Flows without any program location
+    pub flows_without_locations: usize,
+    /// Locations with out-of-range or inverted ranges, which are not shown
+    pub invalid_ranges: usize,
+}
+
+impl Database {
+    /// Number of flows of each length in steps
+    pub fn flow_length_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut lengths = BTreeMap::new();
+        for flow in &self.data_flows {
+            *lengths.entry(flow.len()).or_default() += 1;
+        }
+        lengths
+    }
+
+    /// The `top` type names with the most steps through them
+    pub fn type_frequencies(&self, top: usize) -> Vec<(String, usize)> {
+        self.step_frequencies(top, |uf| match uf {
+            UnitFlow::Type(t) => Some(t.name()),
+            _ => None,
+        })
+    }
+
+    /// The `top` constructors with the most steps through their arguments
+    pub fn constructor_frequencies(&self, top: usize) -> Vec<(String, usize)> {
+        self.step_frequencies(top, |uf| match uf {
+            UnitFlow::ConstructorArg(c) => Some(c.name()),
+            _ => None,
+        })
+    }
+
+    fn step_frequencies(
+        &self,
+        top: usize,
+        name: impl Fn(&UnitFlow) -> Option<&str>,
+    ) -> Vec<(String, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for uf in self.data_flows.iter().flatten() {
+            if let Some(name) = name(uf) {
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(top);
+        counts
+    }
+
+    /// Number of type variables with each in-degree, the number of flows
+    /// through them that `#N` queries match
+    pub fn typevar_in_degree_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut degrees = BTreeMap::new();
+        for (_, degree) in self.typevar_degrees() {
+            *degrees.entry(degree).or_default() += 1;
+        }
+        degrees
+    }
+
+    pub fn loc_coverage(&self) -> LocCoverage {
+        let mut coverage = LocCoverage::default();
+        let mut lines = BTreeSet::new();
+        let mut files = BTreeSet::new();
+        for flow in &self.data_flows {
+            let mut any = false;
+            for uf in flow {
+                if let UnitFlow::ProgLoc(loc) = uf {
+                    any = true;
+                    coverage.locations += 1;
+                    if !loc.has_valid_range() {
+                        coverage.invalid_ranges += 1;
+                    }
+                    let file = loc.file.as_deref().unwrap_or(&self.file_path);
+                    files.insert(file);
+                    if let Some(line) = loc.line_number {
+                        lines.insert((file, line));
+                    }
+                }
+            }
+            if !any {
+                coverage.flows_without_locations += 1;
+            }
+        }
+        coverage.lines = lines.len();
+        coverage.files = files.len();
+        coverage
+    }
+
+    /// Summary of the database, listing the `top` most frequent types and
+    /// constructors
+    pub fn stats(&self, top: usize) -> DatabaseStats {
+        DatabaseStats {
+            flows: self.data_flows.len(),
+            flow_lengths: self.flow_length_histogram(),
+            top_types: self.type_frequencies(top),
+            top_constructors: self.constructor_frequencies(top),
+            typevar_in_degrees: self.typevar_in_degree_histogram(),
+            coverage: self.loc_coverage(),
+            memory: self.memory_usage(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_database_stats() {
+        let stats = crate::tutorial::load_database().stats(2);
+        assert_eq!(stats.flows, 6);
+        assert_eq!(stats.flow_lengths.values().sum::<usize>(), 6);
+        assert_eq!(stats.top_types.len(), 2);
+        assert_eq!(stats.top_types[0], ("List".to_string(), 2));
+        assert_eq!(stats.typevar_in_degrees, [(2, 2)].into());
+        assert_eq!(stats.coverage.files, 1);
+        assert_eq!(stats.coverage.flows_without_locations, 0);
+        assert_eq!(stats.coverage.invalid_ranges, 0);
+    }
+}
@@ -0,0 +1,95 @@
+use super::{ConstructorArg, Database, ProgLoc, Type, TypeVar, UnitFlow};
+
+const TYPES: &[&str] = &[
+    "int", "bool", "string", "List", "Option", "Map", "Tree", "Result",
+];
+const CONSTRUCTORS: &[&str] = &["Some", "Cons", "Tuple", "Ok", "Node"];
+
+/// A xorshift generator, so the same synthetic database comes out every run
+struct Rng(u64);
+
+impl Rng {
+    /// A number below `n`
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+impl Database {
+    /// A database of `n_flows` flows of `flow_len` steps, for benchmarks.
+    /// Each flow starts with a type and alternates program locations with
+    /// types, constructor arguments and type variables drawn from a small
+    /// vocabulary, so queries over it match a realistic share of flows.
+    pub fn generate_synthetic(n_flows: usize, flow_len: usize) -> Database {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let type_vars = (n_flows / 4).max(1);
+        let step = |rng: &mut Rng, flow: usize, idx: usize| match (idx, idx % 2) {
+            (0, _) => UnitFlow::Type(Type {
+                name: TYPES[rng.below(TYPES.len())].into(),
+                args: vec![],
+                desc: None,
+            }),
+            (_, 1) => {
+                let name = format!("v{}", idx);
+                UnitFlow::ProgLoc(ProgLoc {
+                    line: format!("let {} = f{} x", name, flow).into(),
+                    char_range: (5, 5 + name.len()),
+                    desc: None,
+                    depth: 0,
+                    file: Some(format!("src/m{}.ml", flow % 16).into()),
+                    line_number: Some(idx + 1),
+                    column: None,
+                    commit: None,
+                })
+            }
+            _ => match rng.below(3) {
+                0 => UnitFlow::ConstructorArg(ConstructorArg {
+                    name: CONSTRUCTORS[rng.below(CONSTRUCTORS.len())].into(),
+                    arg_index: 1 + rng.below(2),
+                    desc: None,
+                }),
+                1 => UnitFlow::TypeVar(TypeVar {
+                    name: format!("'t{}", rng.below(type_vars)).into(),
+                    desc: None,
+                }),
+                _ => UnitFlow::Type(Type {
+                    name: TYPES[rng.below(TYPES.len())].into(),
+                    args: vec![],
+                    desc: Some("generated".into()),
+                }),
+            },
+        };
+        let flows = (0..n_flows)
+            .map(|flow| {
+                (0..flow_len.max(1))
+                    .map(|idx| step(&mut rng, flow, idx))
+                    .collect()
+            })
+            .collect();
+        Database::new("synthetic.ml".to_string(), flows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::flow_id;
+
+    #[test]
+    fn test_generate_synthetic() {
+        let db = Database::generate_synthetic(40, 6);
+        assert_eq!(db.data_flows.len(), 40);
+        assert!(db.data_flows.iter().all(|flow| flow.len() == 6));
+        assert_eq!(
+            flow_id(&db.data_flows[7]),
+            flow_id(&Database::generate_synthetic(40, 6).data_flows[7])
+        );
+
+        let query = crate::data::QueryOps::parse_query("List").unwrap();
+        let matched = db.matching_indices(&query).len();
+        assert!(matched > 0 && matched < 40);
+    }
+}
@@ -0,0 +1,94 @@
+use super::{Database, UnitFlow};
+use std::collections::BTreeMap;
+
+/// A flow found by [Database::text_search]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    /// Index of the flow in [Database::data_flows]
+    pub flow: usize,
+    /// TF-IDF relevance of the flow's descriptions to the words searched
+    pub score: f64,
+}
+
+/// The words of `text`, lowercased: its runs of letters and digits
+pub(super) fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+impl UnitFlow {
+    /// Words of the step's description
+    pub(super) fn desc_words(&self) -> Vec<String> {
+        self.desc().map_or(vec![], |desc| words(desc).collect())
+    }
+}
+
+impl Database {
+    /// Flows whose descriptions use any of the words of `text`, ranked by
+    /// TF-IDF with the descriptions of each flow taken as one document: each
+    /// word scores the times the flow uses it, weighted by how few flows use
+    /// it. Only the flows at `within` are searched when given. Ties keep
+    /// their database order.
+    pub fn text_search(&self, text: &str, within: Option<&[usize]>) -> Vec<TextMatch> {
+        let flows = self.data_flows.len() as f64;
+        let mut scores: BTreeMap<usize, f64> = BTreeMap::new();
+        for word in words(text) {
+            let Some(uses) = self.desc_words.get(&word) else {
+                continue;
+            };
+            let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+            for idx in uses {
+                *counts.entry(*idx).or_default() += 1;
+            }
+            let idf = ((flows + 1.0) / (counts.len() as f64 + 1.0)).ln() + 1.0;
+            for (idx, count) in counts {
+                if within.is_none_or(|within| within.contains(&idx)) {
+                    *scores.entry(idx).or_default() += count as f64 * idf;
+                }
+            }
+        }
+        let mut matches: Vec<TextMatch> = scores
+            .into_iter()
+            .map(|(flow, score)| TextMatch { flow, score })
+            .collect();
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+
+    const DATA: &str = r#"{
+        "file_path": "main.ml",
+        "dataflow": [
+            [{"Type": {"name": "string", "args": [], "desc": "user input"}},
+             {"Type": {"name": "unit", "args": [], "desc": "printed to the log"}}],
+            [{"Type": {"name": "string", "args": [], "desc": "User input, trimmed"}},
+             {"Type": {"name": "string", "args": [], "desc": "input validated"}}],
+            [{"Type": {"name": "int", "args": [], "desc": "log level"}}]
+        ]
+    }"#;
+
+    #[test]
+    fn test_text_search() {
+        let db = Database::load_from_str(DATA).unwrap();
+        let ranked = |text: &str, within: Option<&[usize]>| -> Vec<usize> {
+            db.text_search(text, within)
+                .into_iter()
+                .map(|m| m.flow)
+                .collect()
+        };
+        assert_eq!(ranked("input", None), vec![1, 0]);
+        assert_eq!(ranked("LOG", None), vec![0, 2]);
+        // Flow 0 uses both words, the others one each
+        assert_eq!(ranked("user log", None), vec![0, 1, 2]);
+        assert!(ranked("output", None).is_empty());
+
+        let ints = db.matching_indices(&QueryOps::parse_query("int").unwrap());
+        assert_eq!(ranked("log", Some(&ints)), vec![2]);
+    }
+}
@@ -0,0 +1,176 @@
+use super::{Database, QueryOps, UnitFlow};
+use std::fmt;
+
+/// What became of one query step when matching a flow
+#[derive(Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// Checked against the notes or every step of the flow, as `note:`
+    /// steps, links, steps at a position and unanchored negations are, and
+    /// satisfied
+    Holds,
+    /// Checked against the whole flow like [StepOutcome::Holds], and not
+    /// satisfied
+    Fails,
+    /// Matched in order after the steps before it
+    Matched,
+    /// Not matched in order after the steps before it
+    Unmatched,
+    /// Not tried, as an earlier step already failed
+    Skipped,
+}
+
+/// One query step of a [MatchTrace]
+#[derive(Debug, PartialEq, Eq)]
+pub struct TraceStep {
+    pub op: QueryOps,
+    pub outcome: StepOutcome,
+    /// Indices of the flow steps matching `op` on their own, whatever the
+    /// other steps; empty for anchors, gaps and steps checked against the
+    /// whole flow
+    pub alone: Vec<usize>,
+}
+
+/// Why a flow did or didn't match a query, step by step
+#[derive(Debug, PartialEq, Eq)]
+pub struct MatchTrace {
+    pub matched: bool,
+    pub steps: Vec<TraceStep>,
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let alone = match self.alone.as_slice() {
+            [] => "no flow step matches it on its own".to_string(),
+            [idx] => format!("flow step {} matches it on its own", idx),
+            indices => {
+                let indices: Vec<String> = indices.iter().map(usize::to_string).collect();
+                format!("flow steps {} match it on their own", indices.join(", "))
+            }
+        };
+        match self.outcome {
+            StepOutcome::Holds => write!(f, "`{}` holds for the flow", self.op),
+            StepOutcome::Fails => write!(f, "`{}` does not hold for the flow", self.op),
+            StepOutcome::Matched if self.op.is_structural() => write!(f, "`{}` matched", self.op),
+            StepOutcome::Matched => write!(f, "`{}` matched; {}", self.op, alone),
+            StepOutcome::Unmatched if self.op.is_structural() => {
+                write!(f, "`{}` cannot match after the steps before it", self.op)
+            }
+            StepOutcome::Unmatched => write!(
+                f,
+                "`{}` cannot match after the steps before it; {}",
+                self.op, alone
+            ),
+            StepOutcome::Skipped => write!(f, "`{}` not tried", self.op),
+        }
+    }
+}
+
+impl Database {
+    /// Match `flow` against `query` as [Database::match_flow] does, recording
+    /// the outcome of each step: first the steps checked against the whole
+    /// flow, then the others in order, where the first that cannot match
+    /// after the steps before it is the one that rejected the flow. Queries
+    /// joined with `&&` are traced one after another.
+    pub fn trace_match(&self, flow: &[UnitFlow], query: &[QueryOps]) -> MatchTrace {
+        if let [QueryOps::QAll(queries)] = query {
+            let traces: Vec<MatchTrace> = queries
+                .iter()
+                .map(|query| self.trace_match(flow, query))
+                .collect();
+            return MatchTrace {
+                matched: traces.iter().all(|trace| trace.matched),
+                steps: traces.into_iter().flat_map(|trace| trace.steps).collect(),
+            };
+        }
+        let positional = query.iter().any(QueryOps::is_structural);
+        let (whole, ordered): (Vec<&QueryOps>, Vec<&QueryOps>) =
+            query.iter().partition(|op| op.is_flow_level(positional));
+
+        let mut steps = vec![];
+        let mut failed = false;
+        for op in whole {
+            let holds = self.flow_contains(flow, op);
+            failed |= !holds;
+            steps.push(TraceStep {
+                op: op.clone(),
+                outcome: if holds {
+                    StepOutcome::Holds
+                } else {
+                    StepOutcome::Fails
+                },
+                alone: vec![],
+            });
+        }
+        let ordered: Vec<QueryOps> = ordered.into_iter().cloned().collect();
+        for len in 1..=ordered.len() {
+            let op = &ordered[len - 1];
+            let outcome = if failed {
+                StepOutcome::Skipped
+            } else if self.match_steps(flow, &ordered[..len]).is_some() {
+                StepOutcome::Matched
+            } else {
+                failed = true;
+                StepOutcome::Unmatched
+            };
+            let alone = if op.is_structural() {
+                vec![]
+            } else {
+                (0..flow.len())
+                    .filter(|&idx| self.match_unit_flow(&flow[idx], op))
+                    .collect()
+            };
+            steps.push(TraceStep {
+                op: op.clone(),
+                outcome,
+                alone,
+            });
+        }
+        MatchTrace {
+            matched: !failed,
+            steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_match() {
+        let db = crate::tutorial::load_database();
+        let trace = |idx: usize, query: &str| {
+            let query = QueryOps::parse_query(query).unwrap();
+            let trace = db.trace_match(&db.data_flows[idx], &query);
+            assert_eq!(trace.matched, db.match_flow(&db.data_flows[idx], &query));
+            trace
+        };
+
+        let matched = trace(2, "List, @Cons");
+        assert!(matched.matched);
+        assert!(matched
+            .steps
+            .iter()
+            .all(|step| step.outcome == StepOutcome::Matched));
+
+        let unmatched = trace(2, "^, List, @Cons");
+        assert!(!unmatched.matched);
+        let outcomes: Vec<&StepOutcome> = unmatched.steps.iter().map(|s| &s.outcome).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                &StepOutcome::Matched,
+                &StepOutcome::Matched,
+                &StepOutcome::Unmatched
+            ]
+        );
+        assert!(!unmatched.steps[2].alone.is_empty());
+        assert!(unmatched.steps[2]
+            .to_string()
+            .starts_with("`@Cons` cannot match after the steps before it; flow step"));
+
+        let noted = trace(0, "note:reviewed, bool");
+        assert_eq!(noted.steps[0].outcome, StepOutcome::Fails);
+        assert_eq!(noted.steps[1].outcome, StepOutcome::Skipped);
+    }
+}
@@ -0,0 +1,206 @@
+use super::{Database, QueryOps, UnitFlow};
+use std::fmt;
+
+/// A reason the query can never match, found without searching
+#[derive(Debug, PartialEq, Eq)]
+pub struct Contradiction {
+    /// The offending query step, or none when the query as a whole cannot
+    /// match
+    pub step: Option<QueryOps>,
+    pub reason: String,
+}
+
+impl fmt::Display for Contradiction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.step {
+            Some(step) => write!(f, "`{}` can never match: {}", step, self.reason),
+            None => write!(f, "the query can never match: {}", self.reason),
+        }
+    }
+}
+
+impl Database {
+    /// Steps of `query` that no flow of the database can satisfy, whatever
+    /// the rest of the query. Names missing from the database are left to
+    /// [Database::suggestions].
+    pub fn contradictions(&self, query: &[QueryOps]) -> Vec<Contradiction> {
+        if let [QueryOps::QAll(queries)] = query {
+            return queries
+                .iter()
+                .flat_map(|query| self.contradictions(query))
+                .collect();
+        }
+        let mut contradictions: Vec<Contradiction> = query
+            .iter()
+            .filter_map(|op| {
+                let reason = match op.unbound() {
+                    QueryOps::QTypeVar(0) => {
+                        "a type variable appears in at least its own flow".to_string()
+                    }
+                    QueryOps::QTypeVarRange { max: Some(0), .. } => {
+                        "a type variable appears in at least its own flow".to_string()
+                    }
+                    QueryOps::QTypeVar(count) | QueryOps::QTypeVarRange { min: count, .. }
+                        if *count > self.data_flows.len() =>
+                    {
+                        format!("the database holds only {} flows", self.data_flows.len())
+                    }
+                    QueryOps::QTypeVar(count)
+                        if !self.typevar_degrees().any(|(_, degree)| degree == *count) =>
+                    {
+                        format!("no type variable flows through exactly {} flows", count)
+                    }
+                    QueryOps::QTypeVarName { name, min, max }
+                        if !self.ignore_case && regex::escape(name) == name.as_str() =>
+                    {
+                        let Some(flows) = self.type_var_flows.get(name.as_str()) else {
+                            return Some(Contradiction {
+                                step: Some(op.clone()),
+                                reason: format!("no type variable is named `{}`", name),
+                            });
+                        };
+                        if flows.len() >= *min && max.is_none_or(|max| flows.len() <= max) {
+                            return None;
+                        }
+                        format!("`{}` flows through {} flows", name, flows.len())
+                    }
+                    QueryOps::QAt(idx, _) => {
+                        let longest = self
+                            .data_flows
+                            .iter()
+                            .map(<[UnitFlow]>::len)
+                            .max()
+                            .unwrap_or(0);
+                        if *idx < longest {
+                            return None;
+                        }
+                        format!("the longest flow has {} steps, numbered from 0", longest)
+                    }
+                    QueryOps::QConstructorArg(q)
+                        if q.arg_index
+                            .as_ref()
+                            .is_some_and(|idx| !idx.any_within(1, usize::MAX)) =>
+                    {
+                        "constructor arguments are numbered from 1".to_string()
+                    }
+                    QueryOps::QConstructorArg(q) => {
+                        let arity = self.constructor_arity(&q.name)?;
+                        if q.arg_index.as_ref()?.any_within(1, arity) {
+                            return None;
+                        }
+                        format!("`@{}` takes at most {} arguments", q.name, arity)
+                    }
+                    _ => return None,
+                };
+                Some(Contradiction {
+                    step: Some(op.clone()),
+                    reason,
+                })
+            })
+            .collect();
+
+        let steps: Vec<&QueryOps> = query
+            .iter()
+            .filter(|op| {
+                !matches!(
+                    op,
+                    QueryOps::QNote(_) | QueryOps::QLen(_) | QueryOps::QAt(..)
+                )
+            })
+            .collect();
+        for (idx, op) in steps.iter().enumerate() {
+            let reason = match op {
+                QueryOps::QStart if idx > 0 => "`^` anchors the first step and must come first",
+                QueryOps::QEnd if idx < steps.len() - 1 => {
+                    "`$` anchors the last step and must come last"
+                }
+                _ => continue,
+            };
+            contradictions.push(Contradiction {
+                step: Some((*op).clone()),
+                reason: reason.to_string(),
+            });
+        }
+
+        // Each step consumes a step of the flow after the previous match. A
+        // negated step may not, so it is left out.
+        let steps = steps
+            .iter()
+            .filter(|op| !op.is_structural() && !matches!(op, QueryOps::QNot(_)))
+            .map(|op| op.step_count())
+            .sum::<usize>();
+        let longest = self
+            .data_flows
+            .iter()
+            .map(<[UnitFlow]>::len)
+            .max()
+            .unwrap_or(0);
+        if steps > longest {
+            contradictions.push(Contradiction {
+                step: None,
+                reason: format!(
+                    "it has {} steps but the longest flow has {}",
+                    steps, longest
+                ),
+            });
+        }
+        contradictions
+    }
+
+    /// Highest argument index of the constructor anywhere in the database
+    fn constructor_arity(&self, name: &str) -> Option<usize> {
+        self.data_flows
+            .iter()
+            .flatten()
+            .filter_map(|uf| match uf {
+                UnitFlow::ConstructorArg(c) if c.name == name => Some(c.arg_index),
+                _ => None,
+            })
+            .max()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contradictions() {
+        let db = crate::tutorial::load_database();
+        let contradictions = |query: &str| -> Vec<String> {
+            db.contradictions(&QueryOps::parse_query(query).unwrap())
+                .iter()
+                .map(Contradiction::to_string)
+                .collect()
+        };
+
+        assert!(contradictions("bool, @Tuple.2, #2").is_empty());
+        assert!(contradictions("@Missing.9").is_empty());
+        assert_eq!(
+            contradictions("#3"),
+            vec!["`#3` can never match: no type variable flows through exactly 3 flows"]
+        );
+        assert_eq!(
+            contradictions("#0, @Tuple.3, @Cons.0"),
+            vec![
+                "`#0` can never match: a type variable appears in at least its own flow",
+                "`@Tuple.3` can never match: `@Tuple` takes at most 2 arguments",
+                "`@Cons.0` can never match: constructor arguments are numbered from 1",
+            ]
+        );
+        assert_eq!(
+            contradictions("#7, List, List, List, List, List, note:x"),
+            vec![
+                "`#7` can never match: the database holds only 6 flows",
+                "the query can never match: it has 6 steps but the longest flow has 5",
+            ]
+        );
+        assert_eq!(
+            contradictions("bool, ^, $, ..,  List"),
+            vec![
+                "`^` can never match: `^` anchors the first step and must come first",
+                "`$` can never match: `$` anchors the last step and must come last",
+            ]
+        );
+    }
+}
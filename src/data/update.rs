@@ -0,0 +1,153 @@
+use super::{flow_id, Database, UnitFlow};
+use crate::error::{self, SemanticSearchError};
+use std::collections::BTreeSet;
+use std::fs;
+
+impl Database {
+    /// Add the types, type variables, constructors and description words of
+    /// the flow at `idx` to the inverted maps. Flows must be indexed in order.
+    pub(super) fn index_flow(&mut self, idx: usize) {
+        for uf in &self.data_flows[idx] {
+            for word in uf.desc_words() {
+                self.desc_words.entry(word).or_default().push(idx);
+            }
+            let (map, name) = match uf {
+                UnitFlow::Type(t) => {
+                    self.types.insert(t.name.to_string(), t.clone());
+                    (&mut self.type_flows, &t.name)
+                }
+                UnitFlow::TypeVar(tv) => {
+                    self.type_vars.insert(tv.name.to_string());
+                    (&mut self.type_var_flows, &tv.name)
+                }
+                UnitFlow::ConstructorArg(c) => (&mut self.constructor_flows, &c.name),
+                UnitFlow::ProgLoc(_) | UnitFlow::Other { .. } => continue,
+            };
+            let flows = map.entry(name.to_string()).or_default();
+            if flows.last() != Some(&idx) {
+                flows.push(idx);
+            }
+        }
+    }
+
+    /// Append `flow`, indexing it as [Database::new] would, and return its
+    /// index
+    pub fn add_flow(&mut self, flow: Vec<UnitFlow>) -> usize {
+        self.data_flows.push(flow);
+        let idx = self.data_flows.len() - 1;
+        self.index_flow(idx);
+        idx
+    }
+
+    /// Remove the flows starting in `file`, as given by
+    /// [Database::flow_file], so the flows of a re-analysed source file can
+    /// be added in their place. The inverted maps are renumbered rather than
+    /// rebuilt. Returns the number of flows removed.
+    pub fn remove_flows_for_file(&mut self, file: &str) -> usize {
+        let keep: Vec<bool> = self
+            .data_flows
+            .iter()
+            .map(|flow| self.flow_file(flow) != file)
+            .collect();
+        let removed = keep.iter().filter(|kept| !**kept).count();
+        if removed == 0 {
+            return 0;
+        }
+
+        // New index of each kept flow
+        let mut renumbered = vec![None; keep.len()];
+        let mut next = 0;
+        for (idx, kept) in keep.iter().enumerate() {
+            if *kept {
+                renumbered[idx] = Some(next);
+                next += 1;
+            }
+        }
+        for map in [
+            &mut self.type_flows,
+            &mut self.type_var_flows,
+            &mut self.constructor_flows,
+            &mut self.desc_words,
+        ] {
+            for flows in map.values_mut() {
+                *flows = flows.iter().filter_map(|idx| renumbered[*idx]).collect();
+            }
+            map.retain(|_, flows| !flows.is_empty());
+        }
+        self.types
+            .retain(|name, _| self.type_flows.contains_key(name));
+        self.type_vars
+            .retain(|name| self.type_var_flows.contains_key(name));
+
+        let mut kept = keep.iter();
+        self.data_flows.retain(|_| *kept.next().unwrap());
+        let ids: BTreeSet<String> = self.data_flows.iter().map(flow_id).collect();
+        self.edges.retain(|id, _| ids.contains(id));
+        self.counts.retain(|id, _| ids.contains(id));
+        self.notes.retain(|id, _| ids.contains(id));
+        removed
+    }
+
+    /// Write the database to `path` in the format read by
+    /// [Database::load_from_str], replacing the file only once it is
+    /// completely written so readers never see it half done
+    pub fn save_to_json(&self, path: &str) -> error::Result<()> {
+        let json = self
+            .to_json()
+            .map_err(|message| SemanticSearchError::Index {
+                path: path.to_string(),
+                message,
+            })?;
+        let partial = format!("{}.partial", path);
+        fs::write(&partial, json).map_err(|e| SemanticSearchError::io(&partial, e))?;
+        fs::rename(&partial, path).map_err(|e| SemanticSearchError::io(path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+
+    #[test]
+    fn test_incremental_updates() {
+        let mut db = Database::generate_synthetic(64, 6);
+        let file = db.flow_file(&db.data_flows[3]).to_string();
+        let regenerated: Vec<Vec<UnitFlow>> = db
+            .data_flows
+            .iter()
+            .filter(|flow| db.flow_file(flow) == file)
+            .map(<[UnitFlow]>::to_vec)
+            .collect();
+
+        assert_eq!(db.remove_flows_for_file(&file), regenerated.len());
+        assert!(db.data_flows.iter().all(|flow| db.flow_file(flow) != file));
+        assert_eq!(db.remove_flows_for_file(&file), 0);
+        for flow in regenerated {
+            db.add_flow(flow);
+        }
+
+        // The maps updated in place agree with those built from scratch
+        let rebuilt = Database::new(db.file_path.clone(), db.data_flows.to_vec());
+        assert_eq!(db.type_flows, rebuilt.type_flows);
+        assert_eq!(db.type_var_flows, rebuilt.type_var_flows);
+        assert_eq!(db.constructor_flows, rebuilt.constructor_flows);
+        assert_eq!(db.desc_words, rebuilt.desc_words);
+        assert_eq!(db.type_vars, rebuilt.type_vars);
+        assert!(db.types.keys().eq(rebuilt.types.keys()));
+        for query in ["List", "#2", "@Some, int"] {
+            let query = QueryOps::parse_query(query).unwrap();
+            assert_eq!(
+                db.matching_indices(&query),
+                rebuilt.matching_indices(&query)
+            );
+        }
+
+        let path = std::env::temp_dir().join(format!("scs-update-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        db.save_to_json(path).unwrap();
+        let saved = Database::load_from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(saved.data_flows.len(), db.data_flows.len());
+        fs::remove_file(path).unwrap();
+    }
+}
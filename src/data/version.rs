@@ -0,0 +1,163 @@
+use super::stream::{self, FlowStream};
+use super::Database;
+use crate::error::{self, SemanticSearchError};
+use serde::Deserialize;
+use std::fs;
+
+/// Version of the database schema this build writes and reads up to.
+/// Databases without a `version` are of schema 1, the first; schema 2 records
+/// its version and lists the [flow_id](super::flow_id) of each flow as `ids`,
+/// in the order of `dataflow`, so that other tools can refer to flows
+/// without hashing them.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Schema of databases written before the version was recorded
+pub(super) fn first_schema() -> u32 {
+    1
+}
+
+/// Fail on databases of a schema newer than this build reads, which would
+/// otherwise load with whatever it does not know silently dropped
+pub(super) fn check(version: u32, path: Option<&str>) -> error::Result<()> {
+    if version > SCHEMA_VERSION {
+        Err(SemanticSearchError::UnsupportedVersion {
+            path: path.map(str::to_string),
+            version,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    #[serde(default = "first_schema")]
+    version: u32,
+}
+
+/// The schema version of the JSON or line-delimited database `data`, read
+/// from its wrapper or header line without parsing its flows
+pub fn schema_version(data: &str) -> u32 {
+    let wrapper = if stream::looks_like_ndjson(data) {
+        data.lines().map(str::trim).find(|line| !line.is_empty())
+    } else {
+        Some(data)
+    };
+    wrapper
+        .and_then(|wrapper| serde_json::from_str::<Versioned>(wrapper).ok())
+        .map_or(first_schema(), |versioned| versioned.version)
+}
+
+/// A database [migrated](Database::migrate)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Migration {
+    /// Schema the database was in
+    pub from: u32,
+    /// Schema it was written in, [SCHEMA_VERSION]
+    pub to: u32,
+    pub flows: usize,
+}
+
+impl Database {
+    /// Rewrite the database at `path` to `out` in the current schema, as a
+    /// line-delimited database when `out` is named like one. Flows are kept
+    /// exactly as read, duplicates and all, so their ids do not change. The
+    /// database is never read from its binary index, and `out` is replaced
+    /// only once completely written, so it may be `path`.
+    pub fn migrate(path: &str, out: &str) -> error::Result<Migration> {
+        let data = Self::read_data(path)?;
+        let from = schema_version(&data);
+        let db = if Self::is_ndjson(path) || stream::looks_like_ndjson(&data) {
+            FlowStream::new(data.as_bytes(), path)?.collect_database(false)?
+        } else {
+            Self::parse(&data, Some(path))?
+        };
+        let migrated = if Self::is_ndjson(out) {
+            db.to_ndjson()
+        } else {
+            db.to_json()
+        }
+        .map_err(|message| SemanticSearchError::Index {
+            path: out.to_string(),
+            message,
+        })?;
+        let partial = format!("{}.partial", out);
+        fs::write(&partial, migrated).map_err(|e| SemanticSearchError::io(&partial, e))?;
+        fs::rename(&partial, out).map_err(|e| SemanticSearchError::io(out, e))?;
+        Ok(Migration {
+            from,
+            to: SCHEMA_VERSION,
+            flows: db.data_flows.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::flow_id;
+    use std::env;
+
+    const V1: &str = r#"{
+        "file_path": "main.ml",
+        "dataflow": [
+            [{"Type": {"name": "string", "args": [], "desc": "user input"}},
+             {"TypeVar": {"name": "'a", "desc": null}}]
+        ]
+    }"#;
+
+    #[test]
+    fn test_schema_versions() {
+        assert_eq!(schema_version(V1), 1);
+        let db = Database::load_from_str(V1).unwrap();
+        let json = db.to_json().unwrap();
+        assert_eq!(schema_version(&json), SCHEMA_VERSION);
+        let written: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(written["ids"][0], flow_id(&db.data_flows[0]));
+        assert_eq!(schema_version(&db.to_ndjson().unwrap()), SCHEMA_VERSION);
+
+        let future = V1.replacen('{', r#"{"version": 99,"#, 1);
+        assert_eq!(schema_version(&future), 99);
+        let error = Database::load_from_str(&future).unwrap_err();
+        assert!(error.to_string().contains("schema version 99"));
+        let header = r#"{"file_path": "main.ml", "version": 99}"#;
+        assert!(FlowStream::new(header.as_bytes(), "flows.ndjson").is_err());
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = env::temp_dir().join(format!("scs-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("v1.json").to_string_lossy().to_string();
+        fs::write(&path, V1).unwrap();
+
+        let migration = Database::migrate(&path, &path).unwrap();
+        assert_eq!(
+            migration,
+            Migration {
+                from: 1,
+                to: SCHEMA_VERSION,
+                flows: 1
+            }
+        );
+        let migrated = Database::read_data(&path).unwrap();
+        assert_eq!(schema_version(&migrated), SCHEMA_VERSION);
+        let old = Database::load_from_str(V1).unwrap();
+        let new = Database::load_from_str(&migrated).unwrap();
+        assert_eq!(flow_id(&old.data_flows[0]), flow_id(&new.data_flows[0]));
+
+        let ndjson = dir.join("v2.ndjson").to_string_lossy().to_string();
+        assert_eq!(
+            Database::migrate(&path, &ndjson).unwrap().from,
+            SCHEMA_VERSION
+        );
+        assert_eq!(
+            Database::load_with_duplicates(&ndjson)
+                .unwrap()
+                .data_flows
+                .len(),
+            1
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,443 @@
+use crate::cache::{CacheKey, CacheStats, CachedMatch, ResultCache};
+use crate::data::{flow_id, Database, QueryOps, UnitFlow, PARALLEL_SEARCH_FLOWS};
+use crate::error;
+use crate::notes::Notes;
+use crate::rank::{Ranked, Score};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A flow matched by a [SearchEngine] query, with what is needed to find it
+/// again in its database and show why it matched
+#[derive(Debug, Clone)]
+pub struct Match<'a> {
+    /// Index of the flow in [Database::data_flows]
+    pub index: usize,
+    pub flow_id: String,
+    pub flow: &'a [UnitFlow],
+    /// File the flow starts in, as given by [Database::flow_file]
+    pub file_path: &'a str,
+    /// Steps of the flow that satisfied the query, as returned by
+    /// [Database::match_flow_positions]
+    pub positions: Vec<usize>,
+    /// Names bound by the query with the indices of the flow steps they
+    /// captured, as given by [QueryOps::captures]
+    pub captures: BTreeMap<String, usize>,
+    pub score: Score,
+}
+
+/// A parsed query with the name its results are reported under, such as a
+/// rule of a [crate::rules::RulePack]
+#[derive(Debug, Clone)]
+pub struct NamedQuery {
+    pub name: String,
+    pub query: Vec<QueryOps>,
+}
+
+impl NamedQuery {
+    /// Parse `query` in the command line syntax
+    pub fn parse(name: &str, query: &str) -> error::Result<Self> {
+        Ok(NamedQuery {
+            name: name.to_string(),
+            query: QueryOps::parse_query(query)?,
+        })
+    }
+}
+
+/// Stops a search between flows once cancelled, from any thread holding a
+/// clone, or once its deadline passes. The default token never cancels.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that cancels itself `timeout` from now
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancelToken {
+            cancelled: Arc::default(),
+            deadline: Some(Instant::now() + timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, AtomicOrdering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.cancel();
+        }
+        self.cancelled.load(AtomicOrdering::Relaxed)
+    }
+}
+
+/// A database opened for searching, for embedding the search in other tools.
+/// The results of recent queries are cached, so that tools running the same
+/// queries again and again only search once.
+pub struct SearchEngine {
+    db: Database,
+    /// [Database::content_hash] of `db`, keying its cached results; `None`
+    /// for an engine that caches nothing
+    db_hash: Option<u64>,
+    cache: Mutex<ResultCache>,
+}
+
+impl SearchEngine {
+    /// Open the database at `path`, which may be anything
+    /// [Database::load_from_json] accepts, with the notes attached to its flows
+    pub fn open(path: &str) -> error::Result<Self> {
+        let mut db = Database::load_from_json(path)?;
+        db.set_notes(Notes::load(path)?.notes);
+        Ok(Self::new(db))
+    }
+
+    /// Search an already loaded database
+    pub fn new(db: Database) -> Self {
+        SearchEngine {
+            db_hash: Some(db.content_hash()),
+            db,
+            cache: Mutex::default(),
+        }
+    }
+
+    /// Search an already loaded database once or twice, caching nothing and
+    /// so never hashing the database, as a single run of the command line
+    /// does
+    pub fn uncached(db: Database) -> Self {
+        SearchEngine {
+            db_hash: None,
+            db,
+            cache: Mutex::new(ResultCache::new(0)),
+        }
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.db
+    }
+
+    /// Change the database in place, as with [Database::add_flow] or
+    /// [Database::remove_flows_for_file], dropping the cached results
+    pub fn update<R>(&mut self, change: impl FnOnce(&mut Database) -> R) -> R {
+        let result = change(&mut self.db);
+        if self.db_hash.is_some() {
+            self.db_hash = Some(self.db.content_hash());
+        }
+        self.cache().clear();
+        result
+    }
+
+    /// Hits and misses of the result cache so far
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache().stats()
+    }
+
+    fn cache(&self) -> MutexGuard<'_, ResultCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The flows matched by a query in the command line syntax
+    pub fn search(&self, query: &str) -> error::Result<Ranked<'_>> {
+        Ok(self.search_ops(&QueryOps::parse_query(query)?))
+    }
+
+    /// Like [SearchEngine::search], stopping once `cancel` is cancelled with
+    /// the flows matched so far, [truncated](Ranked::is_truncated)
+    pub fn search_with_cancel(
+        &self,
+        query: &str,
+        cancel: &CancelToken,
+    ) -> error::Result<Ranked<'_>> {
+        Ok(self.search_ops_with_cancel(&QueryOps::parse_query(query)?, cancel))
+    }
+
+    /// The flows matched by a parsed query, best first. Queries without an
+    /// indexed step on databases of at least [PARALLEL_SEARCH_FLOWS] flows
+    /// are searched on all cores.
+    pub fn search_ops(&self, query: &[QueryOps]) -> Ranked<'_> {
+        self.search_ops_with_cancel(query, &CancelToken::default())
+    }
+
+    /// Like [SearchEngine::search_ops], checking `cancel` between flows.
+    /// Results cut short are not cached.
+    pub fn search_ops_with_cancel(&self, query: &[QueryOps], cancel: &CancelToken) -> Ranked<'_> {
+        let _span = tracing::info_span!("search", steps = query.len()).entered();
+        let key = self.cache_key(query);
+        if let Some(cached) = self.cached(key.as_ref(), cancel) {
+            tracing::debug!(matched = cached.len(), "cached results");
+            return Ranked::new(cached);
+        }
+        let truncated = AtomicBool::new(false);
+        let match_flow = |index: usize| {
+            if cancel.is_cancelled() {
+                truncated.store(true, AtomicOrdering::Relaxed);
+                return None;
+            }
+            self.match_flow(index, query)
+        };
+        let matches: Vec<Match> = if let Some(candidates) = self.db.candidates(query) {
+            tracing::debug!(candidates = candidates.len(), "prefiltered flows");
+            candidates.into_iter().filter_map(match_flow).collect()
+        } else if self.db.data_flows.len() < PARALLEL_SEARCH_FLOWS {
+            (0..self.db.data_flows.len())
+                .filter_map(match_flow)
+                .collect()
+        } else {
+            self.db
+                .data_flows
+                .par_iter()
+                .enumerate()
+                .filter_map(|(index, _)| match_flow(index))
+                .collect()
+        };
+        tracing::info!(matched = matches.len(), "searched flows");
+        let ranked = Ranked::new(matches);
+        match (truncated.into_inner(), key) {
+            (true, _) => ranked.truncated(),
+            (false, Some(key)) => {
+                let cached = ranked.iter().map(CachedMatch::new).collect();
+                self.cache().insert(key, cached);
+                ranked
+            }
+            (false, None) => ranked,
+        }
+    }
+
+    /// What the results of `query` are cached by, unless the engine caches
+    /// nothing
+    fn cache_key(&self, query: &[QueryOps]) -> Option<CacheKey> {
+        Some(CacheKey::new(self.db_hash?, query))
+    }
+
+    /// The matches cached for `key`, borrowing the database again, unless
+    /// the search is already cancelled
+    fn cached(&self, key: Option<&CacheKey>, cancel: &CancelToken) -> Option<Vec<Match<'_>>> {
+        let key = key?;
+        if cancel.is_cancelled() {
+            return None;
+        }
+        let mut cache = self.cache();
+        let cached = cache.get(key)?;
+        Some(
+            cached
+                .iter()
+                .map(|m| Match {
+                    index: m.index,
+                    flow_id: m.flow_id.clone(),
+                    flow: &self.db.data_flows[m.index],
+                    file_path: self.db.flow_file(&self.db.data_flows[m.index]),
+                    positions: m.positions.clone(),
+                    captures: m.captures.clone(),
+                    score: m.score,
+                })
+                .collect(),
+        )
+    }
+
+    /// The flows matched by each of `queries`, in the order of the queries
+    /// whatever order they finish in. The queries run concurrently on all
+    /// cores, sharing the database.
+    pub fn search_many(&self, queries: &[NamedQuery]) -> Vec<Ranked<'_>> {
+        queries
+            .par_iter()
+            .map(|named| self.search_ops(&named.query))
+            .collect()
+    }
+
+    /// Indices of the flows matched by `query` in database order, as
+    /// [Database::matching_indices] gives them, from the cache when the query
+    /// was searched before
+    pub fn matching_indices(&self, query: &[QueryOps]) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.search_ops(query).iter().map(|m| m.index).collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// The matches of `query` in database order, skipping the first
+    /// `offset` and stopping after `limit` more, as
+    /// [Database::search_page] does, or once `cancel` is cancelled. Also
+    /// returns whether the search was cut short by `cancel`.
+    pub fn search_page_with_cancel(
+        &self,
+        query: &[QueryOps],
+        offset: usize,
+        limit: Option<usize>,
+        cancel: &CancelToken,
+    ) -> (Vec<Match<'_>>, bool) {
+        let _span = tracing::info_span!("search", steps = query.len(), offset).entered();
+        if let Some(mut cached) = self.cached(self.cache_key(query).as_ref(), cancel) {
+            cached.sort_by_key(|m| m.index);
+            let matches = cached
+                .into_iter()
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect();
+            return (matches, false);
+        }
+        let indices: Box<dyn Iterator<Item = usize>> = match self.db.candidates(query) {
+            Some(candidates) => Box::new(candidates.into_iter()),
+            None => Box::new(0..self.db.data_flows.len()),
+        };
+        let mut truncated = false;
+        let matches = indices
+            .take_while(|_| {
+                truncated = cancel.is_cancelled();
+                !truncated
+            })
+            .filter_map(|index| self.match_flow(index, query))
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect::<Vec<_>>();
+        tracing::info!(matched = matches.len(), "searched flows");
+        (matches, truncated)
+    }
+
+    fn match_flow(&self, index: usize, query: &[QueryOps]) -> Option<Match<'_>> {
+        let flow = &self.db.data_flows[index];
+        self.db
+            .match_flow_positions(flow, query)
+            .map(|positions| Match {
+                index,
+                flow_id: flow_id(flow),
+                flow,
+                file_path: self.db.flow_file(flow),
+                score: Score::new(
+                    &positions,
+                    flow.len(),
+                    self.db.fuzzy_desc_score(flow, query),
+                ),
+                captures: QueryOps::captures(query, &positions),
+                positions,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_engine() {
+        let engine = SearchEngine::new(crate::tutorial::load_database());
+
+        let matches = engine.search("List, @Cons").unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].index, 2);
+        assert_eq!(matches[0].positions, vec![0, 2]);
+        assert!(matches[0].captures.is_empty());
+        let matches = engine.search("List, @Cons as head").unwrap();
+        assert_eq!(matches[0].captures, [("head".to_string(), 2)].into());
+        assert_eq!(matches[0].file_path, "tutorial/example.ml");
+        assert_eq!(
+            matches[0].flow_id,
+            flow_id(&engine.database().data_flows[2])
+        );
+
+        assert_eq!(engine.search("bool").unwrap().len(), 2);
+        assert!(engine.search("!^").is_err());
+
+        let matches = engine.search("~\"if then else conditon\"").unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches[0].score.similarity >= matches[1].score.similarity);
+        assert!(matches[1].score.similarity > 0.0);
+    }
+
+    #[test]
+    fn test_result_cache() {
+        let mut engine = SearchEngine::new(crate::tutorial::load_database());
+        let first: Vec<usize> = engine
+            .search("bool")
+            .unwrap()
+            .iter()
+            .map(|m| m.index)
+            .collect();
+        let again: Vec<usize> = engine
+            .search("bool ")
+            .unwrap()
+            .iter()
+            .map(|m| m.index)
+            .collect();
+        assert_eq!(first, again);
+        assert_eq!(
+            (engine.cache_stats().hits, engine.cache_stats().misses),
+            (1, 1)
+        );
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(
+            engine.matching_indices(&QueryOps::parse_query("bool").unwrap()),
+            sorted
+        );
+
+        let flow = engine.database().data_flows[first[0]].to_vec();
+        engine.update(|db| db.add_flow(flow));
+        assert_eq!(engine.cache_stats().entries, 0);
+        assert_eq!(engine.search("bool").unwrap().len(), first.len() + 1);
+
+        let uncached = SearchEngine::uncached(crate::tutorial::load_database());
+        assert_eq!(uncached.search("bool").unwrap().len(), first.len());
+        assert_eq!(uncached.search("bool").unwrap().len(), first.len());
+        assert_eq!(uncached.cache_stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_search_many() {
+        let engine = SearchEngine::new(crate::tutorial::load_database());
+        let queries: Vec<NamedQuery> = [("lists", "List"), ("bools", "bool"), ("none", "@Nothing")]
+            .iter()
+            .map(|(name, query)| NamedQuery::parse(name, query).unwrap())
+            .collect();
+        let results = engine.search_many(&queries);
+        assert_eq!(results.len(), queries.len());
+        for (named, ranked) in queries.iter().zip(&results) {
+            let alone = engine.search_ops(&named.query);
+            let indices = |ranked: &Ranked| ranked.iter().map(|m| m.index).collect::<Vec<_>>();
+            assert_eq!(indices(ranked), indices(&alone), "{}", named.name);
+        }
+        assert!(results[2].is_empty());
+        assert!(NamedQuery::parse("bad", "!^").is_err());
+    }
+
+    #[test]
+    fn test_search_with_cancel() {
+        let engine = SearchEngine::new(crate::tutorial::load_database());
+        let query = QueryOps::parse_query("Li.*").unwrap();
+
+        let cancel = CancelToken::new();
+        let matches = engine.search_with_cancel("Li.*", &cancel).unwrap();
+        assert!(!matches.is_truncated());
+        assert_eq!(matches.len(), engine.search_ops(&query).len());
+
+        cancel.clone().cancel();
+        let matches = engine.search_with_cancel("Li.*", &cancel).unwrap();
+        assert!(matches.is_truncated() && matches.is_empty());
+        let (flows, truncated) = engine.search_page_with_cancel(&query, 0, None, &cancel);
+        assert!(truncated && flows.is_empty());
+
+        let expired = CancelToken::with_timeout(Duration::ZERO);
+        assert!(expired.is_cancelled());
+        let (flows, truncated) =
+            engine.search_page_with_cancel(&query, 1, Some(2), &CancelToken::new());
+        assert!(!truncated);
+        let ids: Vec<String> = engine
+            .database()
+            .search_page(&query, 1, Some(2))
+            .into_iter()
+            .map(flow_id)
+            .collect();
+        assert_eq!(
+            flows.iter().map(|m| m.flow_id.clone()).collect::<Vec<_>>(),
+            ids
+        );
+    }
+}
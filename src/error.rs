@@ -0,0 +1,190 @@
+use crate::data::SCHEMA_VERSION;
+use std::fmt;
+use std::io;
+
+/// Errors loading databases and parsing queries
+#[derive(Debug)]
+pub enum SemanticSearchError {
+    /// A file could not be read
+    Io { path: String, source: io::Error },
+    /// A file is not valid JSON, or not in the expected shape
+    JsonParse {
+        path: Option<String>,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A flow of a database that is valid JSON is not a list of valid
+    /// steps; `step` is the index of the step at fault, if the flow is a list
+    Schema {
+        path: Option<String>,
+        flow: usize,
+        step: Option<usize>,
+        message: String,
+    },
+    /// A binary index could not be read back
+    Index { path: String, message: String },
+    /// A database was written in a schema newer than
+    /// [SCHEMA_VERSION](crate::data::SCHEMA_VERSION)
+    UnsupportedVersion { path: Option<String>, version: u32 },
+    /// A database is larger than the [LoadLimits](crate::data::LoadLimits)
+    /// it was loaded with allow
+    Limit {
+        path: Option<String>,
+        message: String,
+    },
+    /// A query could not be parsed; `position` and `end` are the byte
+    /// offsets in `query` of the part at fault
+    QueryParse {
+        query: String,
+        position: usize,
+        end: usize,
+        message: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, SemanticSearchError>;
+
+/// Exit status on errors, as grep uses; 0 and 1 tell whether a search
+/// matched any flows
+pub const ERROR_EXIT_CODE: i32 = 2;
+
+impl SemanticSearchError {
+    pub fn io(path: impl fmt::Display, source: io::Error) -> Self {
+        SemanticSearchError::Io {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    /// A JSON error in the file at `path`, or in a string when `None`
+    pub fn json(path: Option<&str>, error: serde_json::Error) -> Self {
+        SemanticSearchError::JsonParse {
+            path: path.map(str::to_string),
+            line: error.line(),
+            column: error.column(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Exit status for the error, [ERROR_EXIT_CODE] whatever the error so
+    /// scripts can tell it apart from a search matching nothing
+    pub fn exit_code(&self) -> i32 {
+        ERROR_EXIT_CODE
+    }
+}
+
+impl fmt::Display for SemanticSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SemanticSearchError::Io { path, source } => {
+                write!(f, "Could not read {}: {}", path, source)
+            }
+            SemanticSearchError::JsonParse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Invalid JSON in {} at line {}, column {}: {}",
+                path.as_deref().unwrap_or("input"),
+                line,
+                column,
+                message
+            ),
+            SemanticSearchError::Schema {
+                path,
+                flow,
+                step,
+                message,
+            } => {
+                let path = path.as_deref().unwrap_or("input");
+                match step {
+                    Some(step) => write!(
+                        f,
+                        "Invalid flow {} in {}, step {}: {}",
+                        flow, path, step, message
+                    ),
+                    None => write!(f, "Invalid flow {} in {}: {}", flow, path, message),
+                }
+            }
+            SemanticSearchError::Index { path, message } => {
+                write!(f, "Invalid index {}: {}", path, message)
+            }
+            SemanticSearchError::UnsupportedVersion { path, version } => write!(
+                f,
+                "{} uses database schema version {}, newer than the version {} this build reads; upgrade semantic-code-search to load it",
+                path.as_deref().unwrap_or("input"),
+                version,
+                SCHEMA_VERSION
+            ),
+            SemanticSearchError::Limit { path, message } => write!(
+                f,
+                "Refusing to load {}: {}",
+                path.as_deref().unwrap_or("input"),
+                message
+            ),
+            SemanticSearchError::QueryParse {
+                query,
+                position,
+                end,
+                message,
+            } => write!(
+                f,
+                "Could not parse query: {}\n  {}\n  {}{}",
+                message,
+                query,
+                " ".repeat(query[..*position].chars().count()),
+                "^".repeat(query[*position..*end].chars().count().max(1))
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SemanticSearchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SemanticSearchError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// For the APIs that still report errors as strings
+impl From<SemanticSearchError> for String {
+    fn from(error: SemanticSearchError) -> Self {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Database, QueryOps};
+
+    #[test]
+    fn test_error_positions() {
+        let Err(error) = QueryOps::parse_query("List, !^") else {
+            panic!("expected a parse error");
+        };
+        assert!(matches!(
+            error,
+            SemanticSearchError::QueryParse { position: 6, .. }
+        ));
+        assert!(error.to_string().ends_with("\n  List, !^\n        ^^"));
+
+        let Err(error) = Database::load_from_str("{\"file_path\": 1}") else {
+            panic!("expected a JSON error");
+        };
+        assert!(matches!(
+            error,
+            SemanticSearchError::JsonParse { path: None, .. }
+        ));
+
+        let Err(error) = Database::load_from_json("/nonexistent/db.json") else {
+            panic!("expected an IO error");
+        };
+        assert_eq!(error.exit_code(), 2);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::data::{flow_id, Database, QueryOps, UnitFlow};
+use serde::Serialize;
+use std::path::Path;
+
+/// A database searched alongside others, and the label its results are
+/// tagged with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    pub label: String,
+    pub data_json: String,
+}
+
+/// A flow matched in one of several databases
+#[derive(Debug, Serialize)]
+pub struct FederatedMatch<'a> {
+    /// Label of the database the flow was found in
+    pub origin: &'a str,
+    /// Labels of the other databases an identical flow was found in
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_in: Vec<&'a str>,
+    pub flow_id: String,
+    pub flow: &'a [UnitFlow],
+}
+
+impl<'a> FederatedMatch<'a> {
+    /// Every database the flow was found in
+    pub fn origins(&self) -> impl Iterator<Item = &'a str> + '_ {
+        std::iter::once(self.origin).chain(self.also_in.iter().copied())
+    }
+}
+
+impl Origin {
+    /// Parse `[<label>=]<data_json_path>`. The label defaults to the file
+    /// stem of the path.
+    pub fn parse(arg: &str) -> Self {
+        match arg.split_once('=') {
+            Some((label, data_json)) if !label.is_empty() => Origin {
+                label: label.to_string(),
+                data_json: data_json.to_string(),
+            },
+            _ => Origin::unlabeled(arg),
+        }
+    }
+
+    pub fn unlabeled(data_json: &str) -> Self {
+        Origin {
+            label: Path::new(data_json)
+                .file_stem()
+                .map_or(data_json.into(), |stem| stem.to_string_lossy())
+                .into_owned(),
+            data_json: data_json.to_string(),
+        }
+    }
+}
+
+/// Flows matching `query` in each database, in database order. Databases
+/// whose label is not in `only` are skipped unless `only` is empty.
+///
+/// A flow structurally identical to one matched in an earlier database, as
+/// when vendored code is indexed twice, is reported once with both origins.
+pub fn search<'a>(
+    databases: &'a [(Origin, Database)],
+    query: &[QueryOps],
+    only: &[String],
+) -> Vec<FederatedMatch<'a>> {
+    databases
+        .iter()
+        .filter(|(origin, _)| only.is_empty() || only.contains(&origin.label))
+        .flat_map(|(origin, db)| {
+            db.data_flows
+                .iter()
+                .filter(|flow| db.match_flow(flow, query))
+                .map(|flow| FederatedMatch {
+                    origin: &origin.label,
+                    also_in: vec![],
+                    flow_id: flow_id(flow),
+                    flow,
+                })
+        })
+        .fold(vec![], collapse_duplicate)
+}
+
+/// Add `m` to `matches`, or record its origin on an earlier match of an
+/// identical flow from another database. Each earlier match absorbs at most
+/// one flow per database, so repeated flows within a database stay distinct.
+fn collapse_duplicate<'a>(
+    mut matches: Vec<FederatedMatch<'a>>,
+    m: FederatedMatch<'a>,
+) -> Vec<FederatedMatch<'a>> {
+    let key = UnitFlow::similarity_key(m.flow);
+    let earlier = matches.iter_mut().find(|earlier| {
+        !earlier.origins().any(|origin| origin == m.origin)
+            && UnitFlow::similarity_key(earlier.flow) == key
+    });
+    match earlier {
+        Some(earlier) => earlier.also_in.push(m.origin),
+        None => matches.push(m),
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tutorial;
+
+    #[test]
+    fn test_federated_search() {
+        assert_eq!(Origin::parse("api=build/api.json").label, "api");
+        assert_eq!(Origin::parse("build/web.json").label, "web");
+        assert_eq!(
+            Origin::parse("=x.json"),
+            Origin {
+                label: "=x".to_string(),
+                data_json: "=x.json".to_string(),
+            }
+        );
+
+        let tutorial = tutorial::load_database();
+        let databases = vec![
+            (Origin::parse("api=api.json"), tutorial::load_database()),
+            (
+                Origin::parse("web=web.json"),
+                Database::new(
+                    tutorial.file_path.clone(),
+                    tutorial.data_flows.to_vec().split_off(4),
+                ),
+            ),
+        ];
+        let query = QueryOps::parse_query("bool").unwrap();
+        let origins: Vec<Vec<_>> = search(&databases, &query, &[])
+            .iter()
+            .map(|m| m.origins().collect())
+            .collect();
+        assert_eq!(origins, vec![vec!["api"], vec!["api", "web"]]);
+
+        let only = search(&databases, &query, &["web".to_string()]);
+        assert_eq!(only.len(), 1);
+        assert!(only
+            .iter()
+            .all(|m| m.origin == "web" && m.also_in.is_empty()));
+    }
+}
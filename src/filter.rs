@@ -0,0 +1,150 @@
+use crate::data::{Database, UnitFlow};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// Ignore files honored by [FileFilter::load_ignore_files], in the order
+/// their patterns apply
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".scsignore"];
+
+/// Glob filters on the files flows pass through. A flow is kept when one of
+/// its files matches an include pattern (or there are none) and none of its
+/// files matches an exclude pattern or is ignored.
+#[derive(Debug, Default)]
+pub struct FileFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// Patterns of the ignore files found by [FileFilter::load_ignore_files]
+    pub ignore: Option<Gitignore>,
+}
+
+impl FileFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.ignore.is_none()
+    }
+
+    /// Also reject the files matched by the [IGNORE_FILES] present in `root`
+    pub fn load_ignore_files(&mut self, root: &Path) -> Result<(), String> {
+        let mut builder = GitignoreBuilder::new(root);
+        for name in IGNORE_FILES {
+            let path = root.join(name);
+            if path.is_file() {
+                if let Some(e) = builder.add(&path) {
+                    return Err(format!("Could not read {}: {}", path.display(), e));
+                }
+            }
+        }
+        let ignore = builder.build().map_err(|e| e.to_string())?;
+        self.ignore = (!ignore.is_empty()).then_some(ignore);
+        Ok(())
+    }
+
+    fn is_ignored(&self, file: &str) -> bool {
+        let Some(ignore) = &self.ignore else {
+            return false;
+        };
+        let path = Path::new(file.trim_start_matches("./"));
+        // Paths outside the ignore files' root can't be matched by them
+        if path.has_root() && !path.starts_with(ignore.path()) {
+            return false;
+        }
+        ignore.matched_path_or_any_parents(path, false).is_ignore()
+    }
+
+    pub fn accepts<'a>(&self, files: impl IntoIterator<Item = &'a str> + Clone) -> bool {
+        let any_matches = |patterns: &[String]| {
+            files
+                .clone()
+                .into_iter()
+                .any(|file| patterns.iter().any(|p| glob_match(p, file)))
+        };
+        (self.include.is_empty() || any_matches(&self.include))
+            && !any_matches(&self.exclude)
+            && !files.into_iter().any(|file| self.is_ignored(file))
+    }
+
+    /// The database restricted to the flows the filter accepts
+    pub fn apply(&self, db: Database) -> Database {
+        if self.is_empty() {
+            return db;
+        }
+        let flows = db
+            .data_flows
+            .iter()
+            .filter(|flow| self.accepts(db.flow_files(flow)))
+            .map(<[UnitFlow]>::to_vec)
+            .collect();
+        db.with_flows(flows)
+    }
+}
+
+/// Match a path against a glob: `?` matches one character and `*` any run of
+/// characters within a path component, `**` matches across components.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.trim_start_matches("./").chars().collect();
+    match_from(&pattern, &path)
+}
+
+fn match_from(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            match_from(rest, path)
+                || (0..path.len()).any(|idx| path[idx] == '/' && match_from(rest, &path[idx + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|idx| match_from(rest, &path[idx..])),
+        ['*', rest @ ..] => {
+            for idx in 0..=path.len() {
+                if match_from(rest, &path[idx..]) {
+                    return true;
+                }
+                if path.get(idx) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        ['?', rest @ ..] => path.first().is_some_and(|c| *c != '/') && match_from(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && match_from(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("src/**", "src/parser/lexer.ml"));
+        assert!(glob_match("**/tests/**", "tests/main.ml"));
+        assert!(glob_match("**/tests/**", "./lib/tests/unit/a.ml"));
+        assert!(glob_match("*.ml", "main.ml"));
+        assert!(!glob_match("*.ml", "src/main.ml"));
+        assert!(glob_match("src/?ain.ml", "src/main.ml"));
+        assert!(!glob_match("src/**", "vendor/src/a.ml"));
+
+        let filter = FileFilter {
+            include: vec!["src/**".to_string()],
+            exclude: vec!["**/tests/**".to_string()],
+            ..FileFilter::default()
+        };
+        assert!(filter.accepts(["src/a.ml"]));
+        assert!(!filter.accepts(["src/a.ml", "src/tests/b.ml"]));
+        assert!(!filter.accepts(["gen/a.ml"]));
+    }
+
+    #[test]
+    fn test_ignored_files() {
+        let mut builder = GitignoreBuilder::new(".");
+        builder.add_line(None, "_build/").unwrap();
+        builder.add_line(None, "*.gen.ml").unwrap();
+        let filter = FileFilter {
+            ignore: Some(builder.build().unwrap()),
+            ..FileFilter::default()
+        };
+        assert!(filter.accepts(["src/a.ml"]));
+        assert!(!filter.accepts(["src/a.ml", "_build/default/a.ml"]));
+        assert!(!filter.accepts(["./src/parser.gen.ml"]));
+        assert!(filter.accepts(["/elsewhere/a.ml"]));
+    }
+}
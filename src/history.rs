@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// An executed query, recorded one JSON object per line in the history file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub data_json: String,
+    pub query: String,
+    /// Number of flows the query matched
+    pub matches: usize,
+}
+
+pub struct History {
+    path: PathBuf,
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl HistoryEntry {
+    pub fn new(data_json: &str, query: &str, matches: usize) -> Self {
+        HistoryEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            data_json: data_json.to_string(),
+            query: query.to_string(),
+            matches,
+        }
+    }
+
+    /// The timestamp as a UTC `YYYY-MM-DD HH:MM` string
+    pub fn time(&self) -> String {
+        let days = (self.timestamp / 86400) as i64;
+        let secs = self.timestamp % 86400;
+
+        // Civil date from days since the epoch, after Howard Hinnant's
+        // `civil_from_days`
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs % 3600 / 60
+        )
+    }
+}
+
+impl History {
+    /// `$SCS_HISTORY`, or `.scs_history` in the home directory
+    pub fn default_path() -> PathBuf {
+        if let Some(path) = env::var_os("SCS_HISTORY") {
+            return PathBuf::from(path);
+        }
+        env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".scs_history")
+    }
+
+    /// Load the history at `path`. A missing file is an empty history, and
+    /// unreadable lines are skipped.
+    pub fn load(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|data| {
+                data.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        History { path, entries }
+    }
+
+    /// Append an entry to the history and its file
+    pub fn record(&mut self, entry: HistoryEntry) -> Result<(), String> {
+        let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| writeln!(file, "{}", line))
+            .map_err(|e| format!("Could not write history {}: {}", self.path.display(), e))?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// The `n`th entry, counting from 1
+    pub fn get(&self, n: usize) -> Option<&HistoryEntry> {
+        n.checked_sub(1).and_then(|idx| self.entries.get(idx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_timestamps() {
+        let mut entry = HistoryEntry::new("db.json", "List", 3);
+        entry.timestamp = 0;
+        assert_eq!(entry.time(), "1970-01-01 00:00");
+        entry.timestamp = 1_709_210_096;
+        assert_eq!(entry.time(), "2024-02-29 12:34");
+    }
+}
@@ -1,33 +1,700 @@
+pub mod baseline;
+pub mod bookmarks;
+pub mod cache;
+pub mod cli;
+pub mod cluster;
+pub mod commits;
+pub mod config;
+pub mod daemon;
 pub mod data;
-use data::QueryOps;
+pub mod engine;
+pub mod error;
+pub mod federated;
+pub mod filter;
+pub mod history;
+pub mod link;
+pub mod logging;
+pub mod lsp;
+pub mod notes;
+pub mod pager;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod query;
+pub mod rank;
+pub mod render;
+pub mod repl;
+pub mod report;
+pub mod reporter;
+pub mod results;
+pub mod rpc;
+pub mod rules;
+pub mod sarif;
+pub mod tui;
+pub mod tutorial;
+pub mod watch;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+use bookmarks::BookmarkAction;
+use clap::error::ErrorKind;
+use clap::ArgMatches;
+use commits::CommitRange;
+use config::{ColorChoice, ConfigFile};
+use data::{
+    HighlightStyle, LengthBounds, LoadLimits, Position, QueryOps, RedactMode, Redaction,
+    DEFAULT_TAB_WIDTH,
+};
+pub use engine::{CancelToken, Match, NamedQuery, SearchEngine};
+pub use error::SemanticSearchError;
+pub use query::Query;
+use federated::Origin;
+use filter::FileFilter;
+pub use rank::{Ranked, Score};
+use report::ReportFormat;
+use results::{SetOp, SortOrder};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use watch::Hooks;
 
+pub enum Command {
+    /// Search the database with a query or rule pack
+    Search,
+    /// Guided tutorial over the bundled example database
+    Tutorial,
+    /// Flows each rule of the rule pack matches in `new_data_json` but not in
+    /// the database, and the other way round
+    Regress { new_data_json: String },
+    /// Flows the query matches in `new_data_json` but not in the database,
+    /// the other way round, and those that only moved
+    Diff { new_data_json: String },
+    /// Flows passing through one source position and then another
+    Connect { from: Position, to: Position },
+    /// Pairs of flows, one matched by the query and the other by
+    /// `other_query`, passing through a common type variable
+    Join {
+        other_query_json: String,
+        other_query: Vec<QueryOps>,
+    },
+    /// Flows passing through a source position
+    At { pos: Position },
+    /// Shortest chain of flows leading from one type to another
+    Path { from: String, to: String },
+    /// Search flows materialized by joining flows on shared type variables
+    Stitch {
+        max_chain: usize,
+        max_stitched: usize,
+    },
+    /// Flows whose descriptions use the words, most relevant first,
+    /// among those the query matches when one is given
+    Text { words: String, count: usize },
+    /// Group matched (or all) flows by structure
+    Cluster,
+    /// Groups of flows identical up to positions and descriptions
+    Dupes,
+    /// The flow whose id starts with `flow`, shown in full
+    ShowFlow { flow: String },
+    /// Why the flow whose id starts with `flow` does or doesn't match the
+    /// query, step by step
+    TraceMatch { flow: String },
+    /// The `count` flows most similar to the flow whose id starts with `flow`
+    SimilarFlows { flow: String, count: usize },
+    /// Propose repairs for program locations with out-of-range or inverted
+    /// ranges, writing the confirmed ones to `out`
+    FixLocs { out: String },
+    /// Rewrite the database in the current schema to `out`
+    Migrate { out: String },
+    /// Write a binary index of the database next to it, loaded in place of
+    /// the JSON while it is newer
+    Index,
+    /// Write the flow whose id starts with `flow` to `out` as a database of
+    /// its own
+    ExportFlow { flow: String, out: String },
+    /// Interactive search, refining the previous result set on request
+    Repl,
+    /// Browse matched flows in a full-screen terminal UI, re-searching as
+    /// the query is edited
+    Tui,
+    /// Compare the flows matched by the query with those matched by another
+    QDiff {
+        other_query_json: String,
+        other_query: Vec<QueryOps>,
+    },
+    /// How each step of the query narrows the matched flows
+    Explain,
+    /// Flow counts and lengths, frequent types and constructors, type
+    /// variable in-degrees and location coverage of the database
+    Stats,
+    /// Type variables by name with the number of flows through each
+    TypeVars,
+    /// Fan-in and fan-out of type variables, connected components and
+    /// central types of the whole-database type graph
+    GraphStats,
+    /// Write the types and constructors linked by type variables across all
+    /// flows as DOT, or GraphML when `out` ends in `.graphml`
+    GraphExport { out: String },
+    /// Attach a note to the flow whose id starts with `flow`
+    Note { flow: String, text: String },
+    /// Manage the flows bookmarked in the database
+    Bookmark { action: BookmarkAction },
+    /// List past queries, or re-run the `rerun`th one
+    History { rerun: Option<usize> },
+    /// Serve searches over a Unix socket, keeping databases loaded
+    Daemon,
+    /// Answer JSON-RPC requests from editor plugins, one per line, on stdin
+    /// and stdout or with `unix` on the `--socket` path
+    Serve { unix: bool },
+    /// Language server publishing the flows each rule of the rule pack
+    /// matches as diagnostics on their source files
+    Lsp,
+    /// Re-render a saved result set, combined with other saved result sets
+    /// and optionally narrowed by the query
+    LoadResults {
+        path: String,
+        set_ops: Vec<(SetOp, String)>,
+    },
+}
 
 pub struct Config {
+    pub command: Command,
     pub data_json: String,
     pub query_json: String,
     pub query: Vec<QueryOps>,
+    /// Path to a TOML or JSON rule pack evaluated instead of a single query
+    pub rules: Option<String>,
+    /// Narrate matched flows step by step instead of listing locations
+    pub narrate: bool,
+    /// Show the concrete type carried at each program location
+    pub elaborate: bool,
+    /// Lines of source shown above and below each program location
+    pub context: usize,
+    /// How the span of each program location is marked
+    pub highlight_style: HighlightStyle,
+    /// Columns between the tab stops tabs in source lines are expanded to
+    pub tab_width: usize,
+    /// Path to save the matched flows to as a result set
+    pub save_results: Option<String>,
+    /// Path to write the matched flows to as a database
+    pub export: Option<String>,
+    /// File of flow ids whose matches are suppressed
+    pub baseline: Option<String>,
+    /// Record the matches in the baseline file instead of reporting them
+    pub update_baseline: bool,
+    /// On no matches, report the longest query prefix that still matched
+    pub partial: bool,
+    /// On no matches, report the single-step relaxations of the query that match
+    pub suggest: bool,
+    /// Show only the best ranked matches
+    pub top: Option<usize>,
+    /// Print the [data::flow_id] of each matched flow
+    pub show_id: bool,
+    /// Show one flow of each group of near-identical matched flows
+    pub cluster: bool,
+    /// The id of the [cluster::near_clusters] group to show every flow of
+    pub expand_cluster: Option<String>,
+    /// Print the steps of each matched flow as an arrow chain
+    pub show_steps: bool,
+    /// Order matched flows are shown in, instead of the search's own
+    pub sort: Option<SortOrder>,
+    /// Print only the number of matched flows
+    pub count: bool,
+    /// Show the flows the query does not match instead of those it does
+    pub invert: bool,
+    /// Show the flows the query does not match, exiting 1 if there are any
+    pub all: bool,
+    /// Flows considered by `invert` and `all`, all of them when not given
+    pub scope: Option<Vec<QueryOps>>,
+    /// Show at most this many matched flows
+    pub limit: Option<usize>,
+    /// Time after which searches stop with the flows matched so far
+    pub timeout: Option<Duration>,
+    /// Matched flows skipped before the ones shown
+    pub offset: usize,
+    /// Glob filters on the files of the flows loaded from the database
+    pub files: FileFilter,
+    /// Keep flows through files matched by `.gitignore` or `.scsignore`
+    pub no_ignore: bool,
+    /// Match names and descriptions ignoring case
+    pub ignore_case: bool,
+    /// Match type steps by their own names only, not the types declared
+    /// their subtypes
+    pub no_hierarchy: bool,
+    /// Keep every copy of structurally identical flows
+    pub keep_duplicates: bool,
+    /// Drop malformed flows while loading instead of failing
+    pub lenient: bool,
+    /// Clamp the out-of-range or inverted ranges of program locations while
+    /// loading
+    pub repair_spans: bool,
+    /// Bounds on the size of the databases loaded, for files from untrusted
+    /// sources
+    pub limits: LoadLimits,
+    /// Bounds on the length of the flows loaded from the database
+    pub lengths: LengthBounds,
+    /// Revision range the flows loaded from the database were extracted in
+    pub commits: CommitRange,
+    /// Print reports as JSON instead of tables
+    pub json: bool,
+    /// Print matched flows as a SARIF log
+    pub sarif: bool,
+    /// Print a `file:line:col: message` line for each location of the
+    /// matched flows, for editors' quickfix lists
+    pub quickfix: bool,
+    /// Print matched flows as a Markdown or HTML report
+    pub report: Option<ReportFormat>,
+    /// Print matched flows as a Graphviz graph of their types, constructor
+    /// arguments and type variables
+    pub dot: bool,
+    /// Cluster the nodes of the Graphviz graph by the files of the flows
+    pub cluster_files: bool,
+    /// Re-run the search whenever the database file changes
+    pub watch: bool,
+    /// Notifications fired when a watched search's matches change
+    pub hooks: Hooks,
+    /// Send the search to a running daemon instead of loading the database
+    pub remote: bool,
+    /// Unix socket the daemon listens on
+    pub socket: PathBuf,
+    /// Further databases searched alongside `data_json`
+    pub origins: Vec<Origin>,
+    /// Labels of the databases to keep results from when searching several
+    pub only: Vec<String>,
+    /// Fields stripped or hashed from exported flows
+    pub redaction: Redaction,
+    /// When to print with colors
+    pub color: ColorChoice,
+    /// Print results straight to a terminal rather than through a pager
+    pub no_pager: bool,
+    /// Print nothing, only exiting with whether the search matched
+    pub quiet: bool,
+    /// Print load, index and match times and the candidates left by
+    /// prefiltering
+    pub verbose: bool,
+    /// How many `-v` were given, raising the level of the logs written to
+    /// stderr
+    pub log_verbosity: u8,
+    /// Defaults read from the config file
+    pub defaults: ConfigFile,
 }
 
 impl Config {
-    pub fn build(args: &[String]) -> Result<Config, &str> {
-        if args.len() < 3 {
-            return Err("Too few arguments! Usage: <data_json_path> <queries_json_path>");
+    /// Parse the command line with [cli::command]. Arguments not starting
+    /// with a subcommand are taken as a `search`, so `<data> <query>` keeps
+    /// working.
+    pub fn build(args: &[String]) -> Result<Config, clap::Error> {
+        let mut args = args.to_vec();
+        if args.get(1).is_some_and(|arg| !cli::is_subcommand(arg)) {
+            args.insert(1, "search".to_string());
         }
+        let matches = cli::command().try_get_matches_from(&args)?;
+        let string = |m: &ArgMatches, id: &str| m.get_one::<String>(id).cloned();
+        let strings = |m: &ArgMatches, id: &str| -> Vec<String> {
+            m.get_many::<String>(id)
+                .map(|values| values.cloned().collect())
+                .unwrap_or_default()
+        };
 
-        let data_json = args[1].clone();
-        let query_json = args[2].clone();
-        let query = match QueryOps::parse_query(&query_json) {
-            Ok(q) => q,
-            Err(e) => {
-                println!("Could not parse query: {}", e);
-                vec![]
-            }
+        let defaults = ConfigFile::load(string(&matches, "config").as_deref().map(Path::new))
+            .map_err(|e| cli::command().error(ErrorKind::ValueValidation, e.to_string()))?;
+        let format = string(&matches, "format").or(defaults.format.clone());
+
+        let lengths = LengthBounds {
+            locs: string(&matches, "len-of").as_deref() == Some("locs"),
+            min: matches.get_one::<usize>("min-len").copied().unwrap_or(0),
+            max: matches.get_one::<usize>("max-len").copied(),
+        };
+        if lengths.max.is_some_and(|max| max < lengths.min) {
+            return Err(cli::command().error(
+                ErrorKind::ValueValidation,
+                "--min-len cannot be above --max-len",
+            ));
+        }
+
+        let mut config = Config {
+            command: Command::Search,
+            data_json: String::new(),
+            query_json: String::new(),
+            query: vec![],
+            rules: None,
+            narrate: matches.get_flag("narrate"),
+            elaborate: matches.get_flag("elaborate"),
+            context: matches
+                .get_one::<usize>("context")
+                .copied()
+                .or(defaults.context)
+                .unwrap_or(0),
+            highlight_style: match string(&matches, "highlight-style").as_deref() {
+                Some("inline") => HighlightStyle::Inline,
+                Some("both") => HighlightStyle::Both,
+                _ => HighlightStyle::Underline,
+            },
+            tab_width: matches
+                .get_one::<usize>("tab-width")
+                .copied()
+                .or(defaults.tab_width)
+                .unwrap_or(DEFAULT_TAB_WIDTH),
+            save_results: None,
+            export: None,
+            baseline: None,
+            update_baseline: false,
+            partial: false,
+            suggest: false,
+            top: None,
+            sort: None,
+            show_id: false,
+            cluster: false,
+            expand_cluster: None,
+            show_steps: false,
+            count: false,
+            invert: false,
+            all: false,
+            scope: None,
+            limit: None,
+            timeout: None,
+            offset: 0,
+            files: FileFilter {
+                include: strings(&matches, "include"),
+                exclude: strings(&matches, "exclude"),
+                ..FileFilter::default()
+            },
+            no_ignore: matches.get_flag("no-ignore"),
+            ignore_case: matches.get_flag("ignore-case"),
+            no_hierarchy: matches.get_flag("no-hierarchy"),
+            keep_duplicates: matches.get_flag("keep-duplicates"),
+            lenient: matches.get_flag("lenient"),
+            repair_spans: matches.get_flag("repair-spans"),
+            limits: LoadLimits {
+                max_file_size: matches.get_one::<u64>("max-file-size").copied(),
+                max_flows: matches.get_one::<usize>("max-flows").copied(),
+                max_flow_len: matches.get_one::<usize>("max-flow-len").copied(),
+                max_string_len: matches.get_one::<usize>("max-string-len").copied(),
+            },
+            lengths,
+            commits: CommitRange {
+                since: string(&matches, "since"),
+                until: string(&matches, "until"),
+            },
+            json: matches.get_flag("json") || format.as_deref() == Some("json"),
+            sarif: format.as_deref() == Some("sarif"),
+            quickfix: format.as_deref() == Some("quickfix"),
+            report: format.as_deref().and_then(ReportFormat::parse),
+            dot: format.as_deref() == Some("dot"),
+            cluster_files: matches.get_flag("cluster-files"),
+            watch: false,
+            hooks: Hooks::default(),
+            remote: false,
+            socket: string(&matches, "socket")
+                .map_or_else(daemon::default_socket_path, PathBuf::from),
+            origins: vec![],
+            only: vec![],
+            redaction: Redaction::default(),
+            color: if matches.get_flag("no-color") {
+                ColorChoice::Never
+            } else {
+                string(&matches, "color")
+                    .and_then(|when| ColorChoice::parse(&when))
+                    .or(defaults.color)
+                    .unwrap_or_default()
+            },
+            no_pager: matches.get_flag("no-pager"),
+            quiet: matches.get_flag("quiet"),
+            verbose: false,
+            log_verbosity: matches.get_count("log-verbosity"),
+            defaults,
+        };
+
+        let Some((name, sub)) = matches.subcommand() else {
+            return Err(missing("a subcommand"));
         };
+        let data = |id: &str| string(sub, id).unwrap_or_default();
+        if let Ok(Some(data_json)) = sub.try_get_one::<String>("data") {
+            config.data_json = data_json.clone();
+        }
+        match name {
+            "search" | "explain" => {
+                let mut query = string(sub, "query").or(string(sub, "query-flag"));
+                let mut data_json = string(sub, "data").or(string(sub, "data-flag"));
+                if config.defaults.data.is_some() && query.is_none() && only_query(sub) {
+                    query = string(sub, "data");
+                    data_json = string(sub, "data-flag");
+                }
+                config.data_json = data_json
+                    .or(config.defaults.data.clone())
+                    .ok_or_else(|| missing("<DATA>"))?;
+                config.set_query(query.as_ref())?;
+                if name == "explain" {
+                    if query.is_none() {
+                        return Err(missing("<QUERY>"));
+                    }
+                    config.command = Command::Explain;
+                    config.partial = true;
+                    config.suggest = true;
+                    return Ok(config);
+                }
 
-        Ok(Config {
-            data_json,
-            query_json,
-            query,
-        })
+                config.rules = string(sub, "rules");
+                config.partial = sub.get_flag("partial");
+                config.suggest = sub.get_flag("suggest");
+                config.top = sub.get_one::<usize>("top").copied();
+                config.sort = string(sub, "sort").as_deref().and_then(SortOrder::parse);
+                config.show_id = sub.get_flag("show-id");
+                config.expand_cluster = string(sub, "expand-cluster");
+                config.cluster = sub.get_flag("cluster") || config.expand_cluster.is_some();
+                config.show_steps = sub.get_flag("show-steps");
+                config.count = sub.get_flag("count");
+                config.invert = sub.get_flag("invert");
+                config.all = sub.get_flag("all");
+                config.scope = string(sub, "scope")
+                    .map(|scope| parse_query_arg(&scope))
+                    .transpose()?;
+                config.limit = sub.get_one::<usize>("limit").copied();
+                config.timeout = sub
+                    .get_one::<u64>("timeout")
+                    .copied()
+                    .map(Duration::from_secs);
+                config.offset = sub.get_one::<usize>("offset").copied().unwrap_or(0);
+                config.save_results = string(sub, "save-results");
+                config.export = string(sub, "export");
+                config.baseline = string(sub, "baseline");
+                config.update_baseline = sub.get_flag("update-baseline");
+                if config.update_baseline && config.baseline.is_none() {
+                    return Err(missing("--baseline"));
+                }
+                config.watch = sub.get_flag("watch");
+                config.hooks.command = string(sub, "on-change");
+                config.hooks.webhook = string(sub, "webhook");
+                config.remote = sub.get_flag("remote");
+                config.origins = sub
+                    .get_many::<Origin>("db")
+                    .map(|origins| origins.cloned().collect())
+                    .unwrap_or_default();
+                config.only = strings(sub, "only");
+                config.verbose = sub.get_flag("verbose");
+                if let Some(flow) = string(sub, "id") {
+                    config.command = Command::ShowFlow { flow };
+                } else if let Some(flow) = string(sub, "trace-match") {
+                    if query.is_none() {
+                        return Err(missing("<QUERY>"));
+                    }
+                    config.command = Command::TraceMatch { flow };
+                } else if let Some(flow) = string(sub, "like") {
+                    config.command = Command::SimilarFlows {
+                        flow,
+                        count: config.top.unwrap_or(10),
+                    };
+                } else if sub.get_flag("repl") {
+                    config.command = Command::Repl;
+                } else if sub.get_flag("tui") {
+                    config.command = Command::Tui;
+                } else if query.is_none() && config.rules.is_none() {
+                    return Err(missing("<QUERY>"));
+                }
+            }
+            "index" => config.command = Command::Index,
+            "stats" => config.command = Command::Stats,
+            "graph-stats" => config.command = Command::GraphStats,
+            "typevars" => config.command = Command::TypeVars,
+            "tutorial" => config.command = Command::Tutorial,
+            "daemon" => config.command = Command::Daemon,
+            "serve" => {
+                config.command = Command::Serve {
+                    unix: sub.get_flag("unix"),
+                }
+            }
+            "lsp" => {
+                config.command = Command::Lsp;
+                config.rules = string(sub, "rules");
+            }
+            "regress" => {
+                config.command = Command::Regress {
+                    new_data_json: data("new"),
+                };
+                config.data_json = data("old");
+                config.rules = string(sub, "rules");
+            }
+            "diff" => {
+                config.command = Command::Diff {
+                    new_data_json: data("new"),
+                };
+                config.data_json = data("old");
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            "connect" => {
+                config.command = Command::Connect {
+                    from: position(sub, "from"),
+                    to: position(sub, "to"),
+                }
+            }
+            "join" => {
+                let other_query_json = data("other-query");
+                config.command = Command::Join {
+                    other_query: parse_query_arg(&other_query_json)?,
+                    other_query_json,
+                };
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            "at" => {
+                config.command = Command::At {
+                    pos: position(sub, "pos"),
+                }
+            }
+            "path" => {
+                config.command = Command::Path {
+                    from: data("from"),
+                    to: data("to"),
+                }
+            }
+            "stitch" | "cluster" | "dupes" => {
+                config.command = match name {
+                    "stitch" => Command::Stitch {
+                        max_chain: count(sub, "max-chain"),
+                        max_stitched: count(sub, "max-stitched"),
+                    },
+                    "cluster" => Command::Cluster,
+                    _ => Command::Dupes,
+                };
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            "text" => {
+                config.command = Command::Text {
+                    words: data("words"),
+                    count: count(sub, "count"),
+                };
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            "similar-flows" => {
+                config.command = Command::SimilarFlows {
+                    flow: data("flow"),
+                    count: count(sub, "count"),
+                }
+            }
+            "fix-locs" => {
+                config.command = Command::FixLocs {
+                    out: string(sub, "out").unwrap_or_else(|| data("data")),
+                }
+            }
+            "migrate" => {
+                config.command = Command::Migrate {
+                    out: string(sub, "out").unwrap_or_else(|| data("data")),
+                }
+            }
+            "export-flow" => {
+                config.command = Command::ExportFlow {
+                    flow: data("flow"),
+                    out: data("out"),
+                };
+                for (id, mode) in [("redact", RedactMode::Strip), ("hash", RedactMode::Hash)] {
+                    for fields in strings(sub, id) {
+                        config.redaction.add(&fields, mode).map_err(|_| {
+                            cli::command().error(
+                                ErrorKind::ValueValidation,
+                                "Invalid <FIELDS>, expected descs, lines or files",
+                            )
+                        })?;
+                    }
+                }
+            }
+            "repl" => config.command = Command::Repl,
+            "qdiff" => {
+                let other_query_json = data("other-query");
+                config.command = Command::QDiff {
+                    other_query: parse_query_arg(&other_query_json)?,
+                    other_query_json,
+                };
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            "graph-export" => config.command = Command::GraphExport { out: data("out") },
+            "note" => {
+                config.command = Command::Note {
+                    flow: data("flow"),
+                    text: data("text"),
+                }
+            }
+            "bookmark" => {
+                let Some((action, sub)) = sub.subcommand() else {
+                    return Err(missing("add, remove or list"));
+                };
+                config.data_json = string(sub, "data").unwrap_or_default();
+                let flow = sub.try_get_one::<String>("flow").ok().flatten();
+                let flow = flow.cloned().unwrap_or_default();
+                let action = match action {
+                    "add" => BookmarkAction::Add(flow),
+                    "remove" => BookmarkAction::Remove(flow),
+                    _ => BookmarkAction::List,
+                };
+                config.command = Command::Bookmark { action };
+            }
+            "history" => {
+                config.command = Command::History {
+                    rerun: sub.get_one::<usize>("n").copied(),
+                }
+            }
+            "load-results" => {
+                let mut set_ops = vec![];
+                for (id, op) in [
+                    ("union", SetOp::Union),
+                    ("intersect", SetOp::Intersect),
+                    ("minus", SetOp::Minus),
+                ] {
+                    let positions = sub.indices_of(id).into_iter().flatten();
+                    set_ops.extend(
+                        positions
+                            .zip(strings(sub, id))
+                            .map(|(at, path)| (at, op, path)),
+                    );
+                }
+                set_ops.sort_by_key(|(at, _, _)| *at);
+                config.command = Command::LoadResults {
+                    path: data("path"),
+                    set_ops: set_ops
+                        .into_iter()
+                        .map(|(_, op, path)| (op, path))
+                        .collect(),
+                };
+                config.set_query(string(sub, "query").as_ref())?;
+            }
+            _ => return Err(missing("a subcommand")),
+        }
+
+        Ok(config)
+    }
+
+    fn set_query(&mut self, query_json: Option<&String>) -> Result<(), clap::Error> {
+        if let Some(query_json) = query_json {
+            let query_json = QueryOps::expand_aliases(query_json, &self.defaults.aliases)
+                .map_err(|e| cli::command().error(ErrorKind::ValueValidation, e))?;
+            self.query = parse_query_arg(&query_json)?;
+            self.query_json = query_json;
+        }
+        Ok(())
     }
 }
+
+/// Whether nothing but a query was given to a search, so that a lone
+/// positional argument is the query and the database is the config file's
+fn only_query(sub: &ArgMatches) -> bool {
+    let given = |id: &str| sub.try_get_one::<String>(id).ok().flatten().is_some();
+    let set = |id: &str| sub.try_get_one::<bool>(id).ok().flatten() == Some(&true);
+    !["data-flag", "rules", "id", "like"].into_iter().any(given) && !set("repl") && !set("tui")
+}
+
+fn missing(what: &str) -> clap::Error {
+    cli::command().error(
+        ErrorKind::MissingRequiredArgument,
+        format!("Missing {}", what),
+    )
+}
+
+fn position(matches: &ArgMatches, id: &str) -> Position {
+    matches
+        .get_one::<Position>(id)
+        .cloned()
+        .expect("positions are required")
+}
+
+fn count(matches: &ArgMatches, id: &str) -> usize {
+    *matches.get_one::<usize>(id).expect("counts have defaults")
+}
+
+fn parse_query_arg(query_json: &str) -> Result<Vec<QueryOps>, clap::Error> {
+    QueryOps::parse_query(query_json)
+        .map_err(|e| cli::command().error(ErrorKind::ValueValidation, e))
+}
@@ -0,0 +1,103 @@
+use crate::data::Position;
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// `TERM_PROGRAM`s of terminals known to open OSC 8 hyperlinks
+const HYPERLINK_TERMINALS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"];
+
+/// Whether the terminal stdout is connected to opens OSC 8 hyperlinks, as
+/// told by the environment. `FORCE_HYPERLINK` overrides the guess either
+/// way.
+pub fn terminal_supports_hyperlinks() -> bool {
+    supports_hyperlinks(|name| env::var(name).ok())
+}
+
+/// [terminal_supports_hyperlinks] reading variables through `var`
+pub fn supports_hyperlinks(var: impl Fn(&str) -> Option<String>) -> bool {
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if var("TERM").as_deref() == Some("dumb") {
+        return false;
+    }
+    var("TERM_PROGRAM").is_some_and(|program| HYPERLINK_TERMINALS.contains(&program.as_str()))
+        || var("VTE_VERSION")
+            .and_then(|version| version.parse::<u32>().ok())
+            .is_some_and(|version| version >= 5000)
+        || ["WT_SESSION", "KONSOLE_VERSION", "DOMTERM"]
+            .iter()
+            .any(|name| var(name).is_some())
+}
+
+/// `file:line:col`, the form terminals and editors take as a location
+pub fn location(pos: &Position) -> String {
+    match pos.column {
+        Some(column) => format!("{}:{}:{}", pos.file, pos.line, column),
+        None => format!("{}:{}", pos.file, pos.line),
+    }
+}
+
+/// `text` as an OSC 8 hyperlink to the file of `pos`, opening it in the
+/// editor the terminal hands `file://` URLs to
+pub fn hyperlink(text: &str, pos: &Position) -> String {
+    format!(
+        "\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\",
+        file_url(Path::new(&pos.file)),
+        text
+    )
+}
+
+/// A `file://` URL for `path`, made absolute against the working directory
+fn file_url(path: &Path) -> String {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        env::current_dir().unwrap_or_default().join(path)
+    };
+    let path: PathBuf = path.components().collect();
+    let mut url = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url += &format!("%{:02X}", byte),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_links() {
+        let pos = Position {
+            file: "/src/my lib.ml".to_string(),
+            line: 3,
+            column: Some(5),
+        };
+        assert_eq!(location(&pos), "/src/my lib.ml:3:5");
+        assert_eq!(
+            hyperlink("here", &pos),
+            "\x1b]8;;file:///src/my%20lib.ml\x1b\\here\x1b]8;;\x1b\\"
+        );
+
+        let env = |vars: &'static [(&str, &str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        assert!(supports_hyperlinks(env(&[("TERM_PROGRAM", "WezTerm")])));
+        assert!(supports_hyperlinks(env(&[("VTE_VERSION", "6003")])));
+        assert!(!supports_hyperlinks(env(&[("VTE_VERSION", "4600")])));
+        assert!(!supports_hyperlinks(env(&[("TERM", "xterm-256color")])));
+        assert!(!supports_hyperlinks(env(&[
+            ("FORCE_HYPERLINK", "0"),
+            ("WT_SESSION", "1")
+        ])));
+    }
+}
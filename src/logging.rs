@@ -0,0 +1,40 @@
+use tracing_subscriber::EnvFilter;
+
+/// What is logged for `-v` given `verbosity` times when `RUST_LOG` is not
+/// set: this crate's events at the level asked for, and only warnings from
+/// its dependencies
+pub fn default_directives(verbosity: u8) -> String {
+    let level = match verbosity {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    format!("warn,{}={}", env!("CARGO_CRATE_NAME"), level)
+}
+
+/// Send the logs of loading, indexing and searching to stderr, one event per
+/// line, so that they stay apart from results and protocol messages on
+/// stdout. `RUST_LOG` picks what is logged when set, and `-v` otherwise.
+/// Only the first call has any effect.
+pub fn init(verbosity: u8, ansi: bool) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_directives(verbosity)));
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(ansi)
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_directives() {
+        assert_eq!(default_directives(0), "warn,semantic_code_search=warn");
+        assert_eq!(default_directives(2), "warn,semantic_code_search=debug");
+        assert_eq!(default_directives(9), "warn,semantic_code_search=trace");
+    }
+}
@@ -0,0 +1,433 @@
+use crate::daemon::Daemon;
+use crate::data::{flow_id, Database, ProgLoc, UnitFlow};
+use crate::rpc::{RpcResponse, METHOD_NOT_FOUND};
+use crate::rules::{RulePack, RuleResult, Severity};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// LSP `DiagnosticSeverity` of the later steps of a matched flow
+const HINT: u8 = 4;
+
+/// Read a message framed by a `Content-Length` header, or `None` at the end
+/// of the input
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut len = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            len = value.trim().parse::<usize>().ok();
+        }
+    }
+    let len = len.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Message without a Content-Length",
+        )
+    })?;
+    let mut data = vec![0; len];
+    reader.read_exact(&mut data)?;
+    Ok(Some(serde_json::from_slice(&data)?))
+}
+
+/// Write `message` as [read_message] reads it
+pub fn write_message<T: Serialize>(writer: &mut impl Write, message: &T) -> io::Result<()> {
+    let data = serde_json::to_vec(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n", data.len())?;
+    writer.write_all(&data)?;
+    writer.flush()
+}
+
+#[derive(Debug, Serialize)]
+struct Notification<T> {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: T,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LspPosition {
+    /// 0-based
+    pub line: usize,
+    /// 0-based, in the units of the [PositionEncoding] agreed on
+    pub character: usize,
+}
+
+/// What the `character` of an [LspPosition] counts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PositionEncoding {
+    /// UTF-16 code units, which every editor understands
+    #[default]
+    Utf16,
+    /// Characters, for editors offering `utf-32` in `initialize`
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// The encoding to use with an editor sending `capabilities` in
+    /// `initialize`
+    fn negotiate(capabilities: &Value) -> Self {
+        let offered = capabilities["general"]["positionEncodings"].as_array();
+        if offered.is_some_and(|offered| offered.iter().any(|e| e == "utf-32")) {
+            PositionEncoding::Utf32
+        } else {
+            PositionEncoding::Utf16
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PositionEncoding::Utf16 => "utf-16",
+            PositionEncoding::Utf32 => "utf-32",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    /// 1 error, 2 warning, 3 information, 4 hint
+    pub severity: u8,
+    /// Name of the rule that matched
+    pub code: String,
+    pub source: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PublishDiagnosticsParams<'a> {
+    uri: String,
+    diagnostics: &'a [Diagnostic],
+}
+
+/// Diagnostics for the flows each rule matched, by source file, with
+/// positions in `encoding`. A flow is reported at its first program location
+/// with the rule's severity and at each later one as a hint.
+pub fn diagnostics(
+    db: &Database,
+    results: &[RuleResult],
+    encoding: PositionEncoding,
+) -> BTreeMap<String, Vec<Diagnostic>> {
+    let mut files: BTreeMap<String, Vec<Diagnostic>> = BTreeMap::new();
+    for result in results {
+        let message = result
+            .rule
+            .description
+            .as_deref()
+            .unwrap_or(&result.rule.name);
+        for flow in &result.flows {
+            let id = flow_id(flow);
+            let locations = flow.iter().filter_map(|uf| match uf {
+                UnitFlow::ProgLoc(loc) => {
+                    Some((loc.position(&db.file_path)?, columns(loc, encoding)))
+                }
+                _ => None,
+            });
+            for (step, (position, (start, end))) in locations.enumerate() {
+                let line = position.line.saturating_sub(1);
+                let (severity, text) = match step {
+                    0 => (
+                        severity(result.rule.severity),
+                        format!("{} (flow {})", message, &id[..8.min(id.len())]),
+                    ),
+                    _ => (
+                        HINT,
+                        format!(
+                            "Step {} of a flow matching `{}`",
+                            step + 1,
+                            result.rule.name
+                        ),
+                    ),
+                };
+                files.entry(position.file).or_default().push(Diagnostic {
+                    range: Range {
+                        start: LspPosition {
+                            line,
+                            character: start,
+                        },
+                        end: LspPosition {
+                            line,
+                            character: end,
+                        },
+                    },
+                    severity,
+                    code: result.rule.name.clone(),
+                    source: "semantic-code-search",
+                    message: text,
+                });
+            }
+        }
+    }
+    files
+}
+
+/// 0-based start and exclusive end of the span of `loc` in `encoding`. The
+/// text before the span is only known when the line is the whole source
+/// line; otherwise the span starts at its `column`, counted in characters.
+fn columns(loc: &ProgLoc, encoding: PositionEncoding) -> (usize, usize) {
+    let (start, end) = loc.columns();
+    let (start, end) = (start.saturating_sub(1), end.saturating_sub(1));
+    let utf16 = |text: &str| text.encode_utf16().count();
+    match (encoding, loc.split_span()) {
+        (PositionEncoding::Utf16, Some((before, span, _))) => {
+            let start = match loc.column() {
+                Some(_) => start,
+                None => utf16(before),
+            };
+            (start, start + utf16(span))
+        }
+        _ => (start, end),
+    }
+}
+
+fn severity(severity: Severity) -> u8 {
+    match severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Info => 3,
+    }
+}
+
+/// A language server publishing the matches of a rule pack as diagnostics.
+/// The database and rules are re-read whenever the editor saves a file, the
+/// database only when it changed.
+pub struct LanguageServer {
+    data_json: String,
+    rules: String,
+    /// Directory source files named in the database are relative to, the
+    /// workspace root once the editor sends it
+    root: PathBuf,
+    daemon: Daemon,
+    /// Files diagnostics were last published for, cleared when they no longer
+    /// have any
+    published: BTreeSet<String>,
+    encoding: PositionEncoding,
+}
+
+impl LanguageServer {
+    pub fn new(data_json: &str, rules: &str, root: PathBuf) -> Self {
+        LanguageServer {
+            data_json: data_json.to_string(),
+            rules: rules.to_string(),
+            root,
+            daemon: Daemon::default(),
+            published: BTreeSet::new(),
+            encoding: PositionEncoding::default(),
+        }
+    }
+
+    /// Answer messages from `reader` until the editor sends `exit`
+    pub fn serve(&mut self, mut reader: impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+        while let Some(message) = read_message(&mut reader)? {
+            let method = message["method"].as_str().unwrap_or_default();
+            let id = message.get("id").cloned();
+            match (method, id) {
+                ("initialize", Some(id)) => {
+                    let params = &message["params"];
+                    if let Some(root) = params["rootUri"].as_str().and_then(uri_path) {
+                        self.root = root;
+                    }
+                    self.encoding = PositionEncoding::negotiate(&params["capabilities"]);
+                    let capabilities = serde_json::json!({
+                        "capabilities": {
+                            "positionEncoding": self.encoding.name(),
+                            "textDocumentSync": {"openClose": true, "save": true}
+                        },
+                        "serverInfo": {"name": "semantic-code-search"}
+                    });
+                    write_message(writer, &RpcResponse::success(id, capabilities))?;
+                }
+                ("initialized" | "textDocument/didSave", None) => self.publish(writer)?,
+                ("shutdown", Some(id)) => {
+                    write_message(writer, &RpcResponse::success(id, Value::Null))?
+                }
+                ("exit", None) => break,
+                (_, Some(id)) => write_message(
+                    writer,
+                    &RpcResponse::error(id, METHOD_NOT_FOUND, format!("Unknown method {}", method)),
+                )?,
+                (_, None) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate the rules and publish the diagnostics of every file, or show
+    /// why they could not be evaluated
+    fn publish(&mut self, writer: &mut impl Write) -> io::Result<()> {
+        let rules = match RulePack::load(&self.rules) {
+            Ok(rules) => rules,
+            Err(e) => return show_error(writer, e),
+        };
+        let db = match self.daemon.database(&self.data_json) {
+            Ok(db) => db,
+            Err(e) => return show_error(writer, e),
+        };
+        let files = diagnostics(db, &rules.evaluate(db), self.encoding);
+
+        for file in self.published.difference(&files.keys().cloned().collect()) {
+            publish_diagnostics(writer, uri(&self.root, file), &[])?;
+        }
+        for (file, diagnostics) in &files {
+            publish_diagnostics(writer, uri(&self.root, file), diagnostics)?;
+        }
+        self.published = files.into_keys().collect();
+        Ok(())
+    }
+}
+
+fn publish_diagnostics(
+    writer: &mut impl Write,
+    uri: String,
+    diagnostics: &[Diagnostic],
+) -> io::Result<()> {
+    write_message(
+        writer,
+        &Notification {
+            jsonrpc: "2.0",
+            method: "textDocument/publishDiagnostics",
+            params: PublishDiagnosticsParams { uri, diagnostics },
+        },
+    )
+}
+
+fn show_error(writer: &mut impl Write, message: String) -> io::Result<()> {
+    write_message(
+        writer,
+        &Notification {
+            jsonrpc: "2.0",
+            method: "window/showMessage",
+            params: serde_json::json!({"type": 1, "message": message}),
+        },
+    )
+}
+
+/// `file://` URI of `file` resolved against `root`, percent-encoding the
+/// bytes of the path that may not appear in a URI as they are
+fn uri(root: &Path, file: &str) -> String {
+    let path = root.join(file);
+    let mut uri = "file://".to_string();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// The path of a `file://` URI, with its percent-encoded bytes decoded, or
+/// `None` for other URIs
+fn uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // The host is empty or `localhost` for local files
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    let mut bytes = vec![];
+    let mut input = path.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'%' => {
+                let hex = [input.next()?, input.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            _ => bytes.push(byte),
+        }
+    }
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let data = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ProgLoc": {"line": "let x = 1", "char_range": [5, 6], "desc": null, "depth": 0, "line_number": 3}},
+                {"ProgLoc": {"line": "print x", "char_range": [7, 8], "desc": null, "depth": 0, "file": "lib/out.ml", "line_number": 9}},
+                {"ProgLoc": {"line": "no line number", "char_range": [1, 3], "desc": null, "depth": 0}}
+            ]]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let rules =
+            RulePack::parse("[[rule]]\nname = \"ints\"\nquery = \"int\"\nseverity = \"error\"\n")
+                .unwrap();
+        let files = diagnostics(&db, &rules.evaluate(&db), PositionEncoding::Utf16);
+        assert_eq!(files.len(), 2);
+
+        let main = &files["lib/main.ml"];
+        assert_eq!(main.len(), 1);
+        assert_eq!((main[0].severity, main[0].code.as_str()), (1, "ints"));
+        assert_eq!(
+            main[0].range.start,
+            LspPosition {
+                line: 2,
+                character: 4
+            }
+        );
+        assert_eq!(main[0].range.end.character, 5);
+        assert_eq!(files["lib/out.ml"][0].severity, HINT);
+
+        // `é` is one character, and `𝑥` two UTF-16 code units
+        let loc = ProgLoc::new("é = 𝑥 + 1", (5, 6));
+        assert_eq!(columns(&loc, PositionEncoding::Utf32), (4, 5));
+        assert_eq!(columns(&loc, PositionEncoding::Utf16), (4, 6));
+        let loc = ProgLoc::new("𝑥 + 1", (3, 4));
+        assert_eq!(columns(&loc, PositionEncoding::Utf16), (3, 4));
+
+        let utf32 = serde_json::json!({"general": {"positionEncodings": ["utf-16", "utf-32"]}});
+        assert_eq!(PositionEncoding::negotiate(&utf32), PositionEncoding::Utf32);
+        let utf16 = serde_json::json!({});
+        assert_eq!(PositionEncoding::negotiate(&utf16), PositionEncoding::Utf16);
+    }
+
+    #[test]
+    fn test_uris() {
+        let root = Path::new("/home/me/my project");
+        let uri = uri(root, "src/naïve#1.ml");
+        assert_eq!(uri, "file:///home/me/my%20project/src/na%C3%AFve%231.ml");
+        assert_eq!(
+            uri_path(&uri).unwrap(),
+            Path::new("/home/me/my project/src/naïve#1.ml")
+        );
+        assert_eq!(
+            uri_path("file://localhost/tmp/a%20b").unwrap(),
+            Path::new("/tmp/a b")
+        );
+        assert!(uri_path("untitled:Untitled-1").is_none());
+    }
+
+    #[test]
+    fn test_framing() {
+        let mut buffer = vec![];
+        write_message(&mut buffer, &serde_json::json!({"id": 1})).unwrap();
+        write_message(&mut buffer, &serde_json::json!({"id": 2})).unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 8\r\n\r\n"));
+
+        let mut reader = buffer.as_slice();
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["id"], 1);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["id"], 2);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
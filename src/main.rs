@@ -1,61 +1,2075 @@
+// The command line needs an operating system; the wasm32 build is the library
+#![cfg_attr(target_arch = "wasm32", no_main)]
+#![cfg(not(target_arch = "wasm32"))]
+
 use colored::*;
-use semantic_code_search::data::{Database, ProgLoc, QueryOps, UnitFlow};
-use semantic_code_search::Config;
+use notify::{Event, RecursiveMode, Watcher};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use semantic_code_search::baseline::Baseline;
+use semantic_code_search::bookmarks::{BookmarkAction, Bookmarks};
+use semantic_code_search::cluster::{cluster_flows, near_clusters, Cluster};
+use semantic_code_search::daemon::{self, Daemon, Request, Response};
+use semantic_code_search::data::{
+    flow_id, set_load_limits, Database, DatabaseStats, GraphStats, Highlight, MatchDiff,
+    MatchTrace, NodeKind, NodeStats, ProgLoc, QueryOps, StepOutcome, UnitFlow,
+};
+use semantic_code_search::error::ERROR_EXIT_CODE;
+use semantic_code_search::federated::{self, FederatedMatch, Origin};
+use semantic_code_search::history::{History, HistoryEntry};
+use semantic_code_search::link;
+use semantic_code_search::logging;
+use semantic_code_search::lsp::LanguageServer;
+use semantic_code_search::notes::Notes;
+use semantic_code_search::pager::Output;
+use semantic_code_search::render::{RenderOptions, Renderer};
+use semantic_code_search::repl::{self, Session};
+use semantic_code_search::report::Report;
+use semantic_code_search::reporter::{self, Reporter};
+use semantic_code_search::results::ResultSet;
+use semantic_code_search::rpc::Server;
+use semantic_code_search::rules::{Regression, RulePack, RuleResult, Severity};
+use semantic_code_search::sarif::SarifLog;
+#[cfg(feature = "tui")]
+use semantic_code_search::tui;
+use semantic_code_search::tutorial;
+use semantic_code_search::watch::MatchChange;
+use semantic_code_search::{CancelToken, Command, Config, SearchEngine, SemanticSearchError};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// How flows are printed, set from `--context`, `--show-id`, `--show-steps`
+/// and `--highlight-style`, with hyperlinks when printing colors to a
+/// terminal that opens them
+static RENDER_OPTIONS: OnceLock<RenderOptions> = OnceLock::new();
 
 fn main() {
-    let config = Config::build(&std::env::args().collect::<Vec<String>>())
-        .expect("Failed to build configuration");
+    let mut config =
+        Config::build(&std::env::args().collect::<Vec<String>>()).unwrap_or_else(|e| e.exit());
+    let color = config.color.use_color();
+    colored::control::set_override(color);
+    logging::init(config.log_verbosity, color);
+    set_load_limits(config.limits);
+    RENDER_OPTIONS.get_or_init(|| RenderOptions {
+        hyperlinks: color && link::terminal_supports_hyperlinks(),
+        ..RenderOptions::from_config(&config)
+    });
+    if !config.no_ignore {
+        if let Err(e) = config.files.load_ignore_files(Path::new(".")) {
+            tracing::warn!("{}", e);
+        }
+    }
 
-    let db = Database::load_from_json(&config.data_json);
-    let query = config.query;
-    let results = search_dataflows(&db, &query);
-    println!("\n{}", "━".repeat(80).bright_black());
-    if results.is_empty() {
-        println!("{}", "No data flows matched the query.\n".bright_red());
-    } else {
+    if let Command::Tutorial = config.command {
+        run_tutorial();
+        return;
+    }
+
+    if let Command::History { rerun } = config.command {
+        let mut history = History::load(History::default_path());
+        let Some(n) = rerun else {
+            for (idx, entry) in history.entries.iter().enumerate() {
+                println!(
+                    "{} {}  {}  {}  {}",
+                    format!("{:>4}", idx + 1).bright_blue(),
+                    entry.time().bright_black(),
+                    format!("{:>5} matches", entry.matches).bright_black(),
+                    entry.data_json,
+                    entry.query
+                );
+            }
+            return;
+        };
+
+        let Some(entry) = history.get(n).cloned() else {
+            fail(&format!("No history entry {}", n));
+        };
+        let db = load_database(&config, &entry.data_json);
+        let query = QueryOps::parse_query(&entry.query).unwrap_or_else(|e| exit_with(e));
+        let results = db.search(&query);
+        record_history(&mut history, &entry.data_json, &entry.query, results.len());
+        if results.is_empty() {
+            explain_no_matches(&config, &db, &entry.query, &query);
+        }
+        print_search_results(&config, &db, &entry.data_json, &entry.query, &results);
+        exit_with_match_status(!results.is_empty());
+    }
+
+    if let Command::LoadResults { path, set_ops } = &config.command {
+        let mut result_set = ResultSet::load(path).unwrap_or_else(|e| fail(&e));
+        for (op, other) in set_ops {
+            let other = ResultSet::load(other).unwrap_or_else(|e| fail(&e));
+            result_set = result_set.apply(*op, &other);
+        }
+
+        let db = load_database(&config, &result_set.data_json);
+        let results: Vec<_> = result_set
+            .flows(&db)
+            .into_iter()
+            .filter(|flow| db.match_flow(flow, &config.query))
+            .collect();
+        let query = match config.query_json.as_str() {
+            "" => result_set.query.clone(),
+            refinement => format!("({}) & ({})", result_set.query, refinement),
+        };
+        print_search_results(&config, &db, &result_set.data_json, &query, &results);
+        exit_with_match_status(!results.is_empty());
+    }
+
+    if let Command::FixLocs { out } = &config.command {
+        // Repair the whole database, not just the flows the filters keep
+        let db = Database::load_with_duplicates(&config.data_json).unwrap_or_else(|e| exit_with(e));
+        fix_locations(db, out);
+        return;
+    }
+
+    if let Command::Migrate { out } = &config.command {
+        let migration = Database::migrate(&config.data_json, out).unwrap_or_else(|e| exit_with(e));
+        if !config.quiet {
+            println!(
+                "{} {} flows from schema {} to {} in {}",
+                "Migrated".bright_blue(),
+                migration.flows,
+                migration.from,
+                migration.to,
+                out
+            );
+        }
+        return;
+    }
+
+    if let Command::Index = config.command {
+        let db = Database::read_data(&config.data_json)
+            .and_then(|data| Database::load_from_str(&data))
+            .unwrap_or_else(|e| exit_with(e));
+        let path = Database::index_path(&config.data_json);
+        db.write_index(&path).unwrap_or_else(|e| fail(&e));
         println!(
-            "{} {}\n",
-            "Matched data flows:".bright_blue(),
-            results.len()
+            "{} {} flows to {}",
+            "Indexed".bright_green(),
+            db.data_flows.len(),
+            path
+        );
+        return;
+    }
+
+    if let Command::Daemon = config.command {
+        println!(
+            "{} {}",
+            "Listening on".bright_blue(),
+            config.socket.display()
+        );
+        if let Err(e) = Daemon::default().serve(&config.socket) {
+            fail(&format!("Daemon failed: {}", e));
+        }
+        return;
+    }
+
+    if let Command::Serve { unix } = config.command {
+        let mut server = Server::default();
+        let served = if unix {
+            server.serve_unix(&config.socket)
+        } else {
+            server.serve(io::stdin().lock(), &mut io::stdout())
+        };
+        if let Err(e) = served {
+            fail(&format!("Server failed: {}", e));
+        }
+        return;
+    }
+
+    if let Command::Lsp = config.command {
+        let root = std::env::current_dir().unwrap_or_default();
+        let rules = config.rules.as_deref().unwrap_or_default();
+        let mut server = LanguageServer::new(&config.data_json, rules, root);
+        if let Err(e) = server.serve(io::stdin().lock(), &mut io::stdout()) {
+            fail(&format!("Language server failed: {}", e));
+        }
+        return;
+    }
+
+    if config.remote && matches!(config.command, Command::Search) && config.rules.is_none() {
+        let request = Request {
+            data_json: config.data_json.clone(),
+            query: config.query_json.clone(),
+        };
+        let remote_db = match daemon::query(&config.socket, &request).unwrap_or_else(|e| fail(&e)) {
+            Response::Matches {
+                file_path,
+                language,
+                subtypes,
+                flows,
+            } => {
+                let mut db = Database::new(file_path, flows);
+                db.language = language;
+                db.set_subtypes(subtypes);
+                db
+            }
+            Response::Error(e) => fail(&e),
+        };
+        // The daemon answers from the whole database, so the file filters,
+        // commit range and notes still apply here
+        let db = filter_database(&config, &config.data_json, remote_db);
+        let results: Vec<_> = db.data_flows.iter().collect();
+        let mut history = History::load(History::default_path());
+        record_history(
+            &mut history,
+            &config.data_json,
+            &config.query_json,
+            results.len(),
+        );
+        print_search_results(
+            &config,
+            &db,
+            &config.data_json,
+            &config.query_json,
+            &results,
         );
+        exit_with_match_status(!results.is_empty());
+    }
+
+    if !config.origins.is_empty() && matches!(config.command, Command::Search) {
+        let databases: Vec<_> = std::iter::once(Origin::unlabeled(&config.data_json))
+            .chain(config.origins.iter().cloned())
+            .filter(|origin| config.only.is_empty() || config.only.contains(&origin.label))
+            .map(|origin| {
+                let db = load_database(&config, &origin.data_json);
+                (origin, db)
+            })
+            .collect();
+        let matches = federated::search(&databases, &config.query, &config.only);
+        match (config.quiet, config.json) {
+            (true, _) => {}
+            (false, true) => println!(
+                "{}",
+                serde_json::to_string_pretty(&matches).expect("Failed to serialize matches")
+            ),
+            (false, false) => print_federated_results(&databases, &matches, config.elaborate),
+        }
+        exit_with_match_status(!matches.is_empty());
+    }
+
+    let load_start = Instant::now();
+    let db = load_database(&config, &config.data_json);
+    report_verbose(
+        &config,
+        "loaded database in",
+        format_duration(load_start.elapsed()),
+    );
+    report_verbose(
+        &config,
+        "indexed flows in",
+        format_duration(db.index_time()),
+    );
+
+    if let Command::Bookmark { action } = &config.command {
+        let mut bookmarks = Bookmarks::load(&config.data_json).unwrap_or_else(|e| fail(&e));
+        match action {
+            BookmarkAction::Add(prefix) => {
+                let Some(idx) = db.find_flow(prefix) else {
+                    fail(&format!("No single flow with id {}", prefix));
+                };
+                let id = flow_id(&db.data_flows[idx]);
+                if bookmarks.add(&id) {
+                    bookmarks.save().unwrap_or_else(|e| fail(&e));
+                    println!("{} {}", "Bookmarked".bright_blue(), id);
+                } else {
+                    println!("{} is already bookmarked", id);
+                }
+            }
+            BookmarkAction::Remove(prefix) => {
+                let Some(id) = bookmarks.remove(prefix) else {
+                    fail(&format!("No single bookmark with id {}", prefix));
+                };
+                bookmarks.save().unwrap_or_else(|e| fail(&e));
+                println!("{} {}", "Removed bookmark".bright_blue(), id);
+            }
+            BookmarkAction::List => {
+                let flows = db.flows_by_id(&bookmarks.flow_ids);
+                println!("\n{}", "━".repeat(80).bright_black());
+                println!("{} {}\n", "Bookmarked flows:".bright_blue(), flows.len());
+                for flow in flows {
+                    println!("{}", flow_id(flow).bright_black());
+                    print_flow(&db, flow, config.elaborate, &[]);
+                    println!("{}", "━".repeat(80).bright_black());
+                }
+            }
+        }
+        return;
+    }
+
+    if let Command::Note { flow, text } = &config.command {
+        let Some(idx) = db.find_flow(flow) else {
+            fail(&format!("No single flow with id {}", flow));
+        };
+        let id = flow_id(&db.data_flows[idx]);
+        let mut notes = Notes::load(&config.data_json).unwrap_or_else(|e| exit_with(e));
+        notes.add(&id, text);
+        notes.save().unwrap_or_else(|e| fail(&e));
+        println!("{} {}: {}", "Noted".bright_blue(), id, text);
+        return;
+    }
+
+    if let Command::Connect { from, to } = &config.command {
+        let connecting = db.connecting_flows(from, to);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if connecting.is_empty() {
+            println!(
+                "{}",
+                "No data flows connect the two positions.\n".bright_red()
+            );
+        } else {
+            println!(
+                "{} {}\n",
+                "Connecting data flows:".bright_blue(),
+                connecting.len()
+            );
+        }
+        for (flow_idx, (flow, steps)) in connecting.iter().enumerate() {
+            println!("{}", format!("{} intermediate steps", steps).bright_black());
+            print_flow(&db, flow, config.elaborate, &[]);
+            if flow_idx < connecting.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
     }
-    print_results(&results);
+
+    if let Command::Join {
+        other_query_json,
+        other_query,
+    } = &config.command
+    {
+        let pairs = db.join_flows(&config.query, other_query);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if pairs.is_empty() {
+            println!(
+                "{}",
+                format!(
+                    "No flows matching `{}` share a type variable with flows matching `{}`.\n",
+                    config.query_json, other_query_json
+                )
+                .bright_red()
+            );
+        } else {
+            println!("{} {}\n", "Joined flow pairs:".bright_blue(), pairs.len());
+        }
+        for (pair_idx, pair) in pairs.iter().enumerate() {
+            println!(
+                "{}",
+                format!(
+                    "Flow {} ─({})→ Flow {}",
+                    pair.from + 1,
+                    pair.via.join(", "),
+                    pair.to + 1
+                )
+                .bold()
+            );
+            print_flow(&db, &db.data_flows[pair.from], config.elaborate, &[]);
+            print_flow(&db, &db.data_flows[pair.to], config.elaborate, &[]);
+            if pair_idx < pairs.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::At { pos } = &config.command {
+        let flows = db.flows_at(pos);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if flows.is_empty() {
+            println!(
+                "{}",
+                "No data flows pass through this position.\n".bright_red()
+            );
+        } else {
+            println!(
+                "{} {}\n",
+                "Data flows through position:".bright_blue(),
+                flows.len()
+            );
+        }
+        for (flow_idx, (flow, steps)) in flows.iter().enumerate() {
+            print_flow(&db, flow, config.elaborate, steps);
+            if flow_idx < flows.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::Path { from, to } = &config.command {
+        println!("\n{}", "━".repeat(80).bright_black());
+        let Some(path) = db.shortest_type_path(from, to) else {
+            println!(
+                "{}",
+                format!(
+                    "No chain of data flows leads from `{}` to `{}`.\n",
+                    from, to
+                )
+                .bright_red()
+            );
+            return;
+        };
+        println!(
+            "{} {} flows\n",
+            format!("Shortest chain from `{}` to `{}`:", from, to).bright_blue(),
+            path.len()
+        );
+        for (link_idx, link) in path.iter().enumerate() {
+            if let Some(via) = &link.via {
+                println!("{} {}", "linked via".bright_black(), via.cyan());
+            }
+            println!("{}", format!("Flow {}", link.flow + 1).bold());
+            print_flow(&db, &db.data_flows[link.flow], config.elaborate, &[]);
+            if link_idx < path.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::Stitch {
+        max_chain,
+        max_stitched,
+    } = config.command
+    {
+        let mut chains = db.stitch_flows(max_chain);
+        let stitched: Vec<_> = chains
+            .by_ref()
+            .take(max_stitched)
+            .map(|s| (s.flow(&db), s))
+            .filter(|(flow, _)| db.match_flow(flow, &config.query))
+            .collect();
+        if chains.next().is_some() && !config.quiet {
+            let message = format!(
+                "Searched only the first {} joined flows; raise --max-stitched to search more",
+                max_stitched
+            );
+            eprintln!("{}", message.yellow());
+        }
+        println!("\n{}", "━".repeat(80).bright_black());
+        if stitched.is_empty() {
+            println!(
+                "{}",
+                "No stitched data flows matched the query.\n".bright_red()
+            );
+        } else {
+            println!(
+                "{} {}\n",
+                "Matched stitched data flows:".bright_blue(),
+                stitched.len()
+            );
+        }
+        for (flow_idx, (flow, s)) in stitched.iter().enumerate() {
+            let provenance: Vec<String> = s
+                .provenance
+                .iter()
+                .map(|link| match &link.via {
+                    Some(via) => format!("─({})→ Flow {}", via, link.flow + 1),
+                    None => format!("Flow {}", link.flow + 1),
+                })
+                .collect();
+            println!("{}", provenance.join(" ").bold());
+            print_flow(&db, flow, config.elaborate, &[]);
+            if flow_idx < stitched.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::Cluster = config.command {
+        let results = db.search(&config.query);
+        let clusters = cluster_flows(&results);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if clusters.is_empty() {
+            println!("{}", "No data flows matched the query.\n".bright_red());
+            explain_no_matches(&config, &db, &config.query_json, &config.query);
+        } else {
+            println!(
+                "{} {} {}\n",
+                "Clusters:".bright_blue(),
+                clusters.len(),
+                format!("({} flows)", results.len()).bright_black()
+            );
+        }
+        for (cluster_idx, cluster) in clusters.iter().enumerate() {
+            println!(
+                "{} {}",
+                format!("Cluster {}:", cluster_idx + 1).bold(),
+                format!("{} flows", cluster.flows.len()).bright_blue()
+            );
+            println!("{}", cluster.shape.join(" → ").bright_black());
+            print_flow(&db, cluster.representative(), config.elaborate, &[]);
+            if cluster_idx < clusters.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::Dupes = config.command {
+        let groups = db.near_duplicates(&db.matching_indices(&config.query));
+        println!("\n{}", "━".repeat(80).bright_black());
+        if groups.is_empty() {
+            println!("{}", "No near-duplicate data flows found.\n".bright_red());
+        } else {
+            println!(
+                "{} {}\n",
+                "Near-duplicate groups:".bright_blue(),
+                groups.len()
+            );
+        }
+        for (group_idx, group) in groups.iter().enumerate() {
+            let flows: Vec<String> = group.flows.iter().map(|f| (f + 1).to_string()).collect();
+            println!(
+                "{} {}",
+                format!("Group {}:", group_idx + 1).bold(),
+                format!("flows {}", flows.join(", ")).bright_blue()
+            );
+            for (step, fields) in &group.differences {
+                println!(
+                    "{} {}",
+                    format!("  step {} differs in", step + 1).bright_black(),
+                    fields.join(", ").yellow()
+                );
+            }
+            print_flow(&db, &db.data_flows[group.flows[0]], config.elaborate, &[]);
+            if group_idx < groups.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::ShowFlow { flow } = &config.command {
+        let Some(idx) = db.find_flow(flow) else {
+            fail(&format!("No single flow with id {}", flow));
+        };
+        let flow = &db.data_flows[idx];
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{} {}", "Flow".bright_blue(), flow_id(flow));
+        let steps: Vec<_> = flow
+            .iter()
+            .filter(|uf| !matches!(uf, UnitFlow::ProgLoc(_)))
+            .map(UnitFlow::label)
+            .collect();
+        println!("{} {}", "steps:".bright_black(), steps.join(" → "));
+        let seen = db.multiplicity(flow);
+        if seen > 1 {
+            println!("{} {} times", "seen:".bright_black(), seen);
+        }
+        print_flow(&db, flow, config.elaborate, &[]);
+        return;
+    }
+
+    if let Command::TraceMatch { flow } = &config.command {
+        let Some(idx) = db.find_flow(flow) else {
+            fail(&format!("No single flow with id {}", flow));
+        };
+        let flow = &db.data_flows[idx];
+        let trace = db.trace_match(flow, &config.query);
+        if !config.quiet {
+            print_trace(&db, flow, &trace, config.elaborate);
+        }
+        exit_with_match_status(trace.matched);
+    }
+
+    if let Command::Text { words, count } = &config.command {
+        let within = (!config.query.is_empty()).then(|| db.matching_indices(&config.query));
+        let mut matches = db.text_search(words, within.as_deref());
+        matches.truncate(*count);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if matches.is_empty() {
+            println!(
+                "{}",
+                "No data flow descriptions use these words.\n".bright_red()
+            );
+        } else {
+            println!(
+                "{} {}\n",
+                "Relevant data flows:".bright_blue(),
+                matches.len()
+            );
+        }
+        for (rank, m) in matches.iter().enumerate() {
+            println!(
+                "{} {} {}",
+                format!("{}.", rank + 1).bold(),
+                format!("{:.2}", m.score).bright_blue(),
+                flow_id(&db.data_flows[m.flow]).bright_black()
+            );
+            print_flow(&db, &db.data_flows[m.flow], config.elaborate, &[]);
+            if rank < matches.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::SimilarFlows { flow, count } = &config.command {
+        let Some(idx) = db.find_flow(flow) else {
+            fail(&format!("No single flow with id {}", flow));
+        };
+        let similar = db.similar_flows(idx, *count);
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{} {}", "Flow".bright_blue(), flow_id(&db.data_flows[idx]));
+        print_flow(&db, &db.data_flows[idx], config.elaborate, &[]);
+        println!("\n{}", "━".repeat(80).bright_black());
+        if similar.is_empty() {
+            println!("{}", "No similar data flows found.\n".bright_red());
+        } else {
+            println!(
+                "{} {}\n",
+                "Similar data flows:".bright_blue(),
+                similar.len()
+            );
+        }
+        for (rank, s) in similar.iter().enumerate() {
+            println!(
+                "{} {} {}",
+                format!("{}.", rank + 1).bold(),
+                format!("{:.2}", s.score).bright_blue(),
+                flow_id(&db.data_flows[s.flow]).bright_black()
+            );
+            println!(
+                "{}",
+                format!(
+                    "  sequence {:.2}, names {:.2}, location {:.2}",
+                    s.sequence, s.names, s.location
+                )
+                .bright_black()
+            );
+            print_flow(&db, &db.data_flows[s.flow], config.elaborate, &[]);
+            if rank < similar.len() - 1 {
+                println!("{}", "━".repeat(80).bright_black());
+            }
+        }
+        return;
+    }
+
+    if let Command::ExportFlow { flow, out } = &config.command {
+        let Some(idx) = db.find_flow(flow) else {
+            fail(&format!("No single flow with id {}", flow));
+        };
+        let json = db
+            .with_flows(vec![db.data_flows[idx].to_vec()])
+            .redacted(&config.redaction)
+            .to_json()
+            .unwrap_or_else(|e| fail(&e));
+        write_file(out, json);
+        println!(
+            "{} {} to {}",
+            "Exported".bright_blue(),
+            flow_id(&db.data_flows[idx]),
+            out
+        );
+        return;
+    }
+
+    if let Command::Repl = config.command {
+        run_repl(&db, &config);
+        return;
+    }
+
+    if let Command::Tui = config.command {
+        run_tui(&db, &config);
+        return;
+    }
+
+    if let Command::QDiff {
+        other_query_json,
+        other_query,
+    } = &config.command
+    {
+        let diff = db.query_diff(&config.query, other_query);
+        for (label, flows) in [
+            (format!("Only `{}`:", config.query_json), diff.only_first),
+            (format!("Only `{}`:", other_query_json), diff.only_second),
+            ("Both:".to_string(), diff.both),
+        ] {
+            println!("\n{}", "━".repeat(80).bright_black());
+            println!("{} {}\n", label.bright_blue(), flows.len());
+            let flows: Vec<_> = flows.iter().map(|idx| &db.data_flows[*idx]).collect();
+            print_results(&db, &flows, config.elaborate, &[]);
+        }
+        return;
+    }
+
+    if let Command::Stats = config.command {
+        let stats = db.stats(10);
+        if config.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("Failed to serialize stats")
+            );
+        } else {
+            print_database_stats(&stats);
+        }
+        return;
+    }
+
+    if let Command::TypeVars = config.command {
+        print_typevars(&config, &db);
+        return;
+    }
+
+    if let Command::GraphStats = config.command {
+        let stats = db.type_graph().stats(10);
+        if config.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&stats).expect("Failed to serialize graph stats")
+            );
+        } else {
+            print_graph_stats(&stats);
+        }
+        return;
+    }
+
+    if let Command::GraphExport { out } = &config.command {
+        let graph = db.type_graph();
+        let rendered = if out.ends_with(".graphml") {
+            graph.to_graphml()
+        } else {
+            graph.to_dot()
+        };
+        write_file(out, rendered);
+        println!(
+            "{} {} nodes and {} connections to {}",
+            "Wrote".bright_blue(),
+            graph
+                .nodes
+                .values()
+                .filter(|kind| **kind != NodeKind::TypeVar)
+                .count(),
+            graph.type_connections().len(),
+            out
+        );
+        return;
+    }
+
+    if let (Command::Regress { new_data_json }, Some(rules_path)) = (&config.command, &config.rules)
+    {
+        let pack = RulePack::load(rules_path).unwrap_or_else(|e| fail(&e));
+        let new_db = load_database(&config, new_data_json);
+        let regressions = pack.regress(&db, &new_db);
+        print_regressions(&new_db, &regressions, config.elaborate);
+        if regressions.iter().any(|r| !r.introduced.is_empty()) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Command::Diff { new_data_json } = &config.command {
+        let new_db = load_database(&config, new_data_json);
+        let diff = db.diff_matches(&new_db, &config.query);
+        if config.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&diff.ids()).expect("Failed to serialize diff")
+            );
+        } else {
+            print_match_diff(&db, &new_db, &diff, &config);
+        }
+        if !diff.appeared.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Command::Explain = config.command {
+        explain_query(&config, &db);
+        return;
+    }
+
+    if let Some(rules_path) = &config.rules {
+        let pack = RulePack::load(rules_path).unwrap_or_else(|e| fail(&e));
+        let mut results = pack.evaluate(&db);
+        if let Some(mut baseline) = load_baseline(&config) {
+            let flows: Vec<_> = results.iter().flat_map(|r| r.flows.clone()).collect();
+            if config.update_baseline {
+                update_baseline(&mut baseline, &flows);
+            }
+            let mut suppressed = 0;
+            for result in &mut results {
+                let (kept, dropped) = baseline.suppress(&result.flows);
+                result.flows = kept;
+                suppressed += dropped;
+            }
+            report_suppressed(&config, suppressed);
+        }
+        match (config.quiet, config.sarif, config.report) {
+            (true, ..) => {}
+            (false, false, None) if config.dot => {
+                let flows: Vec<_> = results.iter().flat_map(|r| r.flows.clone()).collect();
+                print!("{}", db.flows_to_dot(&flows, config.cluster_files))
+            }
+            (false, true, _) => print_sarif(&SarifLog::for_rules(&db, &results)),
+            (false, false, None) if config.quickfix => {
+                // Checked for lines once all the rules are listed, as any of
+                // them may have some
+                let mut quickfix = reporter::QuickfixReporter::new(io::stdout());
+                let written = results.iter().try_for_each(|result| {
+                    quickfix.report_start(&db, &result.rule.name, &result.flows)?;
+                    result
+                        .flows
+                        .iter()
+                        .try_for_each(|flow| quickfix.report_match(&db, flow))
+                });
+                exit_on_write_error(written.and_then(|()| quickfix.report_summary(&db)));
+            }
+            (false, false, Some(format)) => {
+                print!("{}", Report::for_rules(&db, &results).render(format))
+            }
+            (false, false, None) => {
+                print_rule_summary(&results);
+                print_rule_results(&db, &results, config.elaborate);
+            }
+        }
+        exit_with_match_status(results.iter().any(|r| !r.flows.is_empty()));
+    }
+
+    if config.watch {
+        watch_search(&config);
+    }
+
+    if config.invert || config.all {
+        search_inverted(&config, &db);
+    }
+
+    if config.count {
+        let count = db.count_matches(&config.query);
+        if !config.quiet {
+            println!("{}", count);
+        }
+        exit_with_match_status(count > 0);
+    }
+
+    let engine = SearchEngine::uncached(db);
+    let db = engine.database();
+    if config.verbose {
+        let candidates = db
+            .candidates(&config.query)
+            .map_or(db.data_flows.len(), |c| c.len());
+        report_verbose(
+            &config,
+            "candidates after prefiltering:",
+            format!("{} of {} flows", candidates, db.data_flows.len()),
+        );
+    }
+    let match_start = Instant::now();
+    let cancel = config
+        .timeout
+        .map_or_else(CancelToken::new, CancelToken::with_timeout);
+    let (results, truncated): (Vec<_>, _) = match config.top {
+        // Ranking needs every match, so pages of ranked flows are cut after
+        Some(n) => {
+            let ranked = engine.search_ops_with_cancel(&config.query, &cancel);
+            let truncated = ranked.is_truncated();
+            let flows = ranked
+                .top(n)
+                .iter()
+                .skip(config.offset)
+                .take(config.limit.unwrap_or(usize::MAX))
+                .map(|m| &db.data_flows[m.index])
+                .collect();
+            (flows, truncated)
+        }
+        None if config.limit.is_some() || config.offset > 0 => {
+            let (matches, truncated) =
+                engine.search_page_with_cancel(&config.query, config.offset, config.limit, &cancel);
+            let flows = matches.iter().map(|m| &db.data_flows[m.index]).collect();
+            (flows, truncated)
+        }
+        None => {
+            let ranked = engine.search_ops_with_cancel(&config.query, &cancel);
+            let flows = ranked.iter().map(|m| &db.data_flows[m.index]).collect();
+            (flows, ranked.is_truncated())
+        }
+    };
+    report_verbose(
+        &config,
+        "matched in",
+        format_duration(match_start.elapsed()),
+    );
+    if truncated {
+        report_truncated(&config, results.len());
+        if results.is_empty() {
+            // Flows past where the search stopped may still match
+            std::process::exit(ERROR_EXIT_CODE);
+        }
+    }
+    let results = match load_baseline(&config) {
+        Some(mut baseline) if config.update_baseline => update_baseline(&mut baseline, &results),
+        Some(baseline) => {
+            let (kept, suppressed) = baseline.suppress(&results);
+            report_suppressed(&config, suppressed);
+            kept
+        }
+        None => results,
+    };
+    let mut history = History::load(History::default_path());
+    record_history(
+        &mut history,
+        &config.data_json,
+        &config.query_json,
+        results.len(),
+    );
+    if results.is_empty() && !truncated {
+        explain_no_matches(&config, db, &config.query_json, &config.query);
+    }
+    print_search_results(&config, db, &config.data_json, &config.query_json, &results);
+    exit_with_match_status(!results.is_empty());
 }
 
-fn search_dataflows<'a>(db: &'a Database, query: &'a [QueryOps]) -> Vec<&'a Vec<UnitFlow>> {
-    db.data_flows
-        .iter()
-        .filter(|flow| db.match_flow(flow, query))
-        .collect()
+/// Show the flows in `--scope` the query does not match. With `--all` they
+/// are counterexamples to the query holding for every flow, so exit 1 when
+/// there are any rather than when there are none.
+fn search_inverted(config: &Config, db: &Database) -> ! {
+    let results = db.search_inverted(&config.query, config.scope.as_deref());
+    let results = match load_baseline(config) {
+        Some(mut baseline) if config.update_baseline => update_baseline(&mut baseline, &results),
+        Some(baseline) => {
+            let (kept, suppressed) = baseline.suppress(&results);
+            report_suppressed(config, suppressed);
+            kept
+        }
+        None => results,
+    };
+    let failed = !results.is_empty();
+    if config.all && !config.quiet {
+        let scoped = config
+            .scope
+            .as_ref()
+            .map_or(db.data_flows.len(), |scope| db.count_matches(scope));
+        let summary = if failed {
+            format!(
+                "{} of {} flows do not match the query",
+                results.len(),
+                scoped
+            )
+            .bright_red()
+        } else {
+            format!("All {} flows match the query", scoped).green()
+        };
+        eprintln!("{}", summary);
+    }
+    if config.count {
+        if !config.quiet {
+            println!("{}", results.len());
+        }
+    } else if failed || !config.all {
+        let results: Vec<_> = results
+            .into_iter()
+            .skip(config.offset)
+            .take(config.limit.unwrap_or(usize::MAX))
+            .collect();
+        print_search_results(config, db, &config.data_json, &config.query_json, &results);
+    }
+    exit_with_match_status(failed != config.all);
 }
 
-fn print_results(results: &[&Vec<UnitFlow>]) {
-    for (flow_idx, flow) in results.iter().enumerate() {
-        let prog_locs: Vec<_> = flow
+/// Re-run the search and reprint its results each time the database file
+/// changes, firing the hooks whenever the matched flows differ from the
+/// previous run
+fn watch_search(config: &Config) -> ! {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)
+        .unwrap_or_else(|e| fail(&format!("Failed to watch the database: {}", e)));
+    // Watch the directory, as analyzers often replace the file rather than
+    // write to it
+    let path = Path::new(&config.data_json);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        fail(&format!("Failed to watch the database: {}", e));
+    }
+
+    let mut previous: Option<ResultSet> = None;
+    loop {
+        if !config.json {
+            // Clear the screen and move the cursor to its top left
+            print!("\x1B[2J\x1B[H");
+        }
+        let db = match load_from_json(config, &config.data_json) {
+            Ok(db) => filter_database(config, &config.data_json, db),
+            // Likely caught halfway through a rewrite; the next change retries
+            Err(e) => {
+                println!("{}", e.to_string().bright_red());
+                wait_for_change(&rx, path);
+                continue;
+            }
+        };
+        let results = db.search(&config.query);
+        let current = ResultSet::new(&config.data_json, &config.query_json, &results);
+
+        let change = previous
+            .as_ref()
+            .and_then(|p| MatchChange::between(p, &current));
+        if let Some(change) = &change {
+            println!(
+                "{} {} {}",
+                "Matches changed:".bright_blue(),
+                format!("+{}", change.added.len()).bright_red(),
+                format!("-{}", change.removed.len()).bright_green()
+            );
+        }
+        print_search_results(config, &db, &config.data_json, &config.query_json, &results);
+        if let Some(change) = &change {
+            if let Err(e) = config.hooks.notify(change) {
+                println!("{}", e.bright_red());
+            }
+        }
+        previous = Some(current);
+        wait_for_change(&rx, path);
+    }
+}
+
+/// Block until the file at `path` is written or replaced, then let the burst
+/// of events a single rewrite causes die down
+fn wait_for_change(rx: &Receiver<notify::Result<Event>>, path: &Path) {
+    let touches = |event: &Event| {
+        (event.kind.is_create() || event.kind.is_modify())
+            && event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == path.file_name())
+    };
+    loop {
+        match rx.recv() {
+            Ok(Ok(event)) if touches(&event) => break,
+            Ok(_) => {}
+            Err(_) => {
+                println!("{}", "Stopped watching the database".bright_red());
+                std::process::exit(1);
+            }
+        }
+    }
+    while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+}
+
+/// The number of flows matched by each prefix of the query, followed by the
+/// hints of [explain_no_matches] when the whole query matches nothing
+fn explain_query(config: &Config, db: &Database) {
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!("{} {}\n", "Query:".bright_blue(), config.query_json);
+    let contradictions = db.contradictions(&config.query);
+    let mut matched = db.data_flows.len();
+    for (idx, op) in config.query.iter().enumerate() {
+        matched = db.matching_indices(&config.query[..=idx]).len();
+        println!(
+            "{} {:<30} {}",
+            format!("{:>4}", idx + 1).bright_blue(),
+            op.to_string(),
+            format!("{} flows", matched).bright_black()
+        );
+        for line in op.tree() {
+            println!("     {}", line.bright_black());
+        }
+        let step_contradictions = contradictions
             .iter()
-            .filter_map(|uf| match uf {
-                UnitFlow::ProgLoc(pl) => Some(pl),
-                _ => None,
-            })
-            .collect();
+            .filter(|c| c.step.as_ref() == Some(op));
+        for contradiction in step_contradictions {
+            println!("     {}", contradiction.to_string().bright_yellow());
+        }
+        for suggestion in db.suggestions(std::slice::from_ref(op)) {
+            println!("     {}", suggestion.to_string().bright_yellow());
+        }
+    }
+    for contradiction in contradictions.iter().filter(|c| c.step.is_none()) {
+        println!("\n{}", contradiction.to_string().bright_yellow());
+    }
+    println!("\n{} {}\n", "Matched data flows:".bright_blue(), matched);
+    if matched == 0 {
+        explain_no_matches(config, db, &config.query_json, &config.query);
+    }
+}
 
-        if prog_locs.is_empty() {
+/// Hints for a query matching nothing: steps that can never match,
+/// misspelled names, with `--suggest` the relaxed queries that match, and
+/// with `--partial` the query step that eliminated the last flows
+fn explain_no_matches(config: &Config, db: &Database, query_json: &str, query: &[QueryOps]) {
+    if config.json || config.quiet {
+        return;
+    }
+    // `explain` lists these under the steps they concern
+    if !matches!(config.command, Command::Explain) {
+        for contradiction in db.contradictions(query) {
+            println!("{}", contradiction.to_string().bright_yellow());
+        }
+        for suggestion in db.suggestions(query) {
+            println!("{}", suggestion.to_string().bright_yellow());
+        }
+    }
+
+    if config.suggest {
+        let relaxations: Vec<_> = db
+            .relaxations(query)
+            .into_iter()
+            .filter(|r| r.matches > 0)
+            .collect();
+        if relaxations.is_empty() {
             println!(
                 "{}",
-                "No program locations found for this data flow.".bright_red()
+                "No single relaxation of the query matches any flows".bright_yellow()
             );
+        }
+        for relaxation in relaxations {
+            println!("{}", relaxation.to_string().bright_yellow());
+        }
+    }
+
+    let steps: Vec<&str> = QueryOps::split_steps(query_json)
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if !config.partial || steps.len() != query.len() {
+        return;
+    }
+    match db.longest_matching_prefix(query) {
+        (0, _) => println!(
+            "{}",
+            format!("`{}` alone matched no flows", steps[0]).bright_yellow()
+        ),
+        (len, count) if len < steps.len() => println!(
+            "{}",
+            format!(
+                "`{}` matched {} flows; adding `{}` eliminated all of them",
+                steps[..len].join(", "),
+                count,
+                steps[len]
+            )
+            .bright_yellow()
+        ),
+        _ => {}
+    }
+}
+
+/// Load the database at `data_json`, keeping the flows accepted by the file
+/// filters and commit range and attaching their notes
+fn load_database(config: &Config, data_json: &str) -> Database {
+    let db = load_from_json(config, data_json).unwrap_or_else(|e| exit_with(e));
+    filter_database(config, data_json, db)
+}
+
+/// Load the database at `data_json`, merging identical flows unless
+/// `--keep-duplicates` is given, dropping malformed flows with `--lenient`
+/// and clamping invalid location ranges with `--repair-spans`, and print any
+/// warnings loading it
+fn load_from_json(config: &Config, data_json: &str) -> Result<Database, SemanticSearchError> {
+    let db = if config.lenient {
+        Database::load_lenient(data_json)?
+    } else {
+        Database::load_with_duplicates(data_json)?
+    };
+    let mut db = if config.keep_duplicates {
+        db
+    } else {
+        db.deduplicated()
+    };
+    db.check_spans(data_json, config.repair_spans);
+    for warning in db.warnings() {
+        tracing::warn!("{}", warning);
+    }
+    Ok(db)
+}
+
+/// Print a loading or parsing error and exit with its status
+fn exit_with(error: SemanticSearchError) -> ! {
+    eprintln!("{}", error.to_string().bright_red());
+    std::process::exit(error.exit_code())
+}
+
+/// Print an error without a [SemanticSearchError] of its own and exit with
+/// the same status
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message.bright_red());
+    std::process::exit(ERROR_EXIT_CODE)
+}
+
+/// The `--baseline` file, if one is given
+fn load_baseline(config: &Config) -> Option<Baseline> {
+    let path = config.baseline.as_ref()?;
+    Some(Baseline::load(path).unwrap_or_else(|e| fail(&e)))
+}
+
+/// Record `flows` as the baseline and exit
+fn update_baseline(baseline: &mut Baseline, flows: &[&[UnitFlow]]) -> ! {
+    baseline.record(flows);
+    baseline.save().unwrap_or_else(|e| fail(&e));
+    println!(
+        "{} {} flows in the baseline",
+        "Recorded".bright_blue(),
+        baseline.flow_ids.len()
+    );
+    std::process::exit(0)
+}
+
+fn report_suppressed(config: &Config, suppressed: usize) {
+    if suppressed > 0 && !config.quiet {
+        eprintln!(
+            "{}",
+            format!("{} matched flows suppressed by the baseline", suppressed).bright_black()
+        );
+    }
+}
+
+/// Write the matched flows to `path` as a database of their own, with the
+/// file path, language, edges and subtypes of `db`
+fn export_matches(config: &Config, db: &Database, results: &[&[UnitFlow]], path: &str) {
+    let subset = db
+        .with_flows(results.iter().map(|flow| flow.to_vec()).collect())
+        .redacted(&config.redaction);
+    let data = if Database::is_ndjson(path) {
+        subset.to_ndjson()
+    } else {
+        subset.to_json()
+    };
+    write_file(path, data.unwrap_or_else(|e| fail(&e)));
+    if !config.quiet {
+        eprintln!(
+            "{} {} flows to {}",
+            "Exported".bright_blue(),
+            results.len(),
+            path
+        );
+    }
+}
+
+/// Write `contents` to the file at `path`, exiting with the error status when
+/// it cannot be written
+fn write_file(path: &str, contents: String) {
+    if let Err(e) = fs::write(path, contents) {
+        fail(&format!("Could not write {}: {}", path, e));
+    }
+}
+
+/// Warn that the search hit `--timeout` and `matched` flows are all it found
+fn report_truncated(config: &Config, matched: usize) {
+    if config.quiet {
+        return;
+    }
+    let secs = config.timeout.unwrap_or_default().as_secs();
+    let message = if matched == 0 {
+        format!("Search truncated after {}s before any flow matched", secs)
+    } else {
+        format!(
+            "Search truncated after {}s: showing the {} flows matched so far",
+            secs, matched
+        )
+    };
+    eprintln!("{}", message.yellow());
+}
+
+/// With `--verbose`, print a diagnostic about the search to stderr, out of
+/// the way of the results
+fn report_verbose(config: &Config, label: &str, value: String) {
+    if config.verbose && !config.quiet {
+        eprintln!("{} {}", label.bright_black(), value);
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Exit as grep does: 0 when the search matched any flows and 1 otherwise
+fn exit_with_match_status(matched: bool) -> ! {
+    std::process::exit(if matched { 0 } else { 1 })
+}
+
+/// Keep the flows of `db` accepted by the file filters, commit range and
+/// length bounds, match them ignoring case with `--ignore-case` and without supertypes with
+/// `--no-hierarchy`, and attach the notes of the database at `data_json`
+fn filter_database(config: &Config, data_json: &str, db: Database) -> Database {
+    let mut db = config
+        .commits
+        .apply(config.files.apply(db))
+        .unwrap_or_else(|e| fail(&e))
+        .with_lengths(&config.lengths);
+    db.set_ignore_case(config.ignore_case);
+    db.set_hierarchy(!config.no_hierarchy);
+    match Notes::load(data_json) {
+        Ok(notes) => db.set_notes(notes.notes),
+        Err(e) => println!("{}", e.to_string().bright_red()),
+    }
+    db
+}
+
+fn record_history(history: &mut History, data_json: &str, query: &str, matches: usize) {
+    if let Err(e) = history.record(HistoryEntry::new(data_json, query, matches)) {
+        tracing::warn!("{}", e);
+    }
+}
+
+fn print_search_results(
+    config: &Config,
+    db: &Database,
+    data_json: &str,
+    query: &str,
+    results: &[&[UnitFlow]],
+) {
+    let parsed_query = QueryOps::parse_query(query).unwrap_or_default();
+    let mut results = results.to_vec();
+    if let Some(sort) = config.sort {
+        sort.sort(db, &parsed_query, &mut results);
+    }
+    let results = &results[..];
+    if let Some(path) = &config.save_results {
+        ResultSet::new(data_json, query, results)
+            .save(path)
+            .unwrap_or_else(|e| fail(&e));
+    }
+    if let Some(path) = &config.export {
+        export_matches(config, db, results, path);
+    }
+    if let Some(format) = config.report {
+        if !config.quiet {
+            page(
+                config,
+                &Report::for_search(db, query, results).render(format),
+            );
+        }
+        return;
+    }
+
+    if config.dot && !config.quiet {
+        page(config, &db.flows_to_dot(results, config.cluster_files));
+        return;
+    }
+
+    if config.cluster && !config.quiet {
+        print_clusters(config, db, results, &parsed_query);
+        return;
+    }
+
+    let options = RenderOptions {
+        elaborate: config.elaborate,
+        ..render_options()
+    };
+    exit_on_write_error(reporter::report(
+        &mut *reporter::for_config(config, options),
+        db,
+        query,
+        results,
+    ));
+}
+
+/// Print `text` to stdout, or through a pager as [Output::for_config] decides
+fn page(config: &Config, text: &str) {
+    exit_on_write_error(write!(Output::for_config(config), "{}", text));
+}
+
+/// Exit quietly once whatever reads the results has stopped, as `| head`
+/// does, and with the error status when they cannot be written otherwise
+fn exit_on_write_error(written: io::Result<()>) {
+    match written {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(e) => fail(&format!("Failed to print results: {}", e)),
+    }
+}
+
+/// One flow of each group of near-identical `results`, headed by the id of
+/// the group and followed by how many others it stands for, or every flow of
+/// the group `--expand-cluster` names
+fn print_clusters(config: &Config, db: &Database, results: &[&[UnitFlow]], query: &[QueryOps]) {
+    let clusters = near_clusters(results);
+    let id = |cluster: &Cluster| {
+        let id = flow_id(cluster.representative());
+        id[..8.min(id.len())].to_string()
+    };
+    if let Some(expand) = &config.expand_cluster {
+        let cluster = clusters
+            .iter()
+            .find(|cluster| id(cluster).starts_with(expand.as_str()))
+            .unwrap_or_else(|| fail(&format!("No cluster of matched flows with id {}", expand)));
+        print_results(db, &cluster.flows, config.elaborate, query);
+        return;
+    }
+
+    println!("\n{}", "━".repeat(80).bright_black());
+    if clusters.is_empty() {
+        println!("{}", "No data flows matched the query.\n".bright_red());
+        return;
+    }
+    println!(
+        "{} {} {}\n",
+        "Clusters:".bright_blue(),
+        clusters.len(),
+        format!("({} flows)", results.len()).bright_black()
+    );
+    for (cluster_idx, cluster) in clusters.iter().enumerate() {
+        println!("{} {}", "Cluster".bold(), id(cluster).bold());
+        let mut out = String::new();
+        renderer(db, config.elaborate).write_result(&mut out, cluster.representative(), query);
+        print!("{}", out);
+        match cluster.flows.len() - 1 {
+            0 => {}
+            1 => println!("{}", "… and 1 similar flow".bright_black()),
+            n => println!("{}", format!("… and {} similar flows", n).bright_black()),
+        }
+        if cluster_idx < clusters.len() - 1 {
+            println!("{}", "━".repeat(80).bright_black());
+        }
+    }
+}
+
+/// Each step of the query with whether `flow` satisfied it, then the flow
+fn print_trace(db: &Database, flow: &[UnitFlow], trace: &MatchTrace, elaborate: bool) {
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!("{} {}\n", "Flow".bright_blue(), flow_id(flow));
+    for (idx, step) in trace.steps.iter().enumerate() {
+        let line = match step.outcome {
+            StepOutcome::Holds | StepOutcome::Matched => step.to_string().green(),
+            StepOutcome::Fails | StepOutcome::Unmatched => step.to_string().bright_red(),
+            StepOutcome::Skipped => step.to_string().bright_black(),
+        };
+        println!("{} {}", format!("{:>4}", idx + 1).bright_blue(), line);
+    }
+    if trace.matched {
+        println!("\n{}\n", "The flow matches the query.".green());
+    } else {
+        println!("\n{}\n", "The flow does not match the query.".bright_red());
+    }
+    print_flow(db, flow, elaborate, &[]);
+}
+
+fn print_sarif(log: &SarifLog) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(log).expect("Failed to serialize SARIF log")
+    );
+}
+
+/// Show each location the renderer rejects with its proposed range, and
+/// write the database with the accepted repairs to `out`
+fn fix_locations(mut db: Database, out: &str) {
+    let fixes = db.bad_locations();
+    println!("\n{}", "━".repeat(80).bright_black());
+    if fixes.is_empty() {
+        println!("{}", "All program locations are in range.\n".bright_green());
+        return;
+    }
+    println!(
+        "{} {}. Answer y to accept, n to skip, a to accept the rest, q to stop.",
+        "Program locations out of range:".bright_blue(),
+        fixes.len()
+    );
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut accepted = vec![];
+    let mut accept_rest = false;
+    for fix in fixes {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!(
+            "{} step {}, char_range {:?}",
+            flow_id(&db.data_flows[fix.flow]).bright_black(),
+            fix.step + 1,
+            fix.loc.char_range()
+        );
+        let Some(range) = fix.proposed else {
+            println!("{}", "No range fits an empty line; skipping.".bright_red());
+            continue;
+        };
+        ProgLoc::print_marked_location(
+            &fix.loc.with_char_range(range),
+            &1,
+            Highlight::Marked,
+            render_options().highlight_style,
+            render_options().tab_width,
+        );
+        println!("{} {:?}", "Proposed char_range".bright_blue(), range);
+        if accept_rest {
+            accepted.push(fix);
             continue;
         }
 
-        let mut itr = 1;
-        for loc in prog_locs {
-            if ProgLoc::print_location(loc, &itr) {
-                itr += 1;
+        print!("{} ", "Accept? [y/n/a/q]".bright_blue());
+        exit_on_write_error(io::stdout().flush());
+        match lines.next().and_then(Result::ok).as_deref().map(str::trim) {
+            Some("y") => accepted.push(fix),
+            Some("a") => {
+                accept_rest = true;
+                accepted.push(fix);
+            }
+            Some("q") | None => break,
+            _ => {}
+        }
+    }
+
+    if accepted.is_empty() {
+        println!("\n{}", "No repairs accepted.".bright_red());
+        return;
+    }
+    db.apply_loc_fixes(&accepted);
+    write_file(out, db.to_json().unwrap_or_else(|e| fail(&e)));
+    println!(
+        "\n{} {} locations to {}",
+        "Repaired".bright_blue(),
+        accepted.len(),
+        out
+    );
+}
+
+fn run_tutorial() {
+    let db = tutorial::load_database();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{} {} example flows loaded. Type :hint for help, :skip to move on, :quit to exit.",
+        "Tutorial:".bright_blue(),
+        db.data_flows.len()
+    );
+
+    for (task_idx, task) in tutorial::TASKS.iter().enumerate() {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!(
+            "{} {}",
+            format!("[{}/{}]", task_idx + 1, tutorial::TASKS.len()).bright_blue(),
+            task.prompt
+        );
+
+        loop {
+            print!("{} ", "query>".bright_black());
+            exit_on_write_error(io::stdout().flush());
+
+            let Some(Ok(input)) = lines.next() else {
+                return;
             };
+            match input.trim() {
+                "" => continue,
+                ":quit" => return,
+                ":hint" => println!("{} {}", "Hint:".yellow(), task.hint),
+                ":skip" => {
+                    println!("{} {}", "One answer:".yellow(), task.answer);
+                    break;
+                }
+                query => match task.check(&db, query) {
+                    Ok(true) => {
+                        let matched = QueryOps::parse_query(query)
+                            .map(|q| db.search(&q).len())
+                            .unwrap_or(0);
+                        println!("{} matched {} flows", "Correct!".green(), matched);
+                        break;
+                    }
+                    Ok(false) => println!(
+                        "{}",
+                        "Not quite: that query matches a different set of flows.".bright_red()
+                    ),
+                    Err(e) => println!("{} {}", "Could not parse query:".bright_red(), e),
+                },
+            }
+        }
+    }
+
+    println!("\n{}", "Tutorial complete!".green());
+}
+
+/// Tab-completes query tokens in the REPL from the names in the database
+struct QueryHelper<'a> {
+    db: &'a Database,
+}
+
+impl Completer for QueryHelper<'_> {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(repl::complete(self.db, line, pos))
+    }
+}
+
+impl Hinter for QueryHelper<'_> {
+    type Hint = String;
+}
+
+impl Highlighter for QueryHelper<'_> {}
+
+impl Validator for QueryHelper<'_> {}
+
+impl Helper for QueryHelper<'_> {}
+
+#[cfg(feature = "tui")]
+fn run_tui(db: &Database, config: &Config) {
+    let bookmarks = Bookmarks::load(&config.data_json).unwrap_or_else(|e| fail(&e));
+    let mut browser = tui::Browser::new(db, &config.query_json).with_bookmarks(bookmarks);
+    if let Err(e) = tui::run(&mut browser) {
+        fail(&format!("Could not run the terminal UI: {}", e));
+    }
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_tui(_db: &Database, _config: &Config) {
+    fail("This build has no terminal UI; rebuild with `--features tui`");
+}
+
+fn run_repl(db: &Database, config: &Config) {
+    let mut session = Session::new(db);
+    let mut history = History::load(History::default_path());
+    let mut editor: Editor<QueryHelper, DefaultHistory> =
+        Editor::new().unwrap_or_else(|e| fail(&format!("Could not start line editor: {}", e)));
+    editor.set_helper(Some(QueryHelper { db }));
+    for entry in &history.entries {
+        let _ = editor.add_history_entry(entry.query.as_str());
+    }
+
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{} {} flows loaded. Start a query with && or :refine to narrow the current results, !n to recall history entry n, :bookmark n to bookmark the nth result, :pop to undo, :trail to list refinements, :quit to exit.",
+        "REPL:".bright_blue(),
+        db.data_flows.len()
+    );
+
+    loop {
+        let input = match editor.readline(&format!("query[{}]> ", session.trail().len())) {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return,
+            Err(e) => fail(&format!("Could not read input: {}", e)),
+        };
+
+        let input = match input.trim().strip_prefix('!').map(str::parse::<usize>) {
+            Some(Ok(n)) => match history.get(n) {
+                Some(entry) => {
+                    println!("{} {}", "Recalled:".bright_black(), entry.query);
+                    entry.query.clone()
+                }
+                None => {
+                    println!("{}", format!("No history entry {}", n).bright_red());
+                    continue;
+                }
+            },
+            _ => input.trim().to_string(),
+        };
+
+        match input.as_str() {
+            "" => continue,
+            ":quit" => return,
+            ":pop" => {
+                session.pop();
+                print_trail(&session);
+            }
+            ":trail" => print_trail(&session),
+            input if input.starts_with(":bookmark") => {
+                bookmark_result(config, &session, &input[":bookmark".len()..])
+            }
+            input => {
+                let _ = editor.add_history_entry(input);
+                match session.eval(input) {
+                    Ok(refinement) => {
+                        let query = refinement.query.clone();
+                        let flows: Vec<_> = refinement
+                            .flows
+                            .iter()
+                            .map(|idx| &db.data_flows[*idx])
+                            .collect();
+                        // Refinements are recorded as the whole query they
+                        // amount to, so that recalling them searches again
+                        record_history(
+                            &mut history,
+                            &config.data_json,
+                            &session.resolved_query(),
+                            flows.len(),
+                        );
+                        if flows.is_empty() {
+                            let ops = QueryOps::parse_query(&query).unwrap_or_default();
+                            explain_no_matches(config, db, &query, &ops);
+                        }
+                        print_search_results(config, db, &config.data_json, &query, &flows);
+                        print_trail(&session);
+                    }
+                    Err(e) => println!("{} {}", "Could not parse query:".bright_red(), e),
+                }
+            }
+        }
+    }
+}
+
+/// Bookmark the `n`th flow of the current REPL results
+fn bookmark_result(config: &Config, session: &Session, n: &str) {
+    let flow = n
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|idx| session.current()?.flows.get(idx).copied());
+    let Some(flow) = flow else {
+        println!(
+            "{}",
+            "Usage: :bookmark <n>, where n counts the flows of the current results".bright_red()
+        );
+        return;
+    };
+
+    let id = flow_id(&session.db.data_flows[flow]);
+    let saved = Bookmarks::load(&config.data_json).and_then(|mut bookmarks| {
+        bookmarks.add(&id);
+        bookmarks.save()
+    });
+    match saved {
+        Ok(()) => println!("{} {}", "Bookmarked".bright_blue(), id),
+        Err(e) => println!("{}", e.bright_red()),
+    }
+}
+
+fn print_trail(session: &Session) {
+    let crumbs: Vec<String> = session
+        .trail()
+        .iter()
+        .enumerate()
+        .map(|(idx, r)| {
+            let prefix = if idx == 0 { "" } else { "&& " };
+            format!("{}{} ({})", prefix, r.query, r.flows.len())
+        })
+        .collect();
+    if crumbs.is_empty() {
+        println!("{}", "No active query.".bright_black());
+    } else {
+        println!("{}", crumbs.join(" › ").bright_black());
+    }
+}
+
+fn print_database_stats(stats: &DatabaseStats) {
+    let print_histogram = |title: &str, label: &str, counts: &BTreeMap<usize, usize>| {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{}\n", title.bright_blue());
+        let most = counts.values().copied().max().unwrap_or(1);
+        for (value, count) in counts {
+            println!(
+                "{} {:>6} {}",
+                format!("{:>4} {:<6}", value, label).bright_black(),
+                count,
+                "█".repeat((count * 40).div_ceil(most)).cyan()
+            );
+        }
+    };
+    let print_counts = |title: &str, counts: &[(String, usize)]| {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{}\n", title.bright_blue());
+        for (name, count) in counts {
+            println!("{:<40} {:>8}", name, count);
+        }
+    };
+
+    println!("{} {}", "Flows:".bright_blue(), stats.flows);
+    print_histogram("Flow lengths:", "steps", &stats.flow_lengths);
+    print_counts("Most frequent types:", &stats.top_types);
+    print_counts("Most frequent constructors:", &stats.top_constructors);
+    print_histogram(
+        "Type variables by in-degree:",
+        "flows",
+        &stats.typevar_in_degrees,
+    );
+
+    let coverage = &stats.coverage;
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{} {} locations on {} lines of {} files; {} flows without locations",
+        "Coverage:".bright_blue(),
+        coverage.locations,
+        coverage.lines,
+        coverage.files,
+        coverage.flows_without_locations
+    );
+    if coverage.invalid_ranges > 0 {
+        println!(
+            "{}",
+            format!(
+                "{} locations with out-of-range or inverted ranges are not shown",
+                coverage.invalid_ranges
+            )
+            .bright_yellow()
+        );
+    }
+
+    let memory = &stats.memory;
+    let size = |bytes: usize| match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1048576.0),
+    };
+    println!(
+        "{} {} ({} steps, {} text, {} indices); {} of repeated text shared",
+        "Memory:".bright_blue(),
+        size(memory.total()),
+        size(memory.steps),
+        size(memory.text),
+        size(memory.indices),
+        size(memory.shared)
+    );
+}
+
+fn print_graph_stats(stats: &GraphStats) {
+    let print_table = |title: &str, nodes: &[NodeStats]| {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{}\n", title.bright_blue());
+        println!(
+            "{}",
+            format!(
+                "{:<40} {:>8} {:>8} {:>8}",
+                "name", "fan-in", "fan-out", "degree"
+            )
+            .bright_black()
+        );
+        for node in nodes {
+            println!(
+                "{:<40} {:>8} {:>8} {:>8}",
+                node.name, node.fan_in, node.fan_out, node.degree
+            );
+        }
+    };
+    print_table("Type variables:", &stats.type_vars);
+    print_table("Most central types:", &stats.central_types);
+
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{} {}\n",
+        "Connected components:".bright_blue(),
+        stats.components.len()
+    );
+    for (idx, component) in stats.components.iter().enumerate() {
+        println!(
+            "{} {}",
+            format!("[{}] {} nodes:", idx + 1, component.len()).bright_black(),
+            component.join(", ")
+        );
+    }
+}
+
+/// Each type variable with the number of flows through it, most flows
+/// first, as a table or with `--json` a list of objects
+fn print_typevars(config: &Config, db: &Database) {
+    let mut typevars: Vec<(&str, usize)> = db.typevar_degrees().collect();
+    typevars.sort_by(|(a, a_flows), (b, b_flows)| b_flows.cmp(a_flows).then(a.cmp(b)));
+    if config.json {
+        let typevars: Vec<_> = typevars
+            .iter()
+            .map(|(name, flows)| serde_json::json!({ "name": name, "flows": flows }))
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&typevars).expect("Failed to serialize type variables")
+        );
+        return;
+    }
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!("{} {}\n", "Type variables:".bright_blue(), typevars.len());
+    println!(
+        "{}",
+        format!("{:<40} {:>8}", "Name", "Flows").bright_black()
+    );
+    for (name, flows) in typevars {
+        println!("{:<40} {:>8}", name, flows);
+    }
+}
+
+fn severity_label(severity: Severity) -> ColoredString {
+    let label = format!("{:<8}", severity);
+    match severity {
+        Severity::Info => label.bright_blue(),
+        Severity::Warning => label.yellow(),
+        Severity::Error => label.bright_red(),
+    }
+}
+
+fn print_rule_summary(results: &[RuleResult]) {
+    let name_width = results
+        .iter()
+        .map(|r| r.rule.name.len())
+        .max()
+        .unwrap_or(0)
+        .max("Rule".len());
+
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{}",
+        format!(
+            "{:<name_width$}  {:<8}  {:>7}  Tags",
+            "Rule", "Severity", "Matches"
+        )
+        .bright_blue()
+    );
+    for result in results {
+        let count = format!("{:>7}", result.flows.len());
+        println!(
+            "{:<name_width$}  {}  {}  {}",
+            result.rule.name,
+            severity_label(result.rule.severity),
+            if result.flows.is_empty() {
+                count.bright_black()
+            } else {
+                count.bold()
+            },
+            result.rule.tags.join(", ").bright_black()
+        );
+    }
+}
+
+fn print_rule_results(db: &Database, results: &[RuleResult], elaborate: bool) {
+    for result in results.iter().filter(|r| !r.flows.is_empty()) {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!(
+            "{} {} {}",
+            severity_label(result.rule.severity),
+            result.rule.name.bold(),
+            format!("({} matched)", result.flows.len()).bright_black()
+        );
+        if let Some(description) = &result.rule.description {
+            println!("{}", description.bright_black());
         }
+        println!("{} {}\n", "Query:".bright_blue(), result.rule.query);
+        print_results(db, &result.flows, elaborate, &[]);
+    }
+}
 
-        if itr > 1 && flow_idx < results.len() - 1 {
+fn print_regressions(db: &Database, regressions: &[Regression], elaborate: bool) {
+    println!("\n{}", "━".repeat(80).bright_black());
+    for regression in regressions {
+        println!(
+            "{} {} {} {}",
+            severity_label(regression.rule.severity),
+            regression.rule.name.bold(),
+            format!("+{} introduced", regression.introduced.len()).bright_red(),
+            format!("-{} fixed", regression.fixed.len()).bright_green()
+        );
+    }
+
+    for regression in regressions.iter().filter(|r| !r.introduced.is_empty()) {
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!(
+            "{} {}\n",
+            "Introduced by".bright_red(),
+            regression.rule.name.bold()
+        );
+        print_results(db, &regression.introduced, elaborate, &[]);
+    }
+}
+
+fn print_match_diff(old: &Database, new: &Database, diff: &MatchDiff, config: &Config) {
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!(
+        "{} {} {} {} {}",
+        "Query".bright_blue(),
+        config.query_json.bold(),
+        format!("+{} appeared", diff.appeared.len()).bright_red(),
+        format!("-{} disappeared", diff.disappeared.len()).bright_green(),
+        format!("~{} changed", diff.changed.len()).yellow()
+    );
+
+    for (label, db, flows) in [
+        ("Appeared".bright_red(), new, &diff.appeared),
+        ("Disappeared".bright_green(), old, &diff.disappeared),
+    ] {
+        if flows.is_empty() {
+            continue;
+        }
+        println!("\n{}", "━".repeat(80).bright_black());
+        println!("{} {}\n", label, flows.len());
+        print_results(db, flows, config.elaborate, &config.query);
+    }
+    if diff.changed.is_empty() {
+        return;
+    }
+    println!("\n{}", "━".repeat(80).bright_black());
+    println!("{} {}\n", "Changed".yellow(), diff.changed.len());
+    for (idx, (before, after)) in diff.changed.iter().enumerate() {
+        println!(
+            "{} {} {} {}",
+            "was:".bright_black(),
+            flow_id(before).bright_black(),
+            "now:".bright_black(),
+            flow_id(after)
+        );
+        if print_flow(new, after, config.elaborate, &[]) && idx < diff.changed.len() - 1 {
+            println!("{}", "━".repeat(80).bright_black());
+        }
+    }
+}
+
+/// The options set from the command line
+fn render_options() -> RenderOptions {
+    RENDER_OPTIONS.get().copied().unwrap_or_default()
+}
+
+fn print_federated_results(
+    databases: &[(Origin, Database)],
+    matches: &[FederatedMatch],
+    elaborate: bool,
+) {
+    println!("\n{}", "━".repeat(80).bright_black());
+    if matches.is_empty() {
+        println!("{}", "No data flows matched the query.\n".bright_red());
+        return;
+    }
+    println!(
+        "{} {}\n",
+        "Matched data flows:".bright_blue(),
+        matches.len()
+    );
+    let labels: Vec<String> = matches
+        .iter()
+        .map(|m| m.origins().collect::<Vec<_>>().join(", "))
+        .collect();
+    let width = labels.iter().map(String::len).max().unwrap_or(0);
+    for (idx, (m, label)) in matches.iter().zip(&labels).enumerate() {
+        let Some((_, db)) = databases.iter().find(|(o, _)| o.label == m.origin) else {
+            continue;
+        };
+        println!(
+            "{} {}",
+            format!("{:<width$}", label, width = width).bright_magenta(),
+            m.flow_id.bright_black()
+        );
+        if print_flow(db, m.flow, elaborate, &[]) && idx < matches.len() - 1 {
             println!("{}", "━".repeat(80).bright_black());
         }
     }
 }
+
+/// Print the flows matched by `query`, highlighting the steps that satisfied
+/// it. Flows the query does not match are printed without highlights.
+fn print_results(db: &Database, results: &[&[UnitFlow]], elaborate: bool, query: &[QueryOps]) {
+    print!("{}", renderer(db, elaborate).results(results, query));
+}
+
+/// A renderer of `db` with the options set on the command line
+fn renderer(db: &Database, elaborate: bool) -> Renderer<'_> {
+    Renderer::new(
+        db,
+        RenderOptions {
+            elaborate,
+            ..render_options()
+        },
+    )
+}
+
+/// Print the program locations of a flow as [Renderer::write_flow] writes
+/// them. Returns whether any location was printed.
+fn print_flow(db: &Database, flow: &[UnitFlow], elaborate: bool, marked: &[usize]) -> bool {
+    let mut out = String::new();
+    let printed = renderer(db, elaborate).write_flow(&mut out, flow, marked);
+    print!("{}", out);
+    printed
+}
@@ -0,0 +1,73 @@
+use crate::error::{self, SemanticSearchError};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Free-text notes attached to flows by [flow_id](crate::data::flow_id), kept in a sidecar file
+/// next to the database so they survive regenerating it.
+#[derive(Debug, Default)]
+pub struct Notes {
+    path: String,
+    /// Notes of each flow id, oldest first
+    pub notes: BTreeMap<String, Vec<String>>,
+}
+
+impl Notes {
+    /// `<data_json>.notes.json`
+    pub fn sidecar_path(data_json: &str) -> String {
+        format!("{}.notes.json", data_json)
+    }
+
+    /// Load the notes of the database at `data_json`. A missing sidecar file
+    /// holds no notes.
+    pub fn load(data_json: &str) -> error::Result<Self> {
+        let path = Self::sidecar_path(data_json);
+        let notes = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| SemanticSearchError::json(Some(&path), e))?,
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(Notes { path, notes })
+    }
+
+    pub fn add(&mut self, flow_id: &str, text: &str) {
+        self.notes
+            .entry(flow_id.to_string())
+            .or_default()
+            .push(text.to_string());
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(&self.notes).map_err(|e| e.to_string())?;
+        fs::write(&self.path, data).map_err(|e| format!("Could not write {}: {}", self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{flow_id, QueryOps};
+
+    #[test]
+    fn test_note_queries() {
+        let mut db = crate::tutorial::load_database();
+        let mut notes = Notes::default();
+        notes.add(&flow_id(&db.data_flows[0]), "Confirmed bug, see JIRA-123");
+        notes.add(&flow_id(&db.data_flows[2]), "false positive");
+        db.set_notes(notes.notes);
+
+        assert_eq!(
+            db.notes_for(&db.data_flows[0]),
+            ["Confirmed bug, see JIRA-123"]
+        );
+        assert!(db.notes_for(&db.data_flows[1]).is_empty());
+
+        let query = QueryOps::parse_query("note:confirmed bug").unwrap();
+        assert_eq!(query, vec![QueryOps::QNote("confirmed bug".to_string())]);
+        assert_eq!(db.matching_indices(&query), vec![0]);
+
+        let query = QueryOps::parse_query("bool, note:\"bug\"").unwrap();
+        assert_eq!(db.matching_indices(&query), vec![0]);
+        let query = QueryOps::parse_query("bool, note:positive").unwrap();
+        assert!(db.matching_indices(&query).is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::Config;
+use std::env;
+use std::io::{self, IsTerminal, Stdout, Write};
+use std::process::{Child, Command, Stdio};
+
+/// Pager run when `PAGER` is not set
+const DEFAULT_PAGER: &str = "less";
+
+/// `LESS` given to the pager when it is not set, as git does: quit at once
+/// when the output fits on one screen, pass colors through and leave the
+/// output on the screen on exit
+const DEFAULT_LESS: &str = "FRX";
+
+/// Where results are printed: stdout, or a pager reading them a screen at
+/// a time. Dropping the output waits for the pager to exit.
+pub enum Output {
+    Stdout(Stdout),
+    Pager(Child),
+}
+
+impl Output {
+    /// Stdout, through a pager when printing to a terminal unless
+    /// `--no-pager` is given or the search is `--watch`ed, where the pager
+    /// would hold up the next run
+    pub fn for_config(config: &Config) -> Output {
+        let paged = !config.no_pager && !config.watch && io::stdout().is_terminal();
+        if paged {
+            Output::pager(|name| env::var(name).ok())
+        } else {
+            Output::Stdout(io::stdout())
+        }
+    }
+
+    /// The pager [pager_command] picks reading variables through `var`, or
+    /// stdout when there is none or it cannot be started
+    fn pager(var: impl Fn(&str) -> Option<String>) -> Output {
+        let Some(pager) = pager_command(&var) else {
+            return Output::Stdout(io::stdout());
+        };
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&pager).stdin(Stdio::piped());
+        if var("LESS").is_none() {
+            command.env("LESS", DEFAULT_LESS);
+        }
+        match command.spawn() {
+            Ok(child) => Output::Pager(child),
+            Err(e) => {
+                tracing::warn!(pager, "could not start the pager: {}", e);
+                Output::Stdout(io::stdout())
+            }
+        }
+    }
+}
+
+/// The shell command paging output: `PAGER`, or `less` without it. An empty
+/// `PAGER` or `cat` turns paging off.
+pub fn pager_command(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let pager = var("PAGER").unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    match pager.trim() {
+        "" | "cat" => None,
+        pager => Some(pager.to_string()),
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(out) => out.write(buf),
+            Output::Pager(child) => match child.stdin.as_mut() {
+                // Once the pager is quit the rest of the output is dropped
+                Some(stdin) => match stdin.write(buf) {
+                    Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(buf.len()),
+                    written => written,
+                },
+                None => Ok(buf.len()),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(out) => out.flush(),
+            Output::Pager(child) => match child.stdin.as_mut().map(Write::flush) {
+                Some(Err(e)) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+impl Drop for Output {
+    fn drop(&mut self) {
+        if let Output::Pager(child) = self {
+            // Closing its input lets the pager reach the end of the output
+            child.stdin = None;
+            let _ = child.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_command() {
+        fn vars(pager: Option<&'static str>) -> impl Fn(&str) -> Option<String> {
+            move |name| match name {
+                "PAGER" => pager.map(String::from),
+                _ => None,
+            }
+        }
+        assert_eq!(pager_command(vars(None)).as_deref(), Some("less"));
+        assert_eq!(
+            pager_command(vars(Some("most -s"))).as_deref(),
+            Some("most -s")
+        );
+        assert_eq!(pager_command(vars(Some(""))), None);
+        assert_eq!(pager_command(vars(Some("cat"))), None);
+
+        let mut paged = Output::pager(vars(Some("cat > /dev/null")));
+        assert!(matches!(paged, Output::Pager(_)));
+        writeln!(paged, "paged").unwrap();
+        paged.flush().unwrap();
+    }
+}
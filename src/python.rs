@@ -0,0 +1,283 @@
+use crate::data::{Database, QueryOps};
+use crate::engine;
+use crate::error::SemanticSearchError;
+use pyo3::exceptions::{PyIOError, PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use pyo3::IntoPyObjectExt;
+use serde_json::{json, Map, Value};
+
+/// The `semantic_code_search` Python module:
+///
+/// ```python
+/// import semantic_code_search
+/// engine = semantic_code_search.open("data.json")
+/// for m in engine.search("List,@x.1"):
+///     print(m["flow_id"], m["file_path"], m["positions"])
+/// ```
+#[pymodule]
+fn semantic_code_search(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<SearchEngine>()?;
+    module.add_class::<Match>()?;
+    module.add_function(wrap_pyfunction!(open, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_query, module)?)?;
+    // Let matches pass for read-only dicts, as `isinstance(m, Mapping)`
+    let mapping = module.py().import("collections.abc")?.getattr("Mapping")?;
+    mapping.call_method1("register", (module.getattr("Match")?,))?;
+    Ok(())
+}
+
+impl From<SemanticSearchError> for PyErr {
+    fn from(error: SemanticSearchError) -> PyErr {
+        match error {
+            SemanticSearchError::Io { .. } => PyIOError::new_err(error.to_string()),
+            _ => PyValueError::new_err(error.to_string()),
+        }
+    }
+}
+
+/// Open the database at `path` for searching, as
+/// [engine::SearchEngine::open] does
+#[pyfunction]
+fn open(py: Python<'_>, path: &str) -> PyResult<SearchEngine> {
+    SearchEngine::new(py, path)
+}
+
+/// The steps of `query`, each written back in the query syntax, raising
+/// `ValueError` when it does not parse
+#[pyfunction]
+fn parse_query(query: &str) -> PyResult<Vec<String>> {
+    Ok(QueryOps::parse_query(query)?
+        .iter()
+        .map(QueryOps::to_string)
+        .collect())
+}
+
+/// A database opened for searching from Python, keeping the results of
+/// recent queries cached
+#[pyclass(module = "semantic_code_search", frozen)]
+pub struct SearchEngine {
+    engine: engine::SearchEngine,
+}
+
+#[pymethods]
+impl SearchEngine {
+    #[new]
+    fn new(py: Python<'_>, path: &str) -> PyResult<Self> {
+        let engine = py.allow_threads(|| engine::SearchEngine::open(path))?;
+        Ok(SearchEngine { engine })
+    }
+
+    /// The flows `query` matches, best first, at most `limit` of them. The
+    /// search runs without holding the GIL.
+    #[pyo3(signature = (query, limit = None))]
+    fn search(&self, py: Python<'_>, query: &str, limit: Option<usize>) -> PyResult<Vec<Match>> {
+        py.allow_threads(|| {
+            let query = QueryOps::parse_query(query)?;
+            let db = self.engine.database();
+            Ok(self
+                .engine
+                .search_ops(&query)
+                .iter()
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|m| Match::new(db, m))
+                .collect())
+        })
+    }
+
+    /// Number of flows `query` matches
+    fn count(&self, py: Python<'_>, query: &str) -> PyResult<usize> {
+        py.allow_threads(|| Ok(self.engine.search(query)?.len()))
+    }
+
+    #[getter]
+    fn file_path(&self) -> &str {
+        &self.engine.database().file_path
+    }
+
+    /// Number of flows in the database
+    fn __len__(&self) -> usize {
+        self.engine.database().data_flows.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "SearchEngine({:?}, flows={})",
+            self.file_path(),
+            self.__len__()
+        )
+    }
+}
+
+/// A matched flow, read like a dict with the keys of [Match::new]
+#[pyclass(module = "semantic_code_search", frozen, mapping)]
+pub struct Match {
+    fields: Map<String, Value>,
+}
+
+impl Match {
+    /// The fields of a match: the `flow_id`, `flow`, `notes` and `count` of
+    /// `--format json`, the `index` of the flow in the database, the
+    /// `file_path` it starts in, the `positions` of the steps the query
+    /// matched, the steps each name of the query `captures`, and its
+    /// `score`
+    fn new(db: &Database, m: &engine::Match) -> Self {
+        let captures: Map<String, Value> = m
+            .captures
+            .iter()
+            .map(|(name, idx)| (name.clone(), json!(m.flow[*idx])))
+            .collect();
+        let fields = [
+            ("flow_id", json!(m.flow_id)),
+            ("index", json!(m.index)),
+            ("file_path", json!(m.file_path)),
+            ("flow", json!(m.flow)),
+            ("notes", json!(db.notes_for(m.flow))),
+            ("count", json!(db.multiplicity(m.flow))),
+            ("positions", json!(m.positions)),
+            ("captures", Value::Object(captures)),
+            (
+                "score",
+                json!({
+                    "similarity": m.score.similarity,
+                    "skipped": m.score.skipped,
+                    "length": m.score.length,
+                }),
+            ),
+        ];
+        Match {
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        }
+    }
+}
+
+#[pymethods]
+impl Match {
+    fn __getitem__(&self, py: Python<'_>, key: &str) -> PyResult<PyObject> {
+        match self.fields.get(key) {
+            Some(value) => to_python(py, value),
+            None => Err(PyKeyError::new_err(key.to_string())),
+        }
+    }
+
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: &str, default: Option<PyObject>) -> PyResult<PyObject> {
+        match self.fields.get(key) {
+            Some(value) => to_python(py, value),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        }
+    }
+
+    fn __contains__(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    fn __len__(&self) -> usize {
+        self.fields.len()
+    }
+
+    fn __iter__(&self, py: Python<'_>) -> PyResult<PyObject> {
+        Ok(PyList::new(py, self.fields.keys())?
+            .as_any()
+            .try_iter()?
+            .into_any()
+            .unbind())
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.fields.keys().cloned().collect()
+    }
+
+    fn values(&self, py: Python<'_>) -> PyResult<Vec<PyObject>> {
+        self.fields
+            .values()
+            .map(|value| to_python(py, value))
+            .collect()
+    }
+
+    fn items(&self, py: Python<'_>) -> PyResult<Vec<(String, PyObject)>> {
+        self.fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), to_python(py, value)?)))
+            .collect()
+    }
+
+    /// The match as a plain dict
+    fn to_dict(&self, py: Python<'_>) -> PyResult<PyObject> {
+        to_python(py, &Value::Object(self.fields.clone()))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Match(flow_id={}, file_path={})",
+            self.fields["flow_id"], self.fields["file_path"]
+        )
+    }
+}
+
+/// `value` as the Python object `json.loads` would give for it
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    match value {
+        Value::Null => Ok(py.None()),
+        Value::Bool(b) => b.into_py_any(py),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(n), _) => n.into_py_any(py),
+            (None, Some(n)) => n.into_py_any(py),
+            (None, None) => n.as_f64().into_py_any(py),
+        },
+        Value::String(s) => s.into_py_any(py),
+        Value::Array(values) => {
+            let values = values
+                .iter()
+                .map(|value| to_python(py, value))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, values)?.into_py_any(py)
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (key, value) in fields {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_py_any(py)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tutorial::TUTORIAL_JSON;
+
+    #[test]
+    fn test_match_fields() {
+        let db = Database::load_from_str(TUTORIAL_JSON).unwrap();
+        let engine = engine::SearchEngine::new(db);
+        let results = engine.search("bool as b, @Tuple.1").unwrap();
+        let m = &results[0];
+        let fields = Match::new(engine.database(), m).fields;
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            [
+                "captures",
+                "count",
+                "file_path",
+                "flow",
+                "flow_id",
+                "index",
+                "notes",
+                "positions",
+                "score"
+            ]
+        );
+        assert_eq!(fields["flow_id"], json!(m.flow_id));
+        assert_eq!(fields["flow"], json!(m.flow));
+        assert_eq!(fields["captures"]["b"], json!(m.flow[m.captures["b"]]));
+        assert_eq!(fields["score"]["length"], json!(m.flow.len()));
+
+        assert_eq!(parse_query("List, @x.1").unwrap(), ["List", "@x.1"]);
+        assert!(parse_query("List,@x.").is_err());
+    }
+}
@@ -0,0 +1,392 @@
+use crate::data::{is_identifier, ArgIndex, QConstructorArg, QType, QueryOps};
+use crate::error::{self, SemanticSearchError};
+use std::fmt;
+
+/// A query built step by step, for programs that would rather not write
+/// queries in the query language:
+/// `Query::new().ty("List").ctor("x").index(1).desc_contains("guard")`.
+/// Each step method adds a step, and the others refine the last one; a
+/// refinement that does not apply to its step is reported by [Query::build].
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    steps: Vec<QueryOps>,
+    /// The first refinement that failed, with the index of its step
+    error: Option<(Option<usize>, String)>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a step, as [QueryOps::parse_query] would parse it
+    pub fn step(mut self, op: QueryOps) -> Self {
+        self.steps.push(op);
+        self
+    }
+
+    /// A type step, `List`; the name may be a pattern like `Li*`
+    pub fn ty(self, name: &str) -> Self {
+        self.step(QueryOps::QType(QType {
+            name: name.into(),
+            args: None,
+            desc: None,
+        }))
+    }
+
+    /// A constructor step, `@Cons`
+    pub fn ctor(self, name: &str) -> Self {
+        self.step(QueryOps::QConstructorArg(QConstructorArg {
+            name: name.into(),
+            arg_index: None,
+            desc: None,
+        }))
+    }
+
+    /// A type variable step unified with `count` others, `#2`
+    pub fn typevar(self, count: usize) -> Self {
+        self.step(QueryOps::QTypeVar(count))
+    }
+
+    /// The type variable `name`, whatever the number of flows through it,
+    /// `#'a`
+    pub fn typevar_named(self, name: &str) -> Self {
+        self.step(QueryOps::QTypeVarName {
+            name: name.into(),
+            min: 0,
+            max: None,
+        })
+    }
+
+    /// A step of any kind whose description contains `text`, `"text"`
+    pub fn desc(self, text: &str) -> Self {
+        self.step(QueryOps::QDesc(text.to_string()))
+    }
+
+    /// A step of a kind like `ProgLoc`, `%ProgLoc`
+    pub fn kind(self, kind: &str) -> Self {
+        self.step(QueryOps::QKind(kind.to_string()))
+    }
+
+    /// Flows with a note containing `text`, `note:text`
+    pub fn note(self, text: &str) -> Self {
+        self.step(QueryOps::QNote(text.to_string()))
+    }
+
+    /// Anchor the next step to the start of the flow, `^`
+    pub fn start(self) -> Self {
+        self.step(QueryOps::QStart)
+    }
+
+    /// Anchor the last step to the end of the flow, `$`
+    pub fn end(self) -> Self {
+        self.step(QueryOps::QEnd)
+    }
+
+    /// Any number of steps, `..`
+    pub fn gap(self) -> Self {
+        self.step(QueryOps::QGap)
+    }
+
+    /// Match the constructor step only at argument `idx`, `@Cons.1`
+    pub fn index(self, idx: usize) -> Self {
+        self.arg_index("index", ArgIndex::One(idx))
+    }
+
+    /// Match the constructor step at any of the arguments, `@Cons.{0,2}`
+    pub fn indices(self, indices: &[usize]) -> Self {
+        self.arg_index("indices", ArgIndex::Set(indices.to_vec()))
+    }
+
+    /// Match the constructor step at the arguments from `start` up to `end`
+    /// inclusive, `@Cons.0..2`
+    pub fn index_range(self, start: usize, end: usize) -> Self {
+        if start > end {
+            let message = format!("Invalid argument range {}..{}", start, end);
+            return self.fail(message);
+        }
+        self.arg_index("index_range", ArgIndex::Range { start, end })
+    }
+
+    /// Match the type step only with these type arguments, `_` matching any:
+    /// `Map<_, int>`
+    pub fn args(self, args: &[&str]) -> Self {
+        if args.iter().any(|arg| arg.trim().is_empty()) {
+            return self.fail("Invalid type arguments".to_string());
+        }
+        self.refine("args", |op| match innermost(op) {
+            QueryOps::QType(q) => {
+                q.args = Some(args.iter().map(|arg| arg.trim().to_string()).collect());
+                Ok(())
+            }
+            op => Err(format!("Cannot give type arguments to `{}`", op)),
+        })
+    }
+
+    /// Match the type or constructor step only with a description containing
+    /// `text`, or matched by it as a pattern, `List:text`
+    pub fn desc_contains(self, text: &str) -> Self {
+        self.description("desc_contains", text.to_string())
+    }
+
+    /// Match the type or constructor step only with a description like
+    /// `text`, `List:~"text"`
+    pub fn desc_like(self, text: &str) -> Self {
+        self.description("desc_like", format!("~{}", text))
+    }
+
+    /// Bind the flow step matched by the last step to `name`, `List as name`
+    pub fn bind(self, name: &str) -> Self {
+        if !is_identifier(name) {
+            return self.fail(format!("Invalid name `{}`", name));
+        }
+        self.wrap("bind", |op| match op {
+            QueryOps::QStart
+            | QueryOps::QEnd
+            | QueryOps::QGap
+            | QueryOps::QNote(_)
+            | QueryOps::QLen(_)
+            | QueryOps::QNot(_)
+            | QueryOps::QAt(..)
+            | QueryOps::QRepeat { .. }
+            | QueryOps::QEdge { .. } => Err(format!("Cannot bind `{}`", op)),
+            op => Ok(QueryOps::QBind(name.to_string(), Box::new(op))),
+        })
+    }
+
+    /// Match flows without the last step, `!List`
+    pub fn negate(self) -> Self {
+        self.wrap("negate", |op| {
+            if op.is_structural() {
+                Err(format!("Cannot negate `{}`", op))
+            } else {
+                Ok(QueryOps::QNot(Box::new(op)))
+            }
+        })
+    }
+
+    /// Match the last step only as the flow step at `idx`, `[idx]List`
+    pub fn at(self, idx: usize) -> Self {
+        self.wrap("at", |op| match op {
+            QueryOps::QStart
+            | QueryOps::QEnd
+            | QueryOps::QGap
+            | QueryOps::QNote(_)
+            | QueryOps::QLen(_)
+            | QueryOps::QAt(..)
+            | QueryOps::QEdge { .. } => Err(format!("Cannot position `{}`", op)),
+            op => Ok(QueryOps::QAt(idx, Box::new(op))),
+        })
+    }
+
+    /// Match the last step from `min` up to `max` times, or unbounded above
+    /// without `max`: `List{1,3}`
+    pub fn repeat(self, min: usize, max: Option<usize>) -> Self {
+        if max.is_some_and(|max| max == 0 || max < min) {
+            let max = max.map_or(String::new(), |max| max.to_string());
+            return self.fail(format!("Invalid repetition `{{{},{}}}`", min, max));
+        }
+        self.wrap("repeat", |op| {
+            if op.repeatable() {
+                Ok(QueryOps::QRepeat {
+                    op: Box::new(op),
+                    min,
+                    max,
+                })
+            } else {
+                Err(format!("Cannot repeat `{}`", op))
+            }
+        })
+    }
+
+    /// The steps of the query, or the first refinement that did not apply
+    /// as a [SemanticSearchError::QueryParse] pointing at its step in the
+    /// query as written in the query language
+    pub fn build(self) -> error::Result<Vec<QueryOps>> {
+        let text = self.to_string();
+        if let Some((step, message)) = self.error.clone() {
+            let (position, end) = step.map_or((0, text.len()), |step| self.span(step));
+            return Err(SemanticSearchError::QueryParse {
+                query: text,
+                position,
+                end,
+                message,
+            });
+        }
+        match QueryOps::check_repeats(&self.steps) {
+            Ok(()) => Ok(self.steps),
+            Err(message) => Err(SemanticSearchError::QueryParse {
+                end: text.len(),
+                query: text,
+                position: 0,
+                message,
+            }),
+        }
+    }
+
+    /// Byte range of step `idx` in the query as [Display](fmt::Display)ed
+    fn span(&self, idx: usize) -> (usize, usize) {
+        let start: usize = self.steps[..idx]
+            .iter()
+            .map(|op| op.to_string().len() + 2)
+            .sum();
+        (start, start + self.steps[idx].to_string().len())
+    }
+
+    fn fail(mut self, message: String) -> Self {
+        if self.error.is_none() {
+            self.error = Some((self.steps.len().checked_sub(1), message));
+        }
+        self
+    }
+
+    /// Change the last step in place, through any binding, position,
+    /// negation or repetition around it
+    fn refine(mut self, what: &str, f: impl FnOnce(&mut QueryOps) -> Result<(), String>) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let result = match self.steps.last_mut() {
+            Some(op) => f(op),
+            None => Err(format!("`{}` needs a step before it", what)),
+        };
+        match result {
+            Ok(()) => self,
+            Err(message) => self.fail(message),
+        }
+    }
+
+    /// Replace the last step with one around it
+    fn wrap(mut self, what: &str, f: impl FnOnce(QueryOps) -> Result<QueryOps, String>) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let Some(op) = self.steps.pop() else {
+            return self.fail(format!("`{}` needs a step before it", what));
+        };
+        match f(op.clone()) {
+            Ok(wrapped) => self.step(wrapped),
+            Err(message) => self.step(op).fail(message),
+        }
+    }
+
+    fn arg_index(self, what: &str, idx: ArgIndex) -> Self {
+        self.refine(what, |op| match innermost(op) {
+            QueryOps::QConstructorArg(q) => {
+                q.arg_index = Some(idx);
+                Ok(())
+            }
+            op => Err(format!("Cannot give an argument index to `{}`", op)),
+        })
+    }
+
+    fn description(self, what: &str, desc: String) -> Self {
+        if desc.trim_start_matches('~').is_empty() {
+            return self.fail("Expected a description".to_string());
+        }
+        self.refine(what, |op| match innermost(op) {
+            QueryOps::QType(QType { desc: d, .. })
+            | QueryOps::QConstructorArg(QConstructorArg { desc: d, .. }) => {
+                *d = Some(desc);
+                Ok(())
+            }
+            op => Err(format!("Cannot describe `{}`", op)),
+        })
+    }
+}
+
+/// The step `op` wraps, if it binds, positions, negates or repeats one
+fn innermost(op: &mut QueryOps) -> &mut QueryOps {
+    match op {
+        QueryOps::QBind(_, inner)
+        | QueryOps::QAt(_, inner)
+        | QueryOps::QNot(inner)
+        | QueryOps::QRepeat { op: inner, .. } => innermost(inner),
+        op => op,
+    }
+}
+
+/// The query in the query language
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let steps: Vec<String> = self.steps.iter().map(ToString::to_string).collect();
+        write!(f, "{}", steps.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_builder() {
+        let parse = |query: &str| QueryOps::parse_query(query).unwrap();
+        let query = Query::new()
+            .ty("List")
+            .ctor("x")
+            .index(1)
+            .desc_contains("guard");
+        assert_eq!(query.to_string(), "List, @x.1:guard");
+        assert_eq!(query.build().unwrap(), parse("List, @x.1:guard"));
+        assert_eq!(
+            Query::new()
+                .start()
+                .ty("Map")
+                .args(&["_", "int"])
+                .bind("m")
+                .desc_like("user input")
+                .gap()
+                .ctor("Cons")
+                .index_range(0, 2)
+                .negate()
+                .typevar(2)
+                .repeat(2, Some(2))
+                .build()
+                .unwrap(),
+            parse("^, Map<_, int>:~\"user input\" as m, .., !@Cons.0..2, #2{2}")
+        );
+
+        let db = crate::tutorial::load_database();
+        let query = Query::new()
+            .ty("bool")
+            .ctor("Tuple")
+            .index(1)
+            .desc("if-then-else")
+            .build()
+            .unwrap();
+        assert_eq!(db.count_matches(&query), 1);
+
+        let error = |query: Query| match query.build() {
+            Err(SemanticSearchError::QueryParse {
+                query,
+                position,
+                end,
+                message,
+            }) => (query[position..end].to_string(), message),
+            _ => panic!("expected an error"),
+        };
+        assert_eq!(
+            error(Query::new().ty("List").index(1).ctor("x")),
+            (
+                "List".to_string(),
+                "Cannot give an argument index to `List`".to_string()
+            )
+        );
+        assert_eq!(error(Query::new().gap().negate()).1, "Cannot negate `..`");
+        assert_eq!(
+            error(Query::new().desc_contains("x")).1,
+            "`desc_contains` needs a step before it"
+        );
+        assert_eq!(
+            error(Query::new().ty("List").bind("1x")).1,
+            "Invalid name `1x`"
+        );
+        let varying = Query::new()
+            .ty("List")
+            .bind("l")
+            .gap()
+            .ty("int")
+            .repeat(1, None);
+        assert!(error(varying).1.starts_with("Cannot bind names"));
+    }
+}
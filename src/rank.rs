@@ -0,0 +1,112 @@
+use crate::engine::Match;
+use std::cmp::Ordering;
+use std::ops::Deref;
+
+/// How well a flow matched a query. Flows are ranked by description
+/// similarity first, then by how few steps the query skipped over, then by
+/// length, shorter flows first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    /// How closely the flow's descriptions resemble the fuzzy `~"..."` steps
+    /// of the query, as given by [crate::data::Database::fuzzy_desc_score];
+    /// 0 without any
+    pub similarity: f64,
+    /// Steps of the flow between the first and last matched ones that no
+    /// query step matched
+    pub skipped: usize,
+    /// Number of steps in the flow
+    pub length: usize,
+}
+
+impl Score {
+    /// Score a flow of `length` steps whose steps at `positions` matched
+    pub fn new(positions: &[usize], length: usize, similarity: f64) -> Self {
+        let span = match (positions.iter().min(), positions.iter().max()) {
+            (Some(first), Some(last)) => last - first + 1,
+            _ => 0,
+        };
+        Score {
+            similarity,
+            skipped: span.saturating_sub(positions.len()),
+            length,
+        }
+    }
+
+    /// Ordering with the better score first
+    pub fn rank(&self, other: &Score) -> Ordering {
+        other
+            .similarity
+            .total_cmp(&self.similarity)
+            .then(self.skipped.cmp(&other.skipped))
+            .then(self.length.cmp(&other.length))
+    }
+}
+
+/// Matches ordered best first by [Score::rank]; flows scoring the same keep
+/// their database order
+#[derive(Debug, Clone, Default)]
+pub struct Ranked<'a> {
+    matches: Vec<Match<'a>>,
+    truncated: bool,
+}
+
+impl<'a> Ranked<'a> {
+    pub fn new(mut matches: Vec<Match<'a>>) -> Self {
+        matches.sort_by(|a, b| a.score.rank(&b.score));
+        Ranked {
+            matches,
+            truncated: false,
+        }
+    }
+
+    /// Mark the matches as those found before the search was cancelled
+    pub fn truncated(mut self) -> Self {
+        self.truncated = true;
+        self
+    }
+
+    /// Whether the search was cancelled before every flow was matched, so
+    /// these are only some of the matches
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Keep only the best `n` matches
+    pub fn top(mut self, n: usize) -> Self {
+        self.matches.truncate(n);
+        self
+    }
+
+    pub fn into_vec(self) -> Vec<Match<'a>> {
+        self.matches
+    }
+}
+
+impl<'a> Deref for Ranked<'a> {
+    type Target = [Match<'a>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchEngine;
+
+    #[test]
+    fn test_ranking() {
+        let score = Score::new(&[0, 3], 5, 0.0);
+        assert_eq!(score.skipped, 2);
+        assert_eq!(score.rank(&Score::new(&[0, 1], 5, 0.0)), Ordering::Greater);
+        assert_eq!(score.rank(&Score::new(&[0, 1], 5, 0.5)), Ordering::Greater);
+        assert_eq!(score.rank(&Score::new(&[0, 3], 6, 0.0)), Ordering::Less);
+
+        let engine = SearchEngine::new(crate::tutorial::load_database());
+        let ranked = engine.search_ops(&crate::data::QueryOps::parse_query("bool").unwrap());
+        let indices: Vec<_> = ranked.iter().map(|m| m.index).collect();
+        assert_eq!(indices, vec![5, 0]);
+        assert_eq!(ranked.top(1).len(), 1);
+    }
+}
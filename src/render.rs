@@ -0,0 +1,323 @@
+use crate::data::{
+    flow_id, Database, Highlight, HighlightStyle, ProgLoc, QueryOps, UnitFlow, DEFAULT_TAB_WIDTH,
+    STEP_ARROW,
+};
+use crate::link;
+use crate::Config;
+use colored::*;
+use std::fmt::Write;
+
+/// How a [Renderer] draws flows, as set on the command line
+#[derive(Debug, Clone, Copy)]
+pub struct RenderOptions {
+    /// Lines of source shown around each location
+    pub context: usize,
+    /// Head each flow with its id
+    pub show_id: bool,
+    /// Head each flow with all its steps, the matched ones highlighted
+    pub show_steps: bool,
+    /// Show the type flowing through each location
+    pub elaborate: bool,
+    pub highlight_style: HighlightStyle,
+    /// Link locations to their file with OSC 8 hyperlinks
+    pub hyperlinks: bool,
+    /// Columns between the tab stops tabs in source lines are expanded to
+    pub tab_width: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            context: 0,
+            show_id: false,
+            show_steps: false,
+            elaborate: false,
+            highlight_style: HighlightStyle::default(),
+            hyperlinks: false,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+}
+
+impl RenderOptions {
+    /// The options `config` asks for, without hyperlinks, which depend on
+    /// the terminal printed to
+    pub fn from_config(config: &Config) -> Self {
+        RenderOptions {
+            context: config.context,
+            show_id: config.show_id,
+            show_steps: config.show_steps,
+            elaborate: config.elaborate,
+            highlight_style: config.highlight_style,
+            hyperlinks: false,
+            tab_width: config.tab_width,
+        }
+    }
+}
+
+/// Renders flows of a database to the text the command line prints, colored
+/// as `colored` is set to, so it can be captured and tested instead
+pub struct Renderer<'a> {
+    db: &'a Database,
+    options: RenderOptions,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(db: &'a Database, options: RenderOptions) -> Self {
+        Renderer { db, options }
+    }
+
+    /// The flows matched by `query`, highlighting the steps that satisfied
+    /// it. Flows the query does not match are rendered without highlights.
+    pub fn results(&self, results: &[&[UnitFlow]], query: &[QueryOps]) -> String {
+        let mut out = String::new();
+        for (flow_idx, flow) in results.iter().enumerate() {
+            if self.write_result(&mut out, flow, query) && flow_idx < results.len() - 1 {
+                let _ = writeln!(out, "{}", "━".repeat(80).bright_black());
+            }
+        }
+        out
+    }
+
+    /// Write one flow matched by `query` to `out` as [Renderer::results]
+    /// does, headed by its id, steps, matched steps, captures and
+    /// multiplicity as the options ask. Returns whether any location was
+    /// written.
+    pub fn write_result(&self, out: &mut String, flow: &[UnitFlow], query: &[QueryOps]) -> bool {
+        if self.options.show_id {
+            let _ = writeln!(out, "{} {}", "id:".bright_black(), flow_id(flow));
+        }
+        let positions = self
+            .db
+            .match_flow_positions(flow, query)
+            .unwrap_or_default();
+        if self.options.show_steps {
+            let steps: Vec<String> = flow
+                .iter()
+                .enumerate()
+                .map(|(idx, uf)| {
+                    if positions.contains(&idx) {
+                        uf.to_string().yellow().to_string()
+                    } else {
+                        uf.to_string()
+                    }
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "{} {}",
+                "steps:".bright_black(),
+                steps.join(&STEP_ARROW.bright_black().to_string())
+            );
+        }
+        let matched: Vec<_> = positions
+            .iter()
+            .map(|idx| &flow[*idx])
+            .filter(|uf| !matches!(uf, UnitFlow::ProgLoc(_)))
+            .map(UnitFlow::label)
+            .collect();
+        if !matched.is_empty() {
+            let _ = writeln!(
+                out,
+                "{} {}",
+                "matched:".bright_black(),
+                matched.join(", ").yellow()
+            );
+        }
+        let captured: Vec<_> = QueryOps::captures(query, &positions)
+            .into_iter()
+            .map(|(name, idx)| match &flow[idx] {
+                UnitFlow::ProgLoc(loc) => {
+                    format!("{} = `{}`", name, loc.span_text().unwrap_or(""))
+                }
+                uf => format!("{} = {}", name, uf.label()),
+            })
+            .collect();
+        if !captured.is_empty() {
+            let _ = writeln!(
+                out,
+                "{} {}",
+                "captured:".bright_black(),
+                captured.join(", ").cyan()
+            );
+        }
+        let seen = self.db.multiplicity(flow);
+        if seen > 1 {
+            let _ = writeln!(out, "{} {} times", "seen:".bright_black(), seen);
+        }
+        self.write_flow(out, flow, &positions)
+    }
+
+    /// Write the narration of a flow to `out`, one numbered sentence per
+    /// step, headed by `Flow {number}` and its id when the options ask
+    pub fn write_narration(&self, out: &mut String, number: usize, flow: &[UnitFlow]) {
+        let heading = format!("Flow {}", number).bold();
+        let _ = if self.options.show_id {
+            writeln!(out, "{} {}", heading, flow_id(flow).bright_black())
+        } else {
+            writeln!(out, "{}", heading)
+        };
+        for (step, sentence) in UnitFlow::narrate_flow(flow, &self.db.language)
+            .iter()
+            .enumerate()
+        {
+            let _ = writeln!(
+                out,
+                "{} {} {}",
+                format!("{:>4}.", step + 1).bright_blue(),
+                "│".bright_black(),
+                sentence
+            );
+        }
+    }
+
+    /// The program locations of a flow, as [Renderer::write_flow] writes
+    /// them
+    pub fn flow(&self, flow: &[UnitFlow], marked: &[usize]) -> String {
+        let mut out = String::new();
+        self.write_flow(&mut out, flow, marked);
+        out
+    }
+
+    /// Write the program locations of a flow to `out`, highlighting the steps
+    /// in `marked` and dimming the other locations when any location is
+    /// marked, followed by its notes. Returns whether any location was
+    /// written.
+    pub fn write_flow(&self, out: &mut String, flow: &[UnitFlow], marked: &[usize]) -> bool {
+        let db = self.db;
+        let types = UnitFlow::elaborated_types(flow, &db.language);
+        let prog_locs: Vec<_> = flow
+            .iter()
+            .zip(&types)
+            .enumerate()
+            .filter_map(|(idx, (uf, ty))| match uf {
+                UnitFlow::ProgLoc(pl) => Some((idx, pl, ty)),
+                _ => None,
+            })
+            .collect();
+
+        if prog_locs.is_empty() {
+            let _ = writeln!(
+                out,
+                "{}",
+                "No program locations found for this data flow.".bright_red()
+            );
+            return false;
+        }
+
+        let any_marked = prog_locs.iter().any(|(idx, _, _)| marked.contains(idx));
+        let mut itr = 1;
+        for (idx, loc, ty) in prog_locs {
+            let highlight = if marked.contains(&idx) {
+                Highlight::Marked
+            } else if !any_marked {
+                Highlight::Normal
+            } else {
+                Highlight::Dimmed
+            };
+            let context = match self.options.context {
+                0 => None,
+                n => loc.context_lines(&db.file_path, n),
+            };
+            if let Some(pos) = loc
+                .position(&db.file_path)
+                .filter(|_| loc.has_valid_range())
+            {
+                let text = link::location(&pos);
+                let text = if self.options.hyperlinks {
+                    link::hyperlink(&text, &pos)
+                } else {
+                    text
+                };
+                let _ = writeln!(out, "{} {}", "  -->".bright_black(), text.bright_black());
+            }
+            if let Some((above, _)) = &context {
+                *out += &loc.render_context(above);
+            }
+            let (style, tab_width) = (self.options.highlight_style, self.options.tab_width);
+            if let Some(text) =
+                ProgLoc::render_marked_location(loc, &itr, highlight, style, tab_width)
+            {
+                *out += &text;
+                itr += 1;
+                if let Some((_, below)) = &context {
+                    *out += &loc.render_context(below);
+                }
+                if let (true, Some(ty)) = (self.options.elaborate, ty) {
+                    let _ = writeln!(out, "{} {}", "      :".bright_black(), ty.cyan());
+                }
+            };
+        }
+        for note in db.notes_for(flow) {
+            let _ = writeln!(out, "{} {}", "  note:".bright_black(), note.yellow());
+        }
+
+        itr > 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &str = r#"{
+        "file_path": "lib/main.ml",
+        "dataflow": [
+            [{"Type": {"name": "int", "args": [], "desc": null}},
+             {"ProgLoc": {"line": "let x = a < b", "char_range": [5, 6], "desc": null, "depth": 0, "line_number": 3}},
+             {"Type": {"name": "bool", "args": [], "desc": null}},
+             {"ProgLoc": {"line": "if x then y", "char_range": [4, 5], "desc": null, "depth": 1, "line_number": 4}}],
+            [{"Type": {"name": "string", "args": [], "desc": null}}]
+        ]
+    }"#;
+
+    /// `text` without the spaces carets are padded with
+    fn trimmed(text: &str) -> String {
+        text.lines()
+            .map(str::trim_end)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_render_results() {
+        colored::control::set_override(false);
+        let db = Database::load_from_str(DATA).unwrap();
+        let query = QueryOps::parse_query("int, bool as b").unwrap();
+        let options = RenderOptions {
+            show_steps: true,
+            ..RenderOptions::default()
+        };
+        let flows = db.search(&query);
+        let rendered = Renderer::new(&db, options).results(&flows, &query);
+        insta::assert_snapshot!(trimmed(&rendered), @r"
+        steps: int → [line 3] → bool → [line 4]
+        matched: int, bool
+        captured: b = bool
+          --> lib/main.ml:3:5
+        [1]  │ let x = a < b
+             └     ^
+          --> lib/main.ml:4:4
+        [2]  │   if x then y
+             └      ^
+        ");
+    }
+
+    #[test]
+    fn test_render_flow() {
+        colored::control::set_override(false);
+        let db = Database::load_from_str(DATA).unwrap();
+        let options = RenderOptions {
+            highlight_style: HighlightStyle::Inline,
+            ..RenderOptions::default()
+        };
+        let renderer = Renderer::new(&db, options);
+        insta::assert_snapshot!(renderer.flow(&db.data_flows[1], &[]), @"No program locations found for this data flow.");
+        insta::assert_snapshot!(renderer.flow(&db.data_flows[0], &[2]), @r"
+          --> lib/main.ml:3:5
+        [1]  │ let x = a < b
+          --> lib/main.ml:4:4
+        [2]  │   if x then y
+        ");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::data::{Database, QueryOps};
+
+/// A query applied in the REPL and the flows it left
+pub struct Refinement {
+    pub query: String,
+    /// Indices of the matched flows in [Database::data_flows]
+    pub flows: Vec<usize>,
+}
+
+/// Starts of input lines that narrow the current results instead of
+/// searching the whole database
+pub const REFINE_PREFIXES: [&str; 3] = ["&&", "narrow ", ":refine "];
+
+/// Interactive search state: the trail of refinements applied so far, each
+/// narrowing the result set of the one before it.
+pub struct Session<'a> {
+    pub db: &'a Database,
+    trail: Vec<Refinement>,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Session { db, trail: vec![] }
+    }
+
+    /// Interpret a line of input: `&& <ops>`, `narrow <ops>` or
+    /// `:refine <ops>` narrows the current result set, anything else
+    /// searches the whole database.
+    pub fn eval(&mut self, input: &str) -> Result<&Refinement, String> {
+        let input = input.trim();
+        match REFINE_PREFIXES
+            .iter()
+            .find_map(|prefix| input.strip_prefix(prefix))
+        {
+            Some(query) => self.narrow(query),
+            None => self.search(input),
+        }
+    }
+
+    /// Search the whole database, starting a new trail
+    pub fn search(&mut self, query: &str) -> Result<&Refinement, String> {
+        let ops = QueryOps::parse_query(query)?;
+        self.trail.clear();
+        self.trail.push(Refinement {
+            query: query.trim().to_string(),
+            flows: self.db.matching_indices(&ops),
+        });
+        Ok(&self.trail[0])
+    }
+
+    /// Narrow the current result set, or search the whole database when
+    /// there is none yet
+    pub fn narrow(&mut self, query: &str) -> Result<&Refinement, String> {
+        let Some(current) = self.trail.last() else {
+            return self.search(query);
+        };
+        let ops = QueryOps::parse_query(query)?;
+        let flows = self.db.matching_indices_in(&current.flows, &ops);
+        self.trail.push(Refinement {
+            query: query.trim().to_string(),
+            flows,
+        });
+        Ok(&self.trail[self.trail.len() - 1])
+    }
+
+    /// Undo the last refinement
+    pub fn pop(&mut self) -> Option<Refinement> {
+        self.trail.pop()
+    }
+
+    pub fn current(&self) -> Option<&Refinement> {
+        self.trail.last()
+    }
+
+    pub fn trail(&self) -> &[Refinement] {
+        &self.trail
+    }
+
+    /// A single query matching the current results on its own: the queries
+    /// of the trail joined with `&&`
+    pub fn resolved_query(&self) -> String {
+        self.trail
+            .iter()
+            .map(|refinement| refinement.query.as_str())
+            .collect::<Vec<_>>()
+            .join(" && ")
+    }
+}
+
+/// Completions for the query token ending at `pos` in `line`: type names for
+/// a bare token, type variable names after `'`, constructor names after `@`
+/// and descriptions after `"`.
+/// Returns the byte offset the candidates replace from, as rustyline expects.
+pub fn complete(db: &Database, line: &str, pos: usize) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let mut start = before.rfind(',').map_or(0, |idx| idx + 1);
+    let mut token = &before[start..];
+    for prefix in REFINE_PREFIXES {
+        if start == 0 {
+            if let Some(rest) = token.trim_start().strip_prefix(prefix) {
+                start = pos - rest.len();
+                token = rest;
+            }
+        }
+    }
+    let trimmed = token.trim_start();
+    start += token.len() - trimmed.len();
+
+    let candidates = if let Some(name) = trimmed.strip_prefix('@') {
+        db.constructor_names()
+            .into_iter()
+            .filter(|c| c.starts_with(name))
+            .map(|c| format!("@{}", c))
+            .collect()
+    } else if let Some(desc) = trimmed.strip_prefix('"') {
+        db.descriptions()
+            .into_iter()
+            .filter(|d| d.starts_with(desc))
+            .map(|d| format!("\"{}\"", d))
+            .collect()
+    } else if trimmed.starts_with('#') {
+        vec![]
+    } else if trimmed.starts_with('\'') {
+        db.typevar_names()
+            .filter(|tv| tv.starts_with(trimmed))
+            .map(str::to_string)
+            .collect()
+    } else {
+        db.type_names()
+            .filter(|t| t.starts_with(trimmed))
+            .map(str::to_string)
+            .collect()
+    };
+    (start, candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refinement_trail() {
+        let db = crate::tutorial::load_database();
+        let mut session = Session::new(&db);
+
+        assert_eq!(session.eval("bool").unwrap().flows, vec![0, 5]);
+        assert_eq!(session.eval("&& @Tuple").unwrap().flows, vec![0]);
+        assert_eq!(session.trail().len(), 2);
+        assert_eq!(session.resolved_query(), "bool && @Tuple");
+        let resolved = QueryOps::parse_query(&session.resolved_query()).unwrap();
+        assert_eq!(db.matching_indices(&resolved), vec![0]);
+
+        session.pop();
+        assert_eq!(session.current().unwrap().flows, vec![0, 5]);
+
+        assert_eq!(
+            session
+                .eval("narrow \"if-then-else condition\"")
+                .unwrap()
+                .flows,
+            vec![0, 5]
+        );
+        assert_eq!(session.eval("List").unwrap().flows, vec![2, 3]);
+        assert_eq!(session.trail().len(), 1);
+        assert_eq!(session.eval(":refine @Some").unwrap().flows, vec![3]);
+        assert_eq!(session.trail().len(), 2);
+    }
+
+    #[test]
+    fn test_completion() {
+        let db = crate::tutorial::load_database();
+
+        assert_eq!(complete(&db, "Li", 2), (0, vec!["List".to_string()]));
+        assert_eq!(
+            complete(&db, "bool, @Tu", 9),
+            (6, vec!["@Tuple".to_string()])
+        );
+        assert_eq!(
+            complete(&db, "&& \"if-th", 9),
+            (3, vec!["\"if-then-else condition\"".to_string()])
+        );
+        assert!(complete(&db, "#", 1).1.is_empty());
+        assert_eq!(
+            complete(&db, "List, '", 7),
+            (6, vec!["'a".to_string(), "'b".to_string()])
+        );
+    }
+}
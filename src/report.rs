@@ -0,0 +1,286 @@
+use crate::data::{flow_id, Database, ProgLoc, UnitFlow, DEFAULT_TAB_WIDTH};
+use crate::results::group_by_file;
+use crate::rules::RuleResult;
+use std::fmt::Write;
+
+/// Document formats a [Report] renders to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    pub fn parse(name: &str) -> Option<ReportFormat> {
+        match name {
+            "md" => Some(ReportFormat::Markdown),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Matched flows as a self-contained document to paste into pull requests
+/// and wikis: the query at the top, then the flows grouped by file with
+/// their steps and highlighted source lines. Rule packs get a section per
+/// rule.
+pub struct Report<'a> {
+    db: &'a Database,
+    title: String,
+    sections: Vec<Section<'a>>,
+}
+
+struct Section<'a> {
+    /// Rule the section is for, none for a search
+    heading: Option<String>,
+    description: Option<&'a str>,
+    query: &'a str,
+    flows: Vec<&'a [UnitFlow]>,
+}
+
+impl<'a> Report<'a> {
+    /// The flows matched by a search for `query`
+    pub fn for_search(db: &'a Database, query: &'a str, flows: &[&'a [UnitFlow]]) -> Self {
+        Report {
+            db,
+            title: "Data flows".to_string(),
+            sections: vec![Section {
+                heading: None,
+                description: None,
+                query,
+                flows: flows.to_vec(),
+            }],
+        }
+    }
+
+    /// The flows matched by each rule of a rule pack
+    pub fn for_rules(db: &'a Database, results: &[RuleResult<'a>]) -> Self {
+        Report {
+            db,
+            title: "Rule report".to_string(),
+            sections: results
+                .iter()
+                .map(|r| Section {
+                    heading: Some(format!("{} ({})", r.rule.name, r.rule.severity)),
+                    description: r.rule.description.as_deref(),
+                    query: &r.rule.query,
+                    flows: r.flows.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Markdown => self.markdown(),
+            ReportFormat::Html => self.html(),
+        }
+    }
+
+    fn markdown(&self) -> String {
+        let mut out = format!("# {}\n\nDatabase: `{}`\n", self.title, self.db.file_path);
+        for section in &self.sections {
+            let level = match &section.heading {
+                Some(heading) => {
+                    let _ = write!(out, "\n## {}\n", heading);
+                    "###"
+                }
+                None => "##",
+            };
+            if let Some(description) = section.description {
+                let _ = write!(out, "\n{}\n", description);
+            }
+            let _ = write!(
+                out,
+                "\nQuery: `{}`, {} matched\n",
+                section.query,
+                matched(section.flows.len())
+            );
+            for group in group_by_file(self.db, &section.flows) {
+                let _ = write!(out, "\n{} `{}`\n", level, group.file);
+                for flow in group.flows {
+                    let id = flow_id(flow);
+                    let _ = write!(
+                        out,
+                        "\n- `{}` {}\n",
+                        &id[..8.min(id.len())],
+                        UnitFlow::render_flow(flow).replace('`', "'")
+                    );
+                    let locations: Vec<&ProgLoc> = locations(flow).collect();
+                    if locations.is_empty() {
+                        continue;
+                    }
+                    out += "\n  ```text\n";
+                    for loc in locations {
+                        let gutter = gutter(loc);
+                        let indent = " ".repeat(loc.depth() * 2);
+                        let line = loc.expanded_line(DEFAULT_TAB_WIDTH);
+                        let _ = writeln!(out, "  {} │ {}{}", gutter, indent, line);
+                        let _ = writeln!(
+                            out,
+                            "  {} │ {}",
+                            " ".repeat(gutter.len()),
+                            loc.carets(DEFAULT_TAB_WIDTH).trim_end()
+                        );
+                    }
+                    out += "  ```\n";
+                }
+            }
+        }
+        out
+    }
+
+    fn html(&self) -> String {
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p>Database: <code>{}</code></p>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title),
+            escape(&self.db.file_path)
+        );
+        for section in &self.sections {
+            let level = match &section.heading {
+                Some(heading) => {
+                    let _ = writeln!(out, "<h2>{}</h2>", escape(heading));
+                    3
+                }
+                None => 2,
+            };
+            if let Some(description) = section.description {
+                let _ = writeln!(out, "<p>{}</p>", escape(description));
+            }
+            let _ = writeln!(
+                out,
+                "<p>Query: <code>{}</code>, {} matched</p>",
+                escape(section.query),
+                matched(section.flows.len())
+            );
+            for group in group_by_file(self.db, &section.flows) {
+                let _ = writeln!(
+                    out,
+                    "<h{0}><code>{1}</code></h{0}>",
+                    level,
+                    escape(group.file)
+                );
+                for flow in group.flows {
+                    let id = flow_id(flow);
+                    let _ = writeln!(
+                        out,
+                        "<div class=\"flow\" id=\"{}\">\n<p class=\"steps\">{}</p>",
+                        id,
+                        escape(&UnitFlow::render_flow(flow))
+                    );
+                    let locations: Vec<&ProgLoc> = locations(flow).collect();
+                    if !locations.is_empty() {
+                        out += "<pre>";
+                        for loc in locations {
+                            let indent = " ".repeat(loc.depth() * 2);
+                            let line = match loc.split_span() {
+                                Some((before, span, after)) => format!(
+                                    "{}<mark>{}</mark>{}",
+                                    escape(before),
+                                    escape(span),
+                                    escape(after)
+                                ),
+                                None => escape(loc.line().trim_end_matches('\r')),
+                            };
+                            let _ = writeln!(
+                                out,
+                                "<span class=\"gutter\">{} │</span> {}{}",
+                                gutter(loc),
+                                indent,
+                                line
+                            );
+                        }
+                        out += "</pre>\n";
+                    }
+                    out += "</div>\n";
+                }
+            }
+        }
+        out + "</body>\n</html>\n"
+    }
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:auto}\
+pre{background:#f6f8fa;padding:.5em;overflow-x:auto}\
+mark{background:#fff3a3}.gutter{color:#888}\
+.flow{border-top:1px solid #ddd;margin-top:1em}.steps{font-family:monospace}";
+
+fn locations(flow: &[UnitFlow]) -> impl Iterator<Item = &ProgLoc> {
+    flow.iter().filter_map(|uf| match uf {
+        UnitFlow::ProgLoc(loc) => Some(loc),
+        _ => None,
+    })
+}
+
+/// The line number of a location, right aligned, or blank without one
+fn gutter(loc: &ProgLoc) -> String {
+    match loc.line_number() {
+        Some(line) => format!("{:>4}", line),
+        None => " ".repeat(4),
+    }
+}
+
+fn matched(flows: usize) -> String {
+    match flows {
+        1 => "1 flow".to_string(),
+        n => format!("{} flows", n),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+    use crate::rules::RulePack;
+
+    const DATA: &str = r#"{
+        "file_path": "lib/main.ml",
+        "dataflow": [[
+            {"Type": {"name": "int", "args": [], "desc": null}},
+            {"ProgLoc": {"line": "let x = a < b", "char_range": [5, 6], "desc": null, "depth": 0, "line_number": 3}},
+            {"ProgLoc": {"line": "print x", "char_range": [7, 8], "desc": null, "depth": 0, "line_number": 9}}
+        ]]
+    }"#;
+
+    #[test]
+    fn test_search_report() {
+        let db = Database::load_from_str(DATA).unwrap();
+        let query = QueryOps::parse_query("int").unwrap();
+        let flows = db.search(&query);
+        let report = Report::for_search(&db, "int", &flows);
+
+        let md = report.render(ReportFormat::Markdown);
+        assert!(md.starts_with("# Data flows\n"));
+        assert!(md.contains("Query: `int`, 1 flow matched"));
+        assert!(md.contains("## `lib/main.ml`"));
+        assert!(md.contains("     3 │ let x = a < b\n"));
+        assert!(md.contains("       │     ^\n"));
+
+        let html = report.render(ReportFormat::Html);
+        assert!(html.contains("let <mark>x</mark> = a &lt; b"));
+        assert!(html.contains("<h2><code>lib/main.ml</code></h2>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    #[test]
+    fn test_rules_report() {
+        let db = Database::load_from_str(DATA).unwrap();
+        let pack = RulePack::parse(
+            "[[rule]]\nname = \"ints\"\nquery = \"int\"\ndescription = \"Integers\"\n\n[[rule]]\nname = \"lists\"\nquery = \"List\"\n",
+        )
+        .unwrap();
+        let md = Report::for_rules(&db, &pack.evaluate(&db)).render(ReportFormat::Markdown);
+        assert!(md.contains("## ints (warning)\n\nIntegers\n"));
+        assert!(md.contains("### `lib/main.ml`"));
+        assert!(md.contains("## lists (warning)\n\nQuery: `List`, 0 flows matched"));
+    }
+}
@@ -0,0 +1,476 @@
+use crate::data::{flow_id, Database, QueryOps, UnitFlow};
+use crate::pager::Output;
+use crate::render::{RenderOptions, Renderer};
+use crate::results::SearchResult;
+use crate::sarif::SarifLog;
+use crate::Config;
+use colored::*;
+use std::io::{self, Write};
+
+/// Receives the flows a search matched, one at a time, so that they can be
+/// printed or sent elsewhere: [report] calls `report_start` once, then
+/// `report_match` for each matched flow in the order they are shown, then
+/// `report_summary`. The command line picks one with [for_config];
+/// embedders can implement their own.
+pub trait Reporter {
+    /// Called before the first match with the query searched and every flow
+    /// it matched, for headers that need to know them all
+    fn report_start(
+        &mut self,
+        _db: &Database,
+        _query: &str,
+        _matches: &[&[UnitFlow]],
+    ) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called for each matched flow
+    fn report_match(&mut self, db: &Database, flow: &[UnitFlow]) -> io::Result<()>;
+
+    /// Called after the last match
+    fn report_summary(&mut self, _db: &Database) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Report the flows a search for `query` matched to `reporter`
+pub fn report(
+    reporter: &mut dyn Reporter,
+    db: &Database,
+    query: &str,
+    matches: &[&[UnitFlow]],
+) -> io::Result<()> {
+    reporter.report_start(db, query, matches)?;
+    for flow in matches {
+        reporter.report_match(db, flow)?;
+    }
+    reporter.report_summary(db)
+}
+
+/// The reporter for search results `--format`, `--json` and `--quiet`
+/// select, printing to stdout, or a pager as [Output::for_config] decides,
+/// and drawing flows with `options`
+pub fn for_config(config: &Config, options: RenderOptions) -> Box<dyn Reporter> {
+    if config.quiet {
+        return Box::new(QuietReporter);
+    }
+    let out = Output::for_config(config);
+    if config.sarif {
+        Box::new(SarifReporter::new(out))
+    } else if config.quickfix {
+        Box::new(QuickfixReporter::new(out))
+    } else if config.json {
+        Box::new(JsonReporter::new(out))
+    } else {
+        Box::new(TerminalReporter::new(out, options, config.narrate))
+    }
+}
+
+/// Reports nothing, for `--quiet`
+pub struct QuietReporter;
+
+impl Reporter for QuietReporter {
+    fn report_match(&mut self, _db: &Database, _flow: &[UnitFlow]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Matched flows grouped by the file they start in
+struct FileOutput {
+    file: String,
+    text: String,
+    flows: usize,
+    /// Whether the last flow wrote locations, so the next is ruled off
+    rule: bool,
+}
+
+/// Draws matched flows as the command line prints them: a count of the
+/// matches, then the flows grouped by file with their locations highlighted,
+/// or narrated step by step. Flows are written out once all have been
+/// reported, as the groups are only known then.
+pub struct TerminalReporter<W: Write> {
+    out: W,
+    options: RenderOptions,
+    narrate: bool,
+    query: Vec<QueryOps>,
+    groups: Vec<FileOutput>,
+}
+
+impl<W: Write> TerminalReporter<W> {
+    pub fn new(out: W, options: RenderOptions, narrate: bool) -> Self {
+        TerminalReporter {
+            out,
+            options,
+            narrate,
+            query: vec![],
+            groups: vec![],
+        }
+    }
+}
+
+impl<W: Write> Reporter for TerminalReporter<W> {
+    fn report_start(
+        &mut self,
+        db: &Database,
+        query: &str,
+        matches: &[&[UnitFlow]],
+    ) -> io::Result<()> {
+        self.query = QueryOps::parse_query(query).unwrap_or_default();
+        self.groups.clear();
+        writeln!(self.out, "\n{}", "━".repeat(80).bright_black())?;
+        if matches.is_empty() {
+            return writeln!(
+                self.out,
+                "{}",
+                "No data flows matched the query.\n".bright_red()
+            );
+        }
+        let seen: usize = matches.iter().map(|flow| db.multiplicity(flow)).sum();
+        if seen > matches.len() {
+            writeln!(
+                self.out,
+                "{} {} unique, seen {} times\n",
+                "Matched data flows:".bright_blue(),
+                matches.len(),
+                seen
+            )
+        } else {
+            writeln!(
+                self.out,
+                "{} {}\n",
+                "Matched data flows:".bright_blue(),
+                matches.len()
+            )
+        }
+    }
+
+    fn report_match(&mut self, db: &Database, flow: &[UnitFlow]) -> io::Result<()> {
+        let file = db.flow_file(flow);
+        let idx = match self.groups.iter().position(|group| group.file == file) {
+            Some(idx) => idx,
+            None => {
+                self.groups.push(FileOutput {
+                    file: file.to_string(),
+                    text: String::new(),
+                    flows: 0,
+                    rule: false,
+                });
+                self.groups.len() - 1
+            }
+        };
+        let group = &mut self.groups[idx];
+        let renderer = Renderer::new(db, self.options);
+        if group.rule || (self.narrate && group.flows > 0) {
+            group.text += &format!("{}\n", "━".repeat(80).bright_black());
+        }
+        group.flows += 1;
+        if self.narrate {
+            renderer.write_narration(&mut group.text, group.flows, flow);
+        } else {
+            group.rule = renderer.write_result(&mut group.text, flow, &self.query);
+        }
+        Ok(())
+    }
+
+    fn report_summary(&mut self, _db: &Database) -> io::Result<()> {
+        let grouped = self.groups.len() > 1;
+        for group in self.groups.drain(..) {
+            if grouped {
+                writeln!(
+                    self.out,
+                    "{} {} {}\n",
+                    "File".bright_blue().bold(),
+                    group.file.bold(),
+                    format!("({} flows)", group.flows).bright_black()
+                )?;
+            }
+            write!(self.out, "{}", group.text)?;
+        }
+        self.out.flush()
+    }
+}
+
+/// Writes matched flows as a JSON array of [SearchResult]s, each as soon as
+/// it is reported
+pub struct JsonReporter<W: Write> {
+    out: W,
+    query: Vec<QueryOps>,
+    written: usize,
+}
+
+impl<W: Write> JsonReporter<W> {
+    pub fn new(out: W) -> Self {
+        JsonReporter {
+            out,
+            query: vec![],
+            written: 0,
+        }
+    }
+}
+
+impl<W: Write> Reporter for JsonReporter<W> {
+    fn report_start(
+        &mut self,
+        _db: &Database,
+        query: &str,
+        _matches: &[&[UnitFlow]],
+    ) -> io::Result<()> {
+        self.query = QueryOps::parse_query(query).unwrap_or_default();
+        self.written = 0;
+        Ok(())
+    }
+
+    fn report_match(&mut self, db: &Database, flow: &[UnitFlow]) -> io::Result<()> {
+        let result = SearchResult::new(db, flow).with_captures(db, &self.query);
+        let json = serde_json::to_string_pretty(&result)?;
+        let separator = if self.written == 0 { "[" } else { "," };
+        self.written += 1;
+        writeln!(self.out, "{}", separator)?;
+        // Indented as an element of the array printed pretty
+        let lines: Vec<String> = json.lines().map(|line| format!("  {}", line)).collect();
+        write!(self.out, "{}", lines.join("\n"))
+    }
+
+    fn report_summary(&mut self, _db: &Database) -> io::Result<()> {
+        match self.written {
+            0 => writeln!(self.out, "[]")?,
+            _ => writeln!(self.out, "\n]")?,
+        }
+        self.out.flush()
+    }
+}
+
+/// Writes matched flows as a SARIF 2.1.0 log once all have been reported
+pub struct SarifReporter<W: Write> {
+    out: W,
+    query: String,
+    flows: Vec<Vec<UnitFlow>>,
+}
+
+impl<W: Write> SarifReporter<W> {
+    pub fn new(out: W) -> Self {
+        SarifReporter {
+            out,
+            query: String::new(),
+            flows: vec![],
+        }
+    }
+}
+
+impl<W: Write> Reporter for SarifReporter<W> {
+    fn report_start(
+        &mut self,
+        _db: &Database,
+        query: &str,
+        _matches: &[&[UnitFlow]],
+    ) -> io::Result<()> {
+        self.query = query.to_string();
+        self.flows.clear();
+        Ok(())
+    }
+
+    fn report_match(&mut self, _db: &Database, flow: &[UnitFlow]) -> io::Result<()> {
+        self.flows.push(flow.to_vec());
+        Ok(())
+    }
+
+    fn report_summary(&mut self, db: &Database) -> io::Result<()> {
+        let flows: Vec<&[UnitFlow]> = self.flows.iter().map(Vec::as_slice).collect();
+        let log = SarifLog::for_search(db, &self.query, &flows);
+        writeln!(self.out, "{}", serde_json::to_string_pretty(&log)?)?;
+        self.out.flush()
+    }
+}
+
+/// Writes a `file:line:col: message` line for each location of the matched
+/// flows that has a line number, as Vim's quickfix list (`:cfile`) and
+/// Emacs' compilation mode read them. The message is the query, or the rule
+/// name when reporting a rule pack, with the id of the flow. Fails when
+/// none of the matched flows has a line to jump to, as the list would be
+/// empty.
+pub struct QuickfixReporter<W: Write> {
+    out: W,
+    message: String,
+    matches: usize,
+    lines: usize,
+}
+
+impl<W: Write> QuickfixReporter<W> {
+    pub fn new(out: W) -> Self {
+        QuickfixReporter {
+            out,
+            message: String::new(),
+            matches: 0,
+            lines: 0,
+        }
+    }
+}
+
+impl<W: Write> Reporter for QuickfixReporter<W> {
+    fn report_start(
+        &mut self,
+        _db: &Database,
+        query: &str,
+        _matches: &[&[UnitFlow]],
+    ) -> io::Result<()> {
+        self.message = query.to_string();
+        Ok(())
+    }
+
+    fn report_match(&mut self, db: &Database, flow: &[UnitFlow]) -> io::Result<()> {
+        self.matches += 1;
+        let id = flow_id(flow);
+        let locations = flow.iter().filter_map(|uf| match uf {
+            UnitFlow::ProgLoc(loc) => Some(loc),
+            _ => None,
+        });
+        for loc in locations {
+            let Some(position) = loc.position(&db.file_path) else {
+                continue;
+            };
+            writeln!(
+                self.out,
+                "{}:{}:{}: {} [{}]",
+                position.file,
+                position.line,
+                loc.columns().0,
+                self.message,
+                &id[..8.min(id.len())]
+            )?;
+            self.lines += 1;
+        }
+        Ok(())
+    }
+
+    fn report_summary(&mut self, _db: &Database) -> io::Result<()> {
+        self.out.flush()?;
+        if self.matches > 0 && self.lines == 0 {
+            return Err(io::Error::other(format!(
+                "none of the {} matched flows has a location with a line number",
+                self.matches
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the ids of the matched flows, as an embedder's sink might
+    #[derive(Default)]
+    struct Collect {
+        started: bool,
+        ids: Vec<String>,
+        finished: bool,
+    }
+
+    impl Reporter for Collect {
+        fn report_start(
+            &mut self,
+            _db: &Database,
+            _query: &str,
+            _matches: &[&[UnitFlow]],
+        ) -> io::Result<()> {
+            self.started = true;
+            Ok(())
+        }
+
+        fn report_match(&mut self, _db: &Database, flow: &[UnitFlow]) -> io::Result<()> {
+            self.ids.push(crate::data::flow_id(flow));
+            Ok(())
+        }
+
+        fn report_summary(&mut self, _db: &Database) -> io::Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_reporters() {
+        colored::control::set_override(false);
+        let db = crate::tutorial::load_database();
+        let query = "List";
+        let matches = db.search(&QueryOps::parse_query(query).unwrap());
+
+        let mut collect = Collect::default();
+        report(&mut collect, &db, query, &matches).unwrap();
+        assert!(collect.started && collect.finished);
+        assert_eq!(collect.ids.len(), matches.len());
+
+        let mut json = JsonReporter::new(vec![]);
+        report(&mut json, &db, query, &matches).unwrap();
+        let results: Vec<_> = matches
+            .iter()
+            .map(|flow| SearchResult::new(&db, flow))
+            .collect();
+        assert_eq!(
+            String::from_utf8(json.out).unwrap(),
+            serde_json::to_string_pretty(&results).unwrap() + "\n"
+        );
+        let mut empty = JsonReporter::new(vec![]);
+        report(&mut empty, &db, query, &[]).unwrap();
+        assert_eq!(empty.out, b"[]\n");
+
+        let mut terminal = TerminalReporter::new(vec![], RenderOptions::default(), false);
+        report(&mut terminal, &db, query, &matches).unwrap();
+        let text = String::from_utf8(terminal.out).unwrap();
+        assert!(text.contains(&format!("Matched data flows: {}", matches.len())));
+        assert!(text.contains(
+            &Renderer::new(&db, RenderOptions::default())
+                .results(&matches, &QueryOps::parse_query(query).unwrap())
+        ));
+
+        let mut sarif = SarifReporter::new(vec![]);
+        report(&mut sarif, &db, query, &matches).unwrap();
+        let log: serde_json::Value = serde_json::from_slice(&sarif.out).unwrap();
+        assert_eq!(
+            log["runs"][0]["results"].as_array().unwrap().len(),
+            matches.len()
+        );
+    }
+
+    #[test]
+    fn test_quickfix_reporter() {
+        let data = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ProgLoc": {"line": "let x = 1", "char_range": [5, 6], "desc": null, "depth": 0, "line_number": 3}},
+                {"ProgLoc": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0}},
+                {"ProgLoc": {"line": "print x", "char_range": [7, 8], "desc": null, "depth": 0, "file": "lib/out.ml", "line_number": 9}}
+            ]]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let matches = db.search(&QueryOps::parse_query("int").unwrap());
+        let mut quickfix = QuickfixReporter::new(vec![]);
+        report(&mut quickfix, &db, "int", &matches).unwrap();
+        assert_eq!((quickfix.matches, quickfix.lines), (1, 2));
+        let id = flow_id(matches[0]);
+        assert_eq!(
+            String::from_utf8(quickfix.out).unwrap(),
+            format!(
+                "lib/main.ml:3:5: int [{0}]\nlib/out.ml:9:7: int [{0}]\n",
+                &id[..8]
+            )
+        );
+
+        let lineless = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ProgLoc": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0}}
+            ]]
+        }"#;
+        let db = Database::load_from_str(lineless).unwrap();
+        let matches = db.search(&QueryOps::parse_query("int").unwrap());
+        let mut quickfix = QuickfixReporter::new(vec![]);
+        let error = report(&mut quickfix, &db, "int", &matches).unwrap_err();
+        assert!(error.to_string().contains("none of the 1 matched flows"));
+        assert!(quickfix.out.is_empty());
+        let mut quickfix = QuickfixReporter::new(vec![]);
+        assert!(report(&mut quickfix, &db, "int", &[]).is_ok());
+    }
+}
@@ -0,0 +1,371 @@
+use crate::data::{flow_id, Database, QueryOps, UnitFlow};
+use crate::rank::Score;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+/// A matched flow as printed by `--format json`
+#[derive(Debug, Serialize)]
+pub struct SearchResult<'a> {
+    pub flow_id: String,
+    pub flow: &'a [UnitFlow],
+    /// Notes attached to the flow, oldest first
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    pub notes: &'a [String],
+    /// Flow steps captured by the names the query binds
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub captures: BTreeMap<String, &'a UnitFlow>,
+    /// Times the flow was seen in the database before identical copies were
+    /// merged
+    pub count: usize,
+}
+
+impl<'a> SearchResult<'a> {
+    pub fn new(db: &'a Database, flow: &'a [UnitFlow]) -> Self {
+        SearchResult {
+            flow_id: flow_id(flow),
+            flow,
+            notes: db.notes_for(flow),
+            captures: BTreeMap::new(),
+            count: db.multiplicity(flow),
+        }
+    }
+
+    /// Report the flow steps captured by the names `query` binds
+    pub fn with_captures(mut self, db: &Database, query: &[QueryOps]) -> Self {
+        self.captures = db.captures(self.flow, query).unwrap_or_default();
+        self
+    }
+}
+
+/// Order matched flows are shown and exported in, chosen with `--sort`.
+/// Without one, flows keep database order, which changes whenever the
+/// generator reorders its output; every other order breaks ties by
+/// [flow_id] so it depends only on the flows themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Database order
+    Flows,
+    /// By [flow_id]
+    Id,
+    /// By the file each flow starts in, then the line of its first program
+    /// location
+    File,
+    /// By the line of the first program location, flows without one last
+    Line,
+    /// Best ranked first, as by [Score::rank]
+    Score,
+    /// Shortest flows first
+    Length,
+}
+
+impl SortOrder {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "flows" => Some(SortOrder::Flows),
+            "id" => Some(SortOrder::Id),
+            "file" => Some(SortOrder::File),
+            "line" => Some(SortOrder::Line),
+            "score" => Some(SortOrder::Score),
+            "length" => Some(SortOrder::Length),
+            _ => None,
+        }
+    }
+
+    /// Reorder `flows` of `db` matched by `query`. Only identical flows
+    /// compare equal, keeping their current order, except in database order.
+    pub fn sort(self, db: &Database, query: &[QueryOps], flows: &mut [&[UnitFlow]]) {
+        // Flows without a line sort after those with one
+        let line = |flow: &[UnitFlow]| {
+            let line = first_line(flow);
+            (line.is_none(), line)
+        };
+        match self {
+            // Flows from elsewhere, such as stitched ones, keep their order
+            // after those of the database
+            SortOrder::Flows => {
+                flows.sort_by_key(|flow| db.data_flows.position(flow).unwrap_or(usize::MAX))
+            }
+            SortOrder::Id => flows.sort_by_cached_key(|flow| flow_id(flow)),
+            SortOrder::File => {
+                flows.sort_by_cached_key(|flow| (db.flow_file(flow), line(flow), flow_id(flow)))
+            }
+            SortOrder::Line => flows.sort_by_cached_key(|flow| (line(flow), flow_id(flow))),
+            SortOrder::Score => {
+                // Score each flow once rather than on every comparison
+                let mut scored: Vec<_> = flows
+                    .iter()
+                    .map(|&flow| {
+                        let positions = db.match_flow_positions(flow, query).unwrap_or_default();
+                        let score =
+                            Score::new(&positions, flow.len(), db.fuzzy_desc_score(flow, query));
+                        (score, flow_id(flow), flow)
+                    })
+                    .collect();
+                scored.sort_by(|(a, a_id, _), (b, b_id, _)| a.rank(b).then_with(|| a_id.cmp(b_id)));
+                for (slot, (_, _, flow)) in flows.iter_mut().zip(scored) {
+                    *slot = flow;
+                }
+            }
+            SortOrder::Length => flows.sort_by_cached_key(|flow| (flow.len(), flow_id(flow))),
+        }
+    }
+}
+
+/// Line number of the first program location of `flow`, if it has one
+fn first_line(flow: &[UnitFlow]) -> Option<usize> {
+    flow.iter()
+        .find_map(|uf| match uf {
+            UnitFlow::ProgLoc(loc) => Some(loc.line_number()),
+            _ => None,
+        })
+        .flatten()
+}
+
+/// Matched flows that start in the same file
+#[derive(Debug)]
+pub struct FileGroup<'a> {
+    pub file: &'a str,
+    pub flows: Vec<&'a [UnitFlow]>,
+}
+
+/// Group `flows` by [Database::flow_file], ordering the groups by their
+/// first flow and keeping the order of the flows within each
+pub fn group_by_file<'a>(db: &'a Database, flows: &[&'a [UnitFlow]]) -> Vec<FileGroup<'a>> {
+    let mut groups: Vec<FileGroup> = vec![];
+    for flow in flows {
+        let file = db.flow_file(flow);
+        match groups.iter_mut().find(|group| group.file == file) {
+            Some(group) => group.flows.push(flow),
+            None => groups.push(FileGroup {
+                file,
+                flows: vec![flow],
+            }),
+        }
+    }
+    groups
+}
+
+/// How a saved result set is combined with another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Union,
+    Intersect,
+    Minus,
+}
+
+/// A saved search result: the ids of the matched flows and the query that
+/// matched them, so results can be re-rendered or combined without
+/// re-running the search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultSet {
+    /// Database the results were computed against
+    pub data_json: String,
+    pub query: String,
+    /// [flow_id] of every matched flow, in result order
+    pub flow_ids: Vec<String>,
+}
+
+impl ResultSet {
+    pub fn new(data_json: &str, query: &str, flows: &[&[UnitFlow]]) -> Self {
+        ResultSet {
+            data_json: data_json.to_string(),
+            query: query.to_string(),
+            flow_ids: flows.iter().map(|flow| flow_id(flow)).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        fs::write(path, data).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data =
+            fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        serde_json::from_str(&data).map_err(|e| format!("Invalid result set {}: {}", path, e))
+    }
+
+    /// The flows of `db` with the saved ids, in result order. Ids no longer
+    /// present in the database are skipped.
+    pub fn flows<'a>(&self, db: &'a Database) -> Vec<&'a [UnitFlow]> {
+        db.flows_by_id(&self.flow_ids)
+    }
+
+    pub fn apply(&self, op: SetOp, other: &ResultSet) -> ResultSet {
+        match op {
+            SetOp::Union => self.union(other),
+            SetOp::Intersect => self.intersect(other),
+            SetOp::Minus => self.minus(other),
+        }
+    }
+
+    /// Flows in either result set
+    pub fn union(&self, other: &ResultSet) -> ResultSet {
+        let mut flow_ids = self.flow_ids.clone();
+        flow_ids.extend(
+            other
+                .flow_ids
+                .iter()
+                .filter(|id| !self.flow_ids.contains(id))
+                .cloned(),
+        );
+        self.combine(other, "|", flow_ids)
+    }
+
+    /// Flows in both result sets
+    pub fn intersect(&self, other: &ResultSet) -> ResultSet {
+        let flow_ids = self
+            .flow_ids
+            .iter()
+            .filter(|id| other.flow_ids.contains(id))
+            .cloned()
+            .collect();
+        self.combine(other, "&", flow_ids)
+    }
+
+    /// Flows in this result set but not the other
+    pub fn minus(&self, other: &ResultSet) -> ResultSet {
+        let flow_ids = self
+            .flow_ids
+            .iter()
+            .filter(|id| !other.flow_ids.contains(id))
+            .cloned()
+            .collect();
+        self.combine(other, "-", flow_ids)
+    }
+
+    fn combine(&self, other: &ResultSet, op: &str, flow_ids: Vec<String>) -> ResultSet {
+        ResultSet {
+            data_json: self.data_json.clone(),
+            query: format!("({}) {} ({})", self.query, op, other.query),
+            flow_ids,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result_set(query: &str, ids: &[&str]) -> ResultSet {
+        ResultSet {
+            data_json: "db.json".to_string(),
+            query: query.to_string(),
+            flow_ids: ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_result_set_operations() {
+        let a = result_set("List", &["1", "2", "3"]);
+        let b = result_set("bool", &["3", "4"]);
+
+        assert_eq!(a.union(&b).flow_ids, vec!["1", "2", "3", "4"]);
+        assert_eq!(a.intersect(&b).flow_ids, vec!["3"]);
+        assert_eq!(a.minus(&b).flow_ids, vec!["1", "2"]);
+        assert_eq!(a.minus(&b).query, "(List) - (bool)");
+    }
+
+    #[test]
+    fn test_sort_and_group_by_file() {
+        let data = r#"{
+            "file_path": "main.ml",
+            "dataflow": [
+                [{"Type": {"name": "int", "args": [], "desc": null}},
+                 {"ProgLoc": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0, "file": "b.ml"}},
+                 {"ProgLoc": {"line": "y", "char_range": [1, 2], "desc": null, "depth": 0}}],
+                [{"Type": {"name": "int", "args": [], "desc": null}},
+                 {"ProgLoc": {"line": "z", "char_range": [1, 2], "desc": null, "depth": 0, "file": "a.ml"}}],
+                [{"Type": {"name": "int", "args": [], "desc": null}},
+                 {"ProgLoc": {"line": "w", "char_range": [1, 2], "desc": null, "depth": 0, "file": "b.ml"}}]
+            ]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let all: Vec<_> = db.data_flows.iter().collect();
+        let order = |flows: &[&[UnitFlow]]| -> Vec<usize> {
+            flows
+                .iter()
+                .map(|flow| all.iter().position(|f| std::ptr::eq(*f, *flow)).unwrap())
+                .collect()
+        };
+
+        let mut sorted = all.clone();
+        // Flows 1 and 2 tie on length and flows 0 and 2 on file, so their
+        // order is that of their ids
+        let id_order = |a: usize, b: usize| {
+            if flow_id(all[a]) < flow_id(all[b]) {
+                vec![a, b]
+            } else {
+                vec![b, a]
+            }
+        };
+        SortOrder::Length.sort(&db, &[], &mut sorted);
+        assert_eq!(order(&sorted), [id_order(1, 2), vec![0]].concat());
+        SortOrder::File.sort(&db, &[], &mut sorted);
+        assert_eq!(order(&sorted), [vec![1], id_order(0, 2)].concat());
+        SortOrder::Flows.sort(&db, &[], &mut sorted);
+        assert_eq!(order(&sorted), vec![0, 1, 2]);
+        let copy = all[0].to_vec();
+        let mut mixed = vec![&copy[..], all[2], all[1]];
+        SortOrder::Flows.sort(&db, &[], &mut mixed);
+        assert!(std::ptr::eq(mixed[0], all[1]) && std::ptr::eq(mixed[2], &copy[..]));
+
+        let groups = group_by_file(&db, &all);
+        let sizes: Vec<_> = groups.iter().map(|g| (g.file, g.flows.len())).collect();
+        assert_eq!(sizes, vec![("b.ml", 2), ("a.ml", 1)]);
+
+        let numbered = r#"{
+            "file_path": "main.ml",
+            "dataflow": [
+                [{"ProgLoc": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0, "file": "b.ml", "line_number": 4}}],
+                [{"ProgLoc": {"line": "y", "char_range": [1, 2], "desc": null, "depth": 0, "file": "b.ml", "line_number": 2}}],
+                [{"ProgLoc": {"line": "z", "char_range": [1, 2], "desc": null, "depth": 0, "file": "a.ml"}}],
+                [{"ProgLoc": {"line": "w", "char_range": [1, 2], "desc": null, "depth": 0, "file": "a.ml", "line_number": 9}}]
+            ]
+        }"#;
+        let db = Database::load_from_str(numbered).unwrap();
+        let all: Vec<_> = db.data_flows.iter().collect();
+        let sorted_by = |sort: SortOrder, flows: &[&[UnitFlow]]| -> Vec<usize> {
+            let mut flows = flows.to_vec();
+            sort.sort(&db, &[], &mut flows);
+            flows
+                .iter()
+                .map(|flow| all.iter().position(|f| std::ptr::eq(*f, *flow)).unwrap())
+                .collect()
+        };
+        let reversed: Vec<_> = all.iter().rev().copied().collect();
+        for sort in [
+            SortOrder::Id,
+            SortOrder::File,
+            SortOrder::Line,
+            SortOrder::Length,
+        ] {
+            assert_eq!(sorted_by(sort, &all), sorted_by(sort, &reversed));
+        }
+        assert_eq!(sorted_by(SortOrder::File, &reversed), vec![3, 2, 1, 0]);
+        assert_eq!(sorted_by(SortOrder::Line, &reversed), vec![1, 0, 3, 2]);
+        let mut ids: Vec<_> = all.iter().map(|flow| flow_id(flow)).collect();
+        ids.sort();
+        let by_id: Vec<_> = sorted_by(SortOrder::Id, &all)
+            .into_iter()
+            .map(|idx| flow_id(all[idx]))
+            .collect();
+        assert_eq!(by_id, ids);
+    }
+
+    #[test]
+    fn test_search_result_json() {
+        let mut db = crate::tutorial::load_database();
+        let id = flow_id(&db.data_flows[0]);
+        db.set_notes([(id.clone(), vec!["checked".to_string()])].into());
+
+        let json = |flow: usize| {
+            serde_json::to_value(SearchResult::new(&db, &db.data_flows[flow])).unwrap()
+        };
+        assert_eq!(json(0)["flow_id"].as_str(), Some(id.as_str()));
+        assert_eq!(json(0)["notes"][0].as_str(), Some("checked"));
+        assert!(json(0)["flow"][0]["Type"].is_object());
+        assert!(json(1).get("notes").is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::daemon::Daemon;
+use crate::data::QueryOps;
+use crate::results::SearchResult;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// JSON-RPC 2.0 error codes
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const INVALID_REQUEST: i64 = -32600;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+/// The query or database of a `search` could not be used
+const SEARCH_FAILED: i64 = -32000;
+
+/// A JSON-RPC 2.0 request. Requests without an `id` are notifications and
+/// get no response.
+#[derive(Debug, Deserialize)]
+struct RpcRequest {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub(crate) fn success(id: Value, result: Value) -> Self {
+        RpcResponse {
+            jsonrpc: "2.0",
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub(crate) fn error(id: Value, code: i64, message: String) -> Self {
+        RpcResponse {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(RpcError { code, message }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+/// Parameters of the `search` method
+#[derive(Debug, Deserialize)]
+struct SearchParams {
+    data_json: String,
+    query: String,
+    /// Return at most this many flows, in database order
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+/// Result of the `search` method
+#[derive(Debug, Serialize)]
+struct SearchMatches<'a> {
+    file_path: &'a str,
+    /// Flows matched in all, of which `results` may hold only the first
+    total: usize,
+    results: Vec<SearchResult<'a>>,
+}
+
+/// Answers JSON-RPC requests from editor plugins, one per line, keeping
+/// databases loaded between them as the [Daemon] does.
+///
+/// Methods:
+/// - `search` with `{"data_json", "query", "limit"?}` returns the matched
+///   flows as `--format json` prints them
+/// - `shutdown` stops the server after answering
+#[derive(Default)]
+pub struct Server {
+    daemon: Daemon,
+    shutdown: bool,
+}
+
+impl Server {
+    /// Answer a line of input, or nothing for a notification
+    pub fn handle_line(&mut self, line: &str) -> Option<RpcResponse> {
+        let request: RpcRequest = match serde_json::from_str::<Value>(line) {
+            Err(e) => return Some(RpcResponse::error(Value::Null, PARSE_ERROR, e.to_string())),
+            Ok(value) => match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => {
+                    return Some(RpcResponse::error(
+                        Value::Null,
+                        INVALID_REQUEST,
+                        e.to_string(),
+                    ))
+                }
+            },
+        };
+        let result = self.call(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => RpcResponse::success(id, result),
+            Err((code, message)) => RpcResponse::error(id, code, message),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        match method {
+            "search" => {
+                let params: SearchParams =
+                    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))?;
+                self.search(&params).map_err(|e| (SEARCH_FAILED, e))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+        }
+    }
+
+    fn search(&mut self, params: &SearchParams) -> Result<Value, String> {
+        let query = QueryOps::parse_query(&params.query)?;
+        let engine = self.daemon.engine(&params.data_json)?;
+        let db = engine.database();
+        let flows = engine.matching_indices(&query);
+        let matches = SearchMatches {
+            file_path: &db.file_path,
+            total: flows.len(),
+            results: flows
+                .iter()
+                .take(params.limit.unwrap_or(usize::MAX))
+                .map(|idx| SearchResult::new(db, &db.data_flows[*idx]).with_captures(db, &query))
+                .collect(),
+        };
+        serde_json::to_value(matches).map_err(|e| e.to_string())
+    }
+
+    /// Answer the requests read from `reader` until it ends or a `shutdown`
+    pub fn serve(&mut self, reader: impl BufRead, writer: &mut impl Write) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_line(&line) {
+                serde_json::to_writer(&mut *writer, &response)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer requests on the socket at `path` until a `shutdown`, one
+    /// connection at a time
+    #[cfg(unix)]
+    pub fn serve_unix(&mut self, path: &std::path::Path) -> io::Result<()> {
+        let listener = crate::daemon::bind(path)?;
+        for stream in listener.incoming() {
+            let stream = stream?;
+            self.serve(io::BufReader::new(&stream), &mut &stream)?;
+            if self.shutdown {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rpc_requests() {
+        let data_json = concat!(env!("CARGO_MANIFEST_DIR"), "/data/tutorial.json");
+        let input = [
+            format!(
+                r#"{{"jsonrpc": "2.0", "id": 1, "method": "search", "params": {{"data_json": "{}", "query": "List", "limit": 1}}}}"#,
+                data_json
+            ),
+            r#"{"jsonrpc": "2.0", "id": 2, "method": "search", "params": {}}"#.to_string(),
+            r#"{"jsonrpc": "2.0", "method": "search", "params": {}}"#.to_string(),
+            r#"{"jsonrpc": "2.0", "id": "x", "method": "grep"}"#.to_string(),
+            "{".to_string(),
+            r#"{"jsonrpc": "2.0", "id": 3, "method": "shutdown"}"#.to_string(),
+            r#"{"jsonrpc": "2.0", "id": 4, "method": "shutdown"}"#.to_string(),
+        ]
+        .join("\n");
+
+        let mut output = vec![];
+        Server::default()
+            .serve(input.as_bytes(), &mut output)
+            .unwrap();
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(responses.len(), 5);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"]["total"], 2);
+        assert_eq!(
+            responses[0]["result"]["results"].as_array().unwrap().len(),
+            1
+        );
+        assert_eq!(responses[1]["error"]["code"], INVALID_PARAMS);
+        assert_eq!(responses[2]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[3]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[4]["id"], 3);
+        assert!(responses[4]["result"].is_null());
+    }
+}
@@ -0,0 +1,251 @@
+use crate::data::{Aliases, Database, QueryOps, UnitFlow};
+use rayon::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+/// A bundle of named queries evaluated together against a database.
+///
+/// Rule packs are written in TOML, one `[[rule]]` table per rule:
+///
+/// ```toml
+/// [[rule]]
+/// name = "unguarded-condition"
+/// query = "bool,@Tuple.1,\"if-then-else condition\""
+/// description = "Boolean flowing into a tuple used as a branch condition"
+/// severity = "warning"
+/// tags = ["control-flow"]
+/// ```
+///
+/// or in JSON, as `{"rules": [{"name": ..., "query": ...}]}`, when the file
+/// name ends in `.json`. Queries shared by several rules can be named in an
+/// `[aliases]` table and used as steps of the rules' queries:
+///
+/// ```toml
+/// [aliases]
+/// unsafe_flow = "bool,@Tuple.1,\"if-then-else condition\""
+///
+/// [[rule]]
+/// name = "unguarded-start"
+/// query = "^, unsafe_flow"
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct RulePack {
+    #[serde(rename = "rule", alias = "rules", default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub aliases: Aliases,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub query: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The flows matched by a single rule of a [RulePack].
+pub struct RuleResult<'a> {
+    pub rule: &'a Rule,
+    pub flows: Vec<&'a [UnitFlow]>,
+}
+
+/// How the flows matched by a rule changed from one database to another
+pub struct Regression<'a> {
+    pub rule: &'a Rule,
+    /// Flows matched in the new database with no counterpart in the old one
+    pub introduced: Vec<&'a [UnitFlow]>,
+    /// Flows matched in the old database with no counterpart in the new one
+    pub fixed: Vec<&'a [UnitFlow]>,
+}
+
+impl RulePack {
+    /// Load a rule pack in JSON if `path` ends in `.json`, and TOML otherwise
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read rule pack {}: {}", path, e))?;
+        if path.ends_with(".json") {
+            Self::parse_json(&data)
+        } else {
+            Self::parse(&data)
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        toml::from_str::<RulePack>(input)
+            .map_err(|e| format!("Invalid rule pack: {}", e))?
+            .validated()
+    }
+
+    pub fn parse_json(input: &str) -> Result<Self, String> {
+        serde_json::from_str::<RulePack>(input)
+            .map_err(|e| format!("Invalid rule pack: {}", e))?
+            .validated()
+    }
+
+    fn validated(self) -> Result<Self, String> {
+        for rule in &self.rules {
+            QueryOps::parse_query_with(&rule.query, &self.aliases)
+                .map_err(|e| format!("Rule `{}` has an invalid query: {}", rule.name, e))?;
+        }
+        Ok(self)
+    }
+
+    /// Evaluate every rule against the database, the rules concurrently on
+    /// all cores since matching only reads the database. Results come in the
+    /// order of the rules, each with its flows in database order.
+    pub fn evaluate<'a>(&'a self, db: &'a Database) -> Vec<RuleResult<'a>> {
+        self.rules
+            .par_iter()
+            .map(|rule| {
+                let query =
+                    QueryOps::parse_query_with(&rule.query, &self.aliases).unwrap_or_default();
+                RuleResult {
+                    rule,
+                    flows: db.search(&query),
+                }
+            })
+            .collect()
+    }
+
+    /// Compare the flows each rule matches in `old` and `new`. Flows are
+    /// paired by [UnitFlow::similarity_key], so flows that only moved or were
+    /// renumbered between the databases are neither introduced nor fixed.
+    pub fn regress<'a>(&'a self, old: &'a Database, new: &'a Database) -> Vec<Regression<'a>> {
+        self.evaluate(old)
+            .into_iter()
+            .zip(self.evaluate(new))
+            .map(|(old, new)| Regression {
+                rule: new.rule,
+                introduced: unpaired(&new.flows, &old.flows),
+                fixed: unpaired(&old.flows, &new.flows),
+            })
+            .collect()
+    }
+}
+
+/// The `flows` left once each is paired with a distinct structurally equal
+/// flow of `others`
+fn unpaired<'a>(flows: &[&'a [UnitFlow]], others: &[&[UnitFlow]]) -> Vec<&'a [UnitFlow]> {
+    let mut counts: HashMap<Vec<String>, usize> = HashMap::new();
+    for flow in others {
+        *counts.entry(UnitFlow::similarity_key(flow)).or_default() += 1;
+    }
+    flows
+        .iter()
+        .filter(
+            |flow| match counts.get_mut(&UnitFlow::similarity_key(flow)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            },
+        )
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::flow_id;
+
+    #[test]
+    fn test_rule_pack_parsing() {
+        let pack = RulePack::parse(
+            r#"
+            [[rule]]
+            name = "unguarded-condition"
+            query = "bool,@Tuple.1"
+            severity = "error"
+            tags = ["control-flow"]
+
+            [[rule]]
+            name = "lists"
+            query = "List"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(pack.rules.len(), 2);
+        assert_eq!(pack.rules[0].severity, Severity::Error);
+        assert_eq!(pack.rules[0].tags, vec!["control-flow".to_string()]);
+        assert_eq!(pack.rules[1].severity, Severity::Warning);
+        assert!(pack.rules[1].description.is_none());
+
+        assert!(RulePack::parse("[[rule]]\nname = \"bad\"\nquery = \"@x.1.2\"").is_err());
+
+        let pack = RulePack::parse_json(
+            r#"{"rules": [{"name": "lists", "query": "List", "severity": "info"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(pack.rules[0].name, "lists");
+        assert_eq!(pack.rules[0].severity, Severity::Info);
+        assert!(RulePack::parse_json(r#"{"rules": [{"name": "bad", "query": "!^"}]}"#).is_err());
+
+        let pack = RulePack::parse(
+            "[aliases]\nconds = \"bool, @Tuple.1\"\nagain = \"again\"\n\n[[rule]]\nname = \"c\"\nquery = \"conds\"\n",
+        )
+        .unwrap();
+        let db = Database::load_from_str(crate::tutorial::TUTORIAL_JSON).unwrap();
+        assert_eq!(pack.evaluate(&db)[0].flows.len(), 1);
+        assert!(RulePack::parse(
+            "[aliases]\nagain = \"again\"\n\n[[rule]]\nname = \"a\"\nquery = \"again\"\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_regressions() {
+        let pack = RulePack::parse(
+            "[[rule]]\nname = \"bools\"\nquery = \"bool\"\n\n[[rule]]\nname = \"lists\"\nquery = \"List\"",
+        )
+        .unwrap();
+        let old = crate::tutorial::load_database();
+        // Drop the first flow, reorder the rest and duplicate a list flow
+        let mut flows = old.data_flows.to_vec().split_off(1);
+        flows.reverse();
+        flows.push(old.data_flows[2].to_vec());
+        let new = Database::new(old.file_path.clone(), flows);
+
+        let ids = |flows: &[&[UnitFlow]]| -> Vec<String> {
+            flows.iter().map(|flow| flow_id(flow)).collect()
+        };
+        let regressions = pack.regress(&old, &new);
+        assert_eq!(regressions[0].introduced.len(), 0);
+        assert_eq!(
+            ids(&regressions[0].fixed),
+            vec![flow_id(&old.data_flows[0])]
+        );
+        assert_eq!(
+            ids(&regressions[1].introduced),
+            vec![flow_id(&old.data_flows[2])]
+        );
+        assert_eq!(regressions[1].fixed.len(), 0);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::data::{flow_id, Database, ProgLoc, UnitFlow};
+use crate::rules::{RuleResult, Severity};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Matched flows as a SARIF 2.1.0 log, one result per flow, for code
+/// scanning dashboards to annotate the source with
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    /// Columns count characters, as `char_range` does
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    short_description: Message,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: String,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    code_flows: Vec<CodeFlow>,
+    partial_fingerprints: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<Message>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    /// `None` for a flow without any line, located at its file alone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_column: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CodeFlow {
+    thread_flows: Vec<ThreadFlow>,
+}
+
+#[derive(Debug, Serialize)]
+struct ThreadFlow {
+    locations: Vec<ThreadFlowLocation>,
+}
+
+#[derive(Debug, Serialize)]
+struct ThreadFlowLocation {
+    location: Location,
+}
+
+impl SarifLog {
+    /// The flows matched by a search for `query`, which is also the rule id
+    pub fn for_search(db: &Database, query: &str, flows: &[&[UnitFlow]]) -> Self {
+        let rule = ReportingDescriptor {
+            id: query.to_string(),
+            short_description: Message {
+                text: format!("Data flows matching `{}`", query),
+            },
+        };
+        let results = flows
+            .iter()
+            .map(|flow| result(db, query, Severity::Warning, flow))
+            .collect();
+        Self::new(vec![rule], results)
+    }
+
+    /// The flows matched by each rule of a rule pack, with the rule names as
+    /// rule ids
+    pub fn for_rules(db: &Database, results: &[RuleResult]) -> Self {
+        let rules = results
+            .iter()
+            .map(|r| ReportingDescriptor {
+                id: r.rule.name.clone(),
+                short_description: Message {
+                    text: r.rule.description.clone().unwrap_or(r.rule.query.clone()),
+                },
+            })
+            .collect();
+        let results = results
+            .iter()
+            .flat_map(|r| {
+                r.flows
+                    .iter()
+                    .map(|flow| result(db, &r.rule.name, r.rule.severity, flow))
+            })
+            .collect();
+        Self::new(rules, results)
+    }
+
+    fn new(rules: Vec<ReportingDescriptor>, results: Vec<SarifResult>) -> Self {
+        SarifLog {
+            schema: SARIF_SCHEMA,
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: env!("CARGO_PKG_NAME"),
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules,
+                    },
+                },
+                column_kind: "unicodeCodePoints",
+                results,
+            }],
+        }
+    }
+}
+
+/// A result for `flow`, located at its first program location with a line
+/// number, or at its file when it has none, and tracing all of them as a
+/// code flow
+fn result(db: &Database, rule_id: &str, severity: Severity, flow: &[UnitFlow]) -> SarifResult {
+    let steps: Vec<Location> = flow
+        .iter()
+        .filter_map(|uf| match uf {
+            UnitFlow::ProgLoc(loc) => location(db, loc),
+            _ => None,
+        })
+        .collect();
+    let locations = match steps.first() {
+        Some(first) => vec![first.clone()],
+        None => vec![Location {
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation {
+                    uri: db.flow_file(flow).to_string(),
+                },
+                region: None,
+            },
+            message: None,
+        }],
+    };
+    let code_flows = match steps.len() {
+        0 | 1 => vec![],
+        _ => vec![CodeFlow {
+            thread_flows: vec![ThreadFlow {
+                locations: steps
+                    .into_iter()
+                    .map(|location| ThreadFlowLocation { location })
+                    .collect(),
+            }],
+        }],
+    };
+    let id = flow_id(flow);
+    SarifResult {
+        rule_id: rule_id.to_string(),
+        level: match severity {
+            Severity::Info => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        },
+        message: Message {
+            text: format!("Data flow {} matches `{}`", id, rule_id),
+        },
+        locations,
+        code_flows,
+        partial_fingerprints: [("flowId/v1", id)].into(),
+    }
+}
+
+fn location(db: &Database, loc: &ProgLoc) -> Option<Location> {
+    let position = loc.position(&db.file_path)?;
+    Some(Location {
+        physical_location: PhysicalLocation {
+            artifact_location: ArtifactLocation { uri: position.file },
+            region: Some(Region {
+                start_line: position.line,
+                start_column: loc.columns().0,
+                end_column: loc.columns().1,
+            }),
+        },
+        message: loc.span_text().map(|text| Message {
+            text: text.to_string(),
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::QueryOps;
+
+    #[test]
+    fn test_sarif_log() {
+        let data = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ProgLoc": {"line": "let x = 1", "char_range": [5, 6], "desc": null, "depth": 0, "line_number": 3}},
+                {"ProgLoc": {"line": "print x", "char_range": [7, 8], "desc": null, "depth": 0, "file": "lib/out.ml", "line_number": 9}}
+            ]]
+        }"#;
+        let db = Database::load_from_str(data).unwrap();
+        let flows = db.search(&QueryOps::parse_query("int").unwrap());
+        let log = serde_json::to_value(SarifLog::for_search(&db, "int", &flows)).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "int");
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "int");
+        assert_eq!(result["level"], "warning");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "lib/main.ml");
+        assert_eq!(location["region"]["startLine"], 3);
+        assert_eq!(location["region"]["startColumn"], 5);
+        let steps = &result["codeFlows"][0]["threadFlows"][0]["locations"];
+        assert_eq!(steps.as_array().map(Vec::len), Some(2));
+        assert_eq!(
+            steps[1]["location"]["physicalLocation"]["artifactLocation"]["uri"],
+            "lib/out.ml"
+        );
+
+        let lineless = r#"{
+            "file_path": "lib/main.ml",
+            "dataflow": [[
+                {"Type": {"name": "int", "args": [], "desc": null}},
+                {"ProgLoc": {"line": "x", "char_range": [1, 2], "desc": null, "depth": 0, "file": "lib/out.ml"}}
+            ]]
+        }"#;
+        let db = Database::load_from_str(lineless).unwrap();
+        let flows = db.search(&QueryOps::parse_query("int").unwrap());
+        let log = serde_json::to_value(SarifLog::for_search(&db, "int", &flows)).unwrap();
+        let result = &log["runs"][0]["results"][0];
+        let locations = result["locations"].as_array().unwrap();
+        assert_eq!(locations.len(), 1);
+        let location = &locations[0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "lib/out.ml");
+        assert!(location.get("region").is_none());
+        assert!(result.get("codeFlows").is_none());
+    }
+}
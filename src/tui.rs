@@ -0,0 +1,488 @@
+use crate::bookmarks::Bookmarks;
+use crate::data::{flow_id, Database, QueryOps, UnitFlow};
+use crate::repl::{self, Refinement};
+
+/// State of the `--tui` result browser: the query being typed, the flows it
+/// matched and the one selected, and the queries earlier results were
+/// refined by. The terminal front end only draws this and feeds it keys.
+pub struct Browser<'a> {
+    pub db: &'a Database,
+    pub query: String,
+    /// Indices of the matched flows in [Database::data_flows]
+    pub flows: Vec<usize>,
+    pub selected: usize,
+    /// Why the query as typed does not parse; the flows of the last query
+    /// that did are kept meanwhile
+    pub error: Option<String>,
+    /// Queries committed with [Browser::refine], each with the flows it
+    /// left. The query being typed searches only the flows of the last.
+    pub refinements: Vec<Refinement>,
+    /// Names the last token could be completed to when Tab left a choice
+    pub completions: Vec<String>,
+    /// Bookmarks of the database, toggled with [Browser::toggle_bookmark]
+    pub bookmarks: Bookmarks,
+    /// What the last bookmark toggle did, or why it could not be saved
+    pub status: Option<String>,
+    ops: Vec<QueryOps>,
+}
+
+impl<'a> Browser<'a> {
+    pub fn new(db: &'a Database, query: &str) -> Self {
+        let mut browser = Browser {
+            db,
+            query: query.to_string(),
+            flows: vec![],
+            selected: 0,
+            error: None,
+            refinements: vec![],
+            completions: vec![],
+            bookmarks: Bookmarks::default(),
+            status: None,
+            ops: vec![],
+        };
+        browser.search();
+        browser
+    }
+
+    /// Toggle and save bookmarks in `bookmarks`
+    pub fn with_bookmarks(self, bookmarks: Bookmarks) -> Self {
+        Browser { bookmarks, ..self }
+    }
+
+    /// The query as typed, without a leading `:refine`
+    fn typed_query(&self) -> &str {
+        let query = self.query.trim();
+        query.strip_prefix(":refine").unwrap_or(query).trim()
+    }
+
+    /// Re-run the search for the query as typed within the current
+    /// refinement, or the whole database. An empty query lists every flow
+    /// searched.
+    fn search(&mut self) {
+        let within = self.refinements.last().map(|r| &r.flows[..]);
+        if self.typed_query().is_empty() {
+            self.ops.clear();
+            self.flows = match within {
+                Some(flows) => flows.to_vec(),
+                None => (0..self.db.data_flows.len()).collect(),
+            };
+            self.error = None;
+        } else {
+            match QueryOps::parse_query(self.typed_query()) {
+                Ok(ops) => {
+                    self.flows = match within {
+                        Some(flows) => self.db.matching_indices_in(flows, &ops),
+                        None => self.db.matching_indices(&ops),
+                    };
+                    self.ops = ops;
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        self.selected = self.selected.min(self.flows.len().saturating_sub(1));
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.query.push(c);
+        self.completions.clear();
+        self.status = None;
+        self.search();
+    }
+
+    /// Complete the last token of the query as the REPL does: to the only
+    /// name it could be, or as far as the names it could be agree, listing
+    /// them in [Browser::completions]
+    pub fn complete(&mut self) {
+        let (start, candidates) = repl::complete(self.db, &self.query, self.query.len());
+        let Some(first) = candidates.first() else {
+            self.completions.clear();
+            return;
+        };
+        let common = candidates[1..].iter().fold(first.len(), |len, candidate| {
+            first[..len]
+                .char_indices()
+                .zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(len.min(candidate.len()), |((idx, _), _)| idx)
+        });
+        self.query.truncate(start);
+        self.query.push_str(&first[..common]);
+        self.completions = match candidates.len() {
+            1 => vec![],
+            _ => candidates,
+        };
+        self.search();
+    }
+
+    /// Delete the last character of the query, or undo the last refinement
+    /// when the query is empty
+    pub fn backspace(&mut self) {
+        self.completions.clear();
+        if self.query.pop().is_none() {
+            self.refinements.pop();
+        }
+        self.search();
+    }
+
+    /// Keep only the flows the query as typed matched, and clear it to
+    /// search within them. Does nothing for an empty or invalid query.
+    pub fn refine(&mut self) {
+        if self.typed_query().is_empty() || self.error.is_some() {
+            return;
+        }
+        self.refinements.push(Refinement {
+            query: self.typed_query().to_string(),
+            flows: self.flows.clone(),
+        });
+        self.query.clear();
+        self.search();
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.flows.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Bookmark the selected flow, or drop its bookmark, and save the
+    /// bookmarks
+    pub fn toggle_bookmark(&mut self) {
+        let Some(flow) = self.selected_flow() else {
+            return;
+        };
+        let id = flow_id(flow);
+        let status = if self.bookmarks.add(&id) {
+            format!("Bookmarked {}", id)
+        } else {
+            self.bookmarks
+                .flow_ids
+                .retain(|bookmarked| *bookmarked != id);
+            format!("Removed bookmark {}", id)
+        };
+        self.status = Some(match self.bookmarks.save() {
+            Ok(()) => status,
+            Err(e) => e,
+        });
+    }
+
+    /// Whether the flow at `idx` is bookmarked
+    pub fn is_bookmarked(&self, idx: usize) -> bool {
+        let id = flow_id(&self.db.data_flows[idx]);
+        self.bookmarks.flow_ids.contains(&id)
+    }
+
+    pub fn selected_flow(&self) -> Option<&'a [UnitFlow]> {
+        let db = self.db;
+        self.flows
+            .get(self.selected)
+            .map(|idx| &db.data_flows[*idx][..])
+    }
+
+    /// One-line summary of the flow at `idx` for the result list: its
+    /// non-location steps in order
+    pub fn flow_label(&self, idx: usize) -> String {
+        self.db.data_flows[idx]
+            .iter()
+            .filter(|uf| !matches!(uf, UnitFlow::ProgLoc(_)))
+            .map(UnitFlow::label)
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+
+    /// Steps of the selected flow, each with whether the query matched it
+    pub fn steps(&self) -> Vec<(&'a UnitFlow, bool)> {
+        let Some(flow) = self.selected_flow() else {
+            return vec![];
+        };
+        let positions = self
+            .db
+            .match_flow_positions(flow, &self.ops)
+            .unwrap_or_default();
+        flow.iter()
+            .enumerate()
+            .map(|(idx, uf)| (uf, positions.contains(&idx)))
+            .collect()
+    }
+}
+
+#[cfg(feature = "tui")]
+pub use terminal::run;
+
+#[cfg(feature = "tui")]
+mod terminal {
+    use super::Browser;
+    use crate::data::UnitFlow;
+    use ratatui::backend::CrosstermBackend;
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::crossterm::execute;
+    use ratatui::crossterm::terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    };
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Modifier, Style};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+    use ratatui::{Frame, Terminal};
+    use std::io::{self, Stdout};
+
+    /// Browse `browser` in the alternate screen until Esc or Ctrl-C,
+    /// restoring the terminal afterwards. Ctrl-B toggles the bookmark of
+    /// the selected flow, as other keys type the query.
+    pub fn run(browser: &mut Browser) -> io::Result<()> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        let result = Terminal::new(CrosstermBackend::new(io::stdout()))
+            .and_then(|mut terminal| event_loop(&mut terminal, browser));
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+        result
+    }
+
+    fn event_loop(
+        terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+        browser: &mut Browser,
+    ) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| draw(frame, browser))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    browser.toggle_bookmark()
+                }
+                KeyCode::Up => browser.select_previous(),
+                KeyCode::Down => browser.select_next(),
+                KeyCode::Backspace => browser.backspace(),
+                KeyCode::Enter => browser.refine(),
+                KeyCode::Tab => browser.complete(),
+                KeyCode::Char(c) => browser.insert(c),
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(frame: &mut Frame, browser: &Browser) {
+        let [body, input] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(3)]).areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(body);
+
+        let items: Vec<_> = browser
+            .flows
+            .iter()
+            .map(|idx| {
+                let mark = if browser.is_bookmarked(*idx) {
+                    "★ "
+                } else {
+                    ""
+                };
+                ListItem::new(format!("{}{}", mark, browser.flow_label(*idx)))
+            })
+            .collect();
+        let title = format!(" Flows ({}) ", browser.flows.len());
+        let mut state = ListState::default().with_selected(Some(browser.selected));
+        frame.render_stateful_widget(
+            List::new(items)
+                .block(Block::bordered().title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list,
+            &mut state,
+        );
+
+        frame.render_widget(
+            Paragraph::new(step_lines(browser)).block(Block::bordered().title(" Flow ")),
+            detail,
+        );
+
+        let (title, style) = match &browser.error {
+            Some(e) => (format!(" Query: {} ", e), Style::default().fg(Color::Red)),
+            None if !browser.completions.is_empty() => (
+                format!(" Complete: {} ", browser.completions.join(" ")),
+                Style::default().fg(Color::Yellow),
+            ),
+            None if browser.status.is_some() => (
+                format!(" {} ", browser.status.as_deref().unwrap_or_default()),
+                Style::default().fg(Color::Green),
+            ),
+            None if browser.refinements.is_empty() => (
+                " Query (Tab to complete, Enter to refine, Ctrl-B to bookmark, Esc to quit) "
+                    .to_string(),
+                Style::default().fg(Color::Blue),
+            ),
+            None => {
+                let trail: Vec<&str> = browser
+                    .refinements
+                    .iter()
+                    .map(|r| r.query.as_str())
+                    .collect();
+                (
+                    format!(" Query within {} ", trail.join(" › ")),
+                    Style::default().fg(Color::Blue),
+                )
+            }
+        };
+        frame.render_widget(
+            Paragraph::new(browser.query.as_str())
+                .block(Block::bordered().title(title).border_style(style)),
+            input,
+        );
+        let width = unicode_width::UnicodeWidthStr::width(browser.query.as_str()) as u16;
+        frame.set_cursor_position((input.x + 1 + width, input.y + 1));
+    }
+
+    /// The steps of the selected flow, program locations with their source
+    /// position and span, steps the query matched in yellow
+    fn step_lines<'a>(browser: &Browser<'a>) -> Vec<Line<'a>> {
+        let marked = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        let mut itr = 1;
+        let mut lines = vec![];
+        for (uf, matched) in browser.steps() {
+            let UnitFlow::ProgLoc(loc) = uf else {
+                let style = if matched { marked } else { Style::default() };
+                lines.push(Line::styled(uf.label(), style));
+                continue;
+            };
+            let position = loc
+                .position(&browser.db.file_path)
+                .map_or_else(String::new, |p| format!(" {}:{}", p.file, p.line));
+            lines.push(Line::styled(
+                format!("[{}]{}", itr, position),
+                Style::default().fg(Color::DarkGray),
+            ));
+            let span_style = if matched {
+                marked.add_modifier(Modifier::UNDERLINED)
+            } else {
+                Style::default().add_modifier(Modifier::UNDERLINED)
+            };
+            lines.push(match loc.split_span() {
+                Some((before, span, after)) => Line::from(vec![
+                    Span::raw(format!("  {}", before)),
+                    Span::styled(span, span_style),
+                    Span::raw(after),
+                ]),
+                None => Line::raw(format!("  {}", uf.label())),
+            });
+            itr += 1;
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_browser() {
+        let db = crate::tutorial::load_database();
+        let mut browser = Browser::new(&db, "boo");
+        assert!(browser.flows.is_empty());
+
+        browser.insert('l');
+        assert_eq!(browser.flows, vec![0, 5]);
+        browser.select_next();
+        browser.select_next();
+        assert_eq!(browser.selected, 1);
+        let matched: Vec<_> = browser
+            .steps()
+            .into_iter()
+            .filter(|(_, matched)| *matched)
+            .map(|(uf, _)| uf.label())
+            .collect();
+        assert_eq!(matched, vec!["bool"]);
+
+        browser.insert('"');
+        assert!(browser.error.is_some());
+        assert_eq!(browser.flows, vec![0, 5]);
+
+        browser.query.clear();
+        browser.backspace();
+        assert_eq!(browser.flows.len(), db.data_flows.len());
+        assert_eq!(browser.flow_label(0), "bool → @Tuple.1");
+        browser.select_previous();
+        assert_eq!(browser.selected, 0);
+    }
+
+    #[test]
+    fn test_refine() {
+        let db = crate::tutorial::load_database();
+        let mut browser = Browser::new(&db, "bool");
+        browser.refine();
+        assert!(browser.query.is_empty());
+        assert_eq!(browser.flows, vec![0, 5]);
+
+        for c in ":refine @Tuple".chars() {
+            browser.insert(c);
+        }
+        assert_eq!(browser.flows, vec![0]);
+        browser.refine();
+        assert_eq!(browser.refinements.len(), 2);
+        assert_eq!(browser.refinements[1].query, "@Tuple");
+
+        browser.backspace();
+        assert_eq!(browser.flows, vec![0, 5]);
+        browser.backspace();
+        assert_eq!(browser.flows.len(), db.data_flows.len());
+    }
+
+    #[test]
+    fn test_completion() {
+        let db = crate::tutorial::load_database();
+        let mut browser = Browser::new(&db, "bool, @Tu");
+        browser.complete();
+        assert_eq!(browser.query, "bool, @Tuple");
+        assert!(browser.completions.is_empty());
+        assert_eq!(browser.flows, vec![0]);
+
+        let mut browser = Browser::new(&db, "List, ");
+        browser.insert('\'');
+        browser.complete();
+        assert_eq!(browser.query, "List, '");
+        assert_eq!(browser.completions, vec!["'a", "'b"]);
+        browser.insert('b');
+        assert!(browser.completions.is_empty());
+    }
+
+    #[test]
+    fn test_toggle_bookmark() {
+        let data_json = std::env::temp_dir()
+            .join(format!("scs-tui-{}.json", std::process::id()))
+            .to_string_lossy()
+            .to_string();
+        let db = crate::tutorial::load_database();
+        let bookmarks = Bookmarks::load(&data_json).unwrap();
+        let mut browser = Browser::new(&db, "bool").with_bookmarks(bookmarks);
+        browser.select_next();
+        let id = flow_id(browser.selected_flow().unwrap());
+
+        browser.toggle_bookmark();
+        assert!(browser.is_bookmarked(5));
+        assert!(!browser.is_bookmarked(0));
+        assert_eq!(
+            Bookmarks::load(&data_json).unwrap().flow_ids,
+            vec![id.clone()]
+        );
+        assert_eq!(browser.status, Some(format!("Bookmarked {}", id)));
+
+        browser.toggle_bookmark();
+        assert!(!browser.is_bookmarked(5));
+        assert!(Bookmarks::load(&data_json).unwrap().flow_ids.is_empty());
+        std::fs::remove_file(Bookmarks::sidecar_path(&data_json)).unwrap();
+    }
+}
@@ -0,0 +1,80 @@
+use crate::data::{Database, QueryOps};
+
+/// Small curated database shipped with the crate for the guided tutorial
+pub const TUTORIAL_JSON: &str = include_str!("../data/tutorial.json");
+
+/// A single exercise of the guided tutorial
+pub struct Task {
+    /// What the user is asked to find
+    pub prompt: &'static str,
+    /// Syntax reminder shown on request
+    pub hint: &'static str,
+    /// A query producing the expected result set
+    pub answer: &'static str,
+}
+
+pub const TASKS: &[Task] = &[
+    Task {
+        prompt: "Find every flow that involves a value of type `bool`.",
+        hint: "A bare name matches a type: `List`",
+        answer: "bool",
+    },
+    Task {
+        prompt: "Find the flows where a value reaches the 2nd argument of `Tuple`.",
+        hint: "`@Name` matches a constructor argument, `@Name.N` pins the argument index",
+        answer: "@Tuple.2",
+    },
+    Task {
+        prompt: "Find the flows where a `bool` later flows into a `Tuple`.",
+        hint: "Separate steps with commas; they must appear in that order in the flow",
+        answer: "bool,@Tuple",
+    },
+    Task {
+        prompt: "Find the flows with a step described as \"if-then-else condition\".",
+        hint: "Quote a description to match it: `\"some description\"`",
+        answer: "\"if-then-else condition\"",
+    },
+    Task {
+        prompt: "Find the flows through a type variable shared by exactly 2 flows.",
+        hint: "`#N` matches a type variable that appears in N flows",
+        answer: "#2",
+    },
+    Task {
+        prompt: "Find the flows where a `List` is consed onto another list.",
+        hint: "Combine a type and a constructor argument: `Type,@Name`",
+        answer: "List,@Cons",
+    },
+];
+
+pub fn load_database() -> Database {
+    Database::load_from_str(TUTORIAL_JSON).expect("The tutorial database is valid")
+}
+
+impl Task {
+    /// Check whether `input` selects exactly the flows of the expected answer
+    pub fn check(&self, db: &Database, input: &str) -> Result<bool, String> {
+        let query = QueryOps::parse_query(input)?;
+        let expected = QueryOps::parse_query(self.answer)?;
+        Ok(db.matching_indices(&query) == db.matching_indices(&expected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasks_solvable() {
+        let db = load_database();
+        for task in TASKS {
+            let answer = QueryOps::parse_query(task.answer).unwrap();
+            assert!(
+                !db.matching_indices(&answer).is_empty(),
+                "`{}` matches nothing",
+                task.answer
+            );
+            assert_eq!(task.check(&db, task.answer), Ok(true));
+        }
+        assert_eq!(TASKS[0].check(&db, "int"), Ok(false));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::data::{Database, QueryOps};
+use crate::engine::SearchEngine;
+use crate::error;
+use crate::results::SearchResult;
+use serde::Serialize;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+thread_local! {
+    /// Database searched by [search], as last given to [load_database]
+    static ENGINE: RefCell<Option<SearchEngine>> = const { RefCell::new(None) };
+}
+
+/// A matched flow as [search] hands it to JavaScript: the fields of
+/// `--format json`, with where the flow is in the database and which of its
+/// steps the query matched
+#[derive(Debug, Serialize)]
+struct JsMatch<'a> {
+    #[serde(flatten)]
+    result: SearchResult<'a>,
+    /// Index of the flow in the database
+    index: usize,
+    /// File the flow starts in
+    file_path: &'a str,
+    /// Steps of the flow that satisfied the query
+    positions: Vec<usize>,
+}
+
+/// Load a database from the contents of its JSON file, replacing the one
+/// searched before, and return its number of flows. Throws when the JSON is
+/// not a database.
+///
+/// The module is built with
+/// `cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib`
+/// and then `wasm-bindgen --target web` on the `.wasm` file.
+#[wasm_bindgen]
+pub fn load_database(json: &str) -> Result<usize, JsError> {
+    let db = Database::load_from_str(json)?;
+    let flows = db.data_flows.len();
+    ENGINE.with(|engine| *engine.borrow_mut() = Some(SearchEngine::new(db)));
+    Ok(flows)
+}
+
+/// The flows of the loaded database matched by a query in the command line
+/// syntax, best first, as an array of plain objects. Throws when the query
+/// does not parse or no database is loaded.
+#[wasm_bindgen]
+pub fn search(query: &str) -> Result<JsValue, JsError> {
+    ENGINE.with(|engine| {
+        let engine = engine.borrow();
+        let engine = engine
+            .as_ref()
+            .ok_or_else(|| JsError::new("No database loaded; call load_database first"))?;
+        // Maps become objects rather than `Map`s, as with JSON.parse
+        let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+        Ok(matches(engine, query)?.serialize(&serializer)?)
+    })
+}
+
+/// The matches of `query` in `engine`, best first
+fn matches<'a>(engine: &'a SearchEngine, query: &str) -> error::Result<Vec<JsMatch<'a>>> {
+    let db = engine.database();
+    let ops = QueryOps::parse_query(query)?;
+    Ok(engine
+        .search_ops(&ops)
+        .into_vec()
+        .into_iter()
+        .map(|m| JsMatch {
+            result: SearchResult::new(db, m.flow).with_captures(db, &ops),
+            index: m.index,
+            file_path: m.file_path,
+            positions: m.positions,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tutorial::TUTORIAL_JSON;
+
+    #[test]
+    fn test_matches() {
+        let engine = SearchEngine::new(Database::load_from_str(TUTORIAL_JSON).unwrap());
+        let found = matches(&engine, "bool as b, @Tuple.1").unwrap();
+        assert!(!found.is_empty());
+        let json = serde_json::to_value(&found[0]).unwrap();
+        assert_eq!(json["flow_id"], found[0].result.flow_id);
+        assert_eq!(json["index"], found[0].index);
+        assert_eq!(json["positions"], serde_json::json!([0, 1]));
+        assert_eq!(json["captures"]["b"]["Type"]["name"], "bool");
+
+        assert!(matches(&engine, "@x.").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::results::ResultSet;
+use serde::Serialize;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The payload handed to [Hooks] when a watched query's matches change
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct MatchChange {
+    pub data_json: String,
+    pub query: String,
+    /// [flow_id](crate::data::flow_id) of every flow now matched
+    pub flow_ids: Vec<String>,
+    /// Ids matched now but not before
+    pub added: Vec<String>,
+    /// Ids matched before but not now
+    pub removed: Vec<String>,
+}
+
+impl MatchChange {
+    /// The change from `previous` to `current`, if the matched flows differ
+    pub fn between(previous: &ResultSet, current: &ResultSet) -> Option<Self> {
+        let added = current.minus(previous).flow_ids;
+        let removed = previous.minus(current).flow_ids;
+        if added.is_empty() && removed.is_empty() {
+            return None;
+        }
+        Some(MatchChange {
+            data_json: current.data_json.clone(),
+            query: current.query.clone(),
+            flow_ids: current.flow_ids.clone(),
+            added,
+            removed,
+        })
+    }
+}
+
+/// Notifications fired when a watched query's matches change
+#[derive(Debug, Default)]
+pub struct Hooks {
+    /// Shell command run with the JSON payload on its stdin
+    pub command: Option<String>,
+    /// URL the JSON payload is POSTed to
+    pub webhook: Option<String>,
+}
+
+impl Hooks {
+    pub fn notify(&self, change: &MatchChange) -> Result<(), String> {
+        let payload = serde_json::to_string(change).map_err(|e| e.to_string())?;
+
+        if let Some(command) = &self.command {
+            let mut child = Command::new("sh")
+                .args(["-c", command])
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|e| format!("Could not run `{}`: {}", command, e))?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin
+                    .write_all(payload.as_bytes())
+                    .map_err(|e| format!("Could not write to `{}`: {}", command, e))?;
+            }
+            let status = child.wait().map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("`{}` failed with {}", command, status));
+            }
+        }
+
+        if let Some(url) = &self.webhook {
+            post(url, &payload)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn post(url: &str, payload: &str) -> Result<(), String> {
+    ureq::post(url)
+        .set("Content-Type", "application/json")
+        .send_string(payload)
+        .map_err(|e| format!("Could not POST to {}: {}", url, e))?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn post(url: &str, _: &str) -> Result<(), String> {
+    Err(format!(
+        "Could not POST to {}: no webhooks in wasm32 builds",
+        url
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_changes() {
+        let result_set = |ids: &[&str]| ResultSet {
+            data_json: "db.json".to_string(),
+            query: "List".to_string(),
+            flow_ids: ids.iter().map(|id| id.to_string()).collect(),
+        };
+
+        assert_eq!(
+            MatchChange::between(&result_set(&["a", "b"]), &result_set(&["b", "a"])),
+            None
+        );
+        let change = MatchChange::between(&result_set(&["a", "b"]), &result_set(&["b", "c"]));
+        assert_eq!(
+            change,
+            Some(MatchChange {
+                data_json: "db.json".to_string(),
+                query: "List".to_string(),
+                flow_ids: vec!["b".to_string(), "c".to_string()],
+                added: vec!["c".to_string()],
+                removed: vec!["a".to_string()],
+            })
+        );
+    }
+}